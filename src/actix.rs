@@ -0,0 +1,159 @@
+use actix_web::{
+    http::{header, StatusCode},
+    web, HttpRequest, HttpResponse, Scope,
+};
+
+use crate::{
+    agent::{validation_failure, DataConnectorAgent},
+    dataset::{DatasetCloneName, DatasetTemplateName},
+    dispatch::{dispatch, AgentRequest, AgentResponse, Route},
+    health::HealthCheckRequest,
+    protocol::CONFIG_HEADER,
+};
+
+/// A scope serving every data connector endpoint from the agent, to be added to an app with `App::service`, or nested in another scope.
+///
+/// Requests are decoded from JSON and the `X-Hasura-DataConnector-Config` header, and responses, including errors, are encoded as JSON. Errors are sent as an `ErrorResponse` with the status given by `ErrorResponse::status_code`
+pub fn actix_scope<A: DataConnectorAgent + 'static>(agent: A) -> Scope {
+    web::scope("").configure(actix_configure(agent))
+}
+
+/// Registers every data connector endpoint from the agent, for use with `App::configure` or `Scope::configure`
+pub fn actix_configure<A: DataConnectorAgent + 'static>(
+    agent: A,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |config| {
+        config
+            .app_data(web::Data::new(agent))
+            .route("/capabilities", web::get().to(capabilities::<A>))
+            .route("/schema", web::get().to(schema::<A>))
+            .route("/schema", web::post().to(schema::<A>))
+            .route("/query", web::post().to(query::<A>))
+            .route("/explain", web::post().to(explain::<A>))
+            .route("/mutation", web::post().to(mutation::<A>))
+            .route("/raw", web::post().to(raw::<A>))
+            .route("/health", web::get().to(health::<A>))
+            .route(
+                "/datasets/templates/{template_name}",
+                web::get().to(dataset_get_template::<A>),
+            )
+            .route(
+                "/datasets/clones/{clone_name}",
+                web::post().to(dataset_create_clone::<A>),
+            )
+            .route(
+                "/datasets/clones/{clone_name}",
+                web::delete().to(dataset_delete_clone::<A>),
+            );
+    }
+}
+
+async fn capabilities<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    request: HttpRequest,
+) -> HttpResponse {
+    serve(&**agent, Route::Capabilities, &request, &[]).await
+}
+
+async fn schema<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    request: HttpRequest,
+    body: web::Bytes,
+) -> HttpResponse {
+    serve(&**agent, Route::Schema, &request, &body).await
+}
+
+async fn query<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    request: HttpRequest,
+    body: web::Bytes,
+) -> HttpResponse {
+    serve(&**agent, Route::Query, &request, &body).await
+}
+
+async fn explain<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    request: HttpRequest,
+    body: web::Bytes,
+) -> HttpResponse {
+    serve(&**agent, Route::Explain, &request, &body).await
+}
+
+async fn mutation<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    request: HttpRequest,
+    body: web::Bytes,
+) -> HttpResponse {
+    serve(&**agent, Route::Mutation, &request, &body).await
+}
+
+async fn raw<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    request: HttpRequest,
+    body: web::Bytes,
+) -> HttpResponse {
+    serve(&**agent, Route::Raw, &request, &body).await
+}
+
+async fn health<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    request: HttpRequest,
+) -> HttpResponse {
+    let health_request = web::Query::<HealthCheckRequest>::from_query(request.query_string())
+        .map(web::Query::into_inner)
+        .map_err(|error| validation_failure(error.to_string()));
+    serve(&**agent, Route::Health(health_request), &request, &[]).await
+}
+
+async fn dataset_get_template<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    template_name: web::Path<DatasetTemplateName>,
+    request: HttpRequest,
+) -> HttpResponse {
+    let route = Route::DatasetGetTemplate(template_name.into_inner());
+    serve(&**agent, route, &request, &[]).await
+}
+
+async fn dataset_create_clone<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    clone_name: web::Path<DatasetCloneName>,
+    request: HttpRequest,
+    body: web::Bytes,
+) -> HttpResponse {
+    let route = Route::DatasetCreateClone(clone_name.into_inner());
+    serve(&**agent, route, &request, &body).await
+}
+
+async fn dataset_delete_clone<A: DataConnectorAgent + 'static>(
+    agent: web::Data<A>,
+    clone_name: web::Path<DatasetCloneName>,
+    request: HttpRequest,
+) -> HttpResponse {
+    let route = Route::DatasetDeleteClone(clone_name.into_inner());
+    serve(&**agent, route, &request, &[]).await
+}
+
+async fn serve<A: DataConnectorAgent>(
+    agent: &A,
+    route: Route,
+    request: &HttpRequest,
+    body: &[u8],
+) -> HttpResponse {
+    let headers = request.headers();
+    let request = AgentRequest {
+        config_header: headers.get(CONFIG_HEADER).map(|value| value.as_bytes()),
+        content_type: headers
+            .get(header::CONTENT_TYPE)
+            .map(|value| value.as_bytes()),
+        body,
+        summary: None,
+    };
+    into_response(dispatch(agent, route, request).await)
+}
+
+fn into_response(response: AgentResponse) -> HttpResponse {
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    HttpResponse::build(status)
+        .content_type(response.content_type)
+        .body(response.body)
+}
@@ -0,0 +1,263 @@
+use std::future::Future;
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    capabilities::CapabilitiesResponse,
+    dataset::{
+        DatasetCloneName, DatasetCreateCloneRequest, DatasetCreateCloneResponse,
+        DatasetDeleteCloneResponse, DatasetGetTemplateResponse, DatasetTemplateName,
+    },
+    error::{ErrorResponse, ErrorResponseType},
+    explain::ExplainResponse,
+    health::{HealthCheckRequest, HealthCheckResponse, HealthStatus},
+    mutation::{MutationRequest, MutationResponse},
+    protocol::{ConfigHeader, CONFIG_HEADER},
+    query::{QueryRequest, QueryResponse},
+    raw::{RawRequest, RawResponse},
+    schema::{SchemaRequest, SchemaResponse},
+};
+
+/// The endpoints of a data connector agent. Framework adapters and mock servers are written against this trait, so an agent only implements its behaviour once.
+///
+/// Endpoints an agent does not support have default implementations that fail with an `unsupported-operation` error, except `health`, which reports the agent as healthy
+pub trait DataConnectorAgent: Send + Sync {
+    /// The agent's configuration, as sent by HGE in the `X-Hasura-DataConnector-Config` header
+    type Config: DeserializeOwned + Send + Sync;
+
+    /// `GET /capabilities`
+    fn capabilities(
+        &self,
+    ) -> impl Future<Output = Result<CapabilitiesResponse, ErrorResponse>> + Send;
+
+    /// `GET /schema`, or `POST /schema` with a request body
+    fn schema(
+        &self,
+        config: &Self::Config,
+        request: SchemaRequest,
+    ) -> impl Future<Output = Result<SchemaResponse, ErrorResponse>> + Send;
+
+    /// `POST /query`
+    fn query(
+        &self,
+        config: &Self::Config,
+        request: QueryRequest,
+    ) -> impl Future<Output = Result<QueryResponse, ErrorResponse>> + Send;
+
+    /// `POST /explain`
+    fn query_explain(
+        &self,
+        _config: &Self::Config,
+        _request: QueryRequest,
+    ) -> impl Future<Output = Result<ExplainResponse, ErrorResponse>> + Send {
+        async { Err(unsupported("explain")) }
+    }
+
+    /// `POST /mutation`
+    fn mutation(
+        &self,
+        _config: &Self::Config,
+        _request: MutationRequest,
+    ) -> impl Future<Output = Result<MutationResponse, ErrorResponse>> + Send {
+        async { Err(unsupported("mutations")) }
+    }
+
+    /// `POST /raw`
+    fn raw(
+        &self,
+        _config: &Self::Config,
+        _request: RawRequest,
+    ) -> impl Future<Output = Result<RawResponse, ErrorResponse>> + Send {
+        async { Err(unsupported("raw queries")) }
+    }
+
+    /// `GET /health`. The config is only present when HGE checks the health of a particular source
+    fn health(
+        &self,
+        _config: Option<&Self::Config>,
+        _request: HealthCheckRequest,
+    ) -> impl Future<Output = Result<HealthCheckResponse, ErrorResponse>> + Send {
+        async {
+            Ok(HealthCheckResponse {
+                status: HealthStatus::Healthy,
+                data_sources: None,
+                version: None,
+            })
+        }
+    }
+
+    /// `GET /datasets/templates/:template_name`
+    fn dataset_get_template(
+        &self,
+        _template_name: &DatasetTemplateName,
+    ) -> impl Future<Output = Result<DatasetGetTemplateResponse, ErrorResponse>> + Send {
+        async { Err(unsupported("datasets")) }
+    }
+
+    /// `POST /datasets/clones/:clone_name`
+    fn dataset_create_clone(
+        &self,
+        _clone_name: &DatasetCloneName,
+        _request: DatasetCreateCloneRequest,
+    ) -> impl Future<Output = Result<DatasetCreateCloneResponse, ErrorResponse>> + Send {
+        async { Err(unsupported("datasets")) }
+    }
+
+    /// `DELETE /datasets/clones/:clone_name`
+    fn dataset_delete_clone(
+        &self,
+        _clone_name: &DatasetCloneName,
+    ) -> impl Future<Output = Result<DatasetDeleteCloneResponse, ErrorResponse>> + Send {
+        async { Err(unsupported("datasets")) }
+    }
+}
+
+/// Decodes the value of the `X-Hasura-DataConnector-Config` header for an adapter, which is required by every endpoint that acts on a source
+pub fn decode_config_header<C: DeserializeOwned>(
+    header_value: Option<&[u8]>,
+) -> Result<C, ErrorResponse> {
+    let header_value = header_value
+        .ok_or_else(|| validation_failure(format!("missing {CONFIG_HEADER} header")))?;
+    let header_value = std::str::from_utf8(header_value)
+        .map_err(|_| validation_failure(format!("{CONFIG_HEADER} header is not valid text")))?;
+    ConfigHeader::parse(header_value)
+        .map(|ConfigHeader(config)| config)
+        .map_err(|error| validation_failure(error.to_string()))
+}
+
+/// Decodes a JSON request body for an adapter. Bodies sent with a content type other than JSON are rejected
+pub fn decode_request_body<T: DeserializeOwned>(
+    content_type: Option<&[u8]>,
+    body: &[u8],
+) -> Result<T, ErrorResponse> {
+    if let Some(content_type) = content_type {
+        let is_json = std::str::from_utf8(content_type).is_ok_and(|content_type| {
+            content_type
+                .split(';')
+                .next()
+                .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+        });
+        if !is_json {
+            return Err(validation_failure(
+                "request body must have content type application/json".to_owned(),
+            ));
+        }
+    }
+    serde_json::from_slice(body)
+        .map_err(|error| validation_failure(format!("invalid request body: {error}")))
+}
+
+pub(crate) fn validation_failure(message: String) -> ErrorResponse {
+    ErrorResponse {
+        details: None,
+        message,
+        r#type: Some(ErrorResponseType::RequestValidationFailure),
+    }
+}
+
+fn unsupported(feature: &str) -> ErrorResponse {
+    ErrorResponse {
+        details: None,
+        message: format!("this agent does not support {feature}"),
+        r#type: Some(ErrorResponseType::UnsupportedOperation),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// Polls a future that never waits, as those of the test agents do not
+    pub(crate) fn now<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the future is not ready"),
+        }
+    }
+
+    struct QueryOnly;
+
+    impl DataConnectorAgent for QueryOnly {
+        type Config = serde_json::Value;
+
+        async fn capabilities(&self) -> Result<CapabilitiesResponse, ErrorResponse> {
+            Err(unsupported("capabilities"))
+        }
+
+        async fn schema(
+            &self,
+            _config: &Self::Config,
+            _request: SchemaRequest,
+        ) -> Result<SchemaResponse, ErrorResponse> {
+            Err(unsupported("schemas"))
+        }
+
+        async fn query(
+            &self,
+            _config: &Self::Config,
+            _request: QueryRequest,
+        ) -> Result<QueryResponse, ErrorResponse> {
+            Err(unsupported("queries"))
+        }
+    }
+
+    #[test]
+    fn unimplemented_endpoints_are_unsupported() {
+        let config = json!({});
+        let request: MutationRequest = serde_json::from_str(include_str!(
+            "../fixtures/mutation_requests/update_and_delete.json"
+        ))
+        .unwrap();
+        let error = now(QueryOnly.mutation(&config, request)).unwrap_err();
+        assert_eq!(error.r#type, Some(ErrorResponseType::UnsupportedOperation));
+        assert_eq!(error.message, "this agent does not support mutations");
+        let error = now(QueryOnly.dataset_get_template(&"chinook".to_owned())).unwrap_err();
+        assert_eq!(error.status_code(), 501);
+    }
+
+    #[test]
+    fn agents_are_healthy_by_default() {
+        let request = HealthCheckRequest {
+            check_data_sources: None,
+        };
+        let response = now(QueryOnly.health(None, request)).unwrap();
+        assert_eq!(response.status, HealthStatus::Healthy);
+    }
+
+    #[test]
+    fn decodes_config_headers() {
+        assert_eq!(
+            decode_config_header::<serde_json::Value>(Some(br#"{"db": "a"}"#)).unwrap(),
+            json!({"db": "a"})
+        );
+        let missing = decode_config_header::<serde_json::Value>(None).unwrap_err();
+        assert_eq!(
+            missing.r#type,
+            Some(ErrorResponseType::RequestValidationFailure)
+        );
+        assert_eq!(missing.message, format!("missing {CONFIG_HEADER} header"));
+        let invalid = decode_config_header::<serde_json::Value>(Some(&[0xff])).unwrap_err();
+        assert!(invalid.message.ends_with("is not valid text"));
+    }
+
+    #[test]
+    fn decodes_json_request_bodies() {
+        let body = br#"{"check_data_sources": true}"#;
+        for content_type in [None, Some(&b"application/json; charset=utf-8"[..])] {
+            let request: HealthCheckRequest = decode_request_body(content_type, body).unwrap();
+            assert_eq!(request.check_data_sources, Some(true));
+        }
+        let error =
+            decode_request_body::<HealthCheckRequest>(Some(b"text/plain"), body).unwrap_err();
+        assert_eq!(error.status_code(), 400);
+        let error = decode_request_body::<HealthCheckRequest>(None, b"{").unwrap_err();
+        assert!(error.message.starts_with("invalid request body"));
+    }
+}
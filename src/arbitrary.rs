@@ -0,0 +1,1910 @@
+use std::fmt::Debug;
+
+use proptest::{
+    collection::vec,
+    option,
+    prelude::*,
+    sample::select,
+    strategy::{NewTree, ValueTree},
+    test_runner::TestRunner,
+};
+use serde_json::json;
+
+use crate::{
+    capabilities::{
+        AtomicitySupportLevel, Capabilities, CapabilitiesResponse, ColumnNullability,
+        ComparisonCapabilities, ConfigSchemaResponse, DataSchemaCapabilities, DatasetCapabilities,
+        DeleteCapabilities, ExplainCapabilities, ForeachCapabilities, FunctionArgumentKind,
+        GraphQlType, InsertCapabilities, InterpolatedQueryCapabilities, LicensingCapabilities,
+        MetricsCapabilities, MetricsFormat, MutationCapabilities, PostSchemaCapabilities,
+        QueryCapabilities, RawCapabilities, RelationshipCapabilities, RequestOptionsCapabilities,
+        ReturningCapabilities, ScalarType, ScalarTypeCapabilities, SubqueryComparisonCapabilities,
+        SubscriptionCapabilities, TableName, UpdateCapabilities, UpdateColumnOperatorDefinition,
+        UserDefinedFunctionCapabilities,
+    },
+    licensing::LicensingLevel,
+    map::FieldMap,
+    memory::{MemoryDatabase, MemoryRow},
+    mutation::{
+        InsertFieldSchema, MutationOperation, MutationRequest, ObjectRelationInsertionOrder,
+        RowUpdate, TableInsertSchema,
+    },
+    query::{
+        Aggregate, ArgumentValue, BinaryArrayComparisonOperator, BinaryComparisonOperator,
+        ColumnSelector, ComparisonColumn, ComparisonValue, ExistsInTable, Expression, Field,
+        FunctionRequestArgument, InterpolatedItem, InterpolatedQuery, NullsOrder, OrderBy,
+        OrderByElement, OrderByRelation, OrderByTarget, OrderDirection, Query, QueryRequest,
+        Relationship, RelationshipType, RequestOptions, ScalarValue, TableRelationships, Target,
+        UnaryComparisonOperator,
+    },
+    schema::{
+        ColumnDefault, ColumnInfo, ColumnType, ColumnTypeNonScalar, ColumnValueGenerationStrategy,
+        ComputedColumnInfo, ComputedColumnSource, Constraint, DetailLevel, FunctionInfo,
+        FunctionInformationArgument, FunctionResponseCardinality, FunctionReturnType, FunctionType,
+        ObjectTypeDefinition, ObjectTypeVariant, ParameterMode, PolymorphicObjectType,
+        PolymorphicTypeKind, ProcedureInfo, ProcedureParameter, ProcedureResultSet, SchemaResponse,
+        TableInfo, TableType,
+    },
+};
+
+// Generated values are structurally valid: they serialize and deserialize back to an equal value.
+// They are not consistent with any particular schema, i.e. columns and relationships are named at random
+
+/// Limits on the values generated by the strategies in this module
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StrategyConfig {
+    /// How deeply expressions, subqueries and nested column types may nest
+    pub depth: u32,
+    /// The largest number of elements generated for any list or map
+    pub size: usize,
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self { depth: 3, size: 4 }
+    }
+}
+
+/// Failing requests shrink as usual, and then by their `QueryRequest::shrink_candidates`, so reported failures have no fields, filters or orderings that do not contribute to them
+pub fn arb_query_request(config: StrategyConfig) -> BoxedStrategy<QueryRequest> {
+    let size = config.size;
+    let request = (
+        option::of(vec(
+            map_of(name(), scalar_value(), 1, size),
+            1..=size.max(1),
+        )),
+        option::of(interpolated_queries(size)),
+        query(config, field(config, config.depth)),
+        target(size),
+        vec(table_relationships(size), 0..=size),
+        option::of(map_of(name(), json_value(), 0, size)),
+        option::of(request_options(size)),
+    )
+        .prop_map(
+            |(
+                foreach,
+                interpolated_queries,
+                query,
+                target,
+                relationships,
+                session,
+                request_options,
+            )| QueryRequest {
+                foreach,
+                interpolated_queries,
+                query,
+                target,
+                relationships,
+                session,
+                request_options,
+            },
+        );
+    StructuralShrinking(request).boxed()
+}
+
+pub fn arb_expression(config: StrategyConfig) -> BoxedStrategy<Expression> {
+    let size = config.size;
+    let leaf = prop_oneof![
+        (comparison_column(), unary_operator())
+            .prop_map(|(column, operator)| Expression::ApplyUnaryComparison { column, operator })
+            .boxed(),
+        (
+            comparison_column(),
+            binary_operator(),
+            comparison_value(),
+            option::of(name()),
+        )
+            .prop_map(|(column, operator, value, collation)| {
+                Expression::ApplyBinaryComparison {
+                    column,
+                    operator,
+                    value,
+                    collation,
+                }
+            })
+            .boxed(),
+        (comparison_column(), array_operator(), scalar_type())
+            .prop_flat_map(move |(column, operator, value_type)| {
+                vec(scalar_json(&value_type), 0..=size).prop_map(move |values| {
+                    Expression::ApplyBinaryArrayComparison {
+                        column: column.clone(),
+                        operator: operator.clone(),
+                        value_type: value_type.clone(),
+                        values,
+                    }
+                })
+            })
+            .boxed(),
+        (
+            comparison_column(),
+            comparison_value(),
+            comparison_value(),
+            any::<bool>(),
+            any::<bool>(),
+        )
+            .prop_map(|(column, low, high, low_inclusive, high_inclusive)| {
+                Expression::ApplyBetween {
+                    column,
+                    low,
+                    high,
+                    low_inclusive,
+                    high_inclusive,
+                }
+            })
+            .boxed(),
+    ];
+    leaf.prop_recursive(
+        config.depth,
+        (size as u32 + 1).pow(config.depth.min(4)),
+        size as u32,
+        move |inner| {
+            prop_oneof![
+                vec(inner.clone(), 0..=size)
+                    .prop_map(|expressions| Expression::And { expressions })
+                    .boxed(),
+                vec(inner.clone(), 0..=size)
+                    .prop_map(|expressions| Expression::Or { expressions })
+                    .boxed(),
+                inner
+                    .clone()
+                    .prop_map(|expression| Expression::Not {
+                        expression: Box::new(expression)
+                    })
+                    .boxed(),
+                (exists_in_table(size), inner)
+                    .prop_map(|(in_table, r#where)| {
+                        Expression::Exists {
+                            in_table,
+                            r#where: Box::new(r#where),
+                        }
+                    })
+                    .boxed(),
+            ]
+        },
+    )
+    .boxed()
+}
+
+pub fn arb_schema_response(config: StrategyConfig) -> BoxedStrategy<SchemaResponse> {
+    let size = config.size;
+    (
+        option::of(vec(object_type_definition(config), 0..=size)),
+        vec(table_info(config), 0..=size),
+        option::of(vec(function_info(size), 0..=size)),
+        option::of(vec(procedure_info(config), 0..=size)),
+    )
+        .prop_map(
+            |(object_types, tables, functions, procedures)| SchemaResponse {
+                object_types,
+                tables,
+                functions,
+                procedures,
+            },
+        )
+        .boxed()
+}
+
+pub fn arb_mutation_request(config: StrategyConfig) -> BoxedStrategy<MutationRequest> {
+    let size = config.size;
+    (
+        vec(table_insert_schema(config), 0..=size),
+        vec(mutation_operation(config), 0..=size),
+        vec(table_relationships(size), 0..=size),
+        option::of(atomicity_support_level()),
+        option::of(interpolated_queries(size)),
+        option::of(map_of(name(), json_value(), 0, size)),
+        option::of(request_options(size)),
+    )
+        .prop_map(
+            |(
+                insert_schema,
+                operations,
+                relationships,
+                requested_atomicity,
+                interpolated_queries,
+                session,
+                request_options,
+            )| MutationRequest {
+                insert_schema,
+                operations,
+                relationships,
+                requested_atomicity,
+                interpolated_queries,
+                session,
+                request_options,
+            },
+        )
+        .boxed()
+}
+
+pub fn arb_capabilities_response(config: StrategyConfig) -> BoxedStrategy<CapabilitiesResponse> {
+    (
+        capabilities(config.size),
+        option::of(name()),
+        option::of(name()),
+    )
+        .prop_map(
+            |(capabilities, display_name, release_name)| CapabilitiesResponse {
+                capabilities,
+                config_schemas: config_schemas(),
+                display_name,
+                release_name,
+            },
+        )
+        .boxed()
+}
+
+macro_rules! impl_arbitrary {
+    ($($ty:ty => $strategy:ident),* $(,)?) => {
+        $(
+            impl Arbitrary for $ty {
+                type Parameters = StrategyConfig;
+                type Strategy = BoxedStrategy<Self>;
+
+                fn arbitrary_with(config: StrategyConfig) -> Self::Strategy {
+                    $strategy(config)
+                }
+            }
+        )*
+    };
+}
+
+impl_arbitrary! {
+    CapabilitiesResponse => arb_capabilities_response,
+    Expression => arb_expression,
+    MutationRequest => arb_mutation_request,
+    QueryRequest => arb_query_request,
+    SchemaResponse => arb_schema_response,
+}
+
+const CUSTOM_SCALAR_TYPES: &[&str] = &["geometry", "uuid", "decimal"];
+
+fn name() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9_]{0,7}"
+}
+
+fn qualified_name() -> impl Strategy<Value = TableName> {
+    vec(name(), 1..=2)
+}
+
+fn optional_bool() -> impl Strategy<Value = Option<bool>> {
+    option::of(any::<bool>())
+}
+
+fn small_u64() -> impl Strategy<Value = Option<u64>> {
+    option::of(0..1000u64)
+}
+
+/// A map with between `min` and `size` entries. Duplicate keys are dropped, so maps can be smaller than `min`
+fn map_of<K, V, M>(
+    key: impl Strategy<Value = K>,
+    value: impl Strategy<Value = V>,
+    min: usize,
+    size: usize,
+) -> impl Strategy<Value = M>
+where
+    K: Debug,
+    V: Debug,
+    M: FromIterator<(K, V)> + Debug,
+{
+    vec((key, value), min.min(size)..=size).prop_map(|entries| entries.into_iter().collect())
+}
+
+fn json_value() -> impl Strategy<Value = serde_json::Value> {
+    let leaf = prop_oneof![
+        Just(serde_json::Value::Null),
+        any::<bool>().prop_map(serde_json::Value::from),
+        any::<i64>().prop_map(serde_json::Value::from),
+        "[a-zA-Z0-9 ]{0,8}".prop_map(serde_json::Value::from),
+    ];
+    leaf.prop_recursive(2, 16, 3, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..3).prop_map(serde_json::Value::Array),
+            vec((name(), inner), 0..3)
+                .prop_map(|entries| serde_json::Value::Object(entries.into_iter().collect())),
+        ]
+    })
+}
+
+fn scalar_type() -> impl Strategy<Value = ScalarType> {
+    prop_oneof![
+        Just(ScalarType::String),
+        Just(ScalarType::Number),
+        Just(ScalarType::Bool),
+        Just(ScalarType::DateTime),
+        select(CUSTOM_SCALAR_TYPES).prop_map(|name| ScalarType::Other(name.to_owned())),
+    ]
+}
+
+/// A JSON value of the given scalar type. Custom scalar types may have any value
+fn scalar_json(scalar_type: &ScalarType) -> BoxedStrategy<serde_json::Value> {
+    match scalar_type {
+        ScalarType::String => "[a-zA-Z0-9 ]{0,8}"
+            .prop_map(serde_json::Value::from)
+            .boxed(),
+        ScalarType::Number => any::<i64>().prop_map(serde_json::Value::from).boxed(),
+        ScalarType::Bool => any::<bool>().prop_map(serde_json::Value::from).boxed(),
+        ScalarType::DateTime => "20[0-9]{2}-0[1-9]-1[0-9]T0[0-9]:[0-5][0-9]:00Z"
+            .prop_map(serde_json::Value::from)
+            .boxed(),
+        ScalarType::Other(_) => json_value().boxed(),
+    }
+}
+
+fn scalar_value() -> impl Strategy<Value = ScalarValue> {
+    scalar_type().prop_flat_map(|value_type| {
+        scalar_json(&value_type).prop_map(move |value| ScalarValue {
+            value,
+            value_type: value_type.clone(),
+        })
+    })
+}
+
+fn column_selector() -> impl Strategy<Value = ColumnSelector> {
+    prop_oneof![
+        name().prop_map(ColumnSelector::Name),
+        vec(name(), 2..=3).prop_map(ColumnSelector::Compound),
+    ]
+}
+
+fn comparison_column() -> impl Strategy<Value = ComparisonColumn> {
+    (
+        scalar_type(),
+        column_selector(),
+        option::of(prop_oneof![Just(vec![]), Just(vec!["$".to_owned()])]),
+        option::of(vec(name(), 1..=2)),
+    )
+        .prop_map(|(column_type, name, path, field_path)| ComparisonColumn {
+            column_type,
+            name,
+            path,
+            field_path,
+        })
+}
+
+fn comparison_value() -> impl Strategy<Value = ComparisonValue> {
+    prop_oneof![
+        comparison_column().prop_map(|column| ComparisonValue::Column { column }),
+        scalar_value().prop_map(|ScalarValue { value, value_type }| {
+            ComparisonValue::Scalar { value, value_type }
+        }),
+        (
+            -180..=180i32,
+            -90..=90i32,
+            option::of(Just(4326u32)),
+            option::of((0..10_000i32).prop_map(f64::from)),
+        )
+            .prop_map(|(longitude, latitude, srid, distance)| {
+                ComparisonValue::Geometry {
+                    geometry: json!({ "type": "Point", "coordinates": [longitude, latitude] }),
+                    srid,
+                    distance,
+                }
+            }),
+    ]
+}
+
+fn unary_operator() -> impl Strategy<Value = UnaryComparisonOperator> {
+    prop_oneof![
+        Just(UnaryComparisonOperator::IsNull),
+        Just(UnaryComparisonOperator::Other("_is_empty".to_owned())),
+    ]
+}
+
+fn binary_operator() -> impl Strategy<Value = BinaryComparisonOperator> {
+    select(vec![
+        BinaryComparisonOperator::LessThan,
+        BinaryComparisonOperator::LessThanOrEqual,
+        BinaryComparisonOperator::Equal,
+        BinaryComparisonOperator::GreaterThan,
+        BinaryComparisonOperator::GreaterThanOrEqual,
+        BinaryComparisonOperator::Like,
+        BinaryComparisonOperator::ILike,
+        BinaryComparisonOperator::NotLike,
+        BinaryComparisonOperator::NotILike,
+        BinaryComparisonOperator::Regex,
+        BinaryComparisonOperator::IRegex,
+        BinaryComparisonOperator::StWithin,
+        BinaryComparisonOperator::StIntersects,
+        BinaryComparisonOperator::StDWithin,
+        BinaryComparisonOperator::Other("_similar".to_owned()),
+    ])
+}
+
+fn array_operator() -> impl Strategy<Value = BinaryArrayComparisonOperator> {
+    select(vec![
+        BinaryArrayComparisonOperator::In,
+        BinaryArrayComparisonOperator::NotIn,
+        BinaryArrayComparisonOperator::Contains,
+        BinaryArrayComparisonOperator::ContainedIn,
+        BinaryArrayComparisonOperator::Overlaps,
+        BinaryArrayComparisonOperator::Other("_any".to_owned()),
+    ])
+}
+
+fn target(size: usize) -> impl Strategy<Value = Target> {
+    prop_oneof![
+        qualified_name().prop_map(|name| Target::Table { name }),
+        name().prop_map(|id| Target::Interpolated { id }),
+        (qualified_name(), vec((name(), scalar_value()), 0..=size)).prop_map(
+            |(name, arguments)| Target::Function {
+                name,
+                arguments: arguments
+                    .into_iter()
+                    .map(|(name, ScalarValue { value, value_type })| {
+                        FunctionRequestArgument::Named {
+                            name,
+                            value: ArgumentValue::Scalar { value, value_type },
+                        }
+                    })
+                    .collect(),
+            }
+        ),
+    ]
+}
+
+fn exists_in_table(size: usize) -> impl Strategy<Value = ExistsInTable> {
+    prop_oneof![
+        name().prop_map(|relationship| ExistsInTable::Related { relationship }),
+        target(size).prop_map(|target| ExistsInTable::Unrelated { target }),
+    ]
+}
+
+fn interpolated_queries(size: usize) -> impl Strategy<Value = FieldMap<String, InterpolatedQuery>> {
+    let item = prop_oneof![
+        "[a-zA-Z0-9 ]{0,8}".prop_map(|value| InterpolatedItem::Text { value }),
+        scalar_value().prop_map(|ScalarValue { value, value_type }| {
+            InterpolatedItem::Scalar { value, value_type }
+        }),
+    ];
+    let query =
+        (name(), vec(item, 0..=size)).prop_map(|(id, items)| InterpolatedQuery { id, items });
+    map_of(name(), query, 0, size)
+}
+
+fn request_options(size: usize) -> impl Strategy<Value = RequestOptions> {
+    (
+        small_u64(),
+        small_u64(),
+        option::of(map_of(name(), json_value(), 0, size)),
+    )
+        .prop_map(|(timeout_ms, max_rows, hints)| RequestOptions {
+            timeout_ms,
+            max_rows,
+            hints,
+        })
+}
+
+fn table_relationships(size: usize) -> impl Strategy<Value = TableRelationships> {
+    let relationship = (
+        map_of(name(), name(), 0, size),
+        prop_oneof![
+            Just(RelationshipType::Object),
+            Just(RelationshipType::Array)
+        ],
+        target(size),
+    )
+        .prop_map(|(column_mapping, relationship_type, target)| Relationship {
+            column_mapping,
+            relationship_type,
+            target,
+        });
+    (map_of(name(), relationship, 0, size), qualified_name()).prop_map(
+        |(relationships, source_table)| TableRelationships {
+            relationships,
+            source_table,
+        },
+    )
+}
+
+fn aggregate() -> impl Strategy<Value = Aggregate> {
+    prop_oneof![
+        (name(), any::<bool>())
+            .prop_map(|(column, distinct)| Aggregate::ColumnCount { column, distinct }),
+        (name(), name(), scalar_type()).prop_map(|(column, function, result_type)| {
+            Aggregate::SingleColumn {
+                column,
+                function,
+                result_type,
+            }
+        }),
+        Just(Aggregate::StarCount {}),
+    ]
+}
+
+fn order_by(config: StrategyConfig) -> impl Strategy<Value = OrderBy> {
+    let size = config.size;
+    let target = prop_oneof![
+        (column_selector(), option::of(vec(name(), 1..=2)))
+            .prop_map(|(column, field_path)| OrderByTarget::Column { column, field_path }),
+        (name(), name(), scalar_type()).prop_map(|(column, function, result_type)| {
+            OrderByTarget::SingleColumnAggregate {
+                column,
+                function,
+                result_type,
+            }
+        }),
+        Just(OrderByTarget::StarCountAggregate {}),
+        (name(), any::<bool>()).prop_map(|(column, distinct)| {
+            OrderByTarget::ColumnCountAggregate { column, distinct }
+        }),
+    ];
+    let element = (
+        prop_oneof![Just(OrderDirection::Asc), Just(OrderDirection::Desc)],
+        target,
+        vec(name(), 0..=2),
+        option::of(prop_oneof![Just(NullsOrder::First), Just(NullsOrder::Last)]),
+        option::of(name()),
+    )
+        .prop_map(
+            |(order_direction, target, target_path, nulls, collation)| OrderByElement {
+                order_direction,
+                target,
+                target_path,
+                nulls,
+                collation,
+            },
+        );
+    let expression = arb_expression(config);
+    let relation = option::of(expression.clone())
+        .prop_map(|r#where| OrderByRelation {
+            subrelations: FieldMap::default(),
+            r#where,
+        })
+        .prop_recursive(config.depth, size as u32 * 4, size as u32, move |inner| {
+            (
+                map_of(name(), inner, 0, size),
+                option::of(expression.clone()),
+            )
+                .prop_map(|(subrelations, r#where)| OrderByRelation {
+                    subrelations,
+                    r#where,
+                })
+        });
+    (vec(element, 0..=size), map_of(name(), relation, 0, size)).prop_map(|(elements, relations)| {
+        OrderBy {
+            elements,
+            relations,
+        }
+    })
+}
+
+/// Queries whose fields are drawn from the given strategy
+fn query(config: StrategyConfig, field: BoxedStrategy<Field>) -> BoxedStrategy<Query> {
+    let size = config.size;
+    (
+        option::of(map_of::<_, _, FieldMap<_, _>>(name(), aggregate(), 0, size)).boxed(),
+        small_u64(),
+        option::of(map_of::<_, _, FieldMap<_, _>>(name(), field, 0, size)).boxed(),
+        small_u64(),
+        small_u64(),
+        option::of(order_by(config)).boxed(),
+        option::of(arb_expression(config)).boxed(),
+        option::of(vec(column_selector(), 1..=size.max(1))).boxed(),
+        optional_bool(),
+    )
+        .prop_map(
+            |(
+                aggregates,
+                aggregates_limit,
+                fields,
+                limit,
+                offset,
+                order_by,
+                r#where,
+                distinct_on,
+                include_total_matched,
+            )| Query {
+                aggregates,
+                aggregates_limit,
+                fields,
+                limit,
+                offset,
+                order_by,
+                r#where,
+                distinct_on,
+                include_total_matched,
+            },
+        )
+        .boxed()
+}
+
+/// Fields with subqueries nested at most `depth` levels deep
+fn field(config: StrategyConfig, depth: u32) -> BoxedStrategy<Field> {
+    let column = (name(), scalar_type(), option::of(vec(name(), 1..=2))).prop_map(
+        |(column, column_type, field_path)| Field::Column {
+            column,
+            column_type,
+            field_path,
+        },
+    );
+    if depth == 0 {
+        return column.boxed();
+    }
+    let inner = field(config, depth - 1);
+    let subquery = query(config, inner.clone());
+    // The arms are boxed so that their value trees are not held inline. Unboxed, generating nested fields overflows the default test thread stack in debug builds
+    prop_oneof![
+        column.boxed(),
+        (name(), subquery.clone())
+            .prop_map(|(column, query)| Field::Object { column, query })
+            .boxed(),
+        (
+            inner,
+            small_u64(),
+            small_u64(),
+            option::of(arb_expression(config)),
+            option::of(order_by(config)).boxed(),
+        )
+            .prop_map(|(field, limit, offset, r#where, order_by)| Field::Array {
+                field: Box::new(field),
+                limit,
+                offset,
+                r#where,
+                order_by,
+            })
+            .boxed(),
+        (subquery, name())
+            .prop_map(|(query, relationship)| Field::Relationship {
+                query,
+                relationship,
+            })
+            .boxed(),
+    ]
+    .boxed()
+}
+
+fn column_type(config: StrategyConfig) -> impl Strategy<Value = ColumnType> {
+    scalar_type().prop_map(ColumnType::Scalar).prop_recursive(
+        config.depth,
+        config.depth * 2,
+        2,
+        |inner| {
+            prop_oneof![
+                name().prop_map(|name| {
+                    ColumnType::ColumnTypeNonScalar(ColumnTypeNonScalar::Object { name })
+                }),
+                (inner, any::<bool>()).prop_map(|(element_type, nullable)| {
+                    ColumnType::ColumnTypeNonScalar(ColumnTypeNonScalar::Array {
+                        element_type: Box::new(element_type),
+                        nullable,
+                    })
+                }),
+            ]
+        },
+    )
+}
+
+fn value_generation_strategy() -> impl Strategy<Value = ColumnValueGenerationStrategy> {
+    prop_oneof![
+        Just(ColumnValueGenerationStrategy::AutoIncrement {}),
+        Just(ColumnValueGenerationStrategy::DefaultValue {}),
+        Just(ColumnValueGenerationStrategy::UniqueIdentifier {}),
+    ]
+}
+
+fn column_info(config: StrategyConfig) -> impl Strategy<Value = ColumnInfo> {
+    let computed = (
+        prop_oneof![
+            name().prop_map(|expression| ComputedColumnSource::Expression { expression }),
+            qualified_name().prop_map(|function| ComputedColumnSource::Function { function }),
+        ],
+        optional_bool(),
+        optional_bool(),
+    )
+        .prop_map(|(source, filterable, sortable)| ComputedColumnInfo {
+            source,
+            filterable,
+            sortable,
+        });
+    let default_value = prop_oneof![
+        json_value().prop_map(|value| ColumnDefault::Literal { value }),
+        name().prop_map(|expression| ColumnDefault::Expression { expression }),
+        qualified_name().prop_map(|function| ColumnDefault::Function { function }),
+    ];
+    (
+        option::of(name()),
+        optional_bool(),
+        name(),
+        any::<bool>(),
+        column_type(config),
+        optional_bool(),
+        option::of(value_generation_strategy()),
+        option::of(computed),
+        option::of(default_value),
+        option::of(extensions(config.size)),
+    )
+        .prop_map(
+            |(
+                description,
+                insertable,
+                name,
+                nullable,
+                r#type,
+                updatable,
+                value_generated,
+                computed,
+                default_value,
+                extensions,
+            )| ColumnInfo {
+                description,
+                insertable,
+                name,
+                nullable,
+                r#type,
+                updatable,
+                value_generated,
+                computed,
+                default_value,
+                extensions,
+            },
+        )
+}
+
+fn table_info(config: StrategyConfig) -> impl Strategy<Value = TableInfo> {
+    let size = config.size;
+    let constraint = (map_of(name(), name(), 1, size), qualified_name()).prop_map(
+        |(column_mapping, foreign_table)| Constraint {
+            column_mapping,
+            foreign_table,
+        },
+    );
+    (
+        option::of(vec(column_info(config), 0..=size)),
+        optional_bool(),
+        option::of(name()),
+        option::of(map_of(name(), constraint, 0, size)),
+        optional_bool(),
+        qualified_name(),
+        option::of(vec(name(), 1..=size.max(1))),
+        option::of(prop_oneof![Just(TableType::Table), Just(TableType::View)]),
+        optional_bool(),
+        option::of(extensions(size)),
+    )
+        .prop_map(
+            |(
+                columns,
+                deletable,
+                description,
+                foreign_keys,
+                insertable,
+                name,
+                primary_key,
+                r#type,
+                updatable,
+                extensions,
+            )| TableInfo {
+                columns,
+                deletable,
+                description,
+                foreign_keys,
+                insertable,
+                name,
+                primary_key,
+                r#type,
+                updatable,
+                extensions,
+            },
+        )
+}
+
+fn function_info(size: usize) -> impl Strategy<Value = FunctionInfo> {
+    let argument = (name(), optional_bool(), scalar_type()).prop_map(|(name, optional, r#type)| {
+        FunctionInformationArgument {
+            name,
+            optional,
+            r#type,
+        }
+    });
+    (
+        option::of(vec(argument, 0..=size)),
+        option::of(name()),
+        qualified_name(),
+        option::of(prop_oneof![
+            Just(FunctionResponseCardinality::One),
+            Just(FunctionResponseCardinality::Many),
+        ]),
+        option::of(prop_oneof![
+            qualified_name().prop_map(|table| FunctionReturnType::Table { table }),
+            Just(FunctionReturnType::Unknown {}),
+        ]),
+        prop_oneof![Just(FunctionType::Read), Just(FunctionType::Write)],
+        option::of(extensions(size)),
+    )
+        .prop_map(
+            |(args, description, name, response_cardinality, returns, r#type, extensions)| {
+                FunctionInfo {
+                    args,
+                    description,
+                    name,
+                    response_cardinality,
+                    returns,
+                    r#type,
+                    extensions,
+                }
+            },
+        )
+}
+
+/// Vendor metadata keyed by namespaced names
+fn extensions(size: usize) -> impl Strategy<Value = FieldMap<String, serde_json::Value>> {
+    map_of(
+        (name(), name()).prop_map(|(vendor, key)| format!("{vendor}.{key}")),
+        json_value(),
+        0,
+        size,
+    )
+}
+
+fn procedure_info(config: StrategyConfig) -> impl Strategy<Value = ProcedureInfo> {
+    let size = config.size;
+    let parameter = (
+        name(),
+        scalar_type(),
+        option::of(prop_oneof![
+            Just(ParameterMode::In),
+            Just(ParameterMode::Out),
+            Just(ParameterMode::InOut),
+        ]),
+        optional_bool(),
+        option::of(name()),
+    )
+        .prop_map(
+            |(name, r#type, mode, optional, description)| ProcedureParameter {
+                name,
+                r#type,
+                mode,
+                optional,
+                description,
+            },
+        );
+    let result_set = (
+        option::of(name()),
+        vec(column_info(config), 0..=size),
+        option::of(name()),
+    )
+        .prop_map(|(name, columns, description)| ProcedureResultSet {
+            name,
+            columns,
+            description,
+        });
+    (
+        qualified_name(),
+        option::of(name()),
+        option::of(vec(parameter, 0..=size)),
+        option::of(vec(result_set, 0..=size)),
+        option::of(scalar_type()),
+        option::of(extensions(size)),
+    )
+        .prop_map(
+            |(name, description, parameters, result_sets, return_type, extensions)| ProcedureInfo {
+                name,
+                description,
+                parameters,
+                result_sets,
+                return_type,
+                extensions,
+            },
+        )
+}
+
+fn object_type_definition(config: StrategyConfig) -> impl Strategy<Value = ObjectTypeDefinition> {
+    let size = config.size;
+    let variant = (
+        name(),
+        json_value(),
+        vec(column_info(config), 0..=size),
+        option::of(name()),
+    )
+        .prop_map(
+            |(name, discriminator_value, columns, description)| ObjectTypeVariant {
+                name,
+                discriminator_value,
+                columns,
+                description,
+            },
+        );
+    let polymorphism = (
+        prop_oneof![
+            Just(PolymorphicTypeKind::Union),
+            Just(PolymorphicTypeKind::Interface),
+        ],
+        name(),
+        vec(variant, 0..=size),
+    )
+        .prop_map(
+            |(kind, discriminator_column, variants)| PolymorphicObjectType {
+                kind,
+                discriminator_column,
+                variants,
+            },
+        );
+    (
+        vec(column_info(config), 0..=size),
+        option::of(name()),
+        name(),
+        option::of(polymorphism),
+    )
+        .prop_map(
+            |(columns, description, name, polymorphism)| ObjectTypeDefinition {
+                columns,
+                description,
+                name,
+                polymorphism,
+            },
+        )
+}
+
+fn atomicity_support_level() -> impl Strategy<Value = AtomicitySupportLevel> {
+    prop_oneof![
+        Just(AtomicitySupportLevel::Row),
+        Just(AtomicitySupportLevel::SingleOperation),
+        Just(AtomicitySupportLevel::HomogeneousOperations),
+        Just(AtomicitySupportLevel::HeterogeneousOperations),
+    ]
+}
+
+fn table_insert_schema(config: StrategyConfig) -> impl Strategy<Value = TableInsertSchema> {
+    let size = config.size;
+    let field = prop_oneof![
+        name().prop_map(|relationship| InsertFieldSchema::ArrayRelation { relationship }),
+        (
+            name(),
+            column_type(config),
+            any::<bool>(),
+            option::of(value_generation_strategy()),
+        )
+            .prop_map(|(column, column_type, nullable, value_generated)| {
+                InsertFieldSchema::Column {
+                    column,
+                    column_type,
+                    nullable,
+                    value_generated,
+                }
+            }),
+        (
+            prop_oneof![
+                Just(ObjectRelationInsertionOrder::BeforeParent),
+                Just(ObjectRelationInsertionOrder::AfterParent),
+            ],
+            name(),
+        )
+            .prop_map(|(insertion_order, relationship)| {
+                InsertFieldSchema::ObjectRelation {
+                    insertion_order,
+                    relationship,
+                }
+            }),
+    ];
+    (
+        map_of(name(), field, 0, size),
+        option::of(vec(name(), 1..=size.max(1))),
+        qualified_name(),
+    )
+        .prop_map(|(fields, primary_key, table)| TableInsertSchema {
+            fields,
+            primary_key,
+            table,
+        })
+}
+
+fn mutation_operation(config: StrategyConfig) -> impl Strategy<Value = MutationOperation> {
+    let size = config.size;
+    let returning_fields = option::of(map_of::<_, _, FieldMap<_, _>>(
+        name(),
+        field(config, config.depth),
+        0,
+        size,
+    ))
+    .boxed();
+    let expression = option::of(arb_expression(config)).boxed();
+    let row_update = prop_oneof![
+        (name(), name(), scalar_type()).prop_flat_map(|(column, operator_name, value_type)| {
+            scalar_json(&value_type).prop_map(move |value| RowUpdate::CustomOperator {
+                column: column.clone(),
+                operator_name: operator_name.clone(),
+                value,
+                value_type: value_type.clone(),
+            })
+        }),
+        (name(), scalar_type()).prop_flat_map(|(column, value_type)| {
+            scalar_json(&value_type).prop_map(move |value| RowUpdate::Set {
+                column: column.clone(),
+                value,
+                value_type: value_type.clone(),
+            })
+        }),
+    ];
+    prop_oneof![
+        (
+            returning_fields.clone(),
+            qualified_name(),
+            expression.clone()
+        )
+            .prop_map(
+                |(returning_fields, table, r#where)| MutationOperation::Delete {
+                    returning_fields,
+                    table,
+                    r#where,
+                }
+            ),
+        (
+            expression.clone(),
+            returning_fields.clone(),
+            vec(
+                map_of::<_, _, FieldMap<_, _>>(name(), json_value(), 0, size),
+                0..=size
+            ),
+            qualified_name(),
+        )
+            .prop_map(|(post_insert_check, returning_fields, rows, table)| {
+                MutationOperation::Insert {
+                    post_insert_check,
+                    returning_fields,
+                    rows,
+                    table,
+                }
+            }),
+        (
+            expression.clone(),
+            returning_fields.clone(),
+            qualified_name(),
+            vec(row_update, 0..=size),
+            expression,
+        )
+            .prop_map(
+                |(post_update_check, returning_fields, table, updates, r#where)| {
+                    MutationOperation::Update {
+                        post_update_check,
+                        returning_fields,
+                        table,
+                        updates,
+                        r#where,
+                    }
+                }
+            ),
+        (target(size), returning_fields).prop_map(|(target, returning_fields)| {
+            MutationOperation::Execute {
+                target,
+                returning_fields,
+            }
+        }),
+    ]
+}
+
+fn config_schemas() -> ConfigSchemaResponse {
+    ConfigSchemaResponse {
+        config_schema: serde_json::from_value(json!({ "type": "object", "nullable": false }))
+            .expect("the placeholder config schema is a valid OpenAPI schema"),
+        other_schemas: FieldMap::default(),
+    }
+}
+
+fn capabilities(size: usize) -> impl Strategy<Value = Capabilities> {
+    let comparisons =
+        (
+            option::of(optional_bool().prop_map(|supports_relations| {
+                SubqueryComparisonCapabilities { supports_relations }
+            })),
+            optional_bool(),
+            optional_bool(),
+        )
+            .prop_map(|(subquery, supports_between, supports_geometry_values)| {
+                ComparisonCapabilities {
+                    subquery,
+                    supports_between,
+                    supports_geometry_values,
+                }
+            });
+    let data_schema = (
+        option::of(prop_oneof![
+            Just(ColumnNullability::OnlyNullable),
+            Just(ColumnNullability::NullableAndNonNullable),
+        ]),
+        optional_bool(),
+        optional_bool(),
+        optional_bool(),
+    )
+        .prop_map(
+            |(
+                column_nullability,
+                supports_foreign_keys,
+                supports_primary_keys,
+                supports_schemaless_tables,
+            )| DataSchemaCapabilities {
+                column_nullability,
+                supports_foreign_keys,
+                supports_primary_keys,
+                supports_schemaless_tables,
+            },
+        );
+    let explain = optional_bool().prop_map(|supports_structured_plan| ExplainCapabilities {
+        supports_structured_plan,
+    });
+    let interpolated_queries = (option::of(vec(scalar_type(), 0..=size)), optional_bool())
+        .prop_map(|(supported_scalar_types, supports_relationships)| {
+            InterpolatedQueryCapabilities {
+                supported_scalar_types,
+                supports_relationships,
+            }
+        });
+    let licensing = (
+        optional_bool(),
+        option::of(vec(
+            select(vec![
+                LicensingLevel::Free,
+                LicensingLevel::Trial,
+                LicensingLevel::Standard,
+                LicensingLevel::Enterprise,
+            ]),
+            0..=size,
+        )),
+    )
+        .prop_map(
+            |(requires_license_key, supported_levels)| LicensingCapabilities {
+                requires_license_key,
+                supported_levels,
+            },
+        );
+    let metrics = option::of(vec(
+        prop_oneof![Just(MetricsFormat::Prometheus), Just(MetricsFormat::Json)],
+        0..=size,
+    ))
+    .prop_map(|formats| MetricsCapabilities { formats });
+    let mutations = (
+        option::of(atomicity_support_level()),
+        option::of(
+            optional_bool()
+                .prop_map(|supports_returning| DeleteCapabilities { supports_returning }),
+        ),
+        option::of(
+            optional_bool().prop_map(|supports_nested_inserts| InsertCapabilities {
+                supports_nested_inserts,
+            }),
+        ),
+        option::of(
+            optional_bool().prop_map(|supports_relationships| ReturningCapabilities {
+                supports_relationships,
+            }),
+        ),
+        option::of((optional_bool(), optional_bool()).prop_map(
+            |(supports_post_update_check, supports_custom_operators)| UpdateCapabilities {
+                supports_post_update_check,
+                supports_custom_operators,
+            },
+        )),
+    )
+        .prop_map(
+            |(atomicity_support_level, delete, insert, returning, update)| MutationCapabilities {
+                atomicity_support_level,
+                delete,
+                insert,
+                returning,
+                update,
+            },
+        );
+    let queries = (
+        option::of((small_u64(), optional_bool()).prop_map(
+            |(max_batch_size, supports_mixed_value_types)| ForeachCapabilities {
+                max_batch_size,
+                supports_mixed_value_types,
+            },
+        )),
+        optional_bool(),
+        optional_bool(),
+        optional_bool(),
+    )
+        .prop_map(
+            |(
+                foreach,
+                supports_nested_field_paths,
+                supports_nulls_ordering,
+                supports_distinct_on,
+            )| QueryCapabilities {
+                foreach,
+                supports_nested_field_paths,
+                supports_nulls_ordering,
+                supports_distinct_on,
+            },
+        );
+    let raw = (optional_bool(), option::of(vec(name(), 0..=size))).prop_map(
+        |(supports_mutations, dialects)| RawCapabilities {
+            supports_mutations,
+            dialects,
+        },
+    );
+    let relationships = (
+        optional_bool(),
+        optional_bool(),
+        option::of(0..10u32),
+        map_of(name(), json_value(), 0, size),
+    )
+        .prop_map(
+            |(
+                supports_remote_relationships,
+                supports_relation_comparisons,
+                max_join_depth,
+                extra,
+            )| RelationshipCapabilities {
+                supports_remote_relationships,
+                supports_relation_comparisons,
+                max_join_depth,
+                extra,
+            },
+        );
+    let scalar_types = map_of(scalar_type(), scalar_type_capabilities(size), 0, size);
+    let subscriptions = (option::of(0..100u32), small_u64()).prop_map(
+        |(max_concurrent_subscriptions, keep_alive_interval_ms)| SubscriptionCapabilities {
+            max_concurrent_subscriptions,
+            keep_alive_interval_ms,
+        },
+    );
+    let user_defined_functions = (
+        optional_bool(),
+        optional_bool(),
+        option::of(vec(
+            prop_oneof![
+                Just(FunctionArgumentKind::Scalar),
+                Just(FunctionArgumentKind::Other("table".to_owned())),
+            ],
+            0..=size,
+        )),
+        optional_bool(),
+    )
+        .prop_map(
+            |(
+                supports_write_functions,
+                supports_table_returning_functions,
+                supported_argument_kinds,
+                supports_procedures,
+            )| UserDefinedFunctionCapabilities {
+                supports_write_functions,
+                supports_table_returning_functions,
+                supported_argument_kinds,
+                supports_procedures,
+            },
+        );
+    let post_schema = (
+        optional_bool(),
+        option::of(vec(
+            prop_oneof![Just(DetailLevel::Everything), Just(DetailLevel::BasicInfo)],
+            0..=size,
+        )),
+    )
+        .prop_map(
+            |(supports_filters, supported_detail_levels)| PostSchemaCapabilities {
+                supports_filters,
+                supported_detail_levels,
+            },
+        );
+    let request_options = (
+        optional_bool(),
+        optional_bool(),
+        option::of(vec(name(), 0..=size)),
+    )
+        .prop_map(|(supports_timeout, supports_max_rows, supported_hints)| {
+            RequestOptionsCapabilities {
+                supports_timeout,
+                supports_max_rows,
+                supported_hints,
+            }
+        });
+    (
+        (
+            option::of(comparisons),
+            option::of(data_schema),
+            option::of(Just(DatasetCapabilities {})),
+            option::of(explain),
+            option::of(interpolated_queries),
+            option::of(licensing),
+            option::of(metrics),
+            option::of(mutations),
+        )
+            .boxed(),
+        (
+            option::of(queries),
+            option::of(raw),
+            option::of(relationships),
+            option::of(scalar_types),
+            option::of(subscriptions),
+            option::of(user_defined_functions),
+            option::of(post_schema),
+            option::of(request_options),
+        )
+            .boxed(),
+    )
+        .prop_map(
+            |(
+                (
+                    comparisons,
+                    data_schema,
+                    datasets,
+                    explain,
+                    interpolated_queries,
+                    licensing,
+                    metrics,
+                    mutations,
+                ),
+                (
+                    queries,
+                    raw,
+                    relationships,
+                    scalar_types,
+                    subscriptions,
+                    user_defined_functions,
+                    post_schema,
+                    request_options,
+                ),
+            )| Capabilities {
+                comparisons,
+                data_schema,
+                datasets,
+                explain,
+                interpolated_queries,
+                licensing,
+                metrics,
+                mutations,
+                queries,
+                raw,
+                relationships,
+                scalar_types,
+                subscriptions,
+                user_defined_functions,
+                post_schema,
+                request_options,
+            },
+        )
+}
+
+fn scalar_type_capabilities(size: usize) -> impl Strategy<Value = ScalarTypeCapabilities> {
+    (
+        option::of(map_of(name(), scalar_type(), 0, size)),
+        option::of(map_of(name(), scalar_type(), 0, size)),
+        option::of(select(vec![
+            GraphQlType::Int,
+            GraphQlType::Float,
+            GraphQlType::String,
+            GraphQlType::Boolean,
+            GraphQlType::Id,
+        ])),
+        option::of(map_of(
+            name(),
+            scalar_type()
+                .prop_map(|argument_type| UpdateColumnOperatorDefinition { argument_type }),
+            0,
+            size,
+        )),
+        option::of(vec(array_operator(), 0..=size)),
+        option::of(vec(name(), 0..=size)),
+    )
+        .prop_map(
+            |(
+                aggregate_functions,
+                comparison_operators,
+                graphql_type,
+                update_column_operators,
+                array_comparison_operators,
+                collations,
+            )| ScalarTypeCapabilities {
+                aggregate_functions,
+                comparison_operators,
+                graphql_type,
+                update_column_operators,
+                array_comparison_operators,
+                collations,
+            },
+        )
+}
+
+/// A schema together with rows for its tables and capabilities that agree with it, from `arb_dataset`
+#[derive(Clone, Debug)]
+pub struct GeneratedDataset {
+    pub schema: SchemaResponse,
+    pub capabilities: CapabilitiesResponse,
+    /// The schema's tables, holding the generated rows
+    pub database: MemoryDatabase,
+}
+
+/// Generates between one and `size` tables, each with an integer `id` primary key, up to `size` other scalar columns and up to twice `size` rows. Tables may have a foreign key to an earlier table, whose values refer to existing rows.
+///
+/// The capabilities declare every scalar type used by the schema, with comparison operators, aggregate functions and update operators the `MemoryDatabase` can execute
+pub fn arb_dataset(config: StrategyConfig) -> BoxedStrategy<GeneratedDataset> {
+    let size = config.size.max(1);
+    let columns = vec((name(), builtin_scalar_type(), any::<bool>()), 0..=size);
+    let table = (
+        name(),
+        columns,
+        0..=size * 2,
+        option::of(any::<prop::sample::Index>()),
+    );
+    vec(table, 1..=size)
+        .prop_flat_map(|tables| {
+            let layouts: Vec<TableLayout> = tables
+                .into_iter()
+                .enumerate()
+                .map(
+                    |(index, (name, columns, row_count, foreign_key))| TableLayout {
+                        name: vec![format!("{name}_{index}")],
+                        columns: columns
+                            .into_iter()
+                            .enumerate()
+                            .map(|(column_index, (name, scalar_type, nullable))| {
+                                (format!("{name}_{column_index}"), scalar_type, nullable)
+                            })
+                            .collect(),
+                        row_count,
+                        references: foreign_key
+                            .filter(|_| index > 0)
+                            .map(|target| target.index(index)),
+                    },
+                )
+                .collect();
+            let rows: Vec<_> = layouts.iter().map(|layout| layout.rows(&layouts)).collect();
+            (Just(layouts), rows)
+        })
+        .prop_map(|(layouts, rows)| {
+            let schema = SchemaResponse {
+                object_types: None,
+                tables: layouts
+                    .iter()
+                    .map(|layout| layout.table_info(&layouts))
+                    .collect(),
+                functions: None,
+                procedures: None,
+            };
+            let capabilities = CapabilitiesResponse {
+                capabilities: dataset_capabilities(),
+                config_schemas: config_schemas(),
+                display_name: None,
+                release_name: None,
+            };
+            let mut database =
+                MemoryDatabase::from_schema(&schema).with_capabilities(&capabilities.capabilities);
+            for (table, rows) in schema.tables.iter().zip(rows) {
+                database.insert_table(table.clone(), rows);
+            }
+            GeneratedDataset {
+                schema,
+                capabilities,
+                database,
+            }
+        })
+        .boxed()
+}
+
+impl Arbitrary for GeneratedDataset {
+    type Parameters = StrategyConfig;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(config: StrategyConfig) -> Self::Strategy {
+        arb_dataset(config)
+    }
+}
+
+#[derive(Debug)]
+struct StructuralShrinking<S>(S);
+
+impl<S: Strategy<Value = QueryRequest>> Strategy for StructuralShrinking<S> {
+    type Tree = StructuralShrinkTree<S::Tree>;
+    type Value = QueryRequest;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        self.0.new_tree(runner).map(|inner| StructuralShrinkTree {
+            inner,
+            shrunk: None,
+        })
+    }
+}
+
+/// Simplifies with the inner tree until it cannot, then tries each of the `shrink_candidates` of the smallest failing request in turn
+struct StructuralShrinkTree<T> {
+    inner: T,
+    shrunk: Option<Shrunk>,
+}
+
+struct Shrunk {
+    /// The smallest request known to fail
+    smallest: QueryRequest,
+    /// Candidates not tried yet, in reverse order
+    candidates: Vec<QueryRequest>,
+    /// The candidate being tried, which becomes `smallest` if simplifying continues
+    trying: Option<QueryRequest>,
+}
+
+impl Shrunk {
+    fn new(smallest: QueryRequest) -> Self {
+        let mut candidates = smallest.shrink_candidates();
+        candidates.reverse();
+        Self {
+            smallest,
+            candidates,
+            trying: None,
+        }
+    }
+}
+
+impl<T: ValueTree<Value = QueryRequest>> ValueTree for StructuralShrinkTree<T> {
+    type Value = QueryRequest;
+
+    fn current(&self) -> QueryRequest {
+        match &self.shrunk {
+            None => self.inner.current(),
+            Some(shrunk) => shrunk.trying.as_ref().unwrap_or(&shrunk.smallest).clone(),
+        }
+    }
+
+    fn simplify(&mut self) -> bool {
+        let shrunk = match &mut self.shrunk {
+            Some(shrunk) => shrunk,
+            None if self.inner.simplify() => return true,
+            None => self.shrunk.insert(Shrunk::new(self.inner.current())),
+        };
+        if let Some(still_failing) = shrunk.trying.take() {
+            *shrunk = Shrunk::new(still_failing);
+        }
+        shrunk.trying = shrunk.candidates.pop();
+        shrunk.trying.is_some()
+    }
+
+    fn complicate(&mut self) -> bool {
+        match &mut self.shrunk {
+            None => self.inner.complicate(),
+            Some(shrunk) => shrunk.trying.take().is_some(),
+        }
+    }
+}
+
+fn builtin_scalar_type() -> impl Strategy<Value = ScalarType> {
+    select(vec![
+        ScalarType::String,
+        ScalarType::Number,
+        ScalarType::Bool,
+        ScalarType::DateTime,
+    ])
+}
+
+#[derive(Clone, Debug)]
+struct TableLayout {
+    name: TableName,
+    columns: Vec<(String, ScalarType, bool)>,
+    row_count: usize,
+    /// The earlier table referred to by this table's foreign key column
+    references: Option<usize>,
+}
+
+impl TableLayout {
+    fn foreign_key_column(target: &TableLayout) -> String {
+        format!("{}_id", target.name.join("_"))
+    }
+
+    fn table_info(&self, layouts: &[TableLayout]) -> TableInfo {
+        let column = |name: String, scalar_type: ScalarType, nullable: bool| ColumnInfo {
+            description: None,
+            insertable: Some(true),
+            name,
+            nullable,
+            r#type: ColumnType::Scalar(scalar_type),
+            updatable: Some(true),
+            value_generated: None,
+            computed: None,
+            default_value: None,
+            extensions: None,
+        };
+        let mut columns = vec![ColumnInfo {
+            insertable: Some(false),
+            updatable: Some(false),
+            value_generated: Some(ColumnValueGenerationStrategy::AutoIncrement {}),
+            ..column("id".to_owned(), ScalarType::Number, false)
+        }];
+        columns.extend(self.columns.iter().map(|(name, scalar_type, nullable)| {
+            column(name.clone(), scalar_type.clone(), *nullable)
+        }));
+        let foreign_keys = self.references.map(|target| {
+            let target = &layouts[target];
+            let column_name = Self::foreign_key_column(target);
+            columns.push(column(column_name.clone(), ScalarType::Number, true));
+            [(
+                target.name.join("_"),
+                Constraint {
+                    column_mapping: [(column_name, "id".to_owned())].into_iter().collect(),
+                    foreign_table: target.name.clone(),
+                },
+            )]
+            .into_iter()
+            .collect()
+        });
+        TableInfo {
+            columns: Some(columns),
+            deletable: Some(true),
+            description: None,
+            foreign_keys,
+            insertable: Some(true),
+            name: self.name.clone(),
+            primary_key: Some(vec!["id".to_owned()]),
+            r#type: Some(TableType::Table),
+            updatable: Some(true),
+            extensions: None,
+        }
+    }
+
+    /// Rows with ids counting up from 1, column values of the column's type, and foreign keys referring to rows of the target table
+    fn rows(&self, layouts: &[TableLayout]) -> BoxedStrategy<Vec<MemoryRow>> {
+        let mut names = vec!["id".to_owned()];
+        let mut value_strategies: Vec<BoxedStrategy<serde_json::Value>> =
+            vec![Just(serde_json::Value::Null).boxed()];
+        for (name, scalar_type, nullable) in &self.columns {
+            names.push(name.clone());
+            value_strategies.push(if *nullable {
+                prop_oneof![
+                    1 => Just(serde_json::Value::Null),
+                    3 => scalar_json(scalar_type),
+                ]
+                .boxed()
+            } else {
+                scalar_json(scalar_type)
+            });
+        }
+        if let Some(target) = self.references {
+            let target = &layouts[target];
+            names.push(Self::foreign_key_column(target));
+            value_strategies.push(match target.row_count {
+                0 => Just(serde_json::Value::Null).boxed(),
+                count => prop_oneof![
+                    1 => Just(serde_json::Value::Null),
+                    3 => (1..=count as u64).prop_map(serde_json::Value::from),
+                ]
+                .boxed(),
+            });
+        }
+        vec(value_strategies, self.row_count..=self.row_count)
+            .prop_map(move |rows| {
+                rows.into_iter()
+                    .enumerate()
+                    .map(|(index, mut values)| {
+                        values[0] = serde_json::Value::from(index as u64 + 1);
+                        names.iter().cloned().zip(values).collect()
+                    })
+                    .collect()
+            })
+            .boxed()
+    }
+}
+
+fn dataset_capabilities() -> Capabilities {
+    let operators = |entries: &[(&str, ScalarType)]| {
+        Some(
+            entries
+                .iter()
+                .map(|(name, scalar_type)| (name.to_string(), scalar_type.clone()))
+                .collect(),
+        )
+    };
+    let update_operators = |names: &[&str], argument_type: ScalarType| {
+        Some(
+            names
+                .iter()
+                .map(|name| {
+                    (
+                        name.to_string(),
+                        UpdateColumnOperatorDefinition {
+                            argument_type: argument_type.clone(),
+                        },
+                    )
+                })
+                .collect(),
+        )
+    };
+    let scalar_type = |graphql_type: GraphQlType| ScalarTypeCapabilities {
+        aggregate_functions: None,
+        comparison_operators: None,
+        graphql_type: Some(graphql_type),
+        update_column_operators: None,
+        array_comparison_operators: None,
+        collations: None,
+    };
+    let scalar_types = [
+        (
+            ScalarType::String,
+            ScalarTypeCapabilities {
+                aggregate_functions: operators(&[
+                    ("max", ScalarType::String),
+                    ("min", ScalarType::String),
+                ]),
+                comparison_operators: operators(&[
+                    ("_like", ScalarType::String),
+                    ("_ilike", ScalarType::String),
+                    ("_nlike", ScalarType::String),
+                    ("_nilike", ScalarType::String),
+                ]),
+                update_column_operators: update_operators(
+                    &["append", "prepend"],
+                    ScalarType::String,
+                ),
+                ..scalar_type(GraphQlType::String)
+            },
+        ),
+        (
+            ScalarType::Number,
+            ScalarTypeCapabilities {
+                aggregate_functions: operators(&[
+                    ("max", ScalarType::Number),
+                    ("min", ScalarType::Number),
+                    ("sum", ScalarType::Number),
+                ]),
+                update_column_operators: update_operators(
+                    &["inc", "dec", "mul"],
+                    ScalarType::Number,
+                ),
+                ..scalar_type(GraphQlType::Float)
+            },
+        ),
+        (ScalarType::Bool, scalar_type(GraphQlType::Boolean)),
+        (
+            ScalarType::DateTime,
+            ScalarTypeCapabilities {
+                aggregate_functions: operators(&[
+                    ("max", ScalarType::DateTime),
+                    ("min", ScalarType::DateTime),
+                ]),
+                ..scalar_type(GraphQlType::String)
+            },
+        ),
+    ];
+    Capabilities {
+        comparisons: Some(ComparisonCapabilities {
+            subquery: Some(SubqueryComparisonCapabilities {
+                supports_relations: Some(true),
+            }),
+            supports_between: Some(true),
+            supports_geometry_values: None,
+        }),
+        data_schema: Some(DataSchemaCapabilities {
+            column_nullability: Some(ColumnNullability::NullableAndNonNullable),
+            supports_foreign_keys: Some(true),
+            supports_primary_keys: Some(true),
+            supports_schemaless_tables: None,
+        }),
+        datasets: None,
+        explain: None,
+        interpolated_queries: None,
+        licensing: None,
+        metrics: None,
+        mutations: Some(MutationCapabilities {
+            atomicity_support_level: Some(AtomicitySupportLevel::HeterogeneousOperations),
+            delete: Some(DeleteCapabilities {
+                supports_returning: Some(true),
+            }),
+            insert: Some(InsertCapabilities {
+                supports_nested_inserts: Some(false),
+            }),
+            returning: Some(ReturningCapabilities {
+                supports_relationships: Some(true),
+            }),
+            update: Some(UpdateCapabilities {
+                supports_post_update_check: Some(true),
+                supports_custom_operators: Some(true),
+            }),
+        }),
+        queries: None,
+        raw: None,
+        relationships: Some(RelationshipCapabilities::default()),
+        scalar_types: Some(scalar_types.into_iter().collect()),
+        subscriptions: None,
+        user_defined_functions: None,
+        post_schema: None,
+        request_options: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::test_runner::{Config, TestError};
+
+    use super::*;
+    use crate::contract::check_contract;
+
+    fn round_trips<T>(value: &T) -> Result<(), TestCaseError>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + Debug,
+    {
+        let json = serde_json::to_string(value).unwrap();
+        prop_assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value);
+        Ok(())
+    }
+
+    proptest! {
+        #![proptest_config(Config::with_cases(64))]
+
+        #[test]
+        fn query_requests_round_trip(request in arb_query_request(StrategyConfig::default())) {
+            round_trips(&request)?;
+        }
+
+        #[test]
+        fn mutation_requests_round_trip(request in arb_mutation_request(StrategyConfig::default())) {
+            round_trips(&request)?;
+        }
+
+        #[test]
+        fn schemas_round_trip(schema in arb_schema_response(StrategyConfig::default())) {
+            round_trips(&schema)?;
+        }
+
+        #[test]
+        fn capabilities_round_trip(capabilities in arb_capabilities_response(StrategyConfig::default())) {
+            round_trips(&capabilities)?;
+        }
+
+        #[test]
+        fn expressions_respect_the_depth_limit(expression in arb_expression(StrategyConfig { depth: 2, size: 3 })) {
+            prop_assert!(expression.depth() <= 3);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(Config::with_cases(64))]
+
+        #[test]
+        fn datasets_are_consistent(dataset in arb_dataset(StrategyConfig::default())) {
+            prop_assert_eq!(check_contract(&dataset.capabilities, &dataset.schema), Ok(()));
+            for table in &dataset.schema.tables {
+                let rows = &dataset.database.table(&table.name).unwrap().rows;
+                for (index, row) in rows.iter().enumerate() {
+                    prop_assert_eq!(&row["id"], &json!(index + 1));
+                    for column in table.columns.iter().flatten().filter(|column| !column.nullable) {
+                        prop_assert!(!row[&column.name].is_null());
+                    }
+                }
+                for constraint in table.foreign_keys.iter().flat_map(|keys| keys.values()) {
+                    let target_rows = &dataset.database.table(&constraint.foreign_table).unwrap().rows;
+                    for column in constraint.column_mapping.keys() {
+                        for row in rows {
+                            let value = &row[column];
+                            prop_assert!(
+                                value.is_null() || target_rows.iter().any(|target| &target["id"] == value)
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn failing_requests_shrink_structurally() {
+        let mut runner = TestRunner::deterministic();
+        let result = runner.run(&arb_query_request(StrategyConfig::default()), |request| {
+            prop_assert!(request.query.r#where.is_none());
+            Ok(())
+        });
+        let Err(TestError::Fail(_, minimal)) = result else {
+            panic!("no request with a filter was generated: {result:?}");
+        };
+        assert_eq!(
+            minimal.query.r#where.as_ref().map(Expression::depth),
+            Some(1)
+        );
+        assert_eq!(minimal.query.fields.map_or(0, |fields| fields.len()), 0);
+        assert_eq!(minimal.query.order_by, None);
+    }
+}
@@ -0,0 +1,388 @@
+use std::ops::Index;
+
+use crate::{
+    capabilities::ScalarType,
+    query::{
+        BinaryArrayComparisonOperator, BinaryComparisonOperator, ComparisonColumn, ComparisonValue,
+        ExistsInTable, Expression, UnaryComparisonOperator,
+    },
+};
+
+/// A flattened representation of expression trees, for agents that analyze very large filters.
+///
+/// Nodes are stored contiguously and refer to each other by id rather than through boxes. An arena may hold any number of trees
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ExpressionArena {
+    nodes: Vec<ExpressionNode>,
+    children: Vec<ExpressionId>,
+}
+
+/// The position of a node in an `ExpressionArena`. Only meaningful for the arena that produced it
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ExpressionId(u32);
+
+/// The operands of an `and` or `or` node, resolved with `ExpressionArena::children`
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct ExpressionChildren {
+    start: u32,
+    len: u32,
+}
+
+/// A node of an `ExpressionArena`. Mirrors `Expression`, with subexpressions replaced by ids
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExpressionNode {
+    And {
+        children: ExpressionChildren,
+    },
+    Or {
+        children: ExpressionChildren,
+    },
+    Not {
+        expression: ExpressionId,
+    },
+    ApplyUnaryComparison {
+        column: ComparisonColumn,
+        operator: UnaryComparisonOperator,
+    },
+    ApplyBinaryComparison {
+        column: ComparisonColumn,
+        operator: BinaryComparisonOperator,
+        value: ComparisonValue,
+        collation: Option<String>,
+    },
+    ApplyBinaryArrayComparison {
+        column: ComparisonColumn,
+        operator: BinaryArrayComparisonOperator,
+        value_type: ScalarType,
+        values: Vec<serde_json::Value>,
+    },
+    Exists {
+        in_table: ExistsInTable,
+        r#where: ExpressionId,
+    },
+    ApplyBetween {
+        column: ComparisonColumn,
+        low: ComparisonValue,
+        high: ComparisonValue,
+        low_inclusive: bool,
+        high_inclusive: bool,
+    },
+}
+
+impl ExpressionArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flattens an expression into a new arena, returning the arena and the id of the root node
+    pub fn from_expression(expression: Expression) -> (Self, ExpressionId) {
+        let mut arena = Self::new();
+        let root = arena.insert(expression);
+        (arena, root)
+    }
+
+    /// The number of nodes in the arena
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Flattens an expression into the arena, returning the id of its root node. Subexpressions are added before the nodes that contain them
+    pub fn insert(&mut self, expression: Expression) -> ExpressionId {
+        let node = match expression {
+            Expression::And { expressions } => ExpressionNode::And {
+                children: self.insert_all(expressions),
+            },
+            Expression::Or { expressions } => ExpressionNode::Or {
+                children: self.insert_all(expressions),
+            },
+            Expression::Not { expression } => ExpressionNode::Not {
+                expression: self.insert(*expression),
+            },
+            Expression::ApplyUnaryComparison { column, operator } => {
+                ExpressionNode::ApplyUnaryComparison { column, operator }
+            }
+            Expression::ApplyBinaryComparison {
+                column,
+                operator,
+                value,
+                collation,
+            } => ExpressionNode::ApplyBinaryComparison {
+                column,
+                operator,
+                value,
+                collation,
+            },
+            Expression::ApplyBinaryArrayComparison {
+                column,
+                operator,
+                value_type,
+                values,
+            } => ExpressionNode::ApplyBinaryArrayComparison {
+                column,
+                operator,
+                value_type,
+                values,
+            },
+            Expression::Exists { in_table, r#where } => ExpressionNode::Exists {
+                in_table,
+                r#where: self.insert(*r#where),
+            },
+            Expression::ApplyBetween {
+                column,
+                low,
+                high,
+                low_inclusive,
+                high_inclusive,
+            } => ExpressionNode::ApplyBetween {
+                column,
+                low,
+                high,
+                low_inclusive,
+                high_inclusive,
+            },
+        };
+        self.push(node)
+    }
+
+    fn insert_all(&mut self, expressions: Vec<Expression>) -> ExpressionChildren {
+        let ids: Vec<_> = expressions
+            .into_iter()
+            .map(|expression| self.insert(expression))
+            .collect();
+        self.push_children(ids)
+    }
+
+    /// Adds a node whose subexpressions are already in the arena
+    pub fn push(&mut self, node: ExpressionNode) -> ExpressionId {
+        let id = ExpressionId(to_u32(self.nodes.len()));
+        self.nodes.push(node);
+        id
+    }
+
+    /// Records the operands for a new `and` or `or` node
+    pub fn push_children(
+        &mut self,
+        children: impl IntoIterator<Item = ExpressionId>,
+    ) -> ExpressionChildren {
+        let start = to_u32(self.children.len());
+        self.children.extend(children);
+        ExpressionChildren {
+            start,
+            len: to_u32(self.children.len()) - start,
+        }
+    }
+
+    pub fn get(&self, id: ExpressionId) -> Option<&ExpressionNode> {
+        self.nodes.get(id.0 as usize)
+    }
+
+    pub fn children(&self, children: ExpressionChildren) -> &[ExpressionId] {
+        let start = children.start as usize;
+        &self.children[start..start + children.len as usize]
+    }
+
+    /// The direct subexpressions of a node
+    pub fn subexpressions(&self, id: ExpressionId) -> &[ExpressionId] {
+        match &self[id] {
+            ExpressionNode::And { children } | ExpressionNode::Or { children } => {
+                self.children(*children)
+            }
+            ExpressionNode::Not { expression } => std::slice::from_ref(expression),
+            ExpressionNode::Exists { r#where, .. } => std::slice::from_ref(r#where),
+            ExpressionNode::ApplyUnaryComparison { .. }
+            | ExpressionNode::ApplyBinaryComparison { .. }
+            | ExpressionNode::ApplyBinaryArrayComparison { .. }
+            | ExpressionNode::ApplyBetween { .. } => &[],
+        }
+    }
+
+    /// All nodes in the order they were added, so every node comes after its subexpressions
+    pub fn iter(&self) -> impl Iterator<Item = (ExpressionId, &ExpressionNode)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (ExpressionId(index as u32), node))
+    }
+
+    /// Rebuilds the expression tree rooted at a node
+    pub fn to_expression(&self, id: ExpressionId) -> Expression {
+        let rebuild_all = |children: &ExpressionChildren| {
+            self.children(*children)
+                .iter()
+                .map(|child| self.to_expression(*child))
+                .collect()
+        };
+        match &self[id] {
+            ExpressionNode::And { children } => Expression::And {
+                expressions: rebuild_all(children),
+            },
+            ExpressionNode::Or { children } => Expression::Or {
+                expressions: rebuild_all(children),
+            },
+            ExpressionNode::Not { expression } => Expression::Not {
+                expression: Box::new(self.to_expression(*expression)),
+            },
+            ExpressionNode::ApplyUnaryComparison { column, operator } => {
+                Expression::ApplyUnaryComparison {
+                    column: column.clone(),
+                    operator: operator.clone(),
+                }
+            }
+            ExpressionNode::ApplyBinaryComparison {
+                column,
+                operator,
+                value,
+                collation,
+            } => Expression::ApplyBinaryComparison {
+                column: column.clone(),
+                operator: operator.clone(),
+                value: value.clone(),
+                collation: collation.clone(),
+            },
+            ExpressionNode::ApplyBinaryArrayComparison {
+                column,
+                operator,
+                value_type,
+                values,
+            } => Expression::ApplyBinaryArrayComparison {
+                column: column.clone(),
+                operator: operator.clone(),
+                value_type: value_type.clone(),
+                values: values.clone(),
+            },
+            ExpressionNode::Exists { in_table, r#where } => Expression::Exists {
+                in_table: in_table.clone(),
+                r#where: Box::new(self.to_expression(*r#where)),
+            },
+            ExpressionNode::ApplyBetween {
+                column,
+                low,
+                high,
+                low_inclusive,
+                high_inclusive,
+            } => Expression::ApplyBetween {
+                column: column.clone(),
+                low: low.clone(),
+                high: high.clone(),
+                low_inclusive: *low_inclusive,
+                high_inclusive: *high_inclusive,
+            },
+        }
+    }
+}
+
+/// Panics if the id is not from this arena
+impl Index<ExpressionId> for ExpressionArena {
+    type Output = ExpressionNode;
+
+    fn index(&self, id: ExpressionId) -> &ExpressionNode {
+        &self.nodes[id.0 as usize]
+    }
+}
+
+fn to_u32(index: usize) -> u32 {
+    u32::try_from(index).expect("an expression arena holds at most u32::MAX nodes")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::query::QueryRequest;
+
+    fn mixed() -> Expression {
+        let column = json!({ "path": [], "name": "Milliseconds", "column_type": "number" });
+        serde_json::from_value(json!({
+            "type": "or",
+            "expressions": [
+                {
+                    "type": "binary_arr_op",
+                    "operator": "in",
+                    "column": column,
+                    "value_type": "number",
+                    "values": [1, 2, 3],
+                },
+                {
+                    "type": "between",
+                    "column": column,
+                    "low": { "type": "scalar", "value": 10, "value_type": "number" },
+                    "high": { "type": "scalar", "value": 20, "value_type": "number" },
+                    "high_inclusive": false,
+                },
+                { "type": "and", "expressions": [] },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn fixture_expressions() -> Vec<Expression> {
+        [
+            include_str!("../fixtures/query_requests/array_relationship.json"),
+            include_str!("../fixtures/query_requests/exists_filter.json"),
+        ]
+        .into_iter()
+        .map(|json| {
+            let request: QueryRequest = serde_json::from_str(json).unwrap();
+            request.query.r#where.unwrap()
+        })
+        .chain([mixed()])
+        .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for expression in fixture_expressions() {
+            let (arena, root) = ExpressionArena::from_expression(expression.clone());
+            assert_eq!(arena.to_expression(root), expression);
+        }
+    }
+
+    #[test]
+    fn holds_several_trees() {
+        let mut arena = ExpressionArena::new();
+        let expressions = fixture_expressions();
+        let roots: Vec<_> = expressions
+            .iter()
+            .map(|expression| arena.insert(expression.clone()))
+            .collect();
+        for (root, expression) in roots.into_iter().zip(expressions) {
+            assert_eq!(arena.to_expression(root), expression);
+        }
+    }
+
+    #[test]
+    fn subexpressions_come_before_their_parents() {
+        let (arena, root) = ExpressionArena::from_expression(mixed());
+        assert_eq!(arena.len(), 4);
+        assert_eq!(root, arena.iter().last().unwrap().0);
+        for (id, _) in arena.iter() {
+            assert!(arena.subexpressions(id).iter().all(|child| *child < id));
+        }
+        assert_eq!(arena.subexpressions(root).len(), 3);
+    }
+
+    #[test]
+    fn push_builds_nodes_from_existing_ids() {
+        let (mut arena, first) = ExpressionArena::from_expression(mixed());
+        let negated = arena.push(ExpressionNode::Not { expression: first });
+        let children = arena.push_children([first, negated]);
+        let root = arena.push(ExpressionNode::And { children });
+        assert_eq!(
+            arena.to_expression(root),
+            Expression::And {
+                expressions: vec![
+                    mixed(),
+                    Expression::Not {
+                        expression: Box::new(mixed()),
+                    },
+                ],
+            }
+        );
+        assert_eq!(arena.get(ExpressionId(1000)), None);
+    }
+}
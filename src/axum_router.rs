@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::{rejection::QueryRejection, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Router,
+};
+
+use crate::{
+    agent::{validation_failure, DataConnectorAgent},
+    dataset::{DatasetCloneName, DatasetTemplateName},
+    dispatch::{dispatch, AgentRequest, AgentResponse, Route, SummaryRequested},
+    health::HealthCheckRequest,
+    protocol::CONFIG_HEADER,
+};
+
+/// A router serving every data connector endpoint from the agent.
+///
+/// Requests are decoded from JSON and the `X-Hasura-DataConnector-Config` header, and responses, including errors, are encoded as JSON. Errors are sent as an `ErrorResponse` with the status given by `ErrorResponse::status_code`
+pub fn router<A: DataConnectorAgent + 'static>(agent: A) -> Router {
+    Router::new()
+        .route("/capabilities", get(capabilities::<A>))
+        .route("/schema", get(schema::<A>).post(schema::<A>))
+        .route("/query", post(query::<A>))
+        .route("/explain", post(explain::<A>))
+        .route("/mutation", post(mutation::<A>))
+        .route("/raw", post(raw::<A>))
+        .route("/health", get(health::<A>))
+        .route(
+            "/datasets/templates/{template_name}",
+            get(dataset_get_template::<A>),
+        )
+        .route(
+            "/datasets/clones/{clone_name}",
+            post(dataset_create_clone::<A>).delete(dataset_delete_clone::<A>),
+        )
+        .with_state(Arc::new(agent))
+}
+
+async fn capabilities<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+) -> Response {
+    serve(
+        &*agent,
+        summary,
+        Route::Capabilities,
+        &HeaderMap::new(),
+        &[],
+    )
+    .await
+}
+
+async fn schema<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    serve(&*agent, summary, Route::Schema, &headers, &body).await
+}
+
+async fn query<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    serve(&*agent, summary, Route::Query, &headers, &body).await
+}
+
+async fn explain<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    serve(&*agent, summary, Route::Explain, &headers, &body).await
+}
+
+async fn mutation<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    serve(&*agent, summary, Route::Mutation, &headers, &body).await
+}
+
+async fn raw<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    serve(&*agent, summary, Route::Raw, &headers, &body).await
+}
+
+async fn health<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+    headers: HeaderMap,
+    request: Result<Query<HealthCheckRequest>, QueryRejection>,
+) -> Response {
+    let request = request
+        .map(|Query(request)| request)
+        .map_err(|rejection| validation_failure(rejection.body_text()));
+    serve(&*agent, summary, Route::Health(request), &headers, &[]).await
+}
+
+async fn dataset_get_template<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+    Path(template_name): Path<DatasetTemplateName>,
+) -> Response {
+    let route = Route::DatasetGetTemplate(template_name);
+    serve(&*agent, summary, route, &HeaderMap::new(), &[]).await
+}
+
+async fn dataset_create_clone<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+    Path(clone_name): Path<DatasetCloneName>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    serve(
+        &*agent,
+        summary,
+        Route::DatasetCreateClone(clone_name),
+        &headers,
+        &body,
+    )
+    .await
+}
+
+async fn dataset_delete_clone<A: DataConnectorAgent>(
+    State(agent): State<Arc<A>>,
+    summary: Option<Extension<SummaryRequested>>,
+    Path(clone_name): Path<DatasetCloneName>,
+) -> Response {
+    let route = Route::DatasetDeleteClone(clone_name);
+    serve(&*agent, summary, route, &HeaderMap::new(), &[]).await
+}
+
+async fn serve<A: DataConnectorAgent>(
+    agent: &A,
+    summary: Option<Extension<SummaryRequested>>,
+    route: Route,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Response {
+    let request = AgentRequest {
+        config_header: headers.get(CONFIG_HEADER).map(|value| value.as_bytes()),
+        content_type: headers
+            .get(header::CONTENT_TYPE)
+            .map(|value| value.as_bytes()),
+        body,
+        summary: summary.map(|Extension(summary)| summary),
+    };
+    into_response(dispatch(agent, route, request).await)
+}
+
+fn into_response(response: AgentResponse) -> Response {
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (
+        status,
+        [(header::CONTENT_TYPE, response.content_type)],
+        response.summary.map(Extension),
+        response.body,
+    )
+        .into_response()
+}
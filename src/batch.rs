@@ -0,0 +1,265 @@
+use std::fmt;
+
+use crate::{
+    capabilities::Capabilities,
+    query::{QueryRequest, QueryResponse},
+};
+
+/// A query request split into requests of at most a maximum number of `foreach` rows each, for agents declaring a `max_batch_size`.
+///
+/// Each request is the original with a consecutive slice of its `foreach` rows. Requests without `foreach`, or with few enough rows, are not split
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForeachBatches {
+    requests: Vec<QueryRequest>,
+    /// The number of foreach rows in each request, or `None` if the request was not a foreach query
+    sizes: Option<Vec<usize>>,
+}
+
+impl ForeachBatches {
+    /// Splits the request. A `max_batch_size` of 0 is treated as 1
+    pub fn split(request: &QueryRequest, max_batch_size: usize) -> Self {
+        let Some(foreach) = &request.foreach else {
+            return Self {
+                requests: vec![request.clone()],
+                sizes: None,
+            };
+        };
+        if foreach.len() <= max_batch_size {
+            return Self {
+                requests: vec![request.clone()],
+                sizes: Some(vec![foreach.len()]),
+            };
+        }
+        let (requests, sizes) = foreach
+            .chunks(max_batch_size.max(1))
+            .map(|chunk| {
+                let request = QueryRequest {
+                    foreach: Some(chunk.to_vec()),
+                    ..request.clone()
+                };
+                (request, chunk.len())
+            })
+            .unzip();
+        Self {
+            requests,
+            sizes: Some(sizes),
+        }
+    }
+
+    /// Splits the request by the `max_batch_size` the capabilities declare, if any
+    pub fn for_capabilities(request: &QueryRequest, capabilities: &Capabilities) -> Self {
+        let max_batch_size = capabilities
+            .queries
+            .as_ref()
+            .and_then(|queries| queries.foreach.as_ref())
+            .and_then(|foreach| foreach.max_batch_size)
+            .map_or(usize::MAX, |max| usize::try_from(max).unwrap_or(usize::MAX));
+        Self::split(request, max_batch_size)
+    }
+
+    /// The requests to send, in order
+    pub fn requests(&self) -> &[QueryRequest] {
+        &self.requests
+    }
+
+    /// Combines the responses to the requests, given in the same order, into the response to the original request. The result sets of foreach responses are concatenated
+    pub fn reassemble(
+        &self,
+        responses: Vec<QueryResponse>,
+    ) -> Result<QueryResponse, ForeachBatchError> {
+        if responses.len() != self.requests.len() {
+            return Err(ForeachBatchError::ResponseCountMismatch {
+                expected: self.requests.len(),
+                actual: responses.len(),
+            });
+        }
+        let Some(sizes) = &self.sizes else {
+            return Ok(responses
+                .into_iter()
+                .next()
+                .expect("one request, one response"));
+        };
+        let mut rows = Vec::with_capacity(sizes.iter().sum());
+        for (batch, (response, expected)) in responses.into_iter().zip(sizes).enumerate() {
+            let QueryResponse::ForEach { rows: batch_rows } = response else {
+                return Err(ForeachBatchError::NotForeach { batch });
+            };
+            if batch_rows.len() != *expected {
+                return Err(ForeachBatchError::RowCountMismatch {
+                    batch,
+                    expected: *expected,
+                    actual: batch_rows.len(),
+                });
+            }
+            rows.extend(batch_rows);
+        }
+        Ok(QueryResponse::ForEach { rows })
+    }
+}
+
+/// Responses that cannot be reassembled into a response to the original foreach request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ForeachBatchError {
+    /// A different number of responses than requests was given
+    ResponseCountMismatch { expected: usize, actual: usize },
+    /// The response to a foreach request was not a foreach response
+    NotForeach { batch: usize },
+    /// The response to a foreach request had a different number of result sets than the request had foreach rows
+    RowCountMismatch {
+        batch: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for ForeachBatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ResponseCountMismatch { expected, actual } => {
+                write!(f, "expected {expected} batch responses, got {actual}")
+            }
+            Self::NotForeach { batch } => {
+                write!(f, "the response to batch {batch} is not a foreach response")
+            }
+            Self::RowCountMismatch {
+                batch,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "the response to batch {batch} has {actual} result sets, expected {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ForeachBatchError {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::query::{ForEachRow, ResponseFieldValue, ResponseRow};
+
+    fn request(rows: u64) -> QueryRequest {
+        let mut request: QueryRequest =
+            serde_json::from_str(include_str!("../fixtures/query_requests/foreach.json")).unwrap();
+        request.foreach = Some(
+            (1..=rows)
+                .map(|id| {
+                    serde_json::from_value(
+                        json!({ "ArtistId": { "value": id, "value_type": "number" } }),
+                    )
+                    .unwrap()
+                })
+                .collect(),
+        );
+        request
+    }
+
+    /// The response an agent would send, with one result set per foreach row holding the row's `ArtistId`
+    fn respond(request: &QueryRequest) -> QueryResponse {
+        let rows = request
+            .foreach
+            .iter()
+            .flatten()
+            .map(|row| ForEachRow {
+                query: ResponseRow {
+                    aggregates: None,
+                    rows: Some(vec![[(
+                        "ArtistId".to_owned(),
+                        ResponseFieldValue::column(row["ArtistId"].value.clone()),
+                    )]
+                    .into_iter()
+                    .collect()]),
+                    total_matched: None,
+                },
+            })
+            .collect();
+        QueryResponse::ForEach { rows }
+    }
+
+    #[test]
+    fn splits_into_consecutive_batches() {
+        let batches = ForeachBatches::split(&request(5), 2);
+        let sizes: Vec<_> = batches
+            .requests()
+            .iter()
+            .map(|request| request.foreach.as_ref().unwrap().len())
+            .collect();
+        assert_eq!(sizes, [2, 2, 1]);
+        assert_eq!(
+            batches.requests()[2].foreach.as_ref().unwrap()[0]["ArtistId"].value,
+            json!(5)
+        );
+    }
+
+    #[test]
+    fn small_and_non_foreach_requests_are_not_split() {
+        assert_eq!(
+            ForeachBatches::split(&request(2), 2).requests(),
+            [request(2)]
+        );
+        let mut single = request(0);
+        single.foreach = None;
+        let batches = ForeachBatches::split(&single, 1);
+        assert_eq!(batches.requests(), [single.clone()]);
+        let response = QueryResponse::Single(ResponseRow {
+            aggregates: None,
+            rows: Some(vec![]),
+            total_matched: None,
+        });
+        assert_eq!(batches.reassemble(vec![response.clone()]), Ok(response));
+    }
+
+    #[test]
+    fn reassembles_in_order() {
+        let request = request(5);
+        let batches = ForeachBatches::split(&request, 2);
+        let responses = batches.requests().iter().map(respond).collect();
+        assert_eq!(batches.reassemble(responses), Ok(respond(&request)));
+    }
+
+    #[test]
+    fn splits_by_declared_batch_size() {
+        let capabilities: Capabilities =
+            serde_json::from_value(json!({ "queries": { "foreach": { "max_batch_size": 3 } } }))
+                .unwrap();
+        let batches = ForeachBatches::for_capabilities(&request(7), &capabilities);
+        assert_eq!(batches.requests().len(), 3);
+        let capabilities: Capabilities = serde_json::from_value(json!({})).unwrap();
+        let batches = ForeachBatches::for_capabilities(&request(7), &capabilities);
+        assert_eq!(batches.requests().len(), 1);
+    }
+
+    #[test]
+    fn mismatched_responses_are_rejected() {
+        let batches = ForeachBatches::split(&request(3), 2);
+        let responses: Vec<_> = batches.requests().iter().map(respond).collect();
+        assert_eq!(
+            batches.reassemble(responses[..1].to_vec()),
+            Err(ForeachBatchError::ResponseCountMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert_eq!(
+            batches.reassemble(vec![responses[1].clone(), responses[0].clone()]),
+            Err(ForeachBatchError::RowCountMismatch {
+                batch: 0,
+                expected: 2,
+                actual: 1
+            })
+        );
+        let single = QueryResponse::Single(ResponseRow {
+            aggregates: None,
+            rows: None,
+            total_matched: None,
+        });
+        assert_eq!(
+            batches.reassemble(vec![responses[0].clone(), single]),
+            Err(ForeachBatchError::NotForeach { batch: 1 })
+        );
+    }
+}
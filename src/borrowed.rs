@@ -0,0 +1,599 @@
+use std::{borrow::Cow, fmt};
+
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::Number;
+use serde_with::skip_serializing_none;
+
+use crate::{
+    capabilities::{ColumnName, ScalarType},
+    map::{FieldHasher, FieldMap},
+    query::{
+        default_true, deserialize_legacy_signed, Aggregate, BinaryArrayComparisonOperator,
+        BinaryComparisonOperator, ColumnSelector, ComparisonColumn, ComparisonValue, ExistsInTable,
+        Expression, Field, InterpolatedQuery, OrderBy, Query, QueryRequest, RequestOptions,
+        ScalarValue, TableRelationships, Target, UnaryComparisonOperator,
+    },
+};
+
+// These views mirror the query request types, but read strings in values and foreach column names
+// in place from the input rather than allocating for them. Strings containing escape sequences
+// cannot be borrowed and are still allocated
+
+/// A borrowed view of a `QueryRequest`, for agents that receive very large foreach batches or value lists.
+///
+/// Only the foreach rows are read straight from the input. Fields, expressions and comparison values are internally tagged, so serde first buffers each of them into an intermediate tree, once per level of nesting. Their strings still borrow from the input, but the buffering allocates, so filters with large value lists save less than foreach batches
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QueryRequestRef<'a> {
+    /// If present, a list of columns and values for the columns that the query must be repeated for, applying the column values as a filter for each query.
+    #[serde(borrow, default, deserialize_with = "deserialize_foreach")]
+    pub foreach: Option<Vec<ForeachRowRef<'a>>>,
+    pub interpolated_queries: Option<FieldMap<String, InterpolatedQuery>>,
+    #[serde(borrow)]
+    pub query: QueryRef<'a>,
+    /// The target of the query.
+    pub target: Target,
+    /// The relationships between tables involved in the entire query request
+    pub relationships: Vec<TableRelationships>,
+    /// The session variables of the user making the request, keyed by their lower-cased `x-hasura-*` name
+    pub session: Option<FieldMap<String, serde_json::Value>>,
+    /// Execution limits and hints for the agent. Requires `request_options` capabilities
+    pub request_options: Option<RequestOptions>,
+}
+
+/// The column values for one repetition of a foreach query
+pub type ForeachRowRef<'a> = FieldMap<Cow<'a, str>, ScalarValueRef<'a>>;
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QueryRef<'a> {
+    /// Aggregate fields of the query
+    pub aggregates: Option<FieldMap<String, Aggregate>>,
+    /// Optionally limit the maximum number of rows considered while applying aggregations. This limit does not apply to returned rows.
+    pub aggregates_limit: Option<u64>,
+    /// Fields of the query
+    #[serde(borrow)]
+    pub fields: Option<FieldMap<String, FieldRef<'a>>>,
+    /// Optionally limit the maximum number of returned rows. This limit does not apply to records considered while apply aggregations.
+    pub limit: Option<u64>,
+    /// Optionally offset from the Nth result. This applies to both row and aggregation results.
+    pub offset: Option<u64>,
+    pub order_by: Option<OrderBy>,
+    #[serde(rename = "where", borrow)]
+    pub r#where: Option<ExpressionRef<'a>>,
+    /// Only return the first row of each set of rows with equal values in these columns, as determined by `order_by`. Requires `supports_distinct_on`
+    pub distinct_on: Option<Vec<ColumnSelector>>,
+    /// Whether to return the number of rows matched before `limit` and `offset` were applied, in `ResponseRow.total_matched`
+    pub include_total_matched: Option<bool>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldRef<'a> {
+    Column {
+        column: ColumnName,
+        column_type: ScalarType,
+        /// The path to a nested field within an object typed column. Requires `supports_nested_field_paths`
+        field_path: Option<Vec<String>>,
+    },
+    Object {
+        column: ColumnName,
+        #[serde(borrow)]
+        query: QueryRef<'a>,
+    },
+    #[serde(deserialize_with = "deserialize_array_field")]
+    Array {
+        field: Box<FieldRef<'a>>,
+        /// Optionally limit the maximum number of returned elements
+        limit: Option<u64>,
+        /// Optionally offset from the Nth element
+        offset: Option<u64>,
+        /// Only include array elements matching this expression
+        #[serde(rename = "where")]
+        r#where: Option<ExpressionRef<'a>>,
+        order_by: Option<OrderBy>,
+    },
+    Relationship {
+        #[serde(borrow)]
+        query: QueryRef<'a>,
+        /// The name of the relationship to follow for the subquery
+        relationship: String,
+    },
+}
+
+type ArrayFieldParts<'a> = (
+    Box<FieldRef<'a>>,
+    Option<u64>,
+    Option<u64>,
+    Option<ExpressionRef<'a>>,
+    Option<OrderBy>,
+);
+
+/// Accepts the same legacy array field shapes as `Field`
+fn deserialize_array_field<'de: 'a, 'a, D>(deserializer: D) -> Result<ArrayFieldParts<'a>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct ArrayField<'a> {
+        #[serde(borrow)]
+        field: Box<FieldRef<'a>>,
+        #[serde(default, deserialize_with = "deserialize_legacy_signed")]
+        limit: Option<u64>,
+        #[serde(default, deserialize_with = "deserialize_legacy_signed")]
+        offset: Option<u64>,
+        #[serde(rename = "where", borrow)]
+        r#where: Option<ArrayFieldWhere<'a>>,
+        order_by: Option<OrderBy>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    #[allow(clippy::large_enum_variant)]
+    enum ArrayFieldWhere<'a> {
+        Expression(#[serde(borrow)] ExpressionRef<'a>),
+        LegacyOrderBy(OrderBy),
+    }
+
+    let array = ArrayField::deserialize(deserializer)?;
+    let (r#where, order_by) = match array.r#where {
+        Some(ArrayFieldWhere::Expression(expression)) => (Some(expression), array.order_by),
+        Some(ArrayFieldWhere::LegacyOrderBy(order_by)) => (None, array.order_by.or(Some(order_by))),
+        None => (None, array.order_by),
+    };
+    Ok((array.field, array.limit, array.offset, r#where, order_by))
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExpressionRef<'a> {
+    And {
+        #[serde(borrow)]
+        expressions: Vec<ExpressionRef<'a>>,
+    },
+    Or {
+        #[serde(borrow)]
+        expressions: Vec<ExpressionRef<'a>>,
+    },
+    Not {
+        #[serde(borrow)]
+        expression: Box<ExpressionRef<'a>>,
+    },
+    #[serde(rename = "unary_op")]
+    ApplyUnaryComparison {
+        column: ComparisonColumn,
+        operator: UnaryComparisonOperator,
+    },
+    #[serde(rename = "binary_op")]
+    ApplyBinaryComparison {
+        column: ComparisonColumn,
+        operator: BinaryComparisonOperator,
+        #[serde(borrow)]
+        value: ComparisonValueRef<'a>,
+        /// The collation to compare string values with. Must be one of the `collations` declared for the column's scalar type
+        collation: Option<String>,
+    },
+    #[serde(rename = "binary_arr_op")]
+    ApplyBinaryArrayComparison {
+        column: ComparisonColumn,
+        operator: BinaryArrayComparisonOperator,
+        value_type: ScalarType,
+        #[serde(borrow)]
+        values: Vec<JsonValueRef<'a>>,
+    },
+    Exists {
+        in_table: ExistsInTable,
+        #[serde(rename = "where", borrow)]
+        r#where: Box<ExpressionRef<'a>>,
+    },
+    /// Requires `supports_between`
+    #[serde(rename = "between")]
+    ApplyBetween {
+        column: ComparisonColumn,
+        #[serde(borrow)]
+        low: ComparisonValueRef<'a>,
+        #[serde(borrow)]
+        high: ComparisonValueRef<'a>,
+        /// Whether values equal to `low` match
+        #[serde(default = "default_true")]
+        low_inclusive: bool,
+        /// Whether values equal to `high` match
+        #[serde(default = "default_true")]
+        high_inclusive: bool,
+    },
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ComparisonValueRef<'a> {
+    Column {
+        column: ComparisonColumn,
+    },
+    Scalar {
+        #[serde(borrow)]
+        value: JsonValueRef<'a>,
+        value_type: ScalarType,
+    },
+    /// Requires `supports_geometry_values`
+    Geometry {
+        /// A GeoJSON geometry object
+        #[serde(borrow)]
+        geometry: JsonValueRef<'a>,
+        /// The spatial reference system identifier of the geometry's coordinates. If absent, 4326 (WGS 84) is assumed
+        srid: Option<u32>,
+        /// The distance argument of `_st_d_within`, in the units of the spatial reference system
+        distance: Option<f64>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScalarValueRef<'a> {
+    #[serde(borrow)]
+    pub value: JsonValueRef<'a>,
+    pub value_type: ScalarType,
+}
+
+/// A JSON value whose strings and object keys borrow from the input where possible
+#[derive(Clone, Debug, PartialEq, Default, Serialize)]
+#[serde(untagged)]
+pub enum JsonValueRef<'a> {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(Cow<'a, str>),
+    Array(Vec<JsonValueRef<'a>>),
+    Object(FieldMap<Cow<'a, str>, JsonValueRef<'a>>),
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for JsonValueRef<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonValueRefVisitor)
+    }
+}
+
+struct JsonValueRefVisitor;
+
+impl<'de> Visitor<'de> for JsonValueRefVisitor {
+    type Value = JsonValueRef<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "any JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(JsonValueRef::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(JsonValueRef::Number(value.into()))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(JsonValueRef::Number(value.into()))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(Number::from_f64(value).map_or(JsonValueRef::Null, JsonValueRef::Number))
+    }
+
+    fn visit_borrowed_str<E>(self, value: &'de str) -> Result<Self::Value, E> {
+        Ok(JsonValueRef::String(Cow::Borrowed(value)))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(JsonValueRef::String(Cow::Owned(value.to_owned())))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+        Ok(JsonValueRef::String(Cow::Owned(value)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(JsonValueRef::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(JsonValueRef::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        JsonValueRef::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(JsonValueRef::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut object = FieldMap::with_capacity_and_hasher(
+            map.size_hint().unwrap_or(0),
+            FieldHasher::default(),
+        );
+        while let Some(BorrowedKey(key)) = map.next_key()? {
+            object.insert(key, map.next_value()?);
+        }
+        Ok(JsonValueRef::Object(object))
+    }
+}
+
+/// `Cow<str>` always deserializes to an owned string unless it is a field marked `borrow`
+#[derive(Deserialize, PartialEq, Eq, Hash)]
+struct BorrowedKey<'a>(#[serde(borrow)] Cow<'a, str>);
+
+fn deserialize_foreach<'de: 'a, 'a, D>(
+    deserializer: D,
+) -> Result<Option<Vec<ForeachRowRef<'a>>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let foreach: Option<Vec<FieldMap<BorrowedKey<'a>, ScalarValueRef<'a>>>> =
+        Option::deserialize(deserializer)?;
+    Ok(foreach.map(|rows| {
+        rows.into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(BorrowedKey(key), value)| (key, value))
+                    .collect()
+            })
+            .collect()
+    }))
+}
+
+impl QueryRequestRef<'_> {
+    pub fn into_owned(self) -> QueryRequest {
+        QueryRequest {
+            foreach: self.foreach.map(|rows| {
+                rows.into_iter()
+                    .map(|row| {
+                        row.into_iter()
+                            .map(|(column, value)| (column.into_owned(), value.into_owned()))
+                            .collect()
+                    })
+                    .collect()
+            }),
+            interpolated_queries: self.interpolated_queries,
+            query: self.query.into_owned(),
+            target: self.target,
+            relationships: self.relationships,
+            session: self.session,
+            request_options: self.request_options,
+        }
+    }
+}
+
+impl QueryRef<'_> {
+    pub fn into_owned(self) -> Query {
+        Query {
+            aggregates: self.aggregates,
+            aggregates_limit: self.aggregates_limit,
+            fields: self.fields.map(|fields| {
+                fields
+                    .into_iter()
+                    .map(|(name, field)| (name, field.into_owned()))
+                    .collect()
+            }),
+            limit: self.limit,
+            offset: self.offset,
+            order_by: self.order_by,
+            r#where: self.r#where.map(ExpressionRef::into_owned),
+            distinct_on: self.distinct_on,
+            include_total_matched: self.include_total_matched,
+        }
+    }
+}
+
+impl FieldRef<'_> {
+    pub fn into_owned(self) -> Field {
+        match self {
+            Self::Column {
+                column,
+                column_type,
+                field_path,
+            } => Field::Column {
+                column,
+                column_type,
+                field_path,
+            },
+            Self::Object { column, query } => Field::Object {
+                column,
+                query: query.into_owned(),
+            },
+            Self::Array {
+                field,
+                limit,
+                offset,
+                r#where,
+                order_by,
+            } => Field::Array {
+                field: Box::new(field.into_owned()),
+                limit,
+                offset,
+                r#where: r#where.map(ExpressionRef::into_owned),
+                order_by,
+            },
+            Self::Relationship {
+                query,
+                relationship,
+            } => Field::Relationship {
+                query: query.into_owned(),
+                relationship,
+            },
+        }
+    }
+}
+
+impl ExpressionRef<'_> {
+    pub fn into_owned(self) -> Expression {
+        match self {
+            Self::And { expressions } => Expression::And {
+                expressions: expressions.into_iter().map(Self::into_owned).collect(),
+            },
+            Self::Or { expressions } => Expression::Or {
+                expressions: expressions.into_iter().map(Self::into_owned).collect(),
+            },
+            Self::Not { expression } => Expression::Not {
+                expression: Box::new(expression.into_owned()),
+            },
+            Self::ApplyUnaryComparison { column, operator } => {
+                Expression::ApplyUnaryComparison { column, operator }
+            }
+            Self::ApplyBinaryComparison {
+                column,
+                operator,
+                value,
+                collation,
+            } => Expression::ApplyBinaryComparison {
+                column,
+                operator,
+                value: value.into_owned(),
+                collation,
+            },
+            Self::ApplyBinaryArrayComparison {
+                column,
+                operator,
+                value_type,
+                values,
+            } => Expression::ApplyBinaryArrayComparison {
+                column,
+                operator,
+                value_type,
+                values: values.into_iter().map(JsonValueRef::into_owned).collect(),
+            },
+            Self::Exists { in_table, r#where } => Expression::Exists {
+                in_table,
+                r#where: Box::new(r#where.into_owned()),
+            },
+            Self::ApplyBetween {
+                column,
+                low,
+                high,
+                low_inclusive,
+                high_inclusive,
+            } => Expression::ApplyBetween {
+                column,
+                low: low.into_owned(),
+                high: high.into_owned(),
+                low_inclusive,
+                high_inclusive,
+            },
+        }
+    }
+}
+
+impl ComparisonValueRef<'_> {
+    pub fn into_owned(self) -> ComparisonValue {
+        match self {
+            Self::Column { column } => ComparisonValue::Column { column },
+            Self::Scalar { value, value_type } => ComparisonValue::Scalar {
+                value: value.into_owned(),
+                value_type,
+            },
+            Self::Geometry {
+                geometry,
+                srid,
+                distance,
+            } => ComparisonValue::Geometry {
+                geometry: geometry.into_owned(),
+                srid,
+                distance,
+            },
+        }
+    }
+}
+
+impl ScalarValueRef<'_> {
+    pub fn into_owned(self) -> ScalarValue {
+        ScalarValue {
+            value: self.value.into_owned(),
+            value_type: self.value_type,
+        }
+    }
+}
+
+impl JsonValueRef<'_> {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn into_owned(self) -> serde_json::Value {
+        match self {
+            Self::Null => serde_json::Value::Null,
+            Self::Bool(value) => serde_json::Value::Bool(value),
+            Self::Number(value) => serde_json::Value::Number(value),
+            Self::String(value) => serde_json::Value::String(value.into_owned()),
+            Self::Array(values) => {
+                serde_json::Value::Array(values.into_iter().map(Self::into_owned).collect())
+            }
+            Self::Object(object) => serde_json::Value::Object(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    #[cfg(feature = "example")]
+    use crate::example::Example;
+    use crate::query::QueryRequest;
+
+    use super::QueryRequestRef;
+
+    /// Reading through the borrowed view must not lose anything the owned types model
+    fn assert_mirrored(json: &str) {
+        let owned: QueryRequest = serde_json::from_str(json).unwrap();
+        let borrowed: QueryRequestRef = serde_json::from_str(json).unwrap();
+        assert_eq!(borrowed.into_owned(), owned, "{json}");
+    }
+
+    #[test]
+    fn example_and_fixtures_are_mirrored() {
+        #[cfg(feature = "example")]
+        assert_mirrored(&serde_json::to_string(&QueryRequest::example()).unwrap());
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/query_requests");
+        for entry in fs::read_dir(dir).unwrap() {
+            assert_mirrored(&fs::read_to_string(entry.unwrap().path()).unwrap());
+        }
+    }
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    fn arbitrary_requests_are_mirrored() {
+        use proptest::test_runner::TestRunner;
+
+        // Deeply nested requests need more stack than a test thread has in unoptimized builds
+        std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(|| {
+                let strategy = crate::arbitrary::arb_query_request(Default::default());
+                TestRunner::default()
+                    .run(&strategy, |request| {
+                        assert_mirrored(&serde_json::to_string(&request).unwrap());
+                        Ok(())
+                    })
+                    .unwrap();
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use serde::{
+    ser::{SerializeMap, SerializeSeq},
+    Serialize, Serializer,
+};
+
+use crate::{
+    columnar::ColumnarError,
+    map::FieldMap,
+    query::{ResponseFieldValue, ResponseRow},
+};
+
+/// Builds a `ResponseRow`, allocating the rows vector and each row's map with the expected sizes up front
+#[derive(Clone, Debug, Default)]
+pub struct ResponseRowBuilder {
+    aggregates: Option<FieldMap<String, serde_json::Value>>,
+    rows: Option<Vec<FieldMap<String, ResponseFieldValue>>>,
+    fields_per_row: usize,
+    total_matched: Option<u64>,
+}
+
+impl ResponseRowBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A builder for a result set with rows, given the expected number of rows and fields in each row
+    pub fn with_capacity(row_count: usize, fields_per_row: usize) -> Self {
+        Self {
+            rows: Some(Vec::with_capacity(row_count)),
+            fields_per_row,
+            ..Self::default()
+        }
+    }
+
+    /// Pre-sizes the aggregates map for the expected number of aggregates
+    pub fn with_aggregate_capacity(mut self, aggregate_count: usize) -> Self {
+        self.aggregates = Some(FieldMap::with_capacity_and_hasher(
+            aggregate_count,
+            Default::default(),
+        ));
+        self
+    }
+
+    /// An empty row sized for the expected number of fields
+    pub fn new_row(&self) -> FieldMap<String, ResponseFieldValue> {
+        FieldMap::with_capacity_and_hasher(self.fields_per_row, Default::default())
+    }
+
+    pub fn push_row(&mut self, row: FieldMap<String, ResponseFieldValue>) {
+        self.rows.get_or_insert_with(Vec::new).push(row);
+    }
+
+    pub fn push_aggregate(&mut self, name: impl Into<String>, value: serde_json::Value) {
+        self.aggregates
+            .get_or_insert_with(FieldMap::default)
+            .insert(name.into(), value);
+    }
+
+    pub fn total_matched(mut self, total_matched: u64) -> Self {
+        self.total_matched = Some(total_matched);
+        self
+    }
+
+    pub fn build(self) -> ResponseRow {
+        ResponseRow {
+            aggregates: self.aggregates,
+            rows: self.rows,
+            total_matched: self.total_matched,
+        }
+    }
+}
+
+/// Rows that all have the same fields in the same order. The field names are stored once rather than in every row, which avoids hashing them when building and serializing wide results.
+///
+/// Serializes to the same JSON as the row-wise `ResponseRow.rows`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SharedLayoutRows {
+    fields: Arc<[String]>,
+    values: Vec<ResponseFieldValue>,
+    row_count: usize,
+}
+
+impl SharedLayoutRows {
+    pub fn new(fields: impl Into<Arc<[String]>>) -> Self {
+        Self::with_capacity(fields, 0)
+    }
+
+    /// Rows with the given fields, with space for the expected number of rows
+    pub fn with_capacity(fields: impl Into<Arc<[String]>>, row_count: usize) -> Self {
+        let fields = fields.into();
+        let values = Vec::with_capacity(fields.len() * row_count);
+        Self {
+            fields,
+            values,
+            row_count: 0,
+        }
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.row_count
+    }
+
+    /// Adds a row, with one value per field in field order
+    pub fn push_row(
+        &mut self,
+        values: impl IntoIterator<Item = ResponseFieldValue>,
+    ) -> Result<(), ColumnarError> {
+        let start = self.values.len();
+        self.values.extend(values);
+        if self.values.len() - start != self.fields.len() {
+            self.values.truncate(start);
+            return Err(ColumnarError::FieldMismatch {
+                row: self.row_count,
+            });
+        }
+        self.row_count += 1;
+        Ok(())
+    }
+
+    /// The values of each row, in field order
+    pub fn rows(&self) -> impl Iterator<Item = &[ResponseFieldValue]> {
+        let width = self.fields.len();
+        (0..self.row_count).map(move |row| &self.values[row * width..(row + 1) * width])
+    }
+
+    pub fn into_rows(self) -> Vec<FieldMap<String, ResponseFieldValue>> {
+        let mut values = self.values.into_iter();
+        (0..self.row_count)
+            .map(|_| self.fields.iter().cloned().zip(values.by_ref()).collect())
+            .collect()
+    }
+}
+
+impl From<SharedLayoutRows> for Vec<FieldMap<String, ResponseFieldValue>> {
+    fn from(rows: SharedLayoutRows) -> Self {
+        rows.into_rows()
+    }
+}
+
+impl Serialize for SharedLayoutRows {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut rows = serializer.serialize_seq(Some(self.row_count))?;
+        for values in self.rows() {
+            rows.serialize_element(&SharedLayoutRow {
+                fields: &self.fields,
+                values,
+            })?;
+        }
+        rows.end()
+    }
+}
+
+struct SharedLayoutRow<'a> {
+    fields: &'a [String],
+    values: &'a [ResponseFieldValue],
+}
+
+impl Serialize for SharedLayoutRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut row = serializer.serialize_map(Some(self.fields.len()))?;
+        for (field, value) in self.fields.iter().zip(self.values) {
+            row.serialize_entry(field, value)?;
+        }
+        row.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn builds_response_row() {
+        let mut builder = ResponseRowBuilder::with_capacity(1, 1).total_matched(5);
+        let mut row = builder.new_row();
+        row.insert("id".to_owned(), ResponseFieldValue::Column(json!(1)));
+        builder.push_row(row);
+        builder.push_aggregate("count", json!(5));
+        assert_eq!(
+            serde_json::to_value(builder.build()).unwrap(),
+            json!({
+                "aggregates": {"count": 5},
+                "rows": [{"id": 1}],
+                "total_matched": 5,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(ResponseRowBuilder::new().build()).unwrap(),
+            json!({})
+        );
+    }
+
+    #[test]
+    fn shared_layout_rows_serialize_like_row_wise_rows() {
+        let mut rows = SharedLayoutRows::with_capacity(vec!["id".to_owned(), "name".to_owned()], 2);
+        rows.push_row([
+            ResponseFieldValue::Column(json!(1)),
+            ResponseFieldValue::Column(json!("a")),
+        ])
+        .unwrap();
+        rows.push_row([
+            ResponseFieldValue::Column(json!(2)),
+            ResponseFieldValue::Column(json!("b")),
+        ])
+        .unwrap();
+        assert_eq!(rows.row_count(), 2);
+        let expected = json!([{"id": 1, "name": "a"}, {"id": 2, "name": "b"}]);
+        assert_eq!(serde_json::to_value(&rows).unwrap(), expected);
+        assert_eq!(serde_json::to_value(rows.into_rows()).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_rows_with_the_wrong_width() {
+        let mut rows = SharedLayoutRows::new(vec!["id".to_owned(), "name".to_owned()]);
+        assert_eq!(
+            rows.push_row([ResponseFieldValue::Column(json!(1))]),
+            Err(ColumnarError::FieldMismatch { row: 0 })
+        );
+        assert_eq!(rows.row_count(), 0);
+        assert_eq!(rows.rows().count(), 0);
+        assert_eq!(serde_json::to_value(&rows).unwrap(), json!([]));
+    }
+}
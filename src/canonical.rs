@@ -0,0 +1,158 @@
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    capabilities::CapabilitiesResponse,
+    dataset::{
+        DatasetCreateCloneRequest, DatasetCreateCloneResponse, DatasetDeleteCloneResponse,
+        DatasetGetTemplateResponse,
+    },
+    error::ErrorResponse,
+    explain::ExplainResponse,
+    health::{HealthCheckRequest, HealthCheckResponse},
+    licensing::{
+        LicenseActivationRequest, LicenseActivationResponse, LicenseHeartbeatRequest,
+        LicenseHeartbeatResponse, LicenseKeyRequest, LicenseKeyResponse,
+    },
+    metrics::MetricsResponse,
+    mutation::{MutationRequest, MutationResponse},
+    query::{QueryRequest, QueryResponse},
+    raw::{RawRequest, RawResponse},
+    schema::{SchemaRequest, SchemaResponse},
+    subscription::{SubscriptionClientMessage, SubscriptionServerMessage},
+};
+
+/// Protocol documents that can be compared independently of map key order and number representation.
+pub trait Canonical: Serialize {
+    /// The canonical JSON form of this value: object keys are sorted and numbers with an exact integer value are represented as integers.
+    fn canonical(&self) -> Value {
+        to_canonical_value(self).expect("protocol types always serialize to JSON")
+    }
+    /// Whether both values are equal once converted to their canonical form
+    fn semantic_eq(&self, other: &Self) -> bool {
+        self.canonical() == other.canonical()
+    }
+    /// The canonical JSON text of this value, which is the same for documents that are `semantic_eq`
+    fn canonical_json(&self) -> String {
+        to_canonical_json(self).expect("protocol types always serialize to JSON")
+    }
+}
+
+macro_rules! impl_canonical {
+    ($($t:ty),* $(,)?) => {
+        $(impl Canonical for $t {})*
+    };
+}
+
+impl_canonical!(
+    CapabilitiesResponse,
+    DatasetCreateCloneRequest,
+    DatasetCreateCloneResponse,
+    DatasetDeleteCloneResponse,
+    DatasetGetTemplateResponse,
+    ErrorResponse,
+    ExplainResponse,
+    HealthCheckRequest,
+    HealthCheckResponse,
+    LicenseActivationRequest,
+    LicenseActivationResponse,
+    LicenseHeartbeatRequest,
+    LicenseHeartbeatResponse,
+    LicenseKeyRequest,
+    LicenseKeyResponse,
+    MetricsResponse,
+    MutationRequest,
+    MutationResponse,
+    QueryRequest,
+    QueryResponse,
+    RawRequest,
+    RawResponse,
+    SchemaRequest,
+    SchemaResponse,
+    SubscriptionClientMessage,
+    SubscriptionServerMessage,
+);
+
+/// Serializes any value and converts the result to its canonical form
+pub fn to_canonical_value<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<Value> {
+    serde_json::to_value(value).map(canonicalize)
+}
+
+/// Serializes any value to compact JSON text with sorted object keys and normalized numbers, suitable for snapshots and cache keys
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<String> {
+    to_canonical_value(value).and_then(|value| serde_json::to_string(&value))
+}
+
+/// Recursively sorts object keys and normalizes numbers
+pub fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(canonicalize).collect()),
+        Value::Number(number) => Value::Number(canonicalize_number(number)),
+        other => other,
+    }
+}
+
+fn canonicalize_number(number: Number) -> Number {
+    match number.as_f64() {
+        Some(float) if number.is_f64() && float.fract() == 0.0 => {
+            if float >= 0.0 && float < u64::MAX as f64 {
+                Number::from(float as u64)
+            } else if float < 0.0 && float >= i64::MIN as f64 {
+                Number::from(float as i64)
+            } else {
+                number
+            }
+        }
+        _ => number,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn sorts_keys_at_any_depth() {
+        let value = json!({ "b": [{ "d": 1, "c": 2 }], "a": { "f": null, "e": true } });
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            r#"{"a":{"e":true,"f":null},"b":[{"c":2,"d":1}]}"#
+        );
+    }
+
+    #[test]
+    fn normalizes_integral_floats() {
+        let value = json!([1.0, -2.0, 1.5, 0.0, 1e300, u64::MAX]);
+        assert_eq!(canonicalize(value), json!([1, -2, 1.5, 0, 1e300, u64::MAX]));
+    }
+
+    #[test]
+    fn reordered_documents_are_semantically_equal() {
+        let json = include_str!("../fixtures/query_requests/array_relationship.json");
+        let request: QueryRequest = serde_json::from_str(json).unwrap();
+        let mut reordered = request.clone();
+        reordered.query.fields.as_mut().unwrap().reverse();
+        assert_ne!(
+            serde_json::to_string(&request).unwrap(),
+            serde_json::to_string(&reordered).unwrap()
+        );
+        assert!(request.semantic_eq(&reordered));
+        assert_eq!(request.canonical_json(), reordered.canonical_json());
+
+        let mut changed = request.clone();
+        changed.query.limit = Some(4);
+        assert!(!request.semantic_eq(&changed));
+    }
+}
@@ -1,59 +1,116 @@
-use indexmap::IndexMap;
-use openapiv3::Schema as OpenApiSchema;
 use serde::{Deserialize, Serialize};
+use serde_enum_str::{
+    Deserialize_enum_str as DeserializeEnumStr, Serialize_enum_str as SerializeEnumStr,
+};
 use serde_with::skip_serializing_none;
 
+use crate::{
+    licensing::LicensingLevel,
+    map::FieldMap,
+    query::BinaryArrayComparisonOperator,
+    schema::{DetailLevel, SchemaRequest},
+};
+
 /// Possibly qualified table name. Must be non-empty
 pub type TableName = Vec<String>;
 /// Possibly qualified function name. Must be non-empty
 pub type FunctionName = Vec<String>;
 /// The name of a column
 pub type ColumnName = String;
-pub type ScalarType = String;
 pub type AggregateFunction = String;
 pub type ComparisonOperator = String;
 pub type UpdateOperator = String;
 
+/// The name of a scalar type. The types built into HGE are available as variants, and any other type declared in `scalar_types` is represented by `Other`
+#[derive(
+    Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, SerializeEnumStr, DeserializeEnumStr,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ScalarType {
+    String,
+    Number,
+    Bool,
+    #[serde(rename = "datetime")]
+    DateTime,
+    #[serde(other)]
+    Other(String),
+}
+
+impl ScalarType {
+    /// The scalar type with the given name, using a well-known variant where one matches
+    pub fn from_name(name: &str) -> Self {
+        name.parse()
+            .unwrap_or_else(|_| Self::Other(name.to_owned()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Bool => "bool",
+            Self::DateTime => "datetime",
+            Self::Other(name) => name,
+        }
+    }
+}
+
+/// A capabilities response, generic over the type of its config schemas and of the agent-specific relationship capabilities. These are `OpenApiSchema` and `serde_json::Value` by default. With the `raw_value` feature, use `CapabilitiesResponse<RawJson, RawJson>` to pass them through as JSON text instead of building value trees
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct CapabilitiesResponse {
-    pub capabilities: Capabilities,
-    pub config_schemas: ConfigSchemaResponse,
+pub struct CapabilitiesResponse<S = OpenApiSchema, V = serde_json::Value> {
+    pub capabilities: Capabilities<V>,
+    pub config_schemas: ConfigSchemaResponse<S>,
     pub display_name: Option<String>,
     pub release_name: Option<String>,
 }
 
+/// An OpenAPI schema object. Kept as plain JSON without the `openapi` feature
+#[cfg(feature = "openapi")]
+pub type OpenApiSchema = openapiv3::Schema;
+/// An OpenAPI schema object. Kept as plain JSON without the `openapi` feature
+#[cfg(not(feature = "openapi"))]
+pub type OpenApiSchema = serde_json::Value;
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct ConfigSchemaResponse {
-    pub config_schema: OpenApiSchema,
-    pub other_schemas: IndexMap<String, OpenApiSchema>,
+pub struct ConfigSchemaResponse<S = OpenApiSchema> {
+    pub config_schema: S,
+    pub other_schemas: FieldMap<String, S>,
 }
 
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct Capabilities {
+pub struct Capabilities<V = serde_json::Value> {
     pub comparisons: Option<ComparisonCapabilities>,
     pub data_schema: Option<DataSchemaCapabilities>,
-    pub datasets: Option<serde_json::Value>,
-    pub explain: Option<serde_json::Value>,
-    pub interpolated_queries: Option<serde_json::Value>,
-    pub licensing: Option<serde_json::Value>,
-    pub metrics: Option<serde_json::Value>,
+    pub datasets: Option<DatasetCapabilities>,
+    pub explain: Option<ExplainCapabilities>,
+    pub interpolated_queries: Option<InterpolatedQueryCapabilities>,
+    pub licensing: Option<LicensingCapabilities>,
+    pub metrics: Option<MetricsCapabilities>,
     pub mutations: Option<MutationCapabilities>,
     pub queries: Option<QueryCapabilities>,
-    pub raw: Option<serde_json::Value>,
-    pub relationships: Option<serde_json::Value>,
+    pub raw: Option<RawCapabilities>,
+    pub relationships: Option<RelationshipCapabilities<V>>,
     /// A map from scalar type names to their capabilities. Keys must be valid GraphQL names and must be defined as scalar types in the `graphql_schema`
-    pub scalar_types: Option<IndexMap<ScalarType, ScalarTypeCapabilities>>,
-    pub subscriptions: Option<serde_json::Value>,
-    pub user_defined_functions: Option<serde_json::Value>,
-    pub post_schema: Option<serde_json::Value>,
+    pub scalar_types: Option<FieldMap<ScalarType, ScalarTypeCapabilities>>,
+    pub subscriptions: Option<SubscriptionCapabilities>,
+    pub user_defined_functions: Option<UserDefinedFunctionCapabilities>,
+    pub post_schema: Option<PostSchemaCapabilities>,
+    pub request_options: Option<RequestOptionsCapabilities>,
 }
 
+/// The agent supports the dataset template and clone endpoints
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct DatasetCapabilities {}
+
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ComparisonCapabilities {
     pub subquery: Option<SubqueryComparisonCapabilities>,
+    /// Whether `between` expressions are supported
+    pub supports_between: Option<bool>,
+    /// Whether `geometry` comparison values and the `_st_*` geospatial operators are supported
+    pub supports_geometry_values: Option<bool>,
 }
 
 #[skip_serializing_none]
@@ -81,14 +138,55 @@ pub enum ColumnNullability {
     NullableAndNonNullable,
 }
 
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ExplainCapabilities {
+    /// Whether explain responses include a structured `plan` alongside the formatted lines
+    pub supports_structured_plan: Option<bool>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct InterpolatedQueryCapabilities {
+    /// The scalar types that may be used for `scalar` interpolated items. If absent, any scalar type declared in `scalar_types` may be used
+    pub supported_scalar_types: Option<Vec<ScalarType>>,
+    /// Whether interpolated queries may be the target of relationships
+    pub supports_relationships: Option<bool>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct LicensingCapabilities {
+    /// Whether the agent refuses to serve requests until it has been sent a valid license key
+    pub requires_license_key: Option<bool>,
+    /// The licensing levels the agent can be activated with
+    pub supported_levels: Option<Vec<LicensingLevel>>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct MetricsCapabilities {
+    /// The formats `GET /metrics` can respond with. If absent, only the Prometheus text format is supported
+    pub formats: Option<Vec<MetricsFormat>>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsFormat {
+    /// The Prometheus text exposition format
+    Prometheus,
+    /// A JSON `MetricsResponse` document
+    Json,
+}
+
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MutationCapabilities {
     pub atomicity_support_level: Option<AtomicitySupportLevel>,
-    pub delete: Option<serde_json::Value>,
+    pub delete: Option<DeleteCapabilities>,
     pub insert: Option<InsertCapabilities>,
-    pub returning: Option<serde_json::Value>,
-    pub update: Option<serde_json::Value>,
+    pub returning: Option<ReturningCapabilities>,
+    pub update: Option<UpdateCapabilities>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
@@ -107,10 +205,136 @@ pub struct InsertCapabilities {
     pub supports_nested_inserts: Option<bool>,
 }
 
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct DeleteCapabilities {
+    /// Whether delete operations can return the deleted rows via `returning_fields`
+    pub supports_returning: Option<bool>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ReturningCapabilities {
+    /// Whether `returning_fields` may include relationship fields
+    pub supports_relationships: Option<bool>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct UpdateCapabilities {
+    /// Whether update operations support `post_update_check`
+    pub supports_post_update_check: Option<bool>,
+    /// Whether `custom_operator` row updates using the `update_column_operators` declared in `scalar_types` are supported. If false, only `set` updates may be sent
+    pub supports_custom_operators: Option<bool>,
+}
+
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct QueryCapabilities {
-    pub foreach: Option<serde_json::Value>,
+    pub foreach: Option<ForeachCapabilities>,
+    /// Whether `field_path` may be used to select, filter and order by fields nested within object typed columns
+    pub supports_nested_field_paths: Option<bool>,
+    /// Whether order by elements may specify where nulls are placed
+    pub supports_nulls_ordering: Option<bool>,
+    /// Whether queries may specify `distinct_on`
+    pub supports_distinct_on: Option<bool>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ForeachCapabilities {
+    /// The maximum number of entries the agent accepts in `QueryRequest.foreach`. HGE should split larger batches into multiple requests
+    pub max_batch_size: Option<u64>,
+    /// Whether the foreach entries of a single request may use different value types for the same column
+    pub supports_mixed_value_types: Option<bool>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct PostSchemaCapabilities {
+    /// Whether `SchemaRequest.filters` are honoured
+    pub supports_filters: Option<bool>,
+    /// The detail levels that may be requested in `SchemaRequest.detail_level`. `everything` is always supported
+    pub supported_detail_levels: Option<Vec<DetailLevel>>,
+}
+
+impl PostSchemaCapabilities {
+    /// Whether an agent with these capabilities can fully honour the given request
+    pub fn accepts(&self, request: &SchemaRequest) -> bool {
+        let filters_ok = request.filters.is_none() || self.supports_filters == Some(true);
+        let detail_level_ok = match &request.detail_level {
+            None | Some(DetailLevel::Everything) => true,
+            Some(level) => self
+                .supported_detail_levels
+                .as_ref()
+                .is_some_and(|levels| levels.contains(level)),
+        };
+        filters_ok && detail_level_ok
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct RawCapabilities {
+    /// Whether raw queries may modify data. If absent or false, HGE should only send read-only raw queries
+    pub supports_mutations: Option<bool>,
+    /// The query languages accepted in `RawRequest.query`, e.g. "sql" or "mongodb-aggregation"
+    pub dialects: Option<Vec<String>>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct RelationshipCapabilities<V = serde_json::Value> {
+    /// Whether relationships may target tables in other data sources
+    pub supports_remote_relationships: Option<bool>,
+    /// Whether comparisons may refer to columns across relationships
+    pub supports_relation_comparisons: Option<bool>,
+    /// The maximum number of relationships that may be followed from the query table
+    pub max_join_depth: Option<u32>,
+    /// Agent-specific relationship capabilities not modelled above
+    #[serde(flatten)]
+    pub extra: FieldMap<String, V>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct RequestOptionsCapabilities {
+    /// Whether the agent honours `RequestOptions.timeout_ms`
+    pub supports_timeout: Option<bool>,
+    /// Whether the agent honours `RequestOptions.max_rows`
+    pub supports_max_rows: Option<bool>,
+    /// The names of the `RequestOptions.hints` the agent understands
+    pub supported_hints: Option<Vec<String>>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct SubscriptionCapabilities {
+    /// The maximum number of subscriptions that may be active on a single stream
+    pub max_concurrent_subscriptions: Option<u32>,
+    /// How often the agent sends keep-alive messages on an idle stream, in milliseconds
+    pub keep_alive_interval_ms: Option<u64>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct UserDefinedFunctionCapabilities {
+    /// Whether functions of type `write` are supported, i.e. as mutation targets
+    pub supports_write_functions: Option<bool>,
+    /// Whether functions may return rows of a table
+    pub supports_table_returning_functions: Option<bool>,
+    /// The kinds of argument values functions accept. If absent, only scalar arguments are supported
+    pub supported_argument_kinds: Option<Vec<FunctionArgumentKind>>,
+    /// Whether the schema may describe stored procedures
+    pub supports_procedures: Option<bool>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, SerializeEnumStr, DeserializeEnumStr)]
+#[serde(rename_all = "snake_case")]
+pub enum FunctionArgumentKind {
+    Scalar,
+    #[serde(other)]
+    Other(String),
 }
 
 /// ScalarTypeCapabilities : Capabilities of a scalar type. comparison_operators: The comparison operators supported by the scalar type. aggregate_functions: The aggregate functions supported by the scalar type. update_column_operators: The update column operators supported by the scalar type. graphql_type: Associates the custom scalar type with one of the built-in GraphQL scalar types.  If a `graphql_type` is specified then HGE will use the parser for that built-in type when parsing values of the custom type. If not given then any JSON value will be accepted.
@@ -118,12 +342,16 @@ pub struct QueryCapabilities {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ScalarTypeCapabilities {
     /// A map from aggregate function names to their result types. Function and result type names must be valid GraphQL names. Result type names must be defined scalar types declared in ScalarTypesCapabilities.
-    pub aggregate_functions: Option<IndexMap<AggregateFunction, ScalarType>>,
+    pub aggregate_functions: Option<FieldMap<AggregateFunction, ScalarType>>,
     /// A map from comparison operator names to their argument types. Operator and argument type names must be valid GraphQL names. Argument type names must be defined scalar types declared in ScalarTypesCapabilities.
-    pub comparison_operators: Option<IndexMap<ComparisonOperator, ScalarType>>,
+    pub comparison_operators: Option<FieldMap<ComparisonOperator, ScalarType>>,
     pub graphql_type: Option<GraphQlType>,
     /// A map from update column operator names to their definitions. Operator names must be valid GraphQL names.
-    pub update_column_operators: Option<IndexMap<UpdateOperator, UpdateColumnOperatorDefinition>>,
+    pub update_column_operators: Option<FieldMap<UpdateOperator, UpdateColumnOperatorDefinition>>,
+    /// The array comparison operators supported by the scalar type, other than `in` which is always supported
+    pub array_comparison_operators: Option<Vec<BinaryArrayComparisonOperator>>,
+    /// The collations that may be requested when comparing or ordering values of the scalar type
+    pub collations: Option<Vec<String>>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
@@ -138,5 +366,5 @@ pub enum GraphQlType {
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct UpdateColumnOperatorDefinition {
-    pub argument_type: String,
+    pub argument_type: ScalarType,
 }
@@ -0,0 +1,372 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::{header, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Limited};
+use tower::{Layer, Service};
+
+use crate::{
+    capabilities::{Capabilities, CapabilitiesResponse},
+    error::{ErrorResponse, ErrorResponseType},
+    mutation::MutationRequest,
+    protocol::Endpoint,
+    query::QueryRequest,
+    schema::SchemaRequest,
+    validate::{ErrorCode, ValidationError},
+};
+
+/// A tower layer that rejects requests using features the agent's capabilities do not declare, before they reach the agent.
+///
+/// Requests to `/query`, `/explain` and `/mutation` are checked with `check_capabilities`, `POST /schema` requests with `PostSchemaCapabilities::accepts`, and requests to `/explain`, `/mutation`, `/raw` and `/datasets` are rejected outright if the endpoint is not supported. Rejected requests are answered with an `unsupported-operation` `ErrorResponse` listing the problems in `details.errors`. Bodies that fail to parse are passed on, so the agent can report them.
+///
+/// Checked bodies are read in full before the agent sees them, up to a limit of 2 MiB by default, the same as axum's `DefaultBodyLimit`. Larger bodies are answered with `413 Payload Too Large`, and bodies that fail to read with `400 Bad Request`, both as `request-validation-failure` `ErrorResponse`s
+#[derive(Clone, Debug)]
+pub struct CapabilitiesLayer {
+    capabilities: Arc<Capabilities>,
+    body_limit: usize,
+}
+
+impl CapabilitiesLayer {
+    pub fn new(capabilities: &CapabilitiesResponse) -> Self {
+        Self {
+            capabilities: Arc::new(capabilities.capabilities.clone()),
+            body_limit: DEFAULT_BODY_LIMIT,
+        }
+    }
+
+    /// The largest request body, in bytes, that is read to check it
+    pub fn with_body_limit(mut self, body_limit: usize) -> Self {
+        self.body_limit = body_limit;
+        self
+    }
+}
+
+const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+impl<S> Layer<S> for CapabilitiesLayer {
+    type Service = CapabilitiesService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CapabilitiesService {
+            inner,
+            capabilities: self.capabilities.clone(),
+            body_limit: self.body_limit,
+        }
+    }
+}
+
+/// The service produced by `CapabilitiesLayer`
+#[derive(Clone, Debug)]
+pub struct CapabilitiesService<S> {
+    inner: S,
+    capabilities: Arc<Capabilities>,
+    body_limit: usize,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CapabilitiesService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    ReqBody: http_body::Body + From<Bytes> + Send + 'static,
+    ReqBody::Data: Send,
+    ReqBody::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    ResBody: From<Bytes>,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let capabilities = self.capabilities.clone();
+        let body_limit = self.body_limit;
+        // The ready service is the one that must be called, so a fresh clone is left in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let Some(endpoint) = checked_endpoint(request.method(), request.uri().path()) else {
+                return inner.call(request).await;
+            };
+            if let Err(message) = supported(endpoint, &capabilities) {
+                return Ok(reject(message, &[]));
+            }
+            if !has_body(endpoint) {
+                return inner.call(request).await;
+            }
+            let (parts, body) = request.into_parts();
+            let body = match Limited::new(body, body_limit).collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(error) if error.is::<http_body_util::LengthLimitError>() => {
+                    let message = format!("request body exceeds the limit of {body_limit} bytes");
+                    return Ok(read_failure(StatusCode::PAYLOAD_TOO_LARGE, message));
+                }
+                Err(error) => {
+                    let message = format!("failed to read request body: {error}");
+                    return Ok(read_failure(StatusCode::BAD_REQUEST, message));
+                }
+            };
+            if let Err(errors) = check(endpoint, &capabilities, &body) {
+                let message = errors
+                    .iter()
+                    .map(ValidationError::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                return Ok(reject(message, &errors));
+            }
+            inner.call(Request::from_parts(parts, body.into())).await
+        })
+    }
+}
+
+/// The endpoints whose requests are checked: every `POST` endpoint, and the dataset endpoints
+fn checked_endpoint(method: &Method, path: &str) -> Option<Endpoint> {
+    match Endpoint::of_path(path)? {
+        Endpoint::Capabilities | Endpoint::Health => None,
+        Endpoint::Datasets => Some(Endpoint::Datasets),
+        endpoint => (*method == Method::POST).then_some(endpoint),
+    }
+}
+
+fn has_body(endpoint: Endpoint) -> bool {
+    matches!(
+        endpoint,
+        Endpoint::Query | Endpoint::Explain | Endpoint::Mutation | Endpoint::Schema
+    )
+}
+
+/// Whether the agent serves the endpoint at all
+fn supported(endpoint: Endpoint, capabilities: &Capabilities) -> Result<(), String> {
+    let (supported, feature) = match endpoint {
+        Endpoint::Capabilities | Endpoint::Health | Endpoint::Query | Endpoint::Schema => {
+            (true, "")
+        }
+        Endpoint::Explain => (capabilities.explain.is_some(), "explain"),
+        Endpoint::Mutation => (capabilities.mutations.is_some(), "mutations"),
+        Endpoint::Raw => (capabilities.raw.is_some(), "raw queries"),
+        Endpoint::Datasets => (capabilities.datasets.is_some(), "datasets"),
+    };
+    if supported {
+        Ok(())
+    } else {
+        Err(format!("this agent does not support {feature}"))
+    }
+}
+
+fn check(
+    endpoint: Endpoint,
+    capabilities: &Capabilities,
+    body: &[u8],
+) -> Result<(), Vec<ValidationError>> {
+    match endpoint {
+        Endpoint::Query | Endpoint::Explain => match serde_json::from_slice::<QueryRequest>(body) {
+            Ok(request) => request.check_capabilities(capabilities),
+            Err(_) => Ok(()),
+        },
+        Endpoint::Mutation => match serde_json::from_slice::<MutationRequest>(body) {
+            Ok(request) => request.check_capabilities(capabilities),
+            Err(_) => Ok(()),
+        },
+        Endpoint::Schema => {
+            let Ok(request) = serde_json::from_slice::<SchemaRequest>(body) else {
+                return Ok(());
+            };
+            let post_schema = capabilities.post_schema.clone().unwrap_or_default();
+            if post_schema.accepts(&request) {
+                return Ok(());
+            }
+            let filters_ok =
+                request.filters.is_none() || post_schema.supports_filters == Some(true);
+            let (path, message) = if filters_ok {
+                (
+                    "detail_level",
+                    "the requested detail level is not supported",
+                )
+            } else {
+                ("filters", "schema filters are not supported")
+            };
+            Err(vec![ValidationError {
+                path: path.to_owned(),
+                code: ErrorCode::UnsupportedFeature,
+                message: message.to_owned(),
+            }])
+        }
+        Endpoint::Capabilities | Endpoint::Health | Endpoint::Raw | Endpoint::Datasets => Ok(()),
+    }
+}
+
+fn reject<B: From<Bytes>>(message: String, errors: &[ValidationError]) -> Response<B> {
+    let details = (!errors.is_empty()).then(|| {
+        let errors = errors
+            .iter()
+            .map(|error| {
+                serde_json::json!({
+                    "path": error.path,
+                    "code": error.code,
+                    "message": error.message,
+                })
+            })
+            .collect();
+        [("errors".to_owned(), serde_json::Value::Array(errors))]
+            .into_iter()
+            .collect()
+    });
+    let error = ErrorResponse {
+        details,
+        message,
+        r#type: Some(ErrorResponseType::UnsupportedOperation),
+    };
+    let status = StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::NOT_IMPLEMENTED);
+    json_response(status, &error)
+}
+
+fn read_failure<B: From<Bytes>>(status: StatusCode, message: String) -> Response<B> {
+    let error = ErrorResponse {
+        details: None,
+        message,
+        r#type: Some(ErrorResponseType::RequestValidationFailure),
+    };
+    json_response(status, &error)
+}
+
+fn json_response<B: From<Bytes>>(status: StatusCode, error: &ErrorResponse) -> Response<B> {
+    let body = serde_json::to_vec(error).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Bytes::from(body).into())
+        .expect("status and header are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, pin::pin, task::Waker};
+
+    use http_body_util::Full;
+    use serde_json::json;
+
+    use super::*;
+
+    /// Echoes the request body, so tests can tell whether a request reached the agent
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<Request<Full<Bytes>>> for Echo {
+        type Response = Response<Full<Bytes>>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<Full<Bytes>>) -> Self::Future {
+            Box::pin(async move {
+                let body = request.into_body().collect().await?.to_bytes();
+                Ok(Response::new(Full::new(body)))
+            })
+        }
+    }
+
+    fn capabilities(capabilities: serde_json::Value) -> CapabilitiesResponse {
+        serde_json::from_value(json!({
+            "capabilities": capabilities,
+            "config_schemas": {"config_schema": {}, "other_schemas": {}},
+        }))
+        .unwrap()
+    }
+
+    /// Sends the request through the layer. The echo service and full bodies never wait
+    fn send(
+        layer: &CapabilitiesLayer,
+        method: Method,
+        path: &str,
+        body: &str,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Full::new(Bytes::from(body.to_owned())))
+            .unwrap();
+        let future = layer.layer(Echo).call(request);
+        let Poll::Ready(Ok(response)) = pin!(future).poll(&mut Context::from_waker(Waker::noop()))
+        else {
+            panic!("the response is not ready");
+        };
+        let status = response.status();
+        let future = response.into_body().collect();
+        let Poll::Ready(Ok(body)) = pin!(future).poll(&mut Context::from_waker(Waker::noop()))
+        else {
+            panic!("the body is not ready");
+        };
+        let body = body.to_bytes();
+        let body = serde_json::from_slice(&body)
+            .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned().into());
+        (status, body)
+    }
+
+    #[test]
+    fn passes_supported_requests_through() {
+        let layer = CapabilitiesLayer::new(&capabilities(json!({"queries": {"foreach": {}}})));
+        let query = include_str!("../fixtures/query_requests/foreach.json");
+        let (status, body) = send(&layer, Method::POST, "/query", query);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            serde_json::from_str::<serde_json::Value>(query).unwrap()
+        );
+        let (status, _) = send(&layer, Method::GET, "/capabilities", "");
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = send(&layer, Method::POST, "/query", "not json");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "not json");
+    }
+
+    #[test]
+    fn rejects_undeclared_features() {
+        let layer = CapabilitiesLayer::new(&capabilities(json!({})));
+        let query = include_str!("../fixtures/query_requests/foreach.json");
+        let (status, body) = send(&layer, Method::POST, "/query", query);
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(body["type"], "unsupported-operation");
+        assert!(!body["details"]["errors"].as_array().unwrap().is_empty());
+
+        let (status, body) = send(&layer, Method::POST, "/mutation", "{}");
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(body["message"], "this agent does not support mutations");
+        let (status, _) = send(&layer, Method::GET, "/datasets/templates/chinook", "");
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+    }
+
+    #[test]
+    fn checks_schema_requests() {
+        let layer = CapabilitiesLayer::new(&capabilities(json!({})));
+        let (status, _) = send(&layer, Method::POST, "/schema", "{}");
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = send(
+            &layer,
+            Method::POST,
+            "/schema",
+            r#"{"filters": {"only_tables": [["Artist"]]}}"#,
+        );
+        assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(body["details"]["errors"][0]["path"], "filters");
+    }
+
+    #[test]
+    fn limits_checked_bodies() {
+        let layer = CapabilitiesLayer::new(&capabilities(json!({}))).with_body_limit(8);
+        let (status, body) = send(&layer, Method::POST, "/query", "{\"target\": 1}");
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["type"], "request-validation-failure");
+        let (status, _) = send(&layer, Method::POST, "/metrics", "{\"target\": 1}");
+        assert_eq!(status, StatusCode::OK);
+    }
+}
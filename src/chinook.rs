@@ -0,0 +1,124 @@
+use indexmap::IndexMap;
+
+use crate::{
+    capabilities::CapabilitiesResponse,
+    memory::{MemoryDatabase, MemoryRow},
+    query::TableRelationships,
+    schema::SchemaResponse,
+};
+
+/// The schema of the sample dataset, as an agent would return it from `GET /schema`
+pub const SCHEMA_JSON: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/datasets/chinook/schema.json"
+));
+/// The relationships between the dataset's tables, in the shape of `QueryRequest.relationships`
+pub const RELATIONSHIPS_JSON: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/datasets/chinook/relationships.json"
+));
+/// The rows of each table, keyed by table name
+pub const ROWS_JSON: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/datasets/chinook/rows.json"
+));
+/// Capabilities that agree with the schema and are supported by `MemoryDatabase`
+pub const CAPABILITIES_JSON: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/datasets/chinook/capabilities.json"
+));
+
+/// The tables of a music store, modelled on the Chinook database used by HGE's agent test suite: artists, albums, tracks, genres and media types, and the employees, customers and invoices of the store. `Employee.Contact` is an object typed column
+pub fn schema() -> SchemaResponse {
+    serde_json::from_str(SCHEMA_JSON).expect("the Chinook schema is a valid schema response")
+}
+
+/// Object and array relationships in both directions along each foreign key, i.e. `Artist.Albums` and `Album.Artist`, and `Employee.Manager` and `Employee.Reports`
+pub fn relationships() -> Vec<TableRelationships> {
+    serde_json::from_str(RELATIONSHIPS_JSON)
+        .expect("the Chinook relationships are valid table relationships")
+}
+
+pub fn capabilities() -> CapabilitiesResponse {
+    serde_json::from_str(CAPABILITIES_JSON)
+        .expect("the Chinook capabilities are a valid capabilities response")
+}
+
+/// The rows of a table, or `None` if the dataset has no table with that name
+pub fn rows(table: &str) -> Option<Vec<MemoryRow>> {
+    all_rows().shift_remove(table)
+}
+
+/// A `MemoryDatabase` holding every table of the dataset, with the scalar types of `capabilities()`
+pub fn database() -> MemoryDatabase {
+    let mut rows = all_rows();
+    let schema = schema();
+    let mut database = MemoryDatabase::new().with_capabilities(&capabilities().capabilities);
+    for table in schema.tables {
+        let table_rows = table
+            .name
+            .last()
+            .and_then(|name| rows.shift_remove(name))
+            .unwrap_or_default();
+        database.insert_table(table, table_rows);
+    }
+    database
+}
+
+fn all_rows() -> IndexMap<String, Vec<MemoryRow>> {
+    serde_json::from_str(ROWS_JSON).expect("the Chinook rows are valid JSON objects")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{contract::check_contract, query::Target};
+
+    #[test]
+    fn capabilities_agree_with_the_schema() {
+        assert_eq!(check_contract(&capabilities(), &schema()), Ok(()));
+    }
+
+    #[test]
+    fn rows_only_use_declared_columns() {
+        for table in schema().tables {
+            let name = table.name.last().unwrap();
+            let rows = rows(name).unwrap_or_else(|| panic!("no rows for {name}"));
+            assert!(!rows.is_empty(), "{name} has no rows");
+            let columns: Vec<_> = table.columns.iter().flatten().map(|c| &c.name).collect();
+            for row in rows {
+                for column in row.keys() {
+                    assert!(columns.contains(&column), "{name} has no column {column}");
+                }
+            }
+        }
+        assert!(rows("Playlist").is_none());
+    }
+
+    #[test]
+    fn relationships_refer_to_schema_tables() {
+        let tables: Vec<_> = schema()
+            .tables
+            .into_iter()
+            .map(|table| table.name)
+            .collect();
+        for relationships in relationships() {
+            assert!(tables.contains(&relationships.source_table));
+            for relationship in relationships.relationships.values() {
+                let Target::Table { name } = &relationship.target else {
+                    panic!("relationship to a non-table target");
+                };
+                assert!(tables.contains(name));
+            }
+        }
+    }
+
+    #[test]
+    fn database_holds_every_table() {
+        let database = database();
+        for table in schema().tables {
+            let rows = rows(table.name.last().unwrap()).unwrap();
+            assert_eq!(database.table(&table.name).unwrap().rows, rows);
+        }
+    }
+}
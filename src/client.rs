@@ -0,0 +1,210 @@
+use reqwest::{header, Client, Method, RequestBuilder, Url};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    batch::ForeachBatches,
+    capabilities::CapabilitiesResponse,
+    dataset::{
+        DatasetCloneName, DatasetCreateCloneRequest, DatasetCreateCloneResponse,
+        DatasetDeleteCloneResponse, DatasetGetTemplateResponse, DatasetTemplateName,
+    },
+    error::{ErrorResponse, ErrorResponseType},
+    explain::ExplainResponse,
+    health::{HealthCheckRequest, HealthCheckResponse},
+    mutation::{MutationRequest, MutationResponse},
+    protocol::{ConfigHeader, SourceName, CONFIG_HEADER, SOURCE_NAME_HEADER},
+    query::{QueryRequest, QueryResponse},
+    raw::{RawRequest, RawResponse},
+    schema::{SchemaRequest, SchemaResponse},
+};
+
+/// A client for the endpoints of a data connector agent, sending the config and source name headers with every request.
+///
+/// Errors sent by the agent are decoded into the `ErrorResponse` it sent. Failures to reach the agent or decode its response are reported as `uncaught-error` errors
+#[derive(Clone, Debug)]
+pub struct AgentClient {
+    client: Client,
+    base_url: Url,
+    config: Option<String>,
+    source_name: Option<SourceName>,
+}
+
+impl AgentClient {
+    /// A client for the agent served at the base url, e.g. `http://localhost:8100`
+    pub fn new(base_url: Url) -> Self {
+        Self {
+            client: Client::new(),
+            base_url,
+            config: None,
+            source_name: None,
+        }
+    }
+
+    /// Sends requests with this client, i.e. to share a connection pool or set timeouts
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sends the config in the `X-Hasura-DataConnector-Config` header
+    pub fn with_config<C: Serialize>(mut self, config: &C) -> serde_json::Result<Self> {
+        self.config = Some(ConfigHeader(config).to_header_value()?);
+        Ok(self)
+    }
+
+    /// Sends the source name in the `X-Hasura-DataConnector-SourceName` header
+    pub fn with_source_name(mut self, source_name: SourceName) -> Self {
+        self.source_name = Some(source_name);
+        self
+    }
+
+    /// `GET /capabilities`
+    pub async fn capabilities(&self) -> Result<CapabilitiesResponse, ErrorResponse> {
+        self.send(self.request(Method::GET, &["capabilities"])?)
+            .await
+    }
+
+    /// `POST /schema`
+    pub async fn schema(&self, request: &SchemaRequest) -> Result<SchemaResponse, ErrorResponse> {
+        self.send_json(Method::POST, &["schema"], request).await
+    }
+
+    /// `POST /query`
+    pub async fn query(&self, request: &QueryRequest) -> Result<QueryResponse, ErrorResponse> {
+        self.send_json(Method::POST, &["query"], request).await
+    }
+
+    /// Sends a foreach query as requests of at most `max_batch_size` foreach rows each, one after another, and reassembles their result sets in order. Use `ForeachBatches::for_capabilities` to split by the agent's declared batch size instead
+    pub async fn query_foreach_batched(
+        &self,
+        request: &QueryRequest,
+        max_batch_size: usize,
+    ) -> Result<QueryResponse, ErrorResponse> {
+        let batches = ForeachBatches::split(request, max_batch_size);
+        let mut responses = Vec::with_capacity(batches.requests().len());
+        for request in batches.requests() {
+            responses.push(self.query(request).await?);
+        }
+        batches
+            .reassemble(responses)
+            .map_err(|error| uncaught(format!("failed to reassemble foreach batches: {error}")))
+    }
+
+    /// `POST /explain`
+    pub async fn explain(&self, request: &QueryRequest) -> Result<ExplainResponse, ErrorResponse> {
+        self.send_json(Method::POST, &["explain"], request).await
+    }
+
+    /// `POST /mutation`
+    pub async fn mutation(
+        &self,
+        request: &MutationRequest,
+    ) -> Result<MutationResponse, ErrorResponse> {
+        self.send_json(Method::POST, &["mutation"], request).await
+    }
+
+    /// `POST /raw`
+    pub async fn raw(&self, request: &RawRequest) -> Result<RawResponse, ErrorResponse> {
+        self.send_json(Method::POST, &["raw"], request).await
+    }
+
+    /// `GET /health`
+    pub async fn health(
+        &self,
+        request: &HealthCheckRequest,
+    ) -> Result<HealthCheckResponse, ErrorResponse> {
+        self.send(self.request(Method::GET, &["health"])?.query(request))
+            .await
+    }
+
+    /// `GET /datasets/templates/:template_name`
+    pub async fn dataset_get_template(
+        &self,
+        template_name: &DatasetTemplateName,
+    ) -> Result<DatasetGetTemplateResponse, ErrorResponse> {
+        let path = ["datasets", "templates", template_name.as_str()];
+        self.send(self.request(Method::GET, &path)?).await
+    }
+
+    /// `POST /datasets/clones/:clone_name`
+    pub async fn dataset_create_clone(
+        &self,
+        clone_name: &DatasetCloneName,
+        request: &DatasetCreateCloneRequest,
+    ) -> Result<DatasetCreateCloneResponse, ErrorResponse> {
+        let path = ["datasets", "clones", clone_name.as_str()];
+        self.send_json(Method::POST, &path, request).await
+    }
+
+    /// `DELETE /datasets/clones/:clone_name`
+    pub async fn dataset_delete_clone(
+        &self,
+        clone_name: &DatasetCloneName,
+    ) -> Result<DatasetDeleteCloneResponse, ErrorResponse> {
+        let path = ["datasets", "clones", clone_name.as_str()];
+        self.send(self.request(Method::DELETE, &path)?).await
+    }
+
+    fn request(&self, method: Method, path: &[&str]) -> Result<RequestBuilder, ErrorResponse> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|()| uncaught(format!("{} is not a valid base url", self.base_url)))?
+            .pop_if_empty()
+            .extend(path);
+        let mut request = self.client.request(method, url);
+        if let Some(config) = &self.config {
+            request = request.header(CONFIG_HEADER, config);
+        }
+        if let Some(SourceName(source_name)) = &self.source_name {
+            request = request.header(SOURCE_NAME_HEADER, source_name);
+        }
+        Ok(request)
+    }
+
+    async fn send_json<T: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &[&str],
+        body: &T,
+    ) -> Result<R, ErrorResponse> {
+        let body = serde_json::to_vec(body)
+            .map_err(|error| uncaught(format!("failed to encode request: {error}")))?;
+        let request = self
+            .request(method, path)?
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body);
+        self.send(request).await
+    }
+
+    async fn send<R: DeserializeOwned>(&self, request: RequestBuilder) -> Result<R, ErrorResponse> {
+        let response = request
+            .header(header::ACCEPT, "application/json")
+            .send()
+            .await
+            .map_err(|error| uncaught(format!("failed to reach agent: {error}")))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|error| uncaught(format!("failed to read agent response: {error}")))?;
+        if status.is_success() {
+            serde_json::from_slice(&body)
+                .map_err(|error| uncaught(format!("failed to decode agent response: {error}")))
+        } else {
+            Err(serde_json::from_slice(&body).unwrap_or_else(|_| {
+                uncaught(format!(
+                    "agent responded with status {status}: {}",
+                    String::from_utf8_lossy(&body)
+                ))
+            }))
+        }
+    }
+}
+
+fn uncaught(message: String) -> ErrorResponse {
+    ErrorResponse {
+        details: None,
+        message,
+        r#type: Some(ErrorResponseType::UncaughtError),
+    }
+}
@@ -0,0 +1,328 @@
+use std::fmt;
+
+use serde::{
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Serialize, Serializer,
+};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    map::FieldMap,
+    query::{ResponseFieldValue, ResponseRow},
+};
+
+/// The rows of a result set stored as one vector of values per field, for agents whose backends produce results column by column.
+///
+/// Serializes to the same JSON as the row-wise `ResponseRow.rows`, without building a map per row
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ColumnarResponseRows {
+    row_count: usize,
+    columns: FieldMap<String, Vec<ResponseFieldValue>>,
+}
+
+/// A result set with columnar rows. Serializes to the same JSON as `ResponseRow`
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ColumnarResponseRow {
+    /// The results of the aggregates returned by the query
+    pub aggregates: Option<FieldMap<String, serde_json::Value>>,
+    /// The rows returned by the query, corresponding to the query's fields
+    pub rows: Option<ColumnarResponseRows>,
+    /// The number of rows matched before `limit` and `offset` were applied, if requested via `include_total_matched`
+    pub total_matched: Option<u64>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ColumnarError {
+    /// A column has a different number of values than the other columns
+    LengthMismatch {
+        column: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// A row does not have the same fields as the first row
+    FieldMismatch { row: usize },
+}
+
+impl fmt::Display for ColumnarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LengthMismatch {
+                column,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "column {column} has {actual} values, but the result set has {expected} rows"
+            ),
+            Self::FieldMismatch { row } => {
+                write!(
+                    f,
+                    "row {row} does not have the same fields as the first row"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ColumnarError {}
+
+impl ColumnarResponseRows {
+    /// An empty set of columns for the given number of rows
+    pub fn new(row_count: usize) -> Self {
+        Self {
+            row_count,
+            columns: FieldMap::default(),
+        }
+    }
+
+    /// Adds a field, which must have a value for every row. Replaces any existing field with the same name
+    pub fn insert_column(
+        &mut self,
+        field: impl Into<String>,
+        values: Vec<ResponseFieldValue>,
+    ) -> Result<(), ColumnarError> {
+        let field = field.into();
+        if values.len() != self.row_count {
+            return Err(ColumnarError::LengthMismatch {
+                column: field,
+                expected: self.row_count,
+                actual: values.len(),
+            });
+        }
+        self.columns.insert(field, values);
+        Ok(())
+    }
+
+    pub fn with_column(
+        mut self,
+        field: impl Into<String>,
+        values: Vec<ResponseFieldValue>,
+    ) -> Result<Self, ColumnarError> {
+        self.insert_column(field, values)?;
+        Ok(self)
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.row_count
+    }
+
+    pub fn columns(&self) -> &FieldMap<String, Vec<ResponseFieldValue>> {
+        &self.columns
+    }
+
+    pub fn column(&self, field: &str) -> Option<&[ResponseFieldValue]> {
+        self.columns.get(field).map(Vec::as_slice)
+    }
+
+    /// Converts row-wise rows, which must all have the same fields. Field order is taken from the first row
+    pub fn from_rows(
+        rows: Vec<FieldMap<String, ResponseFieldValue>>,
+    ) -> Result<Self, ColumnarError> {
+        let mut columnar = Self::new(rows.len());
+        let Some(first) = rows.first() else {
+            return Ok(columnar);
+        };
+        columnar.columns = first
+            .keys()
+            .map(|field| (field.clone(), Vec::with_capacity(rows.len())))
+            .collect();
+        for (index, row) in rows.into_iter().enumerate() {
+            if row.len() != columnar.columns.len() {
+                return Err(ColumnarError::FieldMismatch { row: index });
+            }
+            for (field, value) in row {
+                columnar
+                    .columns
+                    .get_mut(&field)
+                    .ok_or(ColumnarError::FieldMismatch { row: index })?
+                    .push(value);
+            }
+        }
+        Ok(columnar)
+    }
+
+    pub fn into_rows(self) -> Vec<FieldMap<String, ResponseFieldValue>> {
+        let mut rows: Vec<FieldMap<String, ResponseFieldValue>> = (0..self.row_count)
+            .map(|_| FieldMap::with_capacity_and_hasher(self.columns.len(), Default::default()))
+            .collect();
+        for (field, values) in self.columns {
+            for (row, value) in rows.iter_mut().zip(values) {
+                row.insert(field.clone(), value);
+            }
+        }
+        rows
+    }
+}
+
+impl TryFrom<Vec<FieldMap<String, ResponseFieldValue>>> for ColumnarResponseRows {
+    type Error = ColumnarError;
+
+    fn try_from(rows: Vec<FieldMap<String, ResponseFieldValue>>) -> Result<Self, ColumnarError> {
+        Self::from_rows(rows)
+    }
+}
+
+impl From<ColumnarResponseRows> for Vec<FieldMap<String, ResponseFieldValue>> {
+    fn from(rows: ColumnarResponseRows) -> Self {
+        rows.into_rows()
+    }
+}
+
+impl TryFrom<ResponseRow> for ColumnarResponseRow {
+    type Error = ColumnarError;
+
+    fn try_from(row: ResponseRow) -> Result<Self, ColumnarError> {
+        Ok(Self {
+            aggregates: row.aggregates,
+            rows: row.rows.map(ColumnarResponseRows::from_rows).transpose()?,
+            total_matched: row.total_matched,
+        })
+    }
+}
+
+impl From<ColumnarResponseRow> for ResponseRow {
+    fn from(row: ColumnarResponseRow) -> Self {
+        Self {
+            aggregates: row.aggregates,
+            rows: row.rows.map(ColumnarResponseRows::into_rows),
+            total_matched: row.total_matched,
+        }
+    }
+}
+
+impl Serialize for ColumnarResponseRows {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut rows = serializer.serialize_seq(Some(self.row_count))?;
+        for index in 0..self.row_count {
+            rows.serialize_element(&ColumnarRow {
+                columns: &self.columns,
+                index,
+            })?;
+        }
+        rows.end()
+    }
+}
+
+struct ColumnarRow<'a> {
+    columns: &'a FieldMap<String, Vec<ResponseFieldValue>>,
+    index: usize,
+}
+
+impl Serialize for ColumnarRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut row = serializer.serialize_map(Some(self.columns.len()))?;
+        for (field, values) in self.columns {
+            row.serialize_entry(field, &values[self.index])?;
+        }
+        row.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ColumnarResponseRows {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rows = Vec::<FieldMap<String, ResponseFieldValue>>::deserialize(deserializer)?;
+        Self::from_rows(rows).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn row(id: i64, name: &str) -> FieldMap<String, ResponseFieldValue> {
+        [
+            ("id".to_owned(), ResponseFieldValue::Column(json!(id))),
+            ("name".to_owned(), ResponseFieldValue::Column(json!(name))),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn serializes_like_row_wise_rows() {
+        let rows = vec![row(1, "a"), row(2, "b")];
+        let columnar = ColumnarResponseRows::from_rows(rows.clone()).unwrap();
+        assert_eq!(columnar.row_count(), 2);
+        assert_eq!(
+            columnar.column("id"),
+            Some(
+                &[
+                    ResponseFieldValue::Column(json!(1)),
+                    ResponseFieldValue::Column(json!(2))
+                ][..]
+            )
+        );
+        assert_eq!(
+            serde_json::to_value(&columnar).unwrap(),
+            serde_json::to_value(&rows).unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trips_through_rows_and_json() {
+        let rows = vec![row(1, "a"), row(2, "b")];
+        let columnar = ColumnarResponseRows::try_from(rows.clone()).unwrap();
+        let value = serde_json::to_value(&columnar).unwrap();
+        assert_eq!(
+            serde_json::from_value::<ColumnarResponseRows>(value).unwrap(),
+            columnar
+        );
+        assert_eq!(Vec::from(columnar), rows);
+    }
+
+    #[test]
+    fn converts_response_rows() {
+        let row = ResponseRow {
+            aggregates: None,
+            rows: Some(vec![row(1, "a")]),
+            total_matched: Some(10),
+        };
+        let columnar = ColumnarResponseRow::try_from(row.clone()).unwrap();
+        assert_eq!(columnar.total_matched, Some(10));
+        assert_eq!(
+            serde_json::to_value(&columnar).unwrap(),
+            serde_json::to_value(&row).unwrap()
+        );
+        assert_eq!(ResponseRow::from(columnar), row);
+    }
+
+    #[test]
+    fn rejects_mismatched_columns() {
+        let error = ColumnarResponseRows::new(2)
+            .with_column("id", vec![ResponseFieldValue::Column(json!(1))])
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ColumnarError::LengthMismatch {
+                column: "id".to_owned(),
+                expected: 2,
+                actual: 1,
+            }
+        );
+
+        let mut short = row(2, "b");
+        short.remove("name");
+        assert_eq!(
+            ColumnarResponseRows::from_rows(vec![row(1, "a"), short]).unwrap_err(),
+            ColumnarError::FieldMismatch { row: 1 }
+        );
+
+        let mut renamed = row(2, "b");
+        renamed.remove("name");
+        renamed.insert("title".to_owned(), ResponseFieldValue::Column(json!("b")));
+        assert_eq!(
+            ColumnarResponseRows::from_rows(vec![row(1, "a"), renamed]).unwrap_err(),
+            ColumnarError::FieldMismatch { row: 1 }
+        );
+    }
+
+    #[test]
+    fn empty_rows() {
+        let columnar = ColumnarResponseRows::from_rows(vec![]).unwrap();
+        assert_eq!(columnar.row_count(), 0);
+        assert_eq!(serde_json::to_value(&columnar).unwrap(), json!([]));
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The content type of protocol documents in the compact encoding
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Encodes a protocol value as MessagePack, for passing documents between chained agents without re-encoding JSON on every hop.
+///
+/// Structs are encoded as maps with field names, so the encoding is self-describing and tolerates the same optional and unknown fields as JSON
+pub fn to_msgpack<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, CompactError> {
+    rmp_serde::to_vec_named(value).map_err(CompactError::Encode)
+}
+
+pub fn from_msgpack<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CompactError> {
+    rmp_serde::from_slice(bytes).map_err(CompactError::Decode)
+}
+
+/// Converts a JSON document into the compact encoding without parsing it as a protocol type
+pub fn json_to_msgpack(json: &[u8]) -> Result<Vec<u8>, CompactError> {
+    let value: serde_json::Value = serde_json::from_slice(json).map_err(CompactError::Json)?;
+    to_msgpack(&value)
+}
+
+/// Converts a document in the compact encoding into JSON without parsing it as a protocol type
+pub fn msgpack_to_json(bytes: &[u8]) -> Result<Vec<u8>, CompactError> {
+    let value: serde_json::Value = from_msgpack(bytes)?;
+    serde_json::to_vec(&value).map_err(CompactError::Json)
+}
+
+#[derive(Debug)]
+pub enum CompactError {
+    Encode(rmp_serde::encode::Error),
+    Decode(rmp_serde::decode::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for CompactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Encode(error) => write!(f, "failed to encode MessagePack: {error}"),
+            Self::Decode(error) => write!(f, "failed to decode MessagePack: {error}"),
+            Self::Json(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for CompactError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Encode(error) => Some(error),
+            Self::Decode(error) => Some(error),
+            Self::Json(error) => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryRequest;
+
+    #[test]
+    fn round_trips_protocol_documents() {
+        let json = include_str!("../fixtures/query_requests/array_relationship.json");
+        let request: QueryRequest = serde_json::from_str(json).unwrap();
+        let bytes = to_msgpack(&request).unwrap();
+        assert_eq!(from_msgpack::<QueryRequest>(&bytes).unwrap(), request);
+    }
+
+    #[test]
+    fn converts_between_json_and_msgpack() {
+        let json = include_str!("../fixtures/query_requests/aggregates.json");
+        let bytes = json_to_msgpack(json.as_bytes()).unwrap();
+        let request: QueryRequest = from_msgpack(&bytes).unwrap();
+        assert_eq!(request, serde_json::from_str(json).unwrap());
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&msgpack_to_json(&bytes).unwrap()).unwrap(),
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+    }
+
+    #[test]
+    fn reports_invalid_input() {
+        assert!(matches!(json_to_msgpack(b"{"), Err(CompactError::Json(_))));
+        assert!(matches!(
+            from_msgpack::<QueryRequest>(&[0xc1]),
+            Err(CompactError::Decode(_))
+        ));
+    }
+}
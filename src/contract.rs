@@ -0,0 +1,360 @@
+use crate::{
+    capabilities::{Capabilities, CapabilitiesResponse, ScalarType},
+    schema::{
+        ColumnInfo, FunctionInfo, FunctionReturnType, FunctionType, ProcedureInfo, SchemaResponse,
+        TableInfo,
+    },
+    validate::{ErrorCode, ValidationError},
+};
+
+/// Checks that a schema only uses what the agent's capabilities declare: every scalar type used by a column or function argument has an entry in `scalar_types`, tables and columns are only insertable, updatable or deletable when the matching mutation is supported, keys are only present when supported, functions only appear when user defined functions are supported and procedures only when stored procedures are. Returns every disagreement found.
+///
+/// Paths in the errors are relative to the schema, e.g. `tables.Artist.columns.Name`
+pub fn check_contract(
+    capabilities: &CapabilitiesResponse,
+    schema: &SchemaResponse,
+) -> Result<(), Vec<ValidationError>> {
+    let mut contract = Contract {
+        capabilities: &capabilities.capabilities,
+        errors: Vec::new(),
+    };
+    for table in &schema.tables {
+        contract.table(table);
+    }
+    for object_type in schema.object_types.iter().flatten() {
+        let path = format!("object_types.{}", object_type.name);
+        contract.columns(&object_type.columns, &path, false);
+        let variants = object_type
+            .polymorphism
+            .iter()
+            .flat_map(|polymorphism| &polymorphism.variants);
+        for variant in variants {
+            let path = format!("{path}.variants.{}", variant.name);
+            contract.columns(&variant.columns, &path, false);
+        }
+    }
+    for function in schema.functions.iter().flatten() {
+        let path = format!("functions.{}", function.name.join("."));
+        contract.function(function, &path);
+    }
+    for procedure in schema.procedures.iter().flatten() {
+        let path = format!("procedures.{}", procedure.name.join("."));
+        contract.procedure(procedure, &path);
+    }
+    if contract.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(contract.errors)
+    }
+}
+
+struct Contract<'a> {
+    capabilities: &'a Capabilities,
+    errors: Vec<ValidationError>,
+}
+
+impl Contract<'_> {
+    fn table(&mut self, table: &TableInfo) {
+        let path = format!("tables.{}", table.name.join("."));
+        let mutations = self.capabilities.mutations.as_ref();
+        let data_schema = self.capabilities.data_schema.as_ref();
+        if table.insertable == Some(true) && mutations.is_none_or(|m| m.insert.is_none()) {
+            self.error(
+                &path,
+                ErrorCode::UnsupportedMutation,
+                "table is insertable, but inserts are not supported".to_owned(),
+            );
+        }
+        if table.updatable == Some(true) && mutations.is_none_or(|m| m.update.is_none()) {
+            self.error(
+                &path,
+                ErrorCode::UnsupportedMutation,
+                "table is updatable, but updates are not supported".to_owned(),
+            );
+        }
+        if table.deletable == Some(true) && mutations.is_none_or(|m| m.delete.is_none()) {
+            self.error(
+                &path,
+                ErrorCode::UnsupportedMutation,
+                "table is deletable, but deletes are not supported".to_owned(),
+            );
+        }
+        if table.primary_key.is_some()
+            && data_schema.is_none_or(|data_schema| data_schema.supports_primary_keys != Some(true))
+        {
+            self.error(
+                &path,
+                ErrorCode::UnsupportedConstraint,
+                "table has a primary key, but primary keys are not supported".to_owned(),
+            );
+        }
+        if table
+            .foreign_keys
+            .as_ref()
+            .is_some_and(|keys| !keys.is_empty())
+            && data_schema.is_none_or(|data_schema| data_schema.supports_foreign_keys != Some(true))
+        {
+            self.error(
+                &path,
+                ErrorCode::UnsupportedConstraint,
+                "table has foreign keys, but foreign keys are not supported".to_owned(),
+            );
+        }
+        if let Some(columns) = &table.columns {
+            self.columns(columns, &path, true);
+        }
+    }
+
+    /// Checks the columns of a table or object type. Only table columns are inserted or updated directly
+    fn columns(&mut self, columns: &[ColumnInfo], path: &str, mutable: bool) {
+        let mutations = self.capabilities.mutations.as_ref();
+        for column in columns {
+            let path = format!("{path}.columns.{}", column.name);
+            if let Some(scalar_type) = column.r#type.leaf_scalar_type() {
+                self.scalar_type(scalar_type, &path);
+            }
+            if !mutable {
+                continue;
+            }
+            if column.insertable == Some(true) && mutations.is_none_or(|m| m.insert.is_none()) {
+                self.error(
+                    &path,
+                    ErrorCode::UnsupportedMutation,
+                    "column is insertable, but inserts are not supported".to_owned(),
+                );
+            }
+            if column.updatable == Some(true) && mutations.is_none_or(|m| m.update.is_none()) {
+                self.error(
+                    &path,
+                    ErrorCode::UnsupportedMutation,
+                    "column is updatable, but updates are not supported".to_owned(),
+                );
+            }
+        }
+    }
+
+    fn function(&mut self, function: &FunctionInfo, path: &str) {
+        let Some(functions) = &self.capabilities.user_defined_functions else {
+            self.error(
+                path,
+                ErrorCode::UnsupportedFunction,
+                "user defined functions are not supported".to_owned(),
+            );
+            return;
+        };
+        if function.r#type == FunctionType::Write
+            && functions.supports_write_functions != Some(true)
+        {
+            self.error(
+                path,
+                ErrorCode::UnsupportedFunction,
+                "function is a write function, but write functions are not supported".to_owned(),
+            );
+        }
+        if matches!(function.returns, Some(FunctionReturnType::Table { .. }))
+            && functions.supports_table_returning_functions != Some(true)
+        {
+            self.error(
+                path,
+                ErrorCode::UnsupportedFunction,
+                "function returns a table, but table returning functions are not supported"
+                    .to_owned(),
+            );
+        }
+        for arg in function.args.iter().flatten() {
+            self.scalar_type(&arg.r#type, &format!("{path}.args.{}", arg.name));
+        }
+    }
+
+    fn procedure(&mut self, procedure: &ProcedureInfo, path: &str) {
+        let supported = self
+            .capabilities
+            .user_defined_functions
+            .as_ref()
+            .is_some_and(|functions| functions.supports_procedures == Some(true));
+        if !supported {
+            self.error(
+                path,
+                ErrorCode::UnsupportedFunction,
+                "stored procedures are not supported".to_owned(),
+            );
+        }
+        for parameter in procedure.parameters.iter().flatten() {
+            self.scalar_type(
+                &parameter.r#type,
+                &format!("{path}.parameters.{}", parameter.name),
+            );
+        }
+        for (index, result_set) in procedure.result_sets.iter().flatten().enumerate() {
+            self.columns(
+                &result_set.columns,
+                &format!("{path}.result_sets.{index}"),
+                false,
+            );
+        }
+        if let Some(return_type) = &procedure.return_type {
+            self.scalar_type(return_type, &format!("{path}.return_type"));
+        }
+    }
+
+    fn scalar_type(&mut self, scalar_type: &ScalarType, path: &str) {
+        let declared = self
+            .capabilities
+            .scalar_types
+            .as_ref()
+            .is_some_and(|scalar_types| scalar_types.contains_key(scalar_type));
+        if !declared {
+            self.error(
+                path,
+                ErrorCode::UndeclaredScalarType,
+                format!("scalar type {scalar_type} is not declared in scalar_types"),
+            );
+        }
+    }
+
+    fn error(&mut self, path: &str, code: ErrorCode, message: String) {
+        self.errors.push(ValidationError {
+            path: path.to_owned(),
+            code,
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn capabilities(capabilities: serde_json::Value) -> CapabilitiesResponse {
+        serde_json::from_value(json!({
+            "capabilities": capabilities,
+            "config_schemas": {
+                "config_schema": { "type": "object" },
+                "other_schemas": {},
+            },
+        }))
+        .unwrap()
+    }
+
+    fn full() -> serde_json::Value {
+        json!({
+            "scalar_types": { "number": {}, "string": {} },
+            "mutations": { "insert": {}, "update": {}, "delete": {} },
+            "data_schema": { "supports_primary_keys": true, "supports_foreign_keys": true },
+            "user_defined_functions": {},
+        })
+    }
+
+    fn schema() -> SchemaResponse {
+        serde_json::from_str(include_str!(
+            "../fixtures/schema_responses/chinook_subset.json"
+        ))
+        .unwrap()
+    }
+
+    fn errors(
+        capabilities_json: serde_json::Value,
+        schema: &SchemaResponse,
+    ) -> Vec<(String, ErrorCode)> {
+        check_contract(&capabilities(capabilities_json), schema)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| (error.path, error.code))
+            .collect()
+    }
+
+    #[test]
+    fn agreeing_documents_pass() {
+        assert_eq!(check_contract(&capabilities(full()), &schema()), Ok(()));
+    }
+
+    #[test]
+    fn undeclared_scalar_types() {
+        let mut capabilities = full();
+        capabilities["scalar_types"] = json!({ "number": {} });
+        assert_eq!(
+            errors(capabilities, &schema()),
+            [
+                (
+                    "tables.Artist.columns.Name".to_owned(),
+                    ErrorCode::UndeclaredScalarType
+                ),
+                (
+                    "tables.Album.columns.Title".to_owned(),
+                    ErrorCode::UndeclaredScalarType
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_mutations_and_keys() {
+        let capabilities = json!({ "scalar_types": { "number": {}, "string": {} } });
+        let artist_errors: Vec<_> = errors(capabilities, &schema())
+            .into_iter()
+            .filter(|(path, _)| path.starts_with("tables.Artist"))
+            .collect();
+        assert_eq!(
+            artist_errors,
+            [
+                ("tables.Artist".to_owned(), ErrorCode::UnsupportedMutation),
+                ("tables.Artist".to_owned(), ErrorCode::UnsupportedMutation),
+                ("tables.Artist".to_owned(), ErrorCode::UnsupportedMutation),
+                ("tables.Artist".to_owned(), ErrorCode::UnsupportedConstraint),
+                (
+                    "tables.Artist.columns.Name".to_owned(),
+                    ErrorCode::UnsupportedMutation
+                ),
+                (
+                    "tables.Artist.columns.Name".to_owned(),
+                    ErrorCode::UnsupportedMutation
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn functions_need_their_capabilities() {
+        let mut schema = schema();
+        schema.functions = Some(
+            serde_json::from_value(json!([{
+                "name": ["albums_by"],
+                "type": "write",
+                "returns": { "type": "table", "table": ["Album"] },
+                "args": [{ "name": "artist", "type": "date" }],
+            }]))
+            .unwrap(),
+        );
+        let mut without_functions = full();
+        without_functions
+            .as_object_mut()
+            .unwrap()
+            .remove("user_defined_functions");
+        assert_eq!(
+            errors(without_functions, &schema),
+            [(
+                "functions.albums_by".to_owned(),
+                ErrorCode::UnsupportedFunction
+            )]
+        );
+        assert_eq!(
+            errors(full(), &schema),
+            [
+                (
+                    "functions.albums_by".to_owned(),
+                    ErrorCode::UnsupportedFunction
+                ),
+                (
+                    "functions.albums_by".to_owned(),
+                    ErrorCode::UnsupportedFunction
+                ),
+                (
+                    "functions.albums_by.args.artist".to_owned(),
+                    ErrorCode::UndeclaredScalarType
+                ),
+            ]
+        );
+    }
+}
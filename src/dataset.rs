@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use crate::map::FieldMap;
+
+/// The name of a dataset template, as used in `GET /datasets/templates/:template_name`
+pub type DatasetTemplateName = String;
+/// The name of a dataset clone, as used in `POST /datasets/clones/:clone_name` and `DELETE /datasets/clones/:clone_name`
+pub type DatasetCloneName = String;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DatasetGetTemplateResponse {
+    /// Message detailing if the dataset exists
+    pub exists: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DatasetCreateCloneRequest {
+    /// The name of the template to clone from
+    pub from: DatasetTemplateName,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DatasetCreateCloneResponse {
+    /// The agent configuration to use when querying the newly created clone
+    pub config: FieldMap<String, serde_json::Value>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DatasetDeleteCloneResponse {
+    /// The named dataset to clone from
+    pub message: String,
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::schema::{ObjectTypeDefinition, SchemaResponse};
+
+impl ObjectTypeDefinition {
+    /// The object types the columns of this type and of its polymorphic variants refer to, including through arrays, in order of first use
+    pub fn referenced_object_types(&self) -> IndexSet<&str> {
+        let mut names = Vec::new();
+        let variants = self
+            .polymorphism
+            .iter()
+            .flat_map(|polymorphism| &polymorphism.variants);
+        let columns = self
+            .columns
+            .iter()
+            .chain(variants.flat_map(|variant| &variant.columns));
+        for column in columns {
+            column.r#type.object_type_names(&mut names);
+        }
+        names.into_iter().collect()
+    }
+}
+
+impl SchemaResponse {
+    /// The object types ordered so that every type comes after the types it refers to, keeping schema order where the references allow it. References to types not in the schema are ignored. Fails if the types refer to each other in a cycle, as for recursive types
+    pub fn object_types_in_dependency_order(
+        &self,
+    ) -> Result<Vec<&ObjectTypeDefinition>, ObjectTypeCycle> {
+        let object_types: IndexMap<&str, &ObjectTypeDefinition> = self
+            .object_types
+            .iter()
+            .flatten()
+            .map(|object_type| (object_type.name.as_str(), object_type))
+            .collect();
+        let mut sorter = Sorter {
+            object_types: &object_types,
+            visiting: IndexSet::new(),
+            sorted: IndexSet::new(),
+        };
+        for name in object_types.keys() {
+            sorter.visit(name)?;
+        }
+        Ok(sorter
+            .sorted
+            .into_iter()
+            .map(|name| object_types[name])
+            .collect())
+    }
+}
+
+struct Sorter<'s, 'a> {
+    object_types: &'s IndexMap<&'a str, &'a ObjectTypeDefinition>,
+    /// The types whose dependencies are being visited, outermost first
+    visiting: IndexSet<&'a str>,
+    sorted: IndexSet<&'a str>,
+}
+
+impl<'a> Sorter<'_, 'a> {
+    fn visit(&mut self, name: &'a str) -> Result<(), ObjectTypeCycle> {
+        if self.sorted.contains(name) {
+            return Ok(());
+        }
+        let Some(object_type) = self.object_types.get(name) else {
+            return Ok(());
+        };
+        if let Some(start) = self.visiting.get_index_of(name) {
+            let mut types: Vec<String> = self.visiting[start..]
+                .iter()
+                .map(|name| (*name).to_owned())
+                .collect();
+            types.push(name.to_owned());
+            return Err(ObjectTypeCycle { types });
+        }
+        self.visiting.insert(name);
+        for dependency in object_type.referenced_object_types() {
+            self.visit(dependency)?;
+        }
+        self.visiting.pop();
+        self.sorted.insert(name);
+        Ok(())
+    }
+}
+
+/// Object types that refer to each other in a cycle
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObjectTypeCycle {
+    /// The types along the cycle, starting and ending with the same type, e.g. `["Person", "Address", "Person"]`
+    pub types: Vec<String>,
+}
+
+impl fmt::Display for ObjectTypeCycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "object types refer to each other in a cycle: {}",
+            self.types.join(" -> ")
+        )
+    }
+}
+
+impl std::error::Error for ObjectTypeCycle {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn object_type(name: &str, references: &[&str]) -> serde_json::Value {
+        let columns: Vec<_> = references
+            .iter()
+            .enumerate()
+            .map(|(index, reference)| {
+                let r#type = json!({ "type": "object", "name": reference });
+                let r#type = if index % 2 == 0 {
+                    r#type
+                } else {
+                    json!({ "type": "array", "element_type": r#type, "nullable": false })
+                };
+                json!({ "name": format!("c{index}"), "type": r#type, "nullable": true })
+            })
+            .collect();
+        json!({ "name": name, "columns": columns })
+    }
+
+    fn schema(object_types: Vec<serde_json::Value>) -> SchemaResponse {
+        serde_json::from_value(json!({ "tables": [], "object_types": object_types })).unwrap()
+    }
+
+    fn order(schema: &SchemaResponse) -> Result<Vec<&str>, ObjectTypeCycle> {
+        schema
+            .object_types_in_dependency_order()
+            .map(|object_types| {
+                object_types
+                    .into_iter()
+                    .map(|object_type| object_type.name.as_str())
+                    .collect()
+            })
+    }
+
+    #[test]
+    fn dependencies_come_first() {
+        let schema = schema(vec![
+            object_type("Order", &["Customer", "Line", "Missing"]),
+            object_type("Line", &["Product"]),
+            object_type("Customer", &["Address"]),
+            object_type("Product", &[]),
+            object_type("Address", &[]),
+        ]);
+        assert_eq!(
+            order(&schema).unwrap(),
+            ["Address", "Customer", "Product", "Line", "Order"]
+        );
+    }
+
+    #[test]
+    fn referenced_types_are_listed_once_in_order_of_use() {
+        let schema = schema(vec![object_type("Order", &["Line", "Customer", "Line"])]);
+        let order = &schema.object_types.as_ref().unwrap()[0];
+        assert_eq!(
+            order
+                .referenced_object_types()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            ["Line", "Customer"]
+        );
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let schema = schema(vec![
+            object_type("Person", &["Address"]),
+            object_type("Address", &["Resident"]),
+            object_type("Resident", &["Person"]),
+        ]);
+        let cycle = order(&schema).unwrap_err();
+        assert_eq!(cycle.types, ["Person", "Address", "Resident", "Person"]);
+        assert_eq!(
+            cycle.to_string(),
+            "object types refer to each other in a cycle: Person -> Address -> Resident -> Person"
+        );
+
+        let recursive = self::schema(vec![object_type("Tree", &["Tree"])]);
+        assert_eq!(order(&recursive).unwrap_err().types, ["Tree", "Tree"]);
+    }
+}
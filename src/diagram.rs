@@ -0,0 +1,379 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    hash::Hash,
+};
+
+use crate::{
+    capabilities::TableName,
+    query::{RelationshipType, TableRelationships, Target},
+    schema::{ColumnInfo, ColumnType, ColumnTypeNonScalar, SchemaResponse, TableInfo},
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum DiagramFormat {
+    /// Graphviz DOT, with tables as record nodes
+    Dot,
+    /// A Mermaid `erDiagram`
+    Mermaid,
+}
+
+/// An entity-relationship diagram of a schema, rendered by its `Display` implementation. Tables are entities listing their columns, and foreign keys are edges from the referencing table. Relationships given with `with_relationships` are drawn as labelled edges too. Mermaid identifiers allow fewer characters than names do, so names that would share an identifier are told apart by a numeric suffix, e.g. `a_b` and `a_b_2`
+#[derive(Clone, Copy, Debug)]
+pub struct ErDiagram<'a> {
+    schema: &'a SchemaResponse,
+    format: DiagramFormat,
+    relationships: &'a [TableRelationships],
+}
+
+impl SchemaResponse {
+    pub fn er_diagram(&self, format: DiagramFormat) -> ErDiagram<'_> {
+        ErDiagram {
+            schema: self,
+            format,
+            relationships: &[],
+        }
+    }
+}
+
+impl<'a> ErDiagram<'a> {
+    /// Also draws these relationships. Only relationships targeting tables are drawn
+    pub fn with_relationships(mut self, relationships: &'a [TableRelationships]) -> Self {
+        self.relationships = relationships;
+        self
+    }
+
+    /// The foreign key edges, followed by the edges of relationships to tables
+    fn edges(&self) -> impl Iterator<Item = Edge<'a>> + 'a {
+        let foreign_keys = self.schema.tables.iter().flat_map(|table| {
+            table
+                .foreign_keys
+                .iter()
+                .flatten()
+                .map(move |(name, constraint)| Edge {
+                    source: &table.name,
+                    target: &constraint.foreign_table,
+                    label: name,
+                    many_to_one: true,
+                    relationship: false,
+                })
+        });
+        let relationships = self.relationships.iter().flat_map(|relationships| {
+            relationships
+                .relationships
+                .iter()
+                .filter_map(move |(name, relationship)| match &relationship.target {
+                    Target::Table { name: target } => Some(Edge {
+                        source: &relationships.source_table,
+                        target,
+                        label: name,
+                        many_to_one: relationship.relationship_type == RelationshipType::Object,
+                        relationship: true,
+                    }),
+                    _ => None,
+                })
+        });
+        foreign_keys.chain(relationships)
+    }
+
+    fn fmt_dot(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "digraph schema {{")?;
+        writeln!(f, "    node [shape=record];")?;
+        for table in &self.schema.tables {
+            write!(f, "    \"{}\" [label=\"{{", dot_id(&table.name))?;
+            write!(f, "{}", dot_record(&table.name.join(".")))?;
+            for column in table.columns.iter().flatten() {
+                let mut label = format!("{}: {}", column.name, TypeName(&column.r#type));
+                if let Some(keys) = keys(table, column) {
+                    label.push_str(&format!(" ({keys})"));
+                }
+                write!(f, "|{}\\l", dot_record(&label))?;
+            }
+            writeln!(f, "}}\"];")?;
+        }
+        for edge in self.edges() {
+            let style = if edge.relationship {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            writeln!(
+                f,
+                "    \"{}\" -> \"{}\" [label=\"{}\"{style}];",
+                dot_id(edge.source),
+                dot_id(edge.target),
+                dot_escape(edge.label)
+            )?;
+        }
+        writeln!(f, "}}")
+    }
+
+    fn fmt_mermaid(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "erDiagram")?;
+        let tables = self.schema.tables.iter().map(|table| &table.name);
+        let edge_tables: Vec<&TableName> = self
+            .edges()
+            .flat_map(|edge| [edge.source, edge.target])
+            .collect();
+        let table_ids = unique_mermaid_ids(tables.chain(edge_tables), |name| name.join("."));
+        for table in &self.schema.tables {
+            write!(f, "    {}", table_ids[&table.name])?;
+            let Some(columns) = table.columns.as_ref().filter(|columns| !columns.is_empty()) else {
+                writeln!(f)?;
+                continue;
+            };
+            writeln!(f, " {{")?;
+            let column_ids =
+                unique_mermaid_ids(columns.iter().map(|column| &column.name), Clone::clone);
+            for column in columns {
+                write!(
+                    f,
+                    "        {} {}",
+                    mermaid_id(&TypeName(&column.r#type).to_string()),
+                    column_ids[&column.name]
+                )?;
+                match keys(table, column) {
+                    Some(keys) => writeln!(f, " {keys}")?,
+                    None => writeln!(f)?,
+                }
+            }
+            writeln!(f, "    }}")?;
+        }
+        for edge in self.edges() {
+            let line = if edge.relationship { ".." } else { "--" };
+            let (source, target) = if edge.many_to_one {
+                ("}o", "||")
+            } else {
+                ("||", "o{")
+            };
+            writeln!(
+                f,
+                "    {} {source}{line}{target} {} : \"{}\"",
+                table_ids[edge.source],
+                table_ids[edge.target],
+                edge.label.replace('"', "'")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ErDiagram<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.format {
+            DiagramFormat::Dot => self.fmt_dot(f),
+            DiagramFormat::Mermaid => self.fmt_mermaid(f),
+        }
+    }
+}
+
+struct Edge<'a> {
+    source: &'a TableName,
+    target: &'a TableName,
+    label: &'a str,
+    many_to_one: bool,
+    relationship: bool,
+}
+
+/// A column type as written in diagrams: the scalar or object type name, followed by `[]` for each level of array nesting
+struct TypeName<'a>(&'a ColumnType);
+
+impl Display for TypeName<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ColumnType::Scalar(scalar_type) => write!(f, "{scalar_type}"),
+            ColumnType::ColumnTypeNonScalar(ColumnTypeNonScalar::Object { name }) => {
+                write!(f, "{name}")
+            }
+            ColumnType::ColumnTypeNonScalar(ColumnTypeNonScalar::Array {
+                element_type, ..
+            }) => {
+                write!(f, "{}[]", TypeName(element_type))
+            }
+        }
+    }
+}
+
+fn is_primary_key(table: &TableInfo, column: &ColumnInfo) -> bool {
+    table
+        .primary_key
+        .as_ref()
+        .is_some_and(|primary_key| primary_key.contains(&column.name))
+}
+
+fn is_foreign_key(table: &TableInfo, column: &ColumnInfo) -> bool {
+    table
+        .foreign_keys
+        .iter()
+        .flatten()
+        .any(|(_, constraint)| constraint.column_mapping.contains_key(&column.name))
+}
+
+/// Whether the column is part of the table's primary key, or of any of its foreign keys
+fn keys(table: &TableInfo, column: &ColumnInfo) -> Option<&'static str> {
+    match (is_primary_key(table, column), is_foreign_key(table, column)) {
+        (true, true) => Some("PK, FK"),
+        (true, false) => Some("PK"),
+        (false, true) => Some("FK"),
+        (false, false) => None,
+    }
+}
+
+/// The table name's parts joined by `.`, with `.` and `\` escaped within them so that no two names share an id
+fn dot_id(name: &TableName) -> String {
+    let parts: Vec<String> = name
+        .iter()
+        .map(|part| part.replace('\\', "\\\\").replace('.', "\\."))
+        .collect();
+    dot_escape(&parts.join("."))
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escapes the characters that structure record labels, on top of those of quoted strings
+fn dot_record(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in dot_escape(text).chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Mermaid identifiers may only contain letters, digits, `_` and `-`, and array types may end in `[]`, so any other character is replaced by `_`
+fn mermaid_id(name: &str) -> String {
+    let (name, arrays) = match name.find("[]") {
+        Some(index) if name[index..].chars().all(|c| c == '[' || c == ']') => name.split_at(index),
+        _ => (name, ""),
+    };
+    let id: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{id}{arrays}")
+}
+
+/// The `mermaid_id` of each name, suffixed with `_2`, `_3` and so on where it is already taken by an earlier name
+fn unique_mermaid_ids<'a, K: Eq + Hash + ?Sized>(
+    keys: impl IntoIterator<Item = &'a K>,
+    name: impl Fn(&K) -> String,
+) -> HashMap<&'a K, String> {
+    let mut ids = HashMap::new();
+    let mut taken = HashSet::new();
+    for key in keys {
+        if ids.contains_key(key) {
+            continue;
+        }
+        let base = mermaid_id(&name(key));
+        let mut id = base.clone();
+        let mut suffix = 2;
+        while !taken.insert(id.clone()) {
+            id = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+        ids.insert(key, id);
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn chinook() -> SchemaResponse {
+        serde_json::from_str(include_str!(
+            "../fixtures/schema_responses/chinook_subset.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_mermaid() {
+        assert_eq!(
+            chinook().er_diagram(DiagramFormat::Mermaid).to_string(),
+            r#"erDiagram
+    Artist {
+        number ArtistId PK
+        string Name
+    }
+    Album {
+        number AlbumId PK
+        string Title
+        number ArtistId FK
+    }
+    Album }o--|| Artist : "Artist"
+"#
+        );
+    }
+
+    #[test]
+    fn renders_dot() {
+        assert_eq!(
+            chinook().er_diagram(DiagramFormat::Dot).to_string(),
+            r#"digraph schema {
+    node [shape=record];
+    "Artist" [label="{Artist|ArtistId: number (PK)\l|Name: string\l}"];
+    "Album" [label="{Album|AlbumId: number (PK)\l|Title: string\l|ArtistId: number (FK)\l}"];
+    "Album" -> "Artist" [label="Artist"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn draws_relationships() {
+        let relationships: Vec<TableRelationships> = serde_json::from_value(json!([{
+            "source_table": ["Artist"],
+            "relationships": {
+                "Albums": {
+                    "target": {"type": "table", "name": ["Album"]},
+                    "relationship_type": "array",
+                    "column_mapping": {"ArtistId": "ArtistId"},
+                },
+            },
+        }]))
+        .unwrap();
+        let schema = chinook();
+        let diagram = schema
+            .er_diagram(DiagramFormat::Mermaid)
+            .with_relationships(&relationships);
+        assert!(diagram
+            .to_string()
+            .ends_with("    Artist ||..o{ Album : \"Albums\"\n"));
+        let diagram = schema
+            .er_diagram(DiagramFormat::Dot)
+            .with_relationships(&relationships);
+        assert!(diagram
+            .to_string()
+            .contains("\"Artist\" -> \"Album\" [label=\"Albums\", style=dashed];"));
+    }
+
+    #[test]
+    fn keeps_ids_of_distinct_names_distinct() {
+        let schema: SchemaResponse = serde_json::from_value(json!({
+            "tables": [
+                {"name": ["a.b"], "columns": [{"name": "x y", "type": "number", "nullable": false}]},
+                {"name": ["a", "b"], "columns": [{"name": "x_y", "type": "number", "nullable": false}]},
+            ],
+        }))
+        .unwrap();
+        let mermaid = schema.er_diagram(DiagramFormat::Mermaid).to_string();
+        assert!(mermaid.contains("    a_b {\n        number x_y\n"));
+        assert!(mermaid.contains("    a_b_2 {\n        number x_y\n"));
+        let dot = schema.er_diagram(DiagramFormat::Dot).to_string();
+        assert!(dot.contains(r#""a\\.b" [label="{a.b|"#));
+        assert!(dot.contains(r#""a.b" [label="{a.b|"#));
+    }
+}
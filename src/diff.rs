@@ -0,0 +1,429 @@
+use std::hash::Hash;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    capabilities::{ColumnName, FunctionName, TableName},
+    map::FieldMap,
+    schema::{
+        ColumnInfo, Constraint, FunctionInfo, ObjectTypeDefinition, ProcedureInfo, SchemaResponse,
+        TableInfo,
+    },
+};
+
+/// The differences between two schemas. Tables, object types, functions, procedures and columns are matched by name, so a rename shows up as a removal and an addition
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchemaDiff {
+    pub added_tables: Vec<TableName>,
+    pub removed_tables: Vec<TableName>,
+    pub changed_tables: Vec<TableDiff>,
+    pub added_object_types: Vec<String>,
+    pub removed_object_types: Vec<String>,
+    pub changed_object_types: Vec<ObjectTypeDiff>,
+    pub added_functions: Vec<FunctionName>,
+    pub removed_functions: Vec<FunctionName>,
+    pub changed_functions: Vec<FunctionDiff>,
+    pub added_procedures: Vec<FunctionName>,
+    pub removed_procedures: Vec<FunctionName>,
+    pub changed_procedures: Vec<ProcedureDiff>,
+}
+
+impl SchemaDiff {
+    /// Whether the schemas are the same
+    pub fn is_empty(&self) -> bool {
+        self.added_tables.is_empty()
+            && self.removed_tables.is_empty()
+            && self.changed_tables.is_empty()
+            && self.added_object_types.is_empty()
+            && self.removed_object_types.is_empty()
+            && self.changed_object_types.is_empty()
+            && self.added_functions.is_empty()
+            && self.removed_functions.is_empty()
+            && self.changed_functions.is_empty()
+            && self.added_procedures.is_empty()
+            && self.removed_procedures.is_empty()
+            && self.changed_procedures.is_empty()
+    }
+}
+
+/// A value before and after a change
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// The differences between the columns of a table or object type
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColumnsDiff {
+    pub added: Vec<ColumnName>,
+    pub removed: Vec<ColumnName>,
+    pub changed: Vec<ColumnDiff>,
+}
+
+impl ColumnsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColumnDiff {
+    pub name: ColumnName,
+    pub old: ColumnInfo,
+    pub new: ColumnInfo,
+}
+
+impl ColumnDiff {
+    pub fn type_changed(&self) -> bool {
+        self.old.r#type != self.new.r#type
+    }
+
+    pub fn nullability_changed(&self) -> bool {
+        self.old.nullable != self.new.nullable
+    }
+}
+
+/// A table present in both schemas that differs between them
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TableDiff {
+    pub name: TableName,
+    pub columns: ColumnsDiff,
+    /// Present if the primary key changed
+    pub primary_key: Option<Change<Option<Vec<ColumnName>>>>,
+    pub added_foreign_keys: Vec<String>,
+    pub removed_foreign_keys: Vec<String>,
+    pub changed_foreign_keys: IndexMap<String, Change<Constraint>>,
+    /// Whether the table's type, description, extensions or insertable, updatable or deletable flags changed
+    pub properties_changed: bool,
+}
+
+/// An object type present in both schemas that differs between them
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjectTypeDiff {
+    pub name: String,
+    pub columns: ColumnsDiff,
+    /// Whether the type's description or polymorphism changed
+    pub properties_changed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FunctionDiff {
+    pub name: FunctionName,
+    pub old: FunctionInfo,
+    pub new: FunctionInfo,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcedureDiff {
+    pub name: FunctionName,
+    pub old: ProcedureInfo,
+    pub new: ProcedureInfo,
+}
+
+/// Compares two schemas. Added and changed items are listed in the order of `new`, removed items in the order of `old`.
+///
+/// Tables without column information, as returned for the `basic_info` detail level, are treated as having no columns
+pub fn schema_diff(old: &SchemaResponse, new: &SchemaResponse) -> SchemaDiff {
+    let mut diff = SchemaDiff::default();
+
+    let tables = by_key(&old.tables, &new.tables, |table| &table.name);
+    diff.added_tables = tables.added;
+    diff.removed_tables = tables.removed;
+    diff.changed_tables = tables
+        .common
+        .into_iter()
+        .filter_map(|(old, new)| table_diff(old, new))
+        .collect();
+
+    let old_object_types = old.object_types.as_deref().unwrap_or_default();
+    let new_object_types = new.object_types.as_deref().unwrap_or_default();
+    let object_types = by_key(old_object_types, new_object_types, |object_type| {
+        &object_type.name
+    });
+    diff.added_object_types = object_types.added;
+    diff.removed_object_types = object_types.removed;
+    diff.changed_object_types = object_types
+        .common
+        .into_iter()
+        .filter_map(|(old, new)| object_type_diff(old, new))
+        .collect();
+
+    let old_functions = old.functions.as_deref().unwrap_or_default();
+    let new_functions = new.functions.as_deref().unwrap_or_default();
+    let functions = by_key(old_functions, new_functions, |function| &function.name);
+    diff.added_functions = functions.added;
+    diff.removed_functions = functions.removed;
+    diff.changed_functions = functions
+        .common
+        .into_iter()
+        .filter(|(old, new)| old != new)
+        .map(|(old, new)| FunctionDiff {
+            name: new.name.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        })
+        .collect();
+
+    let old_procedures = old.procedures.as_deref().unwrap_or_default();
+    let new_procedures = new.procedures.as_deref().unwrap_or_default();
+    let procedures = by_key(old_procedures, new_procedures, |procedure| &procedure.name);
+    diff.added_procedures = procedures.added;
+    diff.removed_procedures = procedures.removed;
+    diff.changed_procedures = procedures
+        .common
+        .into_iter()
+        .filter(|(old, new)| old != new)
+        .map(|(old, new)| ProcedureDiff {
+            name: new.name.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        })
+        .collect();
+
+    diff
+}
+
+/// `None` if the tables differ only in ways the diff does not describe, such as the order of their columns or an empty rather than absent set of foreign keys
+fn table_diff(old: &TableInfo, new: &TableInfo) -> Option<TableDiff> {
+    let columns = columns_diff(
+        old.columns.as_deref().unwrap_or_default(),
+        new.columns.as_deref().unwrap_or_default(),
+    );
+    let primary_key = (old.primary_key != new.primary_key).then(|| Change {
+        old: old.primary_key.clone(),
+        new: new.primary_key.clone(),
+    });
+    let empty = FieldMap::default();
+    let old_keys = old.foreign_keys.as_ref().unwrap_or(&empty);
+    let new_keys = new.foreign_keys.as_ref().unwrap_or(&empty);
+    let added_foreign_keys: Vec<String> = new_keys
+        .keys()
+        .filter(|name| !old_keys.contains_key(*name))
+        .cloned()
+        .collect();
+    let removed_foreign_keys: Vec<String> = old_keys
+        .keys()
+        .filter(|name| !new_keys.contains_key(*name))
+        .cloned()
+        .collect();
+    let changed_foreign_keys: IndexMap<String, Change<Constraint>> = new_keys
+        .iter()
+        .filter_map(|(name, new)| {
+            let old = old_keys.get(name).filter(|old| *old != new)?;
+            let change = Change {
+                old: old.clone(),
+                new: new.clone(),
+            };
+            Some((name.clone(), change))
+        })
+        .collect();
+    let properties_changed = old.r#type != new.r#type
+        || old.description != new.description
+        || old.insertable != new.insertable
+        || old.updatable != new.updatable
+        || old.deletable != new.deletable
+        || old.extensions != new.extensions;
+    let unchanged = columns.is_empty()
+        && primary_key.is_none()
+        && added_foreign_keys.is_empty()
+        && removed_foreign_keys.is_empty()
+        && changed_foreign_keys.is_empty()
+        && !properties_changed;
+    if unchanged {
+        return None;
+    }
+    Some(TableDiff {
+        name: new.name.clone(),
+        columns,
+        primary_key,
+        added_foreign_keys,
+        removed_foreign_keys,
+        changed_foreign_keys,
+        properties_changed,
+    })
+}
+
+/// `None` if the object types differ only in the order of their columns
+fn object_type_diff(
+    old: &ObjectTypeDefinition,
+    new: &ObjectTypeDefinition,
+) -> Option<ObjectTypeDiff> {
+    let columns = columns_diff(&old.columns, &new.columns);
+    let properties_changed =
+        old.description != new.description || old.polymorphism != new.polymorphism;
+    if columns.is_empty() && !properties_changed {
+        return None;
+    }
+    Some(ObjectTypeDiff {
+        name: new.name.clone(),
+        columns,
+        properties_changed,
+    })
+}
+
+fn columns_diff(old: &[ColumnInfo], new: &[ColumnInfo]) -> ColumnsDiff {
+    let columns = by_key(old, new, |column| &column.name);
+    ColumnsDiff {
+        added: columns.added,
+        removed: columns.removed,
+        changed: columns
+            .common
+            .into_iter()
+            .filter(|(old, new)| old != new)
+            .map(|(old, new)| ColumnDiff {
+                name: new.name.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            })
+            .collect(),
+    }
+}
+
+struct Matched<'a, K, T> {
+    added: Vec<K>,
+    removed: Vec<K>,
+    common: Vec<(&'a T, &'a T)>,
+}
+
+/// Matches items by key. If a key appears more than once, its last item is used
+fn by_key<'a, K, T>(old: &'a [T], new: &'a [T], key: impl Fn(&T) -> &K) -> Matched<'a, K, T>
+where
+    K: Clone + Eq + Hash + 'a,
+{
+    let old_by_key: IndexMap<&K, &T> = old.iter().map(|item| (key(item), item)).collect();
+    let new_by_key: IndexMap<&K, &T> = new.iter().map(|item| (key(item), item)).collect();
+    let mut added = Vec::new();
+    let mut common = Vec::new();
+    for (name, new) in &new_by_key {
+        match old_by_key.get(name) {
+            Some(old) => common.push((*old, *new)),
+            None => added.push((*name).clone()),
+        }
+    }
+    let removed = old_by_key
+        .keys()
+        .filter(|name| !new_by_key.contains_key(*name))
+        .map(|name| (*name).clone())
+        .collect();
+    Matched {
+        added,
+        removed,
+        common,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn chinook() -> serde_json::Value {
+        serde_json::from_str(include_str!(
+            "../fixtures/schema_responses/chinook_subset.json"
+        ))
+        .unwrap()
+    }
+
+    fn schema(json: serde_json::Value) -> SchemaResponse {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn name(name: &str) -> TableName {
+        vec![name.to_owned()]
+    }
+
+    #[test]
+    fn equal_schemas_have_no_differences() {
+        assert!(schema_diff(&schema(chinook()), &schema(chinook())).is_empty());
+
+        let mut reordered = chinook();
+        reordered["tables"][1]["columns"]
+            .as_array_mut()
+            .unwrap()
+            .reverse();
+        assert!(schema_diff(&schema(chinook()), &schema(reordered)).is_empty());
+    }
+
+    #[test]
+    fn added_and_removed_tables() {
+        let mut new = chinook();
+        let tables = new["tables"].as_array_mut().unwrap();
+        tables.remove(0);
+        tables.push(json!({ "name": ["Genre"] }));
+        let diff = schema_diff(&schema(chinook()), &schema(new));
+        assert_eq!(diff.added_tables, [name("Genre")]);
+        assert_eq!(diff.removed_tables, [name("Artist")]);
+        assert!(diff.changed_tables.is_empty());
+    }
+
+    #[test]
+    fn changed_columns_keys_and_properties() {
+        let mut new = chinook();
+        let album = &mut new["tables"][1];
+        album["description"] = json!("Albums");
+        album["primary_key"] = json!(["AlbumId", "Title"]);
+        album["foreign_keys"] = json!({});
+        let columns = album["columns"].as_array_mut().unwrap();
+        columns[1]["nullable"] = json!(true);
+        columns[2]["type"] = json!("string");
+        columns.remove(0);
+        columns.push(json!({ "name": "Year", "type": "number", "nullable": true }));
+
+        let diff = schema_diff(&schema(chinook()), &schema(new));
+        assert_eq!(diff.changed_tables.len(), 1);
+        let table = &diff.changed_tables[0];
+        assert_eq!(table.name, name("Album"));
+        assert_eq!(table.columns.added, ["Year"]);
+        assert_eq!(table.columns.removed, ["AlbumId"]);
+        let changed: Vec<_> = table
+            .columns
+            .changed
+            .iter()
+            .map(|column| {
+                (
+                    column.name.as_str(),
+                    column.type_changed(),
+                    column.nullability_changed(),
+                )
+            })
+            .collect();
+        assert_eq!(changed, [("Title", false, true), ("ArtistId", true, false)]);
+        assert_eq!(
+            table.primary_key,
+            Some(Change {
+                old: Some(vec!["AlbumId".to_owned()]),
+                new: Some(vec!["AlbumId".to_owned(), "Title".to_owned()]),
+            })
+        );
+        assert_eq!(table.removed_foreign_keys, ["Artist"]);
+        assert!(table.added_foreign_keys.is_empty());
+        assert!(table.properties_changed);
+    }
+
+    #[test]
+    fn changed_object_types() {
+        let old: serde_json::Value = serde_json::from_str(include_str!(
+            "../fixtures/schema_responses/nested_types.json"
+        ))
+        .unwrap();
+        let mut new = old.clone();
+        new["object_types"][0]["columns"][2]["nullable"] = json!(false);
+        new["object_types"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({ "name": "Phone", "columns": [] }));
+        let diff = schema_diff(&schema(old), &schema(new));
+        assert_eq!(diff.added_object_types, ["Phone"]);
+        assert_eq!(diff.changed_object_types.len(), 1);
+        let address = &diff.changed_object_types[0];
+        assert_eq!(address.name, "Address");
+        assert_eq!(address.columns.changed[0].name, "postcode");
+        assert!(!address.properties_changed);
+        assert!(diff.changed_tables.is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+
+use serde_json::Value;
+use sha2::{Digest as _, Sha256};
+
+use crate::{
+    canonical::Canonical, query::QueryRequest, redact::strip_literals, schema::SchemaResponse,
+};
+
+/// A stable SHA-256 content hash of a protocol document's canonical form
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Digest(pub [u8; 32]);
+
+impl Digest {
+    /// Hashes the compact serialization of an already canonical JSON value
+    pub fn of_canonical(value: &Value) -> Self {
+        let bytes = serde_json::to_vec(value).expect("JSON values always serialize");
+        Self(Sha256::digest(bytes).into())
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DigestOptions {
+    /// Replace every literal value (comparison values, arguments, foreach values, interpolated scalars) with `null` before hashing, so requests differing only in their literals share a digest
+    pub exclude_literals: bool,
+}
+
+impl QueryRequest {
+    /// A stable hash of this request, independent of map key order and number representation
+    pub fn digest(&self) -> Digest {
+        self.digest_with(DigestOptions::default())
+    }
+
+    pub fn digest_with(&self, options: DigestOptions) -> Digest {
+        let mut canonical = self.canonical();
+        if options.exclude_literals {
+            strip_literals(&mut canonical);
+        }
+        Digest::of_canonical(&canonical)
+    }
+}
+
+impl SchemaResponse {
+    /// A stable hash of the normalized schema, so schemas differing only in discovery order or in spelled-out default values share a fingerprint
+    pub fn fingerprint(&self) -> Digest {
+        Digest::of_canonical(&self.clone().normalized().canonical())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn request() -> QueryRequest {
+        serde_json::from_str(include_str!(
+            "../fixtures/query_requests/array_relationship.json"
+        ))
+        .unwrap()
+    }
+
+    fn with_comparison_literal(value: i64) -> QueryRequest {
+        let mut request = serde_json::to_value(request()).unwrap();
+        request["query"]["where"]["expressions"][0]["value"]["value"] = json!(value);
+        serde_json::from_value(request).unwrap()
+    }
+
+    /// Digests are used as cache keys, so they must not change between releases
+    #[test]
+    fn digests_are_stable() {
+        assert_eq!(
+            request().digest().to_string(),
+            "22c661e5209134318ef48b3b3a051996b068155ade7091c8856e7ebb235d10d4"
+        );
+    }
+
+    #[test]
+    fn digests_ignore_key_order() {
+        let mut reordered = request();
+        reordered.query.fields.as_mut().unwrap().reverse();
+        assert_eq!(reordered.digest(), request().digest());
+    }
+
+    #[test]
+    fn literals_can_be_excluded() {
+        let options = DigestOptions {
+            exclude_literals: true,
+        };
+        assert_ne!(with_comparison_literal(11).digest(), request().digest());
+        assert_eq!(
+            with_comparison_literal(11).digest_with(options),
+            request().digest_with(options)
+        );
+        let mut limited = request();
+        limited.query.limit = Some(4);
+        assert_ne!(limited.digest_with(options), request().digest_with(options));
+    }
+
+    #[test]
+    fn fingerprints_ignore_table_order() {
+        let schema: SchemaResponse = serde_json::from_str(include_str!(
+            "../fixtures/schema_responses/chinook_subset.json"
+        ))
+        .unwrap();
+        let mut reordered = schema.clone();
+        reordered.tables.reverse();
+        assert_eq!(reordered.fingerprint(), schema.fingerprint());
+        reordered.tables.pop();
+        assert_ne!(reordered.fingerprint(), schema.fingerprint());
+    }
+}
@@ -0,0 +1,411 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(feature = "digest")]
+use crate::digest::DigestOptions;
+use crate::{
+    agent::{decode_config_header, decode_request_body, DataConnectorAgent},
+    dataset::{DatasetCloneName, DatasetTemplateName},
+    error::{ErrorResponse, ErrorResponseType},
+    health::HealthCheckRequest,
+    mutation::MutationRequest,
+    query::QueryRequest,
+    raw::RawRequest,
+    schema::SchemaRequest,
+    telemetry::SpanAttributes,
+};
+
+// The framework adapters match requests to endpoints themselves, and share everything after that:
+// decoding the config header and body, calling the agent and encoding its response
+
+/// The endpoint a request was routed to, with the parts of its path and query string the agent takes
+pub(crate) enum Route {
+    Capabilities,
+    Schema,
+    Query,
+    Explain,
+    Mutation,
+    Raw,
+    /// The query string, decoded by the adapter. A decoding error is reported after any config header error
+    Health(Result<HealthCheckRequest, ErrorResponse>),
+    DatasetGetTemplate(DatasetTemplateName),
+    DatasetCreateClone(DatasetCloneName),
+    DatasetDeleteClone(DatasetCloneName),
+}
+
+/// The parts of a request the endpoints read, whatever the framework
+#[derive(Clone, Copy)]
+pub(crate) struct AgentRequest<'a> {
+    pub(crate) config_header: Option<&'a [u8]>,
+    pub(crate) content_type: Option<&'a [u8]>,
+    pub(crate) body: &'a [u8],
+    /// Taken by the tower adapters from the request extensions
+    pub(crate) summary: Option<SummaryRequested>,
+}
+
+/// An encoded response, for the adapter to convert into its framework's response type
+pub(crate) struct AgentResponse {
+    pub(crate) status: u16,
+    pub(crate) content_type: &'static str,
+    pub(crate) body: Vec<u8>,
+    /// Returned by the tower adapters in the response extensions. Set by `dispatch` when requested, and by `error_response` to carry the error type
+    pub(crate) summary: Option<Summary>,
+}
+
+/// Added to the request extensions by `LoggingLayer`, asking the adapter for a `Summary` of the request
+#[cfg_attr(not(feature = "logging"), allow(dead_code))]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SummaryRequested {
+    pub(crate) redacted_bodies: bool,
+}
+
+/// What `LoggingLayer` logs about a request beyond its endpoint, status and duration, taken from the typed request and response so that bodies are never parsed twice or buffered by the layer. Never includes literals, session variables or row data
+#[cfg_attr(not(feature = "logging"), allow(dead_code))]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Summary {
+    pub(crate) request_attributes: Option<SpanAttributes>,
+    /// The literal-free digest of query requests
+    pub(crate) digest: Option<String>,
+    pub(crate) redacted_body: Option<serde_json::Value>,
+    pub(crate) response_attributes: Option<SpanAttributes>,
+    pub(crate) error_type: Option<ErrorResponseType>,
+}
+
+impl Summary {
+    fn query_request(&mut self, request: &QueryRequest, redacted_bodies: bool) {
+        self.request_attributes = Some(request.span_attributes());
+        #[cfg(feature = "digest")]
+        {
+            let digest = request.digest_with(DigestOptions {
+                exclude_literals: true,
+            });
+            self.digest = Some(digest.to_string());
+        }
+        self.redacted_body = redacted_bodies.then(|| request.redacted());
+    }
+
+    fn mutation_request(&mut self, request: &MutationRequest, redacted_bodies: bool) {
+        self.request_attributes = Some(request.span_attributes());
+        self.redacted_body = redacted_bodies.then(|| request.redacted());
+    }
+
+    fn raw_request(&mut self, request: &RawRequest, redacted_bodies: bool) {
+        self.redacted_body = redacted_bodies.then(|| request.redacted());
+    }
+}
+
+impl AgentRequest<'_> {
+    fn config<C: DeserializeOwned>(&self) -> Result<C, ErrorResponse> {
+        decode_config_header(self.config_header)
+    }
+
+    fn json_body<T: DeserializeOwned>(&self) -> Result<T, ErrorResponse> {
+        decode_request_body(self.content_type, self.body)
+    }
+}
+
+pub(crate) async fn dispatch<A: DataConnectorAgent>(
+    agent: &A,
+    route: Route,
+    request: AgentRequest<'_>,
+) -> AgentResponse {
+    let mut summary = request.summary.map(|_| Summary::default());
+    let redacted_bodies = request
+        .summary
+        .is_some_and(|requested| requested.redacted_bodies);
+    let mut response = match route {
+        Route::Capabilities => respond(agent.capabilities().await),
+        Route::Schema => respond(
+            async {
+                let config = request.config::<A::Config>()?;
+                let schema_request = if request.body.is_empty() {
+                    SchemaRequest {
+                        detail_level: None,
+                        filters: None,
+                    }
+                } else {
+                    request.json_body()?
+                };
+                agent.schema(&config, schema_request).await
+            }
+            .await,
+        ),
+        Route::Query => {
+            let response = async {
+                let config = request.config::<A::Config>()?;
+                let query_request = request.json_body()?;
+                if let Some(summary) = &mut summary {
+                    summary.query_request(&query_request, redacted_bodies);
+                }
+                agent.query(&config, query_request).await
+            }
+            .await;
+            if let (Some(summary), Ok(response)) = (&mut summary, &response) {
+                summary.response_attributes = Some(response.span_attributes());
+            }
+            respond(response)
+        }
+        Route::Explain => respond(
+            async {
+                let config = request.config::<A::Config>()?;
+                let query_request = request.json_body()?;
+                if let Some(summary) = &mut summary {
+                    summary.query_request(&query_request, redacted_bodies);
+                }
+                agent.query_explain(&config, query_request).await
+            }
+            .await,
+        ),
+        Route::Mutation => {
+            let response = async {
+                let config = request.config::<A::Config>()?;
+                let mutation_request = request.json_body()?;
+                if let Some(summary) = &mut summary {
+                    summary.mutation_request(&mutation_request, redacted_bodies);
+                }
+                agent.mutation(&config, mutation_request).await
+            }
+            .await;
+            if let (Some(summary), Ok(response)) = (&mut summary, &response) {
+                summary.response_attributes = Some(response.span_attributes());
+            }
+            respond(response)
+        }
+        Route::Raw => respond(
+            async {
+                let config = request.config::<A::Config>()?;
+                let raw_request = request.json_body()?;
+                if let Some(summary) = &mut summary {
+                    summary.raw_request(&raw_request, redacted_bodies);
+                }
+                agent.raw(&config, raw_request).await
+            }
+            .await,
+        ),
+        Route::Health(health_request) => respond(
+            async {
+                let config = match request.config_header {
+                    Some(_) => Some(request.config::<A::Config>()?),
+                    None => None,
+                };
+                agent.health(config.as_ref(), health_request?).await
+            }
+            .await,
+        ),
+        Route::DatasetGetTemplate(template_name) => {
+            respond(agent.dataset_get_template(&template_name).await)
+        }
+        Route::DatasetCreateClone(clone_name) => respond(
+            async {
+                let clone_request = request.json_body()?;
+                agent.dataset_create_clone(&clone_name, clone_request).await
+            }
+            .await,
+        ),
+        Route::DatasetDeleteClone(clone_name) => {
+            respond(agent.dataset_delete_clone(&clone_name).await)
+        }
+    };
+    if let Some(summary) = &mut summary {
+        summary.error_type = response.summary.take().and_then(|error| error.error_type);
+    }
+    response.summary = summary;
+    response
+}
+
+fn respond<T: Serialize>(response: Result<T, ErrorResponse>) -> AgentResponse {
+    match response {
+        Ok(response) => json_response(200, &response),
+        Err(error) => error_response(&error),
+    }
+}
+
+/// Sends the error with the status given by `ErrorResponse::status_code`. The error type is kept in the response's summary, for `dispatch` to merge into the requested one
+pub(crate) fn error_response(error: &ErrorResponse) -> AgentResponse {
+    let mut response = json_response(error.status_code(), error);
+    response.summary = Some(Summary {
+        error_type: error.r#type.clone(),
+        ..Summary::default()
+    });
+    response
+}
+
+pub(crate) fn json_response<T: Serialize + ?Sized>(status: u16, body: &T) -> AgentResponse {
+    match serde_json::to_vec(body) {
+        Ok(body) => AgentResponse {
+            status,
+            content_type: "application/json",
+            body,
+            summary: None,
+        },
+        Err(error) => AgentResponse {
+            status: 500,
+            content_type: "text/plain",
+            body: format!("failed to encode response: {error}").into_bytes(),
+            summary: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::tests::now,
+        capabilities::CapabilitiesResponse,
+        query::QueryResponse,
+        schema::{SchemaRequest, SchemaResponse},
+    };
+
+    struct Chinook;
+
+    impl DataConnectorAgent for Chinook {
+        type Config = serde_json::Value;
+
+        async fn capabilities(&self) -> Result<CapabilitiesResponse, ErrorResponse> {
+            Ok(serde_json::from_str(include_str!(
+                "../fixtures/capabilities_responses/sqlite.json"
+            ))
+            .unwrap())
+        }
+
+        async fn schema(
+            &self,
+            _config: &Self::Config,
+            request: SchemaRequest,
+        ) -> Result<SchemaResponse, ErrorResponse> {
+            let schema: SchemaResponse = serde_json::from_str(include_str!(
+                "../fixtures/schema_responses/chinook_subset.json"
+            ))
+            .unwrap();
+            Ok(schema.for_request(&request))
+        }
+
+        async fn query(
+            &self,
+            _config: &Self::Config,
+            _request: QueryRequest,
+        ) -> Result<QueryResponse, ErrorResponse> {
+            Ok(serde_json::from_str(include_str!(
+                "../fixtures/query_responses/rows_with_relationship.json"
+            ))
+            .unwrap())
+        }
+    }
+
+    fn request(body: &[u8]) -> AgentRequest<'_> {
+        AgentRequest {
+            config_header: Some(b"{}"),
+            content_type: Some(b"application/json"),
+            body,
+            summary: None,
+        }
+    }
+
+    fn body(response: &AgentResponse) -> serde_json::Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn serves_the_agent_response() {
+        let query = include_str!("../fixtures/query_requests/simple_fields.json");
+        let response = now(dispatch(&Chinook, Route::Query, request(query.as_bytes())));
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, "application/json");
+        assert_eq!(
+            body(&response),
+            serde_json::from_str::<serde_json::Value>(include_str!(
+                "../fixtures/query_responses/rows_with_relationship.json"
+            ))
+            .unwrap()
+        );
+        assert!(response.summary.is_none());
+
+        let response = now(dispatch(&Chinook, Route::Schema, request(b"")));
+        assert_eq!(response.status, 200);
+        assert_eq!(body(&response)["tables"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn reports_decoding_and_agent_errors() {
+        let missing_config = AgentRequest {
+            config_header: None,
+            ..request(b"{}")
+        };
+        let response = now(dispatch(&Chinook, Route::Query, missing_config));
+        assert_eq!(response.status, 400);
+        assert_eq!(body(&response)["type"], "request-validation-failure");
+
+        let response = now(dispatch(&Chinook, Route::Query, request(b"{}")));
+        assert_eq!(response.status, 400);
+
+        let response = now(dispatch(&Chinook, Route::Mutation, request(b"{}")));
+        assert_eq!(response.status, 400);
+        let mutation = include_str!("../fixtures/mutation_requests/update_and_delete.json");
+        let response = now(dispatch(
+            &Chinook,
+            Route::Mutation,
+            request(mutation.as_bytes()),
+        ));
+        assert_eq!(response.status, 501);
+        assert_eq!(body(&response)["type"], "unsupported-operation");
+    }
+
+    #[test]
+    fn health_only_requires_a_config_when_one_is_sent() {
+        let route = || {
+            Route::Health(Ok(HealthCheckRequest {
+                check_data_sources: None,
+            }))
+        };
+        let without_config = AgentRequest {
+            config_header: None,
+            ..request(b"")
+        };
+        assert_eq!(now(dispatch(&Chinook, route(), without_config)).status, 200);
+        let invalid_config = AgentRequest {
+            config_header: Some(b"not json!"),
+            ..request(b"")
+        };
+        assert_eq!(now(dispatch(&Chinook, route(), invalid_config)).status, 400);
+    }
+
+    #[test]
+    fn gathers_summaries_when_requested() {
+        let query = include_str!("../fixtures/query_requests/simple_fields.json");
+        let summarized = AgentRequest {
+            summary: Some(SummaryRequested {
+                redacted_bodies: true,
+            }),
+            ..request(query.as_bytes())
+        };
+        let summary = now(dispatch(&Chinook, Route::Query, summarized))
+            .summary
+            .unwrap();
+        let query_request: QueryRequest = serde_json::from_str(query).unwrap();
+        assert_eq!(
+            summary.request_attributes,
+            Some(query_request.span_attributes())
+        );
+        assert!(summary.response_attributes.is_some());
+        assert_eq!(summary.redacted_body, Some(query_request.redacted()));
+        #[cfg(feature = "digest")]
+        assert!(summary.digest.is_some());
+        assert_eq!(summary.error_type, None);
+
+        let summarized = AgentRequest {
+            config_header: None,
+            summary: Some(SummaryRequested {
+                redacted_bodies: false,
+            }),
+            ..request(query.as_bytes())
+        };
+        let summary = now(dispatch(&Chinook, Route::Query, summarized))
+            .summary
+            .unwrap();
+        assert_eq!(summary.request_attributes, None);
+        assert_eq!(
+            summary.error_type,
+            Some(ErrorResponseType::RequestValidationFailure)
+        );
+    }
+}
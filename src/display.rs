@@ -0,0 +1,344 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::query::{
+    BinaryArrayComparisonOperator, BinaryComparisonOperator, ColumnSelector, ComparisonColumn,
+    ComparisonValue, ExistsInTable, Expression, QueryRequest, Target, UnaryComparisonOperator,
+};
+
+// These renderings are meant for logs and error messages. They are not a stable format and cannot be parsed back
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::And { expressions } if expressions.is_empty() => write!(f, "TRUE"),
+            Self::Or { expressions } if expressions.is_empty() => write!(f, "FALSE"),
+            Self::And { expressions } => write_joined(f, expressions, " AND "),
+            Self::Or { expressions } => write_joined(f, expressions, " OR "),
+            Self::Not { expression } => write!(f, "NOT {expression}"),
+            Self::ApplyUnaryComparison { column, operator } => match operator {
+                UnaryComparisonOperator::IsNull => write!(f, "{column} IS NULL"),
+                UnaryComparisonOperator::Other(name) => write!(f, "{column} {name}"),
+            },
+            Self::ApplyBinaryComparison {
+                column,
+                operator,
+                value,
+                collation,
+            } => {
+                write!(
+                    f,
+                    "{column} {operator} {value}",
+                    operator = binary_symbol(operator)
+                )?;
+                if let Some(collation) = collation {
+                    write!(f, " COLLATE {collation}")?;
+                }
+                Ok(())
+            }
+            Self::ApplyBinaryArrayComparison {
+                column,
+                operator,
+                values,
+                ..
+            } => {
+                let operator = match operator {
+                    BinaryArrayComparisonOperator::In => "IN",
+                    BinaryArrayComparisonOperator::NotIn => "NOT IN",
+                    BinaryArrayComparisonOperator::Contains => "CONTAINS",
+                    BinaryArrayComparisonOperator::ContainedIn => "CONTAINED IN",
+                    BinaryArrayComparisonOperator::Overlaps => "OVERLAPS",
+                    BinaryArrayComparisonOperator::Other(name) => name,
+                };
+                write!(f, "{column} {operator} (")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Exists { in_table, r#where } => match in_table {
+                ExistsInTable::Related { relationship } => {
+                    write!(f, "EXISTS {relationship} WHERE {where}")
+                }
+                ExistsInTable::Unrelated { target } => {
+                    write!(f, "EXISTS {target} WHERE {where}")
+                }
+            },
+            Self::ApplyBetween {
+                column,
+                low,
+                high,
+                low_inclusive: true,
+                high_inclusive: true,
+            } => write!(f, "{column} BETWEEN {low} AND {high}"),
+            Self::ApplyBetween {
+                column,
+                low,
+                high,
+                low_inclusive,
+                high_inclusive,
+            } => write!(
+                f,
+                "({column} {} {low} AND {column} {} {high})",
+                if *low_inclusive { ">=" } else { ">" },
+                if *high_inclusive { "<=" } else { "<" },
+            ),
+        }
+    }
+}
+
+fn write_joined(f: &mut Formatter<'_>, expressions: &[Expression], separator: &str) -> fmt::Result {
+    write!(f, "(")?;
+    for (index, expression) in expressions.iter().enumerate() {
+        if index > 0 {
+            write!(f, "{separator}")?;
+        }
+        write!(f, "{expression}")?;
+    }
+    write!(f, ")")
+}
+
+fn binary_symbol(operator: &BinaryComparisonOperator) -> &str {
+    match operator {
+        BinaryComparisonOperator::LessThan => "<",
+        BinaryComparisonOperator::LessThanOrEqual => "<=",
+        BinaryComparisonOperator::Equal => "=",
+        BinaryComparisonOperator::GreaterThan => ">",
+        BinaryComparisonOperator::GreaterThanOrEqual => ">=",
+        BinaryComparisonOperator::Like => "LIKE",
+        BinaryComparisonOperator::ILike => "ILIKE",
+        BinaryComparisonOperator::NotLike => "NOT LIKE",
+        BinaryComparisonOperator::NotILike => "NOT ILIKE",
+        other => other.declared_name().unwrap_or_default(),
+    }
+}
+
+impl Display for ComparisonColumn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for segment in self.path.iter().flatten() {
+            write!(f, "{segment}.")?;
+        }
+        write!(f, "{}", self.name)?;
+        for segment in self.field_path.iter().flatten() {
+            write!(f, ".{segment}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ColumnSelector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => write!(f, "{name}"),
+            Self::Compound(names) => write!(f, "{}", names.join(".")),
+        }
+    }
+}
+
+impl Display for ComparisonValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Column { column } => write!(f, "{column}"),
+            Self::Scalar { value, .. } => write!(f, "{value}"),
+            Self::Geometry { srid, distance, .. } => {
+                write!(f, "GEOMETRY(srid={}", srid.unwrap_or(4326))?;
+                if let Some(distance) = distance {
+                    write!(f, ", distance={distance}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Table { name } => write!(f, "table {}", name.join(".")),
+            Self::Function { name, .. } => write!(f, "function {}", name.join(".")),
+            Self::Interpolated { id } => write!(f, "interpolated query {id}"),
+        }
+    }
+}
+
+impl Expression {
+    /// The number of levels in the expression tree. Comparisons have a depth of 1
+    pub fn depth(&self) -> usize {
+        match self {
+            Self::And { expressions } | Self::Or { expressions } => {
+                1 + expressions.iter().map(Expression::depth).max().unwrap_or(0)
+            }
+            Self::Not { expression } => 1 + expression.depth(),
+            Self::Exists { r#where, .. } => 1 + r#where.depth(),
+            Self::ApplyUnaryComparison { .. }
+            | Self::ApplyBinaryComparison { .. }
+            | Self::ApplyBinaryArrayComparison { .. }
+            | Self::ApplyBetween { .. } => 1,
+        }
+    }
+}
+
+/// A one-line overview of a query request, for logs and error messages
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryRequestSummary<'a> {
+    pub target: &'a Target,
+    /// The number of top-level fields
+    pub field_count: usize,
+    /// The number of top-level aggregates
+    pub aggregate_count: usize,
+    /// The depth of the top-level filter expression, or 0 if there is none
+    pub filter_depth: usize,
+    /// The number of foreach entries, if this is a foreach request
+    pub foreach_count: Option<usize>,
+}
+
+impl QueryRequest {
+    pub fn summary(&self) -> QueryRequestSummary<'_> {
+        QueryRequestSummary {
+            target: &self.target,
+            field_count: self.query.fields.as_ref().map_or(0, |fields| fields.len()),
+            aggregate_count: self
+                .query
+                .aggregates
+                .as_ref()
+                .map_or(0, |aggregates| aggregates.len()),
+            filter_depth: self.query.r#where.as_ref().map_or(0, Expression::depth),
+            foreach_count: self.foreach.as_ref().map(Vec::len),
+        }
+    }
+}
+
+impl Display for QueryRequestSummary<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "query on {}: {} fields, {} aggregates, filter depth {}",
+            self.target, self.field_count, self.aggregate_count, self.filter_depth
+        )?;
+        if let Some(foreach_count) = self.foreach_count {
+            write!(f, ", {foreach_count} foreach entries")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn expression(value: serde_json::Value) -> Expression {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn column(name: &str) -> serde_json::Value {
+        json!({"name": name, "column_type": "number"})
+    }
+
+    #[test]
+    fn renders_comparisons() {
+        let filter = expression(json!({
+            "type": "and",
+            "expressions": [
+                {
+                    "type": "binary_op",
+                    "operator": "greater_than",
+                    "column": column("Total"),
+                    "value": {"type": "scalar", "value": 10, "value_type": "number"},
+                },
+                {
+                    "type": "not",
+                    "expression": {"type": "unary_op", "operator": "is_null", "column": column("Name")},
+                },
+                {
+                    "type": "binary_arr_op",
+                    "operator": "in",
+                    "column": column("Id"),
+                    "value_type": "number",
+                    "values": [1, 2],
+                },
+            ],
+        }));
+        assert_eq!(
+            filter.to_string(),
+            "(Total > 10 AND NOT Name IS NULL AND Id IN (1, 2))"
+        );
+        assert_eq!(filter.depth(), 3);
+    }
+
+    #[test]
+    fn renders_empty_junctions_as_constants() {
+        assert_eq!(
+            expression(json!({"type": "and", "expressions": []})).to_string(),
+            "TRUE"
+        );
+        assert_eq!(
+            expression(json!({"type": "or", "expressions": []})).to_string(),
+            "FALSE"
+        );
+    }
+
+    #[test]
+    fn renders_paths_exists_and_between() {
+        let filter = expression(json!({
+            "type": "exists",
+            "in_table": {"type": "related", "relationship": "Invoices"},
+            "where": {
+                "type": "binary_op",
+                "operator": "equal",
+                "column": {
+                    "name": "Address",
+                    "column_type": "string",
+                    "field_path": ["City"],
+                },
+                "value": {
+                    "type": "column",
+                    "column": {"path": ["$"], "name": "City", "column_type": "string"},
+                },
+                "collation": "nocase",
+            },
+        }));
+        assert_eq!(
+            filter.to_string(),
+            "EXISTS Invoices WHERE Address.City = $.City COLLATE nocase"
+        );
+
+        let between = |low_inclusive| {
+            expression(json!({
+                "type": "between",
+                "column": column("Total"),
+                "low": {"type": "scalar", "value": 1, "value_type": "number"},
+                "high": {"type": "scalar", "value": 5, "value_type": "number"},
+                "low_inclusive": low_inclusive,
+            }))
+            .to_string()
+        };
+        assert_eq!(between(true), "Total BETWEEN 1 AND 5");
+        assert_eq!(between(false), "(Total > 1 AND Total <= 5)");
+    }
+
+    #[test]
+    fn summarizes_query_requests() {
+        let request: QueryRequest = serde_json::from_str(include_str!(
+            "../fixtures/query_requests/exists_filter.json"
+        ))
+        .unwrap();
+        let summary = request.summary();
+        assert_eq!(summary.field_count, 2);
+        assert_eq!(summary.filter_depth, 2);
+        assert_eq!(
+            summary.to_string(),
+            "query on table Customer: 2 fields, 0 aggregates, filter depth 2"
+        );
+
+        let request: QueryRequest =
+            serde_json::from_str(include_str!("../fixtures/query_requests/foreach.json")).unwrap();
+        assert!(request.summary().to_string().ends_with(&format!(
+            ", {} foreach entries",
+            request.foreach.unwrap().len()
+        )));
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use serde_with::skip_serializing_none;
+
+use crate::{
+    capabilities::ColumnName,
+    map::FieldMap,
+    query::{
+        InterpolatedQuery, Query, QueryRequest, RequestOptions, ScalarValue, TableRelationships,
+        Target,
+    },
+};
+
+/// A `QueryRequest` whose `query` and `foreach` are kept as unparsed JSON, for routing requests by their target without building the full query tree.
+///
+/// Can only be deserialized with `serde_json`. Requires the `raw_value` feature
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueryRequestEnvelope {
+    /// If present, a list of columns and values for the columns that the query must be repeated for, applying the column values as a filter for each query.
+    pub foreach: Option<Box<RawValue>>,
+    pub interpolated_queries: Option<FieldMap<String, InterpolatedQuery>>,
+    pub query: Box<RawValue>,
+    /// The target of the query.
+    pub target: Target,
+    /// The relationships between tables involved in the entire query request
+    pub relationships: Vec<TableRelationships>,
+    /// The session variables of the user making the request, keyed by their lower-cased `x-hasura-*` name
+    pub session: Option<FieldMap<String, serde_json::Value>>,
+    /// Execution limits and hints for the agent. Requires `request_options` capabilities
+    pub request_options: Option<RequestOptions>,
+}
+
+impl QueryRequestEnvelope {
+    pub fn from_request(request: &QueryRequest) -> serde_json::Result<Self> {
+        Ok(Self {
+            foreach: request
+                .foreach
+                .as_ref()
+                .map(serde_json::value::to_raw_value)
+                .transpose()?,
+            interpolated_queries: request.interpolated_queries.clone(),
+            query: serde_json::value::to_raw_value(&request.query)?,
+            target: request.target.clone(),
+            relationships: request.relationships.clone(),
+            session: request.session.clone(),
+            request_options: request.request_options.clone(),
+        })
+    }
+
+    /// Parses the deferred query
+    pub fn query(&self) -> serde_json::Result<Query> {
+        serde_json::from_str(self.query.get())
+    }
+
+    /// Parses the deferred foreach rows
+    pub fn foreach(&self) -> serde_json::Result<Option<Vec<FieldMap<ColumnName, ScalarValue>>>> {
+        self.foreach
+            .as_deref()
+            .map(|foreach| serde_json::from_str(foreach.get()))
+            .transpose()
+    }
+
+    /// Parses the deferred parts, producing the full request
+    pub fn into_request(self) -> serde_json::Result<QueryRequest> {
+        Ok(QueryRequest {
+            foreach: self.foreach()?,
+            query: self.query()?,
+            interpolated_queries: self.interpolated_queries,
+            target: self.target,
+            relationships: self.relationships,
+            session: self.session,
+            request_options: self.request_options,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn defers_parsing_query_and_foreach() {
+        let json = include_str!("../fixtures/query_requests/foreach.json");
+        let envelope: QueryRequestEnvelope = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            envelope.target,
+            Target::Table {
+                name: vec!["Album".to_owned()]
+            }
+        );
+        let request: QueryRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(envelope.query().unwrap(), request.query);
+        assert_eq!(envelope.foreach().unwrap(), request.foreach);
+        assert_eq!(envelope.into_request().unwrap(), request);
+    }
+
+    #[test]
+    fn round_trips_from_request() {
+        let request: QueryRequest = serde_json::from_str(include_str!(
+            "../fixtures/query_requests/simple_fields.json"
+        ))
+        .unwrap();
+        let envelope = QueryRequestEnvelope::from_request(&request).unwrap();
+        assert!(envelope.foreach.is_none());
+        assert_eq!(
+            serde_json::to_value(&envelope).unwrap(),
+            serde_json::to_value(&request).unwrap()
+        );
+        assert_eq!(envelope.into_request().unwrap(), request);
+    }
+
+    #[test]
+    fn reports_invalid_deferred_parts() {
+        let envelope: QueryRequestEnvelope = serde_json::from_value(json!({
+            "target": {"type": "table", "name": ["Album"]},
+            "relationships": [],
+            "query": {"fields": 1},
+        }))
+        .unwrap();
+        assert!(envelope.query().is_err());
+        assert!(envelope.into_request().is_err());
+    }
+}
@@ -1,11 +1,16 @@
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_enum_str::{
+    Deserialize_enum_str as DeserializeEnumStr, Serialize_enum_str as SerializeEnumStr,
+};
 use serde_with::skip_serializing_none;
 
+use crate::capabilities::{ColumnName, TableName};
+
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ErrorResponse {
-    /// Error details
+    /// Error details. Unlike the other maps of the protocol types, this keeps the standard library's hasher, so that results carrying an `ErrorResponse` stay small
     pub details: Option<IndexMap<String, serde_json::Value>>,
     /// Error message
     pub message: String,
@@ -13,10 +18,109 @@ pub struct ErrorResponse {
     pub r#type: Option<ErrorResponseType>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[derive(
+    Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, SerializeEnumStr, DeserializeEnumStr,
+)]
 #[serde(rename_all = "kebab-case")]
 pub enum ErrorResponseType {
     UncaughtError,
     MutationConstraintViolation,
     MutationPermissionCheckFailure,
+    /// The request was malformed or referred to things that do not exist
+    RequestValidationFailure,
+    /// The request used a feature the agent does not support
+    UnsupportedOperation,
+    /// The dataset template or clone named in a dataset request does not exist
+    DatasetNotFound,
+    #[serde(other)]
+    Other(String),
+}
+
+/// Structured details carried in `ErrorResponse.details` by errors of a particular type
+pub trait ErrorDetails: Serialize + DeserializeOwned {
+    /// The type of the errors these details are sent with
+    fn error_type() -> ErrorResponseType;
+}
+
+/// The details of a `mutation-permission-check-failure` error: a row did not satisfy an operation's `post_insert_check` or `post_update_check`
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PermissionCheckFailureDetails {
+    /// The table the failing row belongs to
+    pub table: TableName,
+    /// The position of the failing operation in `MutationRequest.operations`
+    pub operation_index: usize,
+    /// The position of the failing row among the operation's inserted or updated rows
+    pub row_index: Option<usize>,
+}
+
+impl ErrorDetails for PermissionCheckFailureDetails {
+    fn error_type() -> ErrorResponseType {
+        ErrorResponseType::MutationPermissionCheckFailure
+    }
+}
+
+/// The details of a `mutation-constraint-violation` error
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConstraintViolationDetails {
+    /// The table whose constraint was violated
+    pub table: TableName,
+    /// The position of the failing operation in `MutationRequest.operations`
+    pub operation_index: usize,
+    /// The position of the offending row among the operation's rows
+    pub row_index: Option<usize>,
+    /// The name of the violated constraint, i.e. a unique index or foreign key, if it has one
+    pub constraint: Option<String>,
+    /// The columns the violated constraint applies to
+    pub columns: Option<Vec<ColumnName>>,
+}
+
+impl ErrorDetails for ConstraintViolationDetails {
+    fn error_type() -> ErrorResponseType {
+        ErrorResponseType::MutationConstraintViolation
+    }
+}
+
+impl ErrorResponse {
+    /// An error of the type the details belong to, with the details embedded in `details`
+    pub fn with_details<D: ErrorDetails>(message: impl Into<String>, details: &D) -> Self {
+        let details = match serde_json::to_value(details) {
+            Ok(serde_json::Value::Object(details)) => Some(details.into_iter().collect()),
+            _ => None,
+        };
+        Self {
+            details,
+            message: message.into(),
+            r#type: Some(D::error_type()),
+        }
+    }
+
+    /// The HTTP status an agent responds with for this error: 400 for errors caused by the request, 404 for missing datasets, 501 for unsupported operations and 500 otherwise
+    pub fn status_code(&self) -> u16 {
+        match self.r#type {
+            Some(
+                ErrorResponseType::MutationConstraintViolation
+                | ErrorResponseType::MutationPermissionCheckFailure
+                | ErrorResponseType::RequestValidationFailure,
+            ) => 400,
+            Some(ErrorResponseType::DatasetNotFound) => 404,
+            Some(ErrorResponseType::UnsupportedOperation) => 501,
+            Some(ErrorResponseType::UncaughtError | ErrorResponseType::Other(_)) | None => 500,
+        }
+    }
+
+    /// The details of this error, if it has the type the details belong to and has details. Fails if the details do not have the expected fields
+    pub fn details_as<D: ErrorDetails>(&self) -> Option<serde_json::Result<D>> {
+        if self.r#type.as_ref() != Some(&D::error_type()) {
+            return None;
+        }
+        let details = self.details.as_ref()?;
+        Some(serde_json::from_value(serde_json::Value::Object(
+            details
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        )))
+    }
 }
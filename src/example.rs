@@ -0,0 +1,1064 @@
+use serde_json::json;
+
+use crate::{
+    capabilities::{
+        AtomicitySupportLevel, Capabilities, CapabilitiesResponse, ColumnNullability,
+        ComparisonCapabilities, ConfigSchemaResponse, DataSchemaCapabilities, DatasetCapabilities,
+        DeleteCapabilities, ExplainCapabilities, ForeachCapabilities, FunctionArgumentKind,
+        GraphQlType, InsertCapabilities, InterpolatedQueryCapabilities, LicensingCapabilities,
+        MetricsCapabilities, MetricsFormat, MutationCapabilities, PostSchemaCapabilities,
+        QueryCapabilities, RawCapabilities, RelationshipCapabilities, RequestOptionsCapabilities,
+        ReturningCapabilities, ScalarType, ScalarTypeCapabilities, SubqueryComparisonCapabilities,
+        SubscriptionCapabilities, UpdateCapabilities, UpdateColumnOperatorDefinition,
+        UserDefinedFunctionCapabilities,
+    },
+    dataset::{
+        DatasetCreateCloneRequest, DatasetCreateCloneResponse, DatasetDeleteCloneResponse,
+        DatasetGetTemplateResponse,
+    },
+    error::{ErrorResponse, ErrorResponseType},
+    explain::{ExplainPlanNode, ExplainResponse},
+    health::{
+        DataSourceHealth, HealthCheckRequest, HealthCheckResponse, HealthStatus, HealthVersionInfo,
+    },
+    licensing::{
+        LicenseActivationRequest, LicenseActivationResponse, LicenseEntitlement,
+        LicenseHeartbeatRequest, LicenseHeartbeatResponse, LicenseKeyRequest, LicenseKeyResponse,
+        LicensingLevel,
+    },
+    map::FieldMap,
+    metrics::{HistogramBucket, Metric, MetricFamily, MetricType, MetricValue, MetricsResponse},
+    mutation::{
+        InsertFieldSchema, MutationOperation, MutationOperationResults, MutationOperationStatus,
+        MutationRequest, MutationResponse, RowUpdate, TableInsertSchema,
+    },
+    query::{
+        Aggregate, BinaryArrayComparisonOperator, BinaryComparisonOperator, ColumnSelector,
+        ComparisonColumn, ComparisonValue, ExistsInTable, Expression, Field, OrderBy,
+        OrderByElement, OrderByTarget, OrderDirection, Query, QueryRequest, QueryResponse,
+        Relationship, RelationshipType, RequestOptions, ResponseFieldValue, ResponseRow,
+        TableRelationships, Target, UnaryComparisonOperator,
+    },
+    raw::{RawRequest, RawResponse},
+    schema::{
+        ColumnDefault, ColumnInfo, ColumnType, ColumnTypeNonScalar, ColumnValueGenerationStrategy,
+        Constraint, DetailLevel, FunctionInfo, FunctionInformationArgument,
+        FunctionResponseCardinality, FunctionReturnType, FunctionType, ObjectTypeDefinition,
+        SchemaFilters, SchemaRequest, SchemaResponse, TableInfo, TableType,
+    },
+    subscription::{SubscriptionClientMessage, SubscriptionServerMessage},
+};
+
+/// A small, deterministic instance of a type with its optional fields populated, for documentation, OpenAPI examples and snapshot tests.
+///
+/// The examples describe the same `Artist` and `Album` tables: the requests are valid against `SchemaResponse::example()`, which agrees with `CapabilitiesResponse::example()`, and the responses answer the requests
+pub trait Example {
+    fn example() -> Self;
+}
+
+impl Example for CapabilitiesResponse {
+    fn example() -> Self {
+        Self {
+            capabilities: Capabilities::example(),
+            config_schemas: ConfigSchemaResponse {
+                config_schema: serde_json::from_value(json!({
+                    "type": "object",
+                    "nullable": false,
+                    "properties": { "db": { "type": "string" } },
+                }))
+                .expect("the example config schema is a valid OpenAPI schema"),
+                other_schemas: FieldMap::default(),
+            },
+            display_name: Some("Example agent".to_owned()),
+            release_name: Some("1.0.0".to_owned()),
+        }
+    }
+}
+
+impl Example for Capabilities {
+    fn example() -> Self {
+        Self {
+            comparisons: Some(ComparisonCapabilities {
+                subquery: Some(SubqueryComparisonCapabilities {
+                    supports_relations: Some(true),
+                }),
+                supports_between: Some(true),
+                supports_geometry_values: Some(false),
+            }),
+            data_schema: Some(DataSchemaCapabilities {
+                column_nullability: Some(ColumnNullability::NullableAndNonNullable),
+                supports_foreign_keys: Some(true),
+                supports_primary_keys: Some(true),
+                supports_schemaless_tables: Some(false),
+            }),
+            datasets: Some(DatasetCapabilities {}),
+            explain: Some(ExplainCapabilities {
+                supports_structured_plan: Some(true),
+            }),
+            interpolated_queries: Some(InterpolatedQueryCapabilities {
+                supported_scalar_types: Some(vec![ScalarType::String, ScalarType::Number]),
+                supports_relationships: Some(false),
+            }),
+            licensing: Some(LicensingCapabilities {
+                requires_license_key: Some(false),
+                supported_levels: Some(vec![LicensingLevel::Free, LicensingLevel::Enterprise]),
+            }),
+            metrics: Some(MetricsCapabilities {
+                formats: Some(vec![MetricsFormat::Prometheus, MetricsFormat::Json]),
+            }),
+            mutations: Some(MutationCapabilities {
+                atomicity_support_level: Some(AtomicitySupportLevel::HeterogeneousOperations),
+                delete: Some(DeleteCapabilities {
+                    supports_returning: Some(true),
+                }),
+                insert: Some(InsertCapabilities {
+                    supports_nested_inserts: Some(false),
+                }),
+                returning: Some(ReturningCapabilities {
+                    supports_relationships: Some(true),
+                }),
+                update: Some(UpdateCapabilities {
+                    supports_post_update_check: Some(true),
+                    supports_custom_operators: Some(true),
+                }),
+            }),
+            queries: Some(QueryCapabilities {
+                foreach: Some(ForeachCapabilities {
+                    max_batch_size: Some(100),
+                    supports_mixed_value_types: Some(false),
+                }),
+                supports_nested_field_paths: Some(true),
+                supports_nulls_ordering: Some(true),
+                supports_distinct_on: Some(false),
+            }),
+            raw: Some(RawCapabilities {
+                supports_mutations: Some(false),
+                dialects: Some(vec!["sql".to_owned()]),
+            }),
+            relationships: Some(RelationshipCapabilities {
+                supports_remote_relationships: Some(false),
+                supports_relation_comparisons: Some(true),
+                max_join_depth: Some(4),
+                extra: FieldMap::default(),
+            }),
+            scalar_types: Some(
+                [
+                    (ScalarType::String, ScalarTypeCapabilities::example()),
+                    (
+                        ScalarType::Number,
+                        ScalarTypeCapabilities {
+                            aggregate_functions: Some(
+                                [
+                                    ("max".to_owned(), ScalarType::Number),
+                                    ("min".to_owned(), ScalarType::Number),
+                                    ("sum".to_owned(), ScalarType::Number),
+                                ]
+                                .into_iter()
+                                .collect(),
+                            ),
+                            comparison_operators: Some(FieldMap::default()),
+                            graphql_type: Some(GraphQlType::Float),
+                            update_column_operators: Some(
+                                [(
+                                    "inc".to_owned(),
+                                    UpdateColumnOperatorDefinition {
+                                        argument_type: ScalarType::Number,
+                                    },
+                                )]
+                                .into_iter()
+                                .collect(),
+                            ),
+                            array_comparison_operators: Some(vec![
+                                BinaryArrayComparisonOperator::NotIn,
+                            ]),
+                            collations: None,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            subscriptions: Some(SubscriptionCapabilities {
+                max_concurrent_subscriptions: Some(16),
+                keep_alive_interval_ms: Some(30_000),
+            }),
+            user_defined_functions: Some(UserDefinedFunctionCapabilities {
+                supports_write_functions: Some(false),
+                supports_table_returning_functions: Some(true),
+                supported_argument_kinds: Some(vec![FunctionArgumentKind::Scalar]),
+                supports_procedures: Some(false),
+            }),
+            post_schema: Some(PostSchemaCapabilities {
+                supports_filters: Some(true),
+                supported_detail_levels: Some(vec![DetailLevel::BasicInfo]),
+            }),
+            request_options: Some(RequestOptionsCapabilities {
+                supports_timeout: Some(true),
+                supports_max_rows: Some(true),
+                supported_hints: Some(vec!["statement_timeout".to_owned()]),
+            }),
+        }
+    }
+}
+
+/// The capabilities of the `string` scalar type
+impl Example for ScalarTypeCapabilities {
+    fn example() -> Self {
+        Self {
+            aggregate_functions: Some(
+                [
+                    ("max".to_owned(), ScalarType::String),
+                    ("min".to_owned(), ScalarType::String),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            comparison_operators: Some(
+                [
+                    ("_like".to_owned(), ScalarType::String),
+                    ("_ilike".to_owned(), ScalarType::String),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            graphql_type: Some(GraphQlType::String),
+            update_column_operators: Some(
+                [(
+                    "append".to_owned(),
+                    UpdateColumnOperatorDefinition {
+                        argument_type: ScalarType::String,
+                    },
+                )]
+                .into_iter()
+                .collect(),
+            ),
+            array_comparison_operators: Some(vec![BinaryArrayComparisonOperator::NotIn]),
+            collations: Some(vec!["C".to_owned(), "en_US".to_owned()]),
+        }
+    }
+}
+
+impl Example for SchemaRequest {
+    fn example() -> Self {
+        Self {
+            detail_level: Some(DetailLevel::BasicInfo),
+            filters: Some(SchemaFilters {
+                only_functions: Some(vec![vec!["search_artists".to_owned()]]),
+                only_tables: Some(vec![vec!["Artist".to_owned()], vec!["Album".to_owned()]]),
+            }),
+        }
+    }
+}
+
+impl Example for SchemaResponse {
+    fn example() -> Self {
+        Self {
+            object_types: Some(vec![ObjectTypeDefinition::example()]),
+            tables: vec![
+                TableInfo::example(),
+                TableInfo {
+                    columns: Some(vec![
+                        ColumnInfo {
+                            name: "AlbumId".to_owned(),
+                            description: Some("The album's identifier".to_owned()),
+                            ..ColumnInfo::example()
+                        },
+                        ColumnInfo {
+                            insertable: Some(true),
+                            name: "Title".to_owned(),
+                            nullable: false,
+                            r#type: ColumnType::Scalar(ScalarType::String),
+                            updatable: Some(true),
+                            value_generated: None,
+                            default_value: None,
+                            description: Some("The album's title".to_owned()),
+                            ..ColumnInfo::example()
+                        },
+                        ColumnInfo {
+                            insertable: Some(true),
+                            name: "ArtistId".to_owned(),
+                            updatable: Some(true),
+                            value_generated: None,
+                            default_value: None,
+                            description: Some("The artist who released the album".to_owned()),
+                            ..ColumnInfo::example()
+                        },
+                    ]),
+                    description: Some("Albums released by artists".to_owned()),
+                    foreign_keys: Some(
+                        [(
+                            "Artist".to_owned(),
+                            Constraint {
+                                column_mapping: [("ArtistId".to_owned(), "ArtistId".to_owned())]
+                                    .into_iter()
+                                    .collect(),
+                                foreign_table: vec!["Artist".to_owned()],
+                            },
+                        )]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    name: vec!["Album".to_owned()],
+                    primary_key: Some(vec!["AlbumId".to_owned()]),
+                    ..TableInfo::example()
+                },
+            ],
+            functions: Some(vec![FunctionInfo::example()]),
+            procedures: None,
+        }
+    }
+}
+
+/// The `Artist` table
+impl Example for TableInfo {
+    fn example() -> Self {
+        Self {
+            columns: Some(vec![
+                ColumnInfo::example(),
+                ColumnInfo {
+                    description: Some("The artist's name".to_owned()),
+                    insertable: Some(true),
+                    name: "Name".to_owned(),
+                    nullable: true,
+                    r#type: ColumnType::Scalar(ScalarType::String),
+                    updatable: Some(true),
+                    value_generated: None,
+                    computed: None,
+                    default_value: None,
+                    extensions: None,
+                },
+                ColumnInfo {
+                    description: Some("Links to the artist's pages elsewhere".to_owned()),
+                    insertable: Some(true),
+                    name: "Links".to_owned(),
+                    nullable: true,
+                    r#type: ColumnType::ColumnTypeNonScalar(ColumnTypeNonScalar::Object {
+                        name: "ArtistLinks".to_owned(),
+                    }),
+                    updatable: Some(true),
+                    value_generated: None,
+                    computed: None,
+                    default_value: Some(ColumnDefault::Literal { value: json!(null) }),
+                    extensions: None,
+                },
+            ]),
+            deletable: Some(true),
+            description: Some("Recording artists".to_owned()),
+            foreign_keys: Some(FieldMap::default()),
+            insertable: Some(true),
+            name: vec!["Artist".to_owned()],
+            primary_key: Some(vec!["ArtistId".to_owned()]),
+            r#type: Some(TableType::Table),
+            updatable: Some(true),
+            extensions: None,
+        }
+    }
+}
+
+/// The auto-incrementing `ArtistId` primary key column
+impl Example for ColumnInfo {
+    fn example() -> Self {
+        Self {
+            description: Some("The artist's identifier".to_owned()),
+            insertable: Some(false),
+            name: "ArtistId".to_owned(),
+            nullable: false,
+            r#type: ColumnType::Scalar(ScalarType::Number),
+            updatable: Some(false),
+            value_generated: Some(ColumnValueGenerationStrategy::AutoIncrement {}),
+            computed: None,
+            default_value: None,
+            extensions: None,
+        }
+    }
+}
+
+/// The type of the `Artist.Links` column
+impl Example for ObjectTypeDefinition {
+    fn example() -> Self {
+        Self {
+            columns: vec![ColumnInfo {
+                description: Some("The artist's home page".to_owned()),
+                insertable: None,
+                name: "homepage".to_owned(),
+                nullable: true,
+                r#type: ColumnType::Scalar(ScalarType::String),
+                updatable: None,
+                value_generated: None,
+                computed: None,
+                default_value: None,
+                extensions: None,
+            }],
+            description: Some("Links to an artist's pages elsewhere".to_owned()),
+            name: "ArtistLinks".to_owned(),
+            polymorphism: None,
+        }
+    }
+}
+
+impl Example for FunctionInfo {
+    fn example() -> Self {
+        Self {
+            args: Some(vec![FunctionInformationArgument {
+                name: "query".to_owned(),
+                optional: Some(false),
+                r#type: ScalarType::String,
+            }]),
+            description: Some("Artists whose name contains the query".to_owned()),
+            name: vec!["search_artists".to_owned()],
+            response_cardinality: Some(FunctionResponseCardinality::Many),
+            returns: Some(FunctionReturnType::Table {
+                table: vec!["Artist".to_owned()],
+            }),
+            r#type: FunctionType::Read,
+            extensions: None,
+        }
+    }
+}
+
+/// Artists whose name starts with "A", with their albums
+impl Example for QueryRequest {
+    fn example() -> Self {
+        Self {
+            foreach: None,
+            interpolated_queries: None,
+            query: Query::example(),
+            target: Target::Table {
+                name: vec!["Artist".to_owned()],
+            },
+            relationships: vec![TableRelationships::example()],
+            session: Some(
+                [("x-hasura-role".to_owned(), json!("user"))]
+                    .into_iter()
+                    .collect(),
+            ),
+            request_options: Some(RequestOptions {
+                timeout_ms: Some(5_000),
+                max_rows: Some(1_000),
+                hints: Some(
+                    [("statement_timeout".to_owned(), json!("5s"))]
+                        .into_iter()
+                        .collect(),
+                ),
+            }),
+        }
+    }
+}
+
+impl Example for Query {
+    fn example() -> Self {
+        Self {
+            aggregates: Some(
+                [("count".to_owned(), Aggregate::StarCount {})]
+                    .into_iter()
+                    .collect(),
+            ),
+            aggregates_limit: Some(100),
+            fields: Some(
+                [
+                    (
+                        "ArtistId".to_owned(),
+                        Field::Column {
+                            column: "ArtistId".to_owned(),
+                            column_type: ScalarType::Number,
+                            field_path: None,
+                        },
+                    ),
+                    (
+                        "Name".to_owned(),
+                        Field::Column {
+                            column: "Name".to_owned(),
+                            column_type: ScalarType::String,
+                            field_path: None,
+                        },
+                    ),
+                    ("Albums".to_owned(), Field::example()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            limit: Some(10),
+            offset: Some(0),
+            order_by: Some(OrderBy {
+                elements: vec![OrderByElement {
+                    order_direction: OrderDirection::Asc,
+                    target: OrderByTarget::Column {
+                        column: ColumnSelector::Name("Name".to_owned()),
+                        field_path: None,
+                    },
+                    target_path: vec![],
+                    nulls: None,
+                    collation: None,
+                }],
+                relations: FieldMap::default(),
+            }),
+            r#where: Some(Expression::example()),
+            distinct_on: None,
+            include_total_matched: Some(true),
+        }
+    }
+}
+
+/// The `Albums` relationship field, selecting each album's title
+impl Example for Field {
+    fn example() -> Self {
+        Self::Relationship {
+            query: Query {
+                aggregates: None,
+                aggregates_limit: None,
+                fields: Some(
+                    [(
+                        "Title".to_owned(),
+                        Field::Column {
+                            column: "Title".to_owned(),
+                            column_type: ScalarType::String,
+                            field_path: None,
+                        },
+                    )]
+                    .into_iter()
+                    .collect(),
+                ),
+                limit: None,
+                offset: None,
+                order_by: None,
+                r#where: None,
+                distinct_on: None,
+                include_total_matched: None,
+            },
+            relationship: "Albums".to_owned(),
+        }
+    }
+}
+
+/// Artists whose name starts with "A" and have at least one album
+impl Example for Expression {
+    fn example() -> Self {
+        let name = ComparisonColumn {
+            column_type: ScalarType::String,
+            name: ColumnSelector::Name("Name".to_owned()),
+            path: None,
+            field_path: None,
+        };
+        Self::And {
+            expressions: vec![
+                Self::Not {
+                    expression: Box::new(Self::ApplyUnaryComparison {
+                        column: name.clone(),
+                        operator: UnaryComparisonOperator::IsNull,
+                    }),
+                },
+                Self::ApplyBinaryComparison {
+                    column: name,
+                    operator: BinaryComparisonOperator::Like,
+                    value: ComparisonValue::Scalar {
+                        value: json!("A%"),
+                        value_type: ScalarType::String,
+                    },
+                    collation: None,
+                },
+                Self::Exists {
+                    in_table: ExistsInTable::Related {
+                        relationship: "Albums".to_owned(),
+                    },
+                    r#where: Box::new(Self::And {
+                        expressions: vec![],
+                    }),
+                },
+            ],
+        }
+    }
+}
+
+/// The `Albums` array relationship from `Artist` to `Album`
+impl Example for TableRelationships {
+    fn example() -> Self {
+        Self {
+            relationships: [(
+                "Albums".to_owned(),
+                Relationship {
+                    column_mapping: [("ArtistId".to_owned(), "ArtistId".to_owned())]
+                        .into_iter()
+                        .collect(),
+                    relationship_type: RelationshipType::Array,
+                    target: Target::Table {
+                        name: vec!["Album".to_owned()],
+                    },
+                },
+            )]
+            .into_iter()
+            .collect(),
+            source_table: vec!["Artist".to_owned()],
+        }
+    }
+}
+
+impl Example for QueryResponse {
+    fn example() -> Self {
+        Self::Single(ResponseRow::example())
+    }
+}
+
+/// The result of `QueryRequest::example()`
+impl Example for ResponseRow {
+    fn example() -> Self {
+        let albums = |titles: &[&str]| {
+            ResponseFieldValue::Relationship(Box::new(ResponseRow {
+                aggregates: None,
+                rows: Some(
+                    titles
+                        .iter()
+                        .map(|title| {
+                            [("Title".to_owned(), ResponseFieldValue::Column(json!(title)))]
+                                .into_iter()
+                                .collect()
+                        })
+                        .collect(),
+                ),
+                total_matched: None,
+            }))
+        };
+        let artist = |id: u64, name: &str, titles: &[&str]| {
+            [
+                ("ArtistId".to_owned(), ResponseFieldValue::Column(json!(id))),
+                ("Name".to_owned(), ResponseFieldValue::Column(json!(name))),
+                ("Albums".to_owned(), albums(titles)),
+            ]
+            .into_iter()
+            .collect()
+        };
+        Self {
+            aggregates: Some([("count".to_owned(), json!(2))].into_iter().collect()),
+            rows: Some(vec![
+                artist(
+                    1,
+                    "AC/DC",
+                    &["For Those About To Rock We Salute You", "Let There Be Rock"],
+                ),
+                artist(2, "Accept", &["Balls to the Wall", "Restless and Wild"]),
+            ]),
+            total_matched: Some(2),
+        }
+    }
+}
+
+/// Inserts an album, renames it and deletes another, returning the affected rows
+impl Example for MutationRequest {
+    fn example() -> Self {
+        let album = vec!["Album".to_owned()];
+        let returning_fields = || {
+            Some(
+                [
+                    (
+                        "AlbumId".to_owned(),
+                        Field::Column {
+                            column: "AlbumId".to_owned(),
+                            column_type: ScalarType::Number,
+                            field_path: None,
+                        },
+                    ),
+                    (
+                        "Title".to_owned(),
+                        Field::Column {
+                            column: "Title".to_owned(),
+                            column_type: ScalarType::String,
+                            field_path: None,
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            )
+        };
+        let album_id = |id: u64| Expression::ApplyBinaryComparison {
+            column: ComparisonColumn {
+                column_type: ScalarType::Number,
+                name: ColumnSelector::Name("AlbumId".to_owned()),
+                path: None,
+                field_path: None,
+            },
+            operator: BinaryComparisonOperator::Equal,
+            value: ComparisonValue::Scalar {
+                value: json!(id),
+                value_type: ScalarType::Number,
+            },
+            collation: None,
+        };
+        let column = |name: &str, scalar_type: ScalarType| InsertFieldSchema::Column {
+            column: name.to_owned(),
+            column_type: ColumnType::Scalar(scalar_type),
+            nullable: false,
+            value_generated: None,
+        };
+        Self {
+            insert_schema: vec![TableInsertSchema {
+                fields: [
+                    ("Title".to_owned(), column("Title", ScalarType::String)),
+                    (
+                        "ArtistId".to_owned(),
+                        column("ArtistId", ScalarType::Number),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+                primary_key: Some(vec!["AlbumId".to_owned()]),
+                table: album.clone(),
+            }],
+            operations: vec![
+                MutationOperation::Insert {
+                    post_insert_check: None,
+                    returning_fields: returning_fields(),
+                    rows: vec![[
+                        ("Title".to_owned(), json!("High Voltage")),
+                        ("ArtistId".to_owned(), json!(1)),
+                    ]
+                    .into_iter()
+                    .collect()],
+                    table: album.clone(),
+                },
+                MutationOperation::Update {
+                    post_update_check: None,
+                    returning_fields: returning_fields(),
+                    table: album.clone(),
+                    updates: vec![RowUpdate::Set {
+                        column: "Title".to_owned(),
+                        value: json!("Let There Be Rock (Remastered)"),
+                        value_type: ScalarType::String,
+                    }],
+                    r#where: Some(album_id(4)),
+                },
+                MutationOperation::Delete {
+                    returning_fields: returning_fields(),
+                    table: album,
+                    r#where: Some(album_id(3)),
+                },
+            ],
+            relationships: vec![],
+            requested_atomicity: Some(AtomicitySupportLevel::HeterogeneousOperations),
+            interpolated_queries: None,
+            session: Some(
+                [("x-hasura-role".to_owned(), json!("editor"))]
+                    .into_iter()
+                    .collect(),
+            ),
+            request_options: Some(RequestOptions {
+                timeout_ms: Some(5_000),
+                max_rows: None,
+                hints: None,
+            }),
+        }
+    }
+}
+
+impl Example for MutationOperation {
+    fn example() -> Self {
+        MutationRequest::example().operations.swap_remove(0)
+    }
+}
+
+/// The result of `MutationRequest::example()`
+impl Example for MutationResponse {
+    fn example() -> Self {
+        let returning = |id: u64, title: &str| {
+            Some(vec![[
+                ("AlbumId".to_owned(), ResponseFieldValue::Column(json!(id))),
+                ("Title".to_owned(), ResponseFieldValue::Column(json!(title))),
+            ]
+            .into_iter()
+            .collect()])
+        };
+        Self {
+            operation_results: vec![
+                MutationOperationResults {
+                    affected_rows: 1,
+                    returning: returning(348, "High Voltage"),
+                    status: Some(MutationOperationStatus::Succeeded),
+                    error: None,
+                },
+                MutationOperationResults {
+                    affected_rows: 1,
+                    returning: returning(4, "Let There Be Rock (Remastered)"),
+                    status: Some(MutationOperationStatus::Succeeded),
+                    error: None,
+                },
+                MutationOperationResults {
+                    affected_rows: 1,
+                    returning: returning(3, "Restless and Wild"),
+                    status: Some(MutationOperationStatus::Succeeded),
+                    error: None,
+                },
+            ],
+        }
+    }
+}
+
+impl Example for ErrorResponse {
+    fn example() -> Self {
+        Self {
+            details: Some(
+                [("table".to_owned(), json!(["Artist"]))]
+                    .into_iter()
+                    .collect(),
+            ),
+            message: "table Artist has no column Genre".to_owned(),
+            r#type: Some(ErrorResponseType::RequestValidationFailure),
+        }
+    }
+}
+
+impl Example for ExplainResponse {
+    fn example() -> Self {
+        let plan = ExplainPlanNode::example();
+        Self {
+            lines: plan.to_lines(),
+            query: "SELECT \"ArtistId\", \"Name\" FROM \"Artist\" WHERE \"Name\" LIKE 'A%' ORDER BY \"Name\" LIMIT 10".to_owned(),
+            plan: Some(plan),
+        }
+    }
+}
+
+impl Example for ExplainPlanNode {
+    fn example() -> Self {
+        Self {
+            operation: "Limit".to_owned(),
+            target: None,
+            estimated_cost: Some(12.5),
+            estimated_rows: Some(10),
+            details: None,
+            children: vec![Self {
+                operation: "Seq Scan".to_owned(),
+                target: Some(vec!["Artist".to_owned()]),
+                estimated_cost: Some(11.0),
+                estimated_rows: Some(27),
+                details: Some(
+                    [("filter".to_owned(), json!("\"Name\" LIKE 'A%'"))]
+                        .into_iter()
+                        .collect(),
+                ),
+                children: vec![],
+            }],
+        }
+    }
+}
+
+impl Example for HealthCheckRequest {
+    fn example() -> Self {
+        Self {
+            check_data_sources: Some(true),
+        }
+    }
+}
+
+impl Example for HealthCheckResponse {
+    fn example() -> Self {
+        Self {
+            status: HealthStatus::Healthy,
+            data_sources: Some(
+                [(
+                    "chinook".to_owned(),
+                    DataSourceHealth {
+                        status: HealthStatus::Healthy,
+                        message: None,
+                        latency_ms: Some(3),
+                    },
+                )]
+                .into_iter()
+                .collect(),
+            ),
+            version: Some(HealthVersionInfo {
+                agent_version: Some("1.0.0".to_owned()),
+                api_version: Some("0.1.0".to_owned()),
+                backend_version: Some("3.45.0".to_owned()),
+            }),
+        }
+    }
+}
+
+impl Example for LicenseKeyRequest {
+    fn example() -> Self {
+        Self {
+            license_key: "EXAMPLE-LICENSE-KEY".to_owned(),
+        }
+    }
+}
+
+impl Example for LicenseKeyResponse {
+    fn example() -> Self {
+        Self {
+            valid: true,
+            level: Some(LicensingLevel::Enterprise),
+            expires_at: Some("2030-01-01T00:00:00Z".to_owned()),
+            message: None,
+        }
+    }
+}
+
+impl Example for LicenseActivationRequest {
+    fn example() -> Self {
+        Self {
+            license_key: "EXAMPLE-LICENSE-KEY".to_owned(),
+            instance_id: Some("hge-instance-1".to_owned()),
+        }
+    }
+}
+
+impl Example for LicenseActivationResponse {
+    fn example() -> Self {
+        Self {
+            valid: true,
+            activation_id: Some("activation-1".to_owned()),
+            level: Some(LicensingLevel::Enterprise),
+            expires_at: Some("2030-01-01T00:00:00Z".to_owned()),
+            entitlements: Some(vec![
+                LicenseEntitlement::Mutations,
+                LicenseEntitlement::Subscriptions,
+            ]),
+            heartbeat_interval_seconds: Some(3600),
+            message: None,
+        }
+    }
+}
+
+impl Example for LicenseHeartbeatRequest {
+    fn example() -> Self {
+        Self {
+            activation_id: "activation-1".to_owned(),
+            instance_id: Some("hge-instance-1".to_owned()),
+        }
+    }
+}
+
+impl Example for LicenseHeartbeatResponse {
+    fn example() -> Self {
+        Self {
+            valid: true,
+            expires_at: None,
+            entitlements: None,
+            heartbeat_interval_seconds: None,
+            message: None,
+        }
+    }
+}
+
+impl Example for MetricsResponse {
+    fn example() -> Self {
+        Self {
+            families: vec![
+                MetricFamily {
+                    name: "agent_requests_total".to_owned(),
+                    help: Some("The number of requests handled".to_owned()),
+                    r#type: MetricType::Counter,
+                    metrics: vec![Metric {
+                        labels: [("endpoint".to_owned(), "query".to_owned())]
+                            .into_iter()
+                            .collect(),
+                        value: MetricValue::Single { value: 42.0 },
+                        timestamp_ms: Some(1_700_000_000_000),
+                    }],
+                },
+                MetricFamily {
+                    name: "agent_request_duration_seconds".to_owned(),
+                    help: Some("How long requests took".to_owned()),
+                    r#type: MetricType::Histogram,
+                    metrics: vec![Metric {
+                        labels: FieldMap::default(),
+                        value: MetricValue::Histogram {
+                            buckets: vec![
+                                HistogramBucket {
+                                    upper_bound: 0.1,
+                                    cumulative_count: 40,
+                                },
+                                HistogramBucket {
+                                    upper_bound: 1.0,
+                                    cumulative_count: 42,
+                                },
+                            ],
+                            sum: 3.5,
+                            count: 42,
+                        },
+                        timestamp_ms: None,
+                    }],
+                },
+            ],
+        }
+    }
+}
+
+impl Example for RawRequest {
+    fn example() -> Self {
+        Self {
+            query: "SELECT \"ArtistId\", \"Name\" FROM \"Artist\" LIMIT 2".to_owned(),
+            session: Some(
+                [("x-hasura-role".to_owned(), json!("admin"))]
+                    .into_iter()
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// The result of `RawRequest::example()`
+impl Example for RawResponse {
+    fn example() -> Self {
+        let artist = |id: u64, name: &str| {
+            [
+                ("ArtistId".to_owned(), json!(id)),
+                ("Name".to_owned(), json!(name)),
+            ]
+            .into_iter()
+            .collect()
+        };
+        Self {
+            rows: vec![artist(1, "AC/DC"), artist(2, "Accept")],
+        }
+    }
+}
+
+impl Example for DatasetGetTemplateResponse {
+    fn example() -> Self {
+        Self { exists: true }
+    }
+}
+
+impl Example for DatasetCreateCloneRequest {
+    fn example() -> Self {
+        Self {
+            from: "chinook".to_owned(),
+        }
+    }
+}
+
+impl Example for DatasetCreateCloneResponse {
+    fn example() -> Self {
+        Self {
+            config: [("db".to_owned(), json!("/tmp/chinook-clone.sqlite"))]
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl Example for DatasetDeleteCloneResponse {
+    fn example() -> Self {
+        Self {
+            message: "Dataset clone deleted".to_owned(),
+        }
+    }
+}
+
+impl Example for SubscriptionClientMessage {
+    fn example() -> Self {
+        Self::Subscribe {
+            id: "1".to_owned(),
+            query: Box::new(QueryRequest::example()),
+        }
+    }
+}
+
+impl Example for SubscriptionServerMessage {
+    fn example() -> Self {
+        Self::Data {
+            id: "1".to_owned(),
+            data: QueryResponse::example(),
+        }
+    }
+}
@@ -1,9 +1,62 @@
 use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
 
+use crate::{capabilities::TableName, map::FieldMap};
+
+#[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ExplainResponse {
     /// Lines of the formatted explain plan response
     pub lines: Vec<String>,
     /// The generated query - i.e. SQL for a relational DB
     pub query: String,
+    /// The root of the structured explain plan, for agents that declare `supports_structured_plan`
+    pub plan: Option<ExplainPlanNode>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExplainPlanNode {
+    /// The operation performed by this node, e.g. "Seq Scan" or "Hash Join"
+    pub operation: String,
+    /// The table this node reads from, if any
+    pub target: Option<TableName>,
+    /// The backend's estimated cost of this node, in backend-specific units
+    pub estimated_cost: Option<f64>,
+    /// The backend's estimate of the number of rows this node produces
+    pub estimated_rows: Option<u64>,
+    /// Any further backend-specific information about this node
+    pub details: Option<FieldMap<String, serde_json::Value>>,
+    /// The nodes whose output this node consumes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ExplainPlanNode>,
+}
+
+impl ExplainPlanNode {
+    /// Renders the plan as an indented tree, one node per line, suitable for `ExplainResponse.lines`
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        self.push_lines(0, &mut lines);
+        lines
+    }
+
+    fn push_lines(&self, depth: usize, lines: &mut Vec<String>) {
+        let mut line = format!("{}{}", "  ".repeat(depth), self.operation);
+        if let Some(target) = &self.target {
+            line.push_str(&format!(" on {}", target.join(".")));
+        }
+        if let Some(cost) = self.estimated_cost {
+            line.push_str(&format!(" (cost={cost}"));
+            if let Some(rows) = self.estimated_rows {
+                line.push_str(&format!(" rows={rows}"));
+            }
+            line.push(')');
+        } else if let Some(rows) = self.estimated_rows {
+            line.push_str(&format!(" (rows={rows})"));
+        }
+        lines.push(line);
+        for child in &self.children {
+            child.push_lines(depth + 1, lines);
+        }
+    }
 }
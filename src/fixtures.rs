@@ -0,0 +1,161 @@
+use serde::de::DeserializeOwned;
+
+use crate::{
+    capabilities::CapabilitiesResponse,
+    mutation::{MutationRequest, MutationResponse},
+    query::{QueryRequest, QueryResponse},
+    schema::SchemaResponse,
+};
+
+/// A protocol document from the fixture corpus, in the shape HGE sends or expects from agents. The queries are written against the Chinook sample database
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Fixture {
+    /// The file name of the fixture, without the extension
+    pub name: &'static str,
+    pub kind: FixtureKind,
+    /// The document as it appears on the wire
+    pub json: &'static str,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum FixtureKind {
+    QueryRequest,
+    QueryResponse,
+    SchemaResponse,
+    CapabilitiesResponse,
+    MutationRequest,
+    MutationResponse,
+}
+
+impl Fixture {
+    pub fn parse<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(self.json)
+    }
+}
+
+macro_rules! fixtures {
+    ($($kind:ident in $dir:literal: [$($name:literal),* $(,)?]),* $(,)?) => {
+        const FIXTURES: &[Fixture] = &[
+            $($(
+                Fixture {
+                    name: $name,
+                    kind: FixtureKind::$kind,
+                    json: include_str!(concat!(
+                        env!("CARGO_MANIFEST_DIR"),
+                        "/fixtures/",
+                        $dir,
+                        "/",
+                        $name,
+                        ".json"
+                    )),
+                },
+            )*)*
+        ];
+    };
+}
+
+fixtures! {
+    QueryRequest in "query_requests": [
+        "aggregates",
+        "array_relationship",
+        "exists_filter",
+        "foreach",
+        "simple_fields",
+    ],
+    QueryResponse in "query_responses": ["aggregates", "foreach", "rows_with_relationship"],
+    SchemaResponse in "schema_responses": ["chinook_subset", "nested_types"],
+    CapabilitiesResponse in "capabilities_responses": ["sqlite"],
+    MutationRequest in "mutation_requests": ["insert_with_returning", "update_and_delete"],
+    MutationResponse in "mutation_responses": ["insert_with_returning", "update_and_delete"],
+}
+
+/// Every fixture in the corpus
+pub fn all() -> &'static [Fixture] {
+    FIXTURES
+}
+
+pub fn of_kind(kind: FixtureKind) -> impl Iterator<Item = &'static Fixture> {
+    FIXTURES.iter().filter(move |fixture| fixture.kind == kind)
+}
+
+/// The fixtures of a kind, parsed. Every fixture in the corpus is valid, so this does not fail
+fn parsed<T: DeserializeOwned>(kind: FixtureKind) -> impl Iterator<Item = (&'static str, T)> {
+    of_kind(kind).map(|fixture| {
+        let value = fixture
+            .parse()
+            .unwrap_or_else(|error| panic!("fixture {} does not parse: {error}", fixture.name));
+        (fixture.name, value)
+    })
+}
+
+pub fn query_requests() -> impl Iterator<Item = (&'static str, QueryRequest)> {
+    parsed(FixtureKind::QueryRequest)
+}
+
+pub fn query_responses() -> impl Iterator<Item = (&'static str, QueryResponse)> {
+    parsed(FixtureKind::QueryResponse)
+}
+
+pub fn schema_responses() -> impl Iterator<Item = (&'static str, SchemaResponse)> {
+    parsed(FixtureKind::SchemaResponse)
+}
+
+pub fn capabilities_responses() -> impl Iterator<Item = (&'static str, CapabilitiesResponse)> {
+    parsed(FixtureKind::CapabilitiesResponse)
+}
+
+pub fn mutation_requests() -> impl Iterator<Item = (&'static str, MutationRequest)> {
+    parsed(FixtureKind::MutationRequest)
+}
+
+pub fn mutation_responses() -> impl Iterator<Item = (&'static str, MutationResponse)> {
+    parsed(FixtureKind::MutationResponse)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use serde::Serialize;
+
+    use super::*;
+
+    fn round_trips<T>(kind: FixtureKind) -> usize
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        of_kind(kind)
+            .map(|fixture| {
+                let value: T = fixture.parse().unwrap();
+                let json = serde_json::to_string(&value).unwrap();
+                assert_eq!(
+                    serde_json::from_str::<T>(&json).unwrap(),
+                    value,
+                    "{}",
+                    fixture.name
+                );
+            })
+            .count()
+    }
+
+    #[test]
+    fn every_fixture_parses_and_round_trips() {
+        let count = round_trips::<QueryRequest>(FixtureKind::QueryRequest)
+            + round_trips::<QueryResponse>(FixtureKind::QueryResponse)
+            + round_trips::<SchemaResponse>(FixtureKind::SchemaResponse)
+            + round_trips::<CapabilitiesResponse>(FixtureKind::CapabilitiesResponse)
+            + round_trips::<MutationRequest>(FixtureKind::MutationRequest)
+            + round_trips::<MutationResponse>(FixtureKind::MutationResponse);
+        assert_eq!(count, all().len());
+    }
+
+    #[test]
+    fn typed_accessors_parse_every_fixture_of_their_kind() {
+        assert_eq!(query_requests().count(), 5);
+        assert_eq!(query_responses().count(), 3);
+        assert_eq!(schema_responses().count(), 2);
+        assert_eq!(capabilities_responses().count(), 1);
+        assert_eq!(mutation_requests().count(), 2);
+        assert_eq!(mutation_responses().count(), 2);
+    }
+}
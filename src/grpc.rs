@@ -0,0 +1,458 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use bytes::{Buf, BufMut, Bytes};
+use http::uri::PathAndQuery;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tonic::{
+    body::Body,
+    client::GrpcService,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    metadata::{AsciiMetadataValue, MetadataMap},
+    server::{Grpc, NamedService},
+    Code, Status,
+};
+
+use crate::{
+    agent::{decode_config_header, DataConnectorAgent},
+    capabilities::CapabilitiesResponse,
+    dataset::{
+        DatasetCloneName, DatasetCreateCloneRequest, DatasetCreateCloneResponse,
+        DatasetDeleteCloneResponse, DatasetGetTemplateResponse, DatasetTemplateName,
+    },
+    error::{ErrorResponse, ErrorResponseType},
+    explain::ExplainResponse,
+    health::{HealthCheckRequest, HealthCheckResponse},
+    mutation::{MutationRequest, MutationResponse},
+    query::{QueryRequest, QueryResponse},
+    raw::{RawRequest, RawResponse},
+    schema::{SchemaRequest, SchemaResponse},
+};
+
+/// The fully qualified name of the gRPC service
+pub const GRPC_SERVICE_NAME: &str = "hasura.gdc.DataConnector";
+/// The gRPC metadata key carrying the agent configuration, as JSON or base64 encoded JSON
+pub const CONFIG_METADATA_KEY: &str = "x-hasura-dataconnector-config";
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A tonic codec encoding and decoding messages as JSON, used for every method of the gRPC service
+#[derive(Debug)]
+pub struct JsonCodec<E, D>(PhantomData<fn(E) -> D>);
+
+impl<E, D> Default for JsonCodec<E, D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E, D> Codec for JsonCodec<E, D>
+where
+    E: Serialize + Send + 'static,
+    D: DeserializeOwned + Send + 'static,
+{
+    type Encode = E;
+    type Decode = D;
+    type Encoder = JsonEncoder<E>;
+    type Decoder = JsonDecoder<D>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        JsonEncoder(PhantomData)
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        JsonDecoder(PhantomData)
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonEncoder<E>(PhantomData<fn(E)>);
+
+impl<E: Serialize> Encoder for JsonEncoder<E> {
+    type Item = E;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        serde_json::to_writer(dst.writer(), &item)
+            .map_err(|error| Status::internal(format!("failed to encode message: {error}")))
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonDecoder<D>(PhantomData<fn() -> D>);
+
+impl<D: DeserializeOwned> Decoder for JsonDecoder<D> {
+    type Item = D;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let message = src.copy_to_bytes(src.remaining());
+        serde_json::from_slice(&message)
+            .map(Some)
+            .map_err(|error| Status::invalid_argument(format!("failed to decode message: {error}")))
+    }
+}
+
+/// The request message of the `GetDatasetTemplate` method
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GetDatasetTemplateRequest {
+    pub template_name: DatasetTemplateName,
+}
+
+/// The request message of the `CreateDatasetClone` method: the clone name, alongside the fields of a `DatasetCreateCloneRequest`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CreateDatasetCloneRequest {
+    pub clone_name: DatasetCloneName,
+    #[serde(flatten)]
+    pub request: DatasetCreateCloneRequest,
+}
+
+/// The request message of the `DeleteDatasetClone` method
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeleteDatasetCloneRequest {
+    pub clone_name: DatasetCloneName,
+}
+
+/// A tonic service serving the agent over gRPC, with the `hasura.gdc.DataConnector` service name.
+///
+/// Each endpoint is a unary method taking and returning the JSON encoding of these types: `Capabilities` (taking `null`), `Schema`, `Query`, `Explain`, `Mutation`, `Raw`, `Health`, `GetDatasetTemplate`, `CreateDatasetClone` and `DeleteDatasetClone`. The config is read from the `x-hasura-dataconnector-config` metadata. Errors are sent as a `Status` carrying the JSON encoded `ErrorResponse` in its details. Mount it with `tonic::transport::Server::add_service`
+pub struct DataConnectorGrpc<A> {
+    agent: Arc<A>,
+}
+
+impl<A> DataConnectorGrpc<A> {
+    pub fn new(agent: A) -> Self {
+        Self {
+            agent: Arc::new(agent),
+        }
+    }
+}
+
+impl<A> Clone for DataConnectorGrpc<A> {
+    fn clone(&self) -> Self {
+        Self {
+            agent: self.agent.clone(),
+        }
+    }
+}
+
+impl<A> NamedService for DataConnectorGrpc<A> {
+    const NAME: &'static str = GRPC_SERVICE_NAME;
+}
+
+impl<A, B> tower::Service<http::Request<B>> for DataConnectorGrpc<A>
+where
+    A: DataConnectorAgent + 'static,
+    B: http_body::Body + Send + 'static,
+    B::Error: Into<BoxError> + Send,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let agent = self.agent.clone();
+        Box::pin(async move {
+            let method = request
+                .uri()
+                .path()
+                .strip_prefix('/')
+                .and_then(|path| path.strip_prefix(GRPC_SERVICE_NAME))
+                .and_then(|path| path.strip_prefix('/'))
+                .unwrap_or_default()
+                .to_owned();
+            let response = match method.as_str() {
+                "Capabilities" => {
+                    unary(request, |_, ()| async move { agent.capabilities().await }).await
+                }
+                "Schema" => {
+                    unary(request, |metadata, message| async move {
+                        agent.schema(&config::<A>(&metadata)?, message).await
+                    })
+                    .await
+                }
+                "Query" => {
+                    unary(request, |metadata, message| async move {
+                        agent.query(&config::<A>(&metadata)?, message).await
+                    })
+                    .await
+                }
+                "Explain" => {
+                    unary(request, |metadata, message| async move {
+                        agent.query_explain(&config::<A>(&metadata)?, message).await
+                    })
+                    .await
+                }
+                "Mutation" => {
+                    unary(request, |metadata, message| async move {
+                        agent.mutation(&config::<A>(&metadata)?, message).await
+                    })
+                    .await
+                }
+                "Raw" => {
+                    unary(request, |metadata, message| async move {
+                        agent.raw(&config::<A>(&metadata)?, message).await
+                    })
+                    .await
+                }
+                "Health" => {
+                    unary(request, |metadata, message| async move {
+                        let config = if metadata.contains_key(CONFIG_METADATA_KEY) {
+                            Some(config::<A>(&metadata)?)
+                        } else {
+                            None
+                        };
+                        agent.health(config.as_ref(), message).await
+                    })
+                    .await
+                }
+                "GetDatasetTemplate" => {
+                    unary(
+                        request,
+                        |_, message: GetDatasetTemplateRequest| async move {
+                            agent.dataset_get_template(&message.template_name).await
+                        },
+                    )
+                    .await
+                }
+                "CreateDatasetClone" => {
+                    unary(
+                        request,
+                        |_, message: CreateDatasetCloneRequest| async move {
+                            agent
+                                .dataset_create_clone(&message.clone_name, message.request)
+                                .await
+                        },
+                    )
+                    .await
+                }
+                "DeleteDatasetClone" => {
+                    unary(
+                        request,
+                        |_, message: DeleteDatasetCloneRequest| async move {
+                            agent.dataset_delete_clone(&message.clone_name).await
+                        },
+                    )
+                    .await
+                }
+                _ => Status::unimplemented(format!("unknown method {method}")).into_http(),
+            };
+            Ok(response)
+        })
+    }
+}
+
+/// Serves a unary call with the handler, which is given the request metadata and message
+async fn unary<Req, Res, B, F, Fut>(request: http::Request<B>, handler: F) -> http::Response<Body>
+where
+    Req: DeserializeOwned + Send + 'static,
+    Res: Serialize + Send + 'static,
+    B: http_body::Body + Send + 'static,
+    B::Error: Into<BoxError> + Send,
+    F: FnOnce(MetadataMap, Req) -> Fut,
+    Fut: Future<Output = Result<Res, ErrorResponse>>,
+{
+    let mut handler = Some(handler);
+    let service = tower::service_fn(move |request: tonic::Request<Req>| {
+        let handler = handler.take().expect("unary services are called once");
+        let (metadata, _, message) = request.into_parts();
+        let response = handler(metadata, message);
+        async move {
+            response
+                .await
+                .map(tonic::Response::new)
+                .map_err(Status::from)
+        }
+    });
+    Grpc::new(JsonCodec::<Res, Req>::default())
+        .unary(service, request)
+        .await
+}
+
+fn config<A: DataConnectorAgent>(metadata: &MetadataMap) -> Result<A::Config, ErrorResponse> {
+    decode_config_header(
+        metadata
+            .get(CONFIG_METADATA_KEY)
+            .map(|value| value.as_bytes()),
+    )
+}
+
+impl From<ErrorResponse> for Status {
+    /// Maps the error's HTTP status to the closest gRPC code, sending the error itself as JSON in the details
+    fn from(error: ErrorResponse) -> Self {
+        let code = match error.status_code() {
+            400 => Code::InvalidArgument,
+            404 => Code::NotFound,
+            501 => Code::Unimplemented,
+            _ => Code::Internal,
+        };
+        let details = serde_json::to_vec(&error).unwrap_or_default();
+        Status::with_details(code, error.message, Bytes::from(details))
+    }
+}
+
+impl From<Status> for ErrorResponse {
+    /// Decodes the `ErrorResponse` sent in the details, or reports the status as an `uncaught-error`
+    fn from(status: Status) -> Self {
+        serde_json::from_slice(status.details()).unwrap_or_else(|_| ErrorResponse {
+            details: None,
+            message: format!(
+                "gRPC call failed with {}: {}",
+                status.code(),
+                status.message()
+            ),
+            r#type: Some(ErrorResponseType::UncaughtError),
+        })
+    }
+}
+
+/// A client for an agent served over gRPC by `DataConnectorGrpc`, over any tonic transport such as `tonic::transport::Channel`.
+///
+/// Errors sent by the agent are decoded into the `ErrorResponse` it sent. Other failed calls are reported as `uncaught-error` errors
+#[derive(Clone, Debug)]
+pub struct GrpcAgentClient<T> {
+    inner: tonic::client::Grpc<T>,
+    config: Option<AsciiMetadataValue>,
+}
+
+impl<T> GrpcAgentClient<T>
+where
+    T: GrpcService<Body>,
+    T::Error: Into<BoxError>,
+    T::ResponseBody: http_body::Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as http_body::Body>::Error: Into<BoxError> + Send,
+{
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner: tonic::client::Grpc::new(inner),
+            config: None,
+        }
+    }
+
+    /// Sends the config in the `x-hasura-dataconnector-config` metadata, base64 encoded so that any JSON is valid metadata
+    pub fn with_config<C: Serialize>(mut self, config: &C) -> serde_json::Result<Self> {
+        let config = BASE64.encode(serde_json::to_vec(config)?);
+        self.config = Some(config.parse().expect("base64 is valid metadata"));
+        Ok(self)
+    }
+
+    pub async fn capabilities(&mut self) -> Result<CapabilitiesResponse, ErrorResponse> {
+        self.unary("Capabilities", ()).await
+    }
+
+    pub async fn schema(
+        &mut self,
+        request: SchemaRequest,
+    ) -> Result<SchemaResponse, ErrorResponse> {
+        self.unary("Schema", request).await
+    }
+
+    pub async fn query(&mut self, request: QueryRequest) -> Result<QueryResponse, ErrorResponse> {
+        self.unary("Query", request).await
+    }
+
+    pub async fn explain(
+        &mut self,
+        request: QueryRequest,
+    ) -> Result<ExplainResponse, ErrorResponse> {
+        self.unary("Explain", request).await
+    }
+
+    pub async fn mutation(
+        &mut self,
+        request: MutationRequest,
+    ) -> Result<MutationResponse, ErrorResponse> {
+        self.unary("Mutation", request).await
+    }
+
+    pub async fn raw(&mut self, request: RawRequest) -> Result<RawResponse, ErrorResponse> {
+        self.unary("Raw", request).await
+    }
+
+    pub async fn health(
+        &mut self,
+        request: HealthCheckRequest,
+    ) -> Result<HealthCheckResponse, ErrorResponse> {
+        self.unary("Health", request).await
+    }
+
+    pub async fn dataset_get_template(
+        &mut self,
+        template_name: DatasetTemplateName,
+    ) -> Result<DatasetGetTemplateResponse, ErrorResponse> {
+        self.unary(
+            "GetDatasetTemplate",
+            GetDatasetTemplateRequest { template_name },
+        )
+        .await
+    }
+
+    pub async fn dataset_create_clone(
+        &mut self,
+        clone_name: DatasetCloneName,
+        request: DatasetCreateCloneRequest,
+    ) -> Result<DatasetCreateCloneResponse, ErrorResponse> {
+        self.unary(
+            "CreateDatasetClone",
+            CreateDatasetCloneRequest {
+                clone_name,
+                request,
+            },
+        )
+        .await
+    }
+
+    pub async fn dataset_delete_clone(
+        &mut self,
+        clone_name: DatasetCloneName,
+    ) -> Result<DatasetDeleteCloneResponse, ErrorResponse> {
+        self.unary(
+            "DeleteDatasetClone",
+            DeleteDatasetCloneRequest { clone_name },
+        )
+        .await
+    }
+
+    async fn unary<Req, Res>(&mut self, method: &str, message: Req) -> Result<Res, ErrorResponse>
+    where
+        Req: Serialize + Send + Sync + 'static,
+        Res: DeserializeOwned + Send + Sync + 'static,
+    {
+        self.inner.ready().await.map_err(|error| {
+            let error: BoxError = error.into();
+            uncaught(format!("failed to reach agent: {error}"))
+        })?;
+        let mut request = tonic::Request::new(message);
+        if let Some(config) = &self.config {
+            request
+                .metadata_mut()
+                .insert(CONFIG_METADATA_KEY, config.clone());
+        }
+        let path = PathAndQuery::try_from(format!("/{GRPC_SERVICE_NAME}/{method}"))
+            .map_err(|error| uncaught(format!("invalid method {method}: {error}")))?;
+        self.inner
+            .unary(request, path, JsonCodec::<Req, Res>::default())
+            .await
+            .map(tonic::Response::into_inner)
+            .map_err(ErrorResponse::from)
+    }
+}
+
+fn uncaught(message: String) -> ErrorResponse {
+    ErrorResponse {
+        details: None,
+        message,
+        r#type: Some(ErrorResponseType::UncaughtError),
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::map::FieldMap;
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct HealthCheckRequest {
+    /// Whether to check connectivity to the configured data sources, rather than only the agent itself
+    pub check_data_sources: Option<bool>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HealthCheckResponse {
+    /// The overall health of the agent
+    pub status: HealthStatus,
+    /// Health of each data source checked, keyed by source name
+    pub data_sources: Option<FieldMap<String, DataSourceHealth>>,
+    pub version: Option<HealthVersionInfo>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    /// The agent can serve some requests, i.e. because one of several data sources is unreachable
+    Degraded,
+    Unhealthy,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DataSourceHealth {
+    pub status: HealthStatus,
+    /// Why the data source is not healthy
+    pub message: Option<String>,
+    /// How long the connectivity check took, in milliseconds
+    pub latency_ms: Option<u64>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct HealthVersionInfo {
+    /// The version of the agent
+    pub agent_version: Option<String>,
+    /// The version of the data connector API the agent implements
+    pub api_version: Option<String>,
+    /// The version of the backend database, if known
+    pub backend_version: Option<String>,
+}
@@ -0,0 +1,235 @@
+use indexmap::IndexMap;
+
+use crate::{
+    capabilities::TableName,
+    map::FieldMap,
+    query::{Relationship, RelationshipType, TableRelationships, Target},
+    schema::SchemaResponse,
+};
+
+impl SchemaResponse {
+    /// Derives relationships from the foreign keys of the schema's tables, in the shape of `QueryRequest.relationships`.
+    ///
+    /// Each foreign key gives an object relationship from the referencing table to the referenced one, named after the foreign key, and an array relationship back, named after the plural of the referencing table's name, e.g. `Album.Artist` and `Artist.Albums`. If that name is already taken on the referenced table, the foreign key's name is appended, e.g. `Albums_by_Artist`, followed by `_2`, `_3` and so on if that is taken too, so no relationship is ever replaced. Foreign keys to tables not in the schema are skipped
+    pub fn inferred_relationships(&self) -> Vec<TableRelationships> {
+        let mut by_table: IndexMap<&TableName, FieldMap<String, Relationship>> = IndexMap::new();
+        for table in &self.tables {
+            by_table.entry(&table.name).or_default();
+        }
+        let foreign_keys = || {
+            self.tables.iter().flat_map(|table| {
+                table
+                    .foreign_keys
+                    .iter()
+                    .flatten()
+                    .map(move |(name, constraint)| (&table.name, name, constraint))
+            })
+        };
+        // Object relationships are added first, so that their names are kept and array relationships are renamed around them
+        for (table, name, constraint) in foreign_keys() {
+            if !by_table.contains_key(&constraint.foreign_table) {
+                continue;
+            }
+            let object = Relationship {
+                column_mapping: constraint.column_mapping.clone(),
+                relationship_type: RelationshipType::Object,
+                target: Target::Table {
+                    name: constraint.foreign_table.clone(),
+                },
+            };
+            by_table[table].insert(name.clone(), object);
+        }
+        for (table, name, constraint) in foreign_keys() {
+            let Some(referenced) = by_table.get_mut(&constraint.foreign_table) else {
+                continue;
+            };
+            let array = Relationship {
+                column_mapping: constraint
+                    .column_mapping
+                    .iter()
+                    .map(|(source, target)| (target.clone(), source.clone()))
+                    .collect(),
+                relationship_type: RelationshipType::Array,
+                target: Target::Table {
+                    name: table.clone(),
+                },
+            };
+            let plural = plural(table.last().map_or("", String::as_str));
+            let mut array_name = plural.clone();
+            let mut suffix = 1;
+            while referenced.contains_key(&array_name) {
+                array_name = match suffix {
+                    1 => format!("{plural}_by_{name}"),
+                    _ => format!("{plural}_by_{name}_{suffix}"),
+                };
+                suffix += 1;
+            }
+            referenced.insert(array_name, array);
+        }
+        by_table
+            .into_iter()
+            .filter(|(_, relationships)| !relationships.is_empty())
+            .map(|(source_table, relationships)| TableRelationships {
+                relationships,
+                source_table: source_table.clone(),
+            })
+            .collect()
+    }
+}
+
+/// The English plural of a table name, by the regular rules only
+fn plural(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    if ["s", "x", "z", "ch", "sh"]
+        .iter()
+        .any(|suffix| lower.ends_with(suffix))
+    {
+        format!("{name}es")
+    } else if lower.ends_with('y')
+        && !lower.ends_with("ay")
+        && !lower.ends_with("ey")
+        && !lower.ends_with("oy")
+        && !lower.ends_with("uy")
+    {
+        format!("{}ies", &name[..name.len() - 1])
+    } else {
+        format!("{name}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema(tables: serde_json::Value) -> SchemaResponse {
+        serde_json::from_value(json!({ "tables": tables })).unwrap()
+    }
+
+    /// A relationship's name, type and target table
+    type Summary = (String, RelationshipType, String);
+
+    /// The relationships of each table
+    fn summary(schema: &SchemaResponse) -> Vec<(String, Vec<Summary>)> {
+        schema
+            .inferred_relationships()
+            .into_iter()
+            .map(|table| {
+                let relationships = table
+                    .relationships
+                    .into_iter()
+                    .map(|(name, relationship)| {
+                        let Target::Table { name: target } = relationship.target else {
+                            panic!("relationships target tables");
+                        };
+                        (name, relationship.relationship_type, target.join("."))
+                    })
+                    .collect();
+                (table.source_table.join("."), relationships)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn infers_both_directions() {
+        let schema: SchemaResponse = serde_json::from_str(include_str!(
+            "../fixtures/schema_responses/chinook_subset.json"
+        ))
+        .unwrap();
+        let relationships = schema.inferred_relationships();
+        assert_eq!(
+            serde_json::to_value(relationships).unwrap(),
+            json!([
+                {
+                    "source_table": ["Artist"],
+                    "relationships": {
+                        "Albums": {
+                            "target": { "type": "table", "name": ["Album"] },
+                            "relationship_type": "array",
+                            "column_mapping": { "ArtistId": "ArtistId" },
+                        },
+                    },
+                },
+                {
+                    "source_table": ["Album"],
+                    "relationships": {
+                        "Artist": {
+                            "target": { "type": "table", "name": ["Artist"] },
+                            "relationship_type": "object",
+                            "column_mapping": { "ArtistId": "ArtistId" },
+                        },
+                    },
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn colliding_names_are_suffixed() {
+        let schema = schema(json!([
+            {
+                "name": ["Artist"],
+                "foreign_keys": {
+                    "Albums": { "column_mapping": { "LatestAlbumId": "AlbumId" }, "foreign_table": ["Album"] },
+                    "Albums_by_Artist": { "column_mapping": { "FirstAlbumId": "AlbumId" }, "foreign_table": ["Album"] },
+                },
+            },
+            {
+                "name": ["Album"],
+                "foreign_keys": {
+                    "Artist": { "column_mapping": { "ArtistId": "ArtistId" }, "foreign_table": ["Artist"] },
+                    "Producer": { "column_mapping": { "ProducerId": "ArtistId" }, "foreign_table": ["Artist"] },
+                },
+            },
+        ]));
+        let artist_names: Vec<_> = summary(&schema)[0]
+            .1
+            .iter()
+            .map(|(name, _, _)| name.clone())
+            .collect();
+        assert_eq!(
+            artist_names,
+            [
+                "Albums",
+                "Albums_by_Artist",
+                "Albums_by_Artist_2",
+                "Albums_by_Producer",
+            ]
+        );
+        let album = &summary(&schema)[1].1;
+        assert_eq!(album.len(), 4);
+        assert!(album.iter().any(|relationship| relationship
+            == &(
+                "Artists".to_owned(),
+                RelationshipType::Array,
+                "Artist".to_owned()
+            )));
+    }
+
+    #[test]
+    fn foreign_keys_to_unknown_tables_are_skipped() {
+        let schema = schema(json!([{
+            "name": ["Track"],
+            "foreign_keys": {
+                "Genre": { "column_mapping": { "GenreId": "GenreId" }, "foreign_table": ["Genre"] },
+            },
+        }]));
+        assert!(schema.inferred_relationships().is_empty());
+    }
+
+    #[test]
+    fn plurals() {
+        for (name, expected) in [
+            ("Album", "Albums"),
+            ("Address", "Addresses"),
+            ("Box", "Boxes"),
+            ("Batch", "Batches"),
+            ("Category", "Categories"),
+            ("Day", "Days"),
+            ("Key", "Keys"),
+        ] {
+            assert_eq!(plural(name), expected);
+        }
+    }
+}
@@ -1,15 +1,172 @@
+#[cfg(feature = "actix-web")]
+mod actix;
+#[cfg(feature = "agent")]
+mod agent;
+#[cfg(feature = "proptest")]
+mod arbitrary;
+mod arena;
+#[cfg(feature = "axum")]
+mod axum_router;
+mod batch;
+mod borrowed;
+mod builder;
+mod canonical;
 mod capabilities;
+#[cfg(feature = "tower")]
+mod capability_layer;
+#[cfg(feature = "chinook")]
+pub mod chinook;
+#[cfg(feature = "reqwest")]
+mod client;
+mod columnar;
+#[cfg(feature = "msgpack")]
+mod compact;
+mod contract;
+mod dataset;
+mod dependencies;
+mod diagram;
+mod diff;
+#[cfg(feature = "digest")]
+mod digest;
+#[cfg(any(
+    feature = "actix-web",
+    feature = "axum",
+    feature = "logging",
+    feature = "service"
+))]
+// With `logging` alone only the summaries are used
+#[cfg_attr(
+    not(any(feature = "actix-web", feature = "axum", feature = "service")),
+    allow(dead_code)
+)]
+mod dispatch;
+mod display;
+#[cfg(feature = "raw_value")]
+mod envelope;
 mod error;
+#[cfg(feature = "example")]
+mod example;
 mod explain;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod health;
+mod inference;
+mod licensing;
+#[cfg(feature = "logging")]
+mod logging_layer;
+mod map;
+#[cfg(feature = "memory")]
+mod memory;
+mod metrics;
+mod minimize;
+#[cfg(feature = "mock")]
+mod mock;
 mod mutation;
+#[cfg(feature = "raw_value")]
+mod opaque;
+mod preserve;
+mod protocol;
 mod query;
+#[cfg(feature = "quickcheck")]
+mod quickcheck_arbitrary;
 mod raw;
+mod redact;
+mod registry;
+mod response;
+mod rewrite;
+mod routing;
 mod schema;
+#[cfg(feature = "service")]
+mod service;
+#[cfg(feature = "simd-json")]
+mod simd;
+mod stats;
+mod stream;
+mod strict;
+mod subscription;
+mod supported;
+mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod validate;
+mod value;
+mod version;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+#[cfg(feature = "actix-web")]
+pub use actix::*;
+#[cfg(feature = "agent")]
+pub use agent::*;
+#[cfg(feature = "proptest")]
+pub use arbitrary::*;
+pub use arena::*;
+#[cfg(feature = "axum")]
+pub use axum_router::*;
+pub use batch::*;
+pub use borrowed::*;
+pub use builder::*;
+pub use canonical::*;
 pub use capabilities::*;
+#[cfg(feature = "tower")]
+pub use capability_layer::*;
+#[cfg(feature = "reqwest")]
+pub use client::*;
+pub use columnar::*;
+#[cfg(feature = "msgpack")]
+pub use compact::*;
+pub use contract::*;
+pub use dataset::*;
+pub use dependencies::*;
+pub use diagram::*;
+pub use diff::*;
+#[cfg(feature = "digest")]
+pub use digest::*;
+pub use display::*;
+#[cfg(feature = "raw_value")]
+pub use envelope::*;
 pub use error::*;
+#[cfg(feature = "example")]
+pub use example::*;
 pub use explain::*;
+#[cfg(feature = "grpc")]
+pub use grpc::*;
+pub use health::*;
+pub use licensing::*;
+#[cfg(feature = "logging")]
+pub use logging_layer::*;
+pub use map::*;
+#[cfg(feature = "memory")]
+pub use memory::*;
+pub use metrics::*;
+pub use minimize::*;
+#[cfg(feature = "mock")]
+pub use mock::*;
 pub use mutation::*;
+#[cfg(feature = "raw_value")]
+pub use opaque::*;
+pub use preserve::*;
+pub use protocol::*;
 pub use query::*;
 pub use raw::*;
+pub use registry::*;
+pub use response::*;
+pub use rewrite::*;
+pub use routing::*;
 pub use schema::*;
+#[cfg(feature = "service")]
+pub use service::*;
+#[cfg(feature = "simd-json")]
+pub use simd::*;
+pub use stats::*;
+pub use stream::*;
+pub use strict::*;
+pub use subscription::*;
+pub use telemetry::*;
+pub use validate::*;
+pub use value::*;
+pub use version::*;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
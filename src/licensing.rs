@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use serde_enum_str::{
+    Deserialize_enum_str as DeserializeEnumStr, Serialize_enum_str as SerializeEnumStr,
+};
+use serde_with::skip_serializing_none;
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, SerializeEnumStr, DeserializeEnumStr)]
+#[serde(rename_all = "snake_case")]
+pub enum LicensingLevel {
+    Free,
+    Trial,
+    Standard,
+    Enterprise,
+    #[serde(other)]
+    Other(String),
+}
+
+/// Sent by HGE to hand the agent a license key to validate
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LicenseKeyRequest {
+    /// The license key, as configured in HGE
+    pub license_key: String,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LicenseKeyResponse {
+    /// Whether the agent accepted the license key
+    pub valid: bool,
+    /// The licensing level granted by the key
+    pub level: Option<LicensingLevel>,
+    /// When the license expires, as an RFC 3339 timestamp. Absent means the license does not expire
+    pub expires_at: Option<String>,
+    /// A human readable explanation, i.e. why the key was rejected
+    pub message: Option<String>,
+}
+
+/// A feature a license entitles the agent to serve
+#[derive(Clone, Debug, Eq, PartialEq, Hash, SerializeEnumStr, DeserializeEnumStr)]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseEntitlement {
+    Mutations,
+    Subscriptions,
+    Datasets,
+    Raw,
+    Explain,
+    Metrics,
+    #[serde(other)]
+    Other(String),
+}
+
+/// Sent by HGE to activate a license key for one of its instances
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LicenseActivationRequest {
+    /// The license key, as configured in HGE
+    pub license_key: String,
+    /// An identifier of the HGE instance activating the key, for licenses limited to a number of instances
+    pub instance_id: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LicenseActivationResponse {
+    /// Whether the agent accepted the license key
+    pub valid: bool,
+    /// Identifies the activation in heartbeats. Absent if the key was rejected
+    pub activation_id: Option<String>,
+    /// The licensing level granted by the key
+    pub level: Option<LicensingLevel>,
+    /// When the license expires, as an RFC 3339 timestamp. Absent means the license does not expire
+    pub expires_at: Option<String>,
+    /// The features the license entitles the agent to serve. Absent means every feature the licensing level includes
+    pub entitlements: Option<Vec<LicenseEntitlement>>,
+    /// How often HGE should send a heartbeat to keep the activation alive. Absent means no heartbeats are needed
+    pub heartbeat_interval_seconds: Option<u64>,
+    /// A human readable explanation, i.e. why the key was rejected
+    pub message: Option<String>,
+}
+
+/// Sent by HGE periodically to keep an activation alive and pick up changes to the license
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LicenseHeartbeatRequest {
+    /// The `activation_id` of the activation being kept alive
+    pub activation_id: String,
+    pub instance_id: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LicenseHeartbeatResponse {
+    /// Whether the activation is still valid. HGE should activate the key again if it is not
+    pub valid: bool,
+    /// When the license expires, which may have changed since activation
+    pub expires_at: Option<String>,
+    /// The features the license now entitles the agent to serve, if they have changed since activation
+    pub entitlements: Option<Vec<LicenseEntitlement>>,
+    /// The interval until the next heartbeat, if it has changed
+    pub heartbeat_interval_seconds: Option<u64>,
+    pub message: Option<String>,
+}
@@ -0,0 +1,196 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use http::{Method, Request, Response};
+use tower::{Layer, Service};
+
+use crate::{
+    dispatch::{Summary, SummaryRequested},
+    protocol::Endpoint,
+    telemetry::{AttributeValue, SpanAttributes},
+};
+
+/// The `tracing` target request summaries are logged under
+pub const LOGGING_TARGET: &str = "gdc_rust_types::logging";
+
+/// A tower layer that logs a structured summary of each request to the agent and its response as a `tracing` event.
+///
+/// Summaries never include literals, session variables or row data. Each event records the endpoint, method, status and duration. When the inner service is this crate's `router` or `AgentService`, which describe each request from its decoded body as they serve it, query and mutation requests add their span attributes, queries their literal-free digest, and responses their span attributes, such as row counts. Error responses add their error type only, as agent error messages may quote user data. Bodies are neither buffered nor parsed by the layer, so responses stream through it unchanged, and other inner services are logged with the endpoint, method, status and duration only. Events are logged at `info` level, or `warn` for error responses. Requests to paths that are not agent endpoints are not logged
+#[derive(Clone, Debug)]
+pub struct LoggingLayer {
+    sampling: [u64; Endpoint::ALL.len()],
+    redacted_bodies: bool,
+    counters: Arc<[AtomicU64; Endpoint::ALL.len()]>,
+}
+
+impl LoggingLayer {
+    /// Logs every request
+    pub fn new() -> Self {
+        Self {
+            sampling: [1; Endpoint::ALL.len()],
+            redacted_bodies: false,
+            counters: Arc::default(),
+        }
+    }
+
+    /// Logs the first of every `one_in` requests to the endpoint, or none if `one_in` is 0, e.g. to quieten `/health` checks
+    pub fn with_sampling(mut self, endpoint: Endpoint, one_in: u64) -> Self {
+        self.sampling[index(endpoint)] = one_in;
+        self
+    }
+
+    /// Also logs the `redacted` JSON of query, mutation and raw request bodies decoded by `router` or `AgentService` at `debug` level
+    pub fn with_redacted_bodies(mut self, redacted_bodies: bool) -> Self {
+        self.redacted_bodies = redacted_bodies;
+        self
+    }
+
+    fn sampled(&self, endpoint: Endpoint) -> bool {
+        let one_in = self.sampling[index(endpoint)];
+        let seen = self.counters[index(endpoint)].fetch_add(1, Ordering::Relaxed);
+        one_in != 0 && seen.is_multiple_of(one_in)
+    }
+}
+
+impl Default for LoggingLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for LoggingLayer {
+    type Service = LoggingService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoggingService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// The service produced by `LoggingLayer`
+#[derive(Clone, Debug)]
+pub struct LoggingService<S> {
+    inner: S,
+    layer: LoggingLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for LoggingService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        // The ready service is the one that must be called, so a fresh clone is left in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let endpoint = Endpoint::of_path(request.uri().path())
+            .filter(|endpoint| self.layer.sampled(*endpoint));
+        let Some(endpoint) = endpoint else {
+            return Box::pin(inner.call(request));
+        };
+        request.extensions_mut().insert(SummaryRequested {
+            redacted_bodies: self.layer.redacted_bodies,
+        });
+        let method = request.method().clone();
+        Box::pin(async move {
+            let start = Instant::now();
+            let response = inner.call(request).await?;
+            log(endpoint, &method, &response, start);
+            Ok(response)
+        })
+    }
+}
+
+fn log<B>(endpoint: Endpoint, method: &Method, response: &Response<B>, start: Instant) {
+    let status = response.status();
+    let duration_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let summary = response.extensions().get::<Summary>();
+    if let Some(body) = summary.and_then(|summary| summary.redacted_body.as_ref()) {
+        tracing::debug!(
+            target: LOGGING_TARGET,
+            endpoint = endpoint.as_str(),
+            body = %body,
+            "agent request body"
+        );
+    }
+    let request = summary
+        .and_then(|summary| summary.request_attributes.as_ref())
+        .map(format_attributes);
+    let digest = summary.and_then(|summary| summary.digest.as_deref());
+    if status.is_success() {
+        let attributes = summary
+            .and_then(|summary| summary.response_attributes.as_ref())
+            .map(format_attributes);
+        tracing::info!(
+            target: LOGGING_TARGET,
+            endpoint = endpoint.as_str(),
+            method = method.as_str(),
+            status = status.as_u16(),
+            duration_ms,
+            request = request.as_deref(),
+            digest,
+            response = attributes.as_deref(),
+            "agent request"
+        );
+    } else {
+        let error_type = summary
+            .and_then(|summary| summary.error_type.as_ref())
+            .map(ToString::to_string);
+        tracing::warn!(
+            target: LOGGING_TARGET,
+            endpoint = endpoint.as_str(),
+            method = method.as_str(),
+            status = status.as_u16(),
+            duration_ms,
+            request = request.as_deref(),
+            digest,
+            error_type = error_type.as_deref(),
+            "agent request failed"
+        );
+    }
+}
+
+/// Formats attributes as space separated `key=value` pairs, with arrays as comma separated lists
+fn format_attributes(attributes: &SpanAttributes) -> String {
+    attributes
+        .iter()
+        .map(|(key, value)| match value {
+            AttributeValue::I64(value) => format!("{key}={value}"),
+            AttributeValue::String(value) => format!("{key}={value}"),
+            AttributeValue::StringArray(values) => format!("{key}={}", values.join(",")),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn index(endpoint: Endpoint) -> usize {
+    match endpoint {
+        Endpoint::Capabilities => 0,
+        Endpoint::Schema => 1,
+        Endpoint::Query => 2,
+        Endpoint::Explain => 3,
+        Endpoint::Mutation => 4,
+        Endpoint::Raw => 5,
+        Endpoint::Health => 6,
+        Endpoint::Datasets => 7,
+    }
+}
@@ -0,0 +1,33 @@
+use indexmap::IndexMap;
+
+/// The hasher of the request and response types' maps: `ahash` with the `ahash` feature, else `fxhash` with the `fxhash` feature, else the standard library's
+#[cfg(feature = "ahash")]
+pub type FieldHasher = ahash::RandomState;
+#[cfg(all(feature = "fxhash", not(feature = "ahash")))]
+pub type FieldHasher = fxhash::FxBuildHasher;
+#[cfg(not(any(feature = "ahash", feature = "fxhash")))]
+pub type FieldHasher = std::collections::hash_map::RandomState;
+
+/// An insertion ordered map hashed with the `FieldHasher`. Every map in the request and response types is one, except `ErrorResponse.details`. Build them with `FieldMap::default()` or `collect`, as `IndexMap::new` only exists for the standard library's hasher
+pub type FieldMap<K, V, S = FieldHasher> = IndexMap<K, V, S>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryRequest;
+
+    #[test]
+    fn request_maps_use_the_field_hasher() {
+        let request: QueryRequest = serde_json::from_value(serde_json::json!({
+            "target": {"type": "table", "name": ["Artist"]},
+            "relationships": [],
+            "query": {"fields": {"Name": {"type": "column", "column": "Name", "column_type": "string"}}},
+            "session": {"x-hasura-role": "user"},
+        }))
+        .unwrap();
+        let fields: &FieldMap<String, _, FieldHasher> = request.query.fields.as_ref().unwrap();
+        let session: &FieldMap<String, _, FieldHasher> = request.session.as_ref().unwrap();
+        assert!(fields.contains_key("Name"));
+        assert_eq!(session["x-hasura-role"], "user");
+    }
+}
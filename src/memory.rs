@@ -0,0 +1,1256 @@
+use std::{cmp::Ordering, fmt};
+
+use indexmap::IndexMap;
+use serde_json::Value;
+
+use crate::{
+    capabilities::{Capabilities, ColumnName, ScalarType, ScalarTypeCapabilities, TableName},
+    error::{
+        ConstraintViolationDetails, ErrorResponse, ErrorResponseType, PermissionCheckFailureDetails,
+    },
+    map::FieldMap,
+    mutation::{
+        InsertFieldSchema, MutationOperation, MutationOperationResults, MutationRequest,
+        MutationResponse, RowUpdate,
+    },
+    query::{
+        Aggregate, BinaryArrayComparisonOperator, BinaryComparisonOperator, ColumnSelector,
+        ComparisonColumn, ComparisonValue, ExistsInTable, Expression, Field, Query,
+        ResponseFieldValue, ResponseRow, TableRelationships, Target, UnaryComparisonOperator,
+    },
+    schema::{
+        ColumnDefault, ColumnInfo, ColumnType, ColumnValueGenerationStrategy, SchemaResponse,
+        TableInfo,
+    },
+};
+
+/// A row of a `MemoryTable`, keyed by column name
+pub type MemoryRow = IndexMap<ColumnName, Value>;
+
+/// Applies a custom update operator to a column's current value and the operator's argument. Returns `None` if the operator does not apply to the values
+pub type UpdateOperatorFn = fn(&Value, &Value) -> Option<Value>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryTable {
+    pub info: TableInfo,
+    pub rows: Vec<MemoryRow>,
+}
+
+/// Tables held in memory that mutation requests can be executed against, as a reference for the results an agent should produce in differential tests.
+///
+/// Custom update operators must be declared for the column's scalar type in the capabilities given to `with_capabilities`, and implemented by a function registered with `with_update_operator`. `inc`, `dec`, `mul`, `append` and `prepend` are registered by default
+#[derive(Clone, Debug)]
+pub struct MemoryDatabase {
+    tables: IndexMap<TableName, MemoryTable>,
+    scalar_types: FieldMap<ScalarType, ScalarTypeCapabilities>,
+    update_operators: IndexMap<String, UpdateOperatorFn>,
+    generated_ids: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExecutionError {
+    UnknownTable(TableName),
+    UnknownColumn {
+        table: TableName,
+        column: ColumnName,
+    },
+    UnknownRelationship {
+        table: TableName,
+        relationship: String,
+    },
+    /// A row would have a null value in a column that is not nullable
+    NotNullViolation {
+        table: TableName,
+        column: ColumnName,
+        operation: usize,
+        row: usize,
+    },
+    /// A row did not satisfy the `post_insert_check` or `post_update_check` of an operation
+    CheckFailed {
+        table: TableName,
+        operation: usize,
+        row: usize,
+    },
+    /// A custom update operator is not declared for the column's scalar type in the capabilities
+    UndeclaredOperator {
+        operator: String,
+        scalar_type: ScalarType,
+    },
+    /// A custom update operator could not be applied to a column's value
+    InvalidOperand {
+        operator: String,
+        column: ColumnName,
+    },
+    /// The request uses a feature the in-memory executor does not implement
+    Unsupported(String),
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTable(table) => write!(f, "table {} does not exist", table.join(".")),
+            Self::UnknownColumn { table, column } => {
+                write!(f, "table {} has no column {column}", table.join("."))
+            }
+            Self::UnknownRelationship {
+                table,
+                relationship,
+            } => write!(
+                f,
+                "relationship {relationship} is not defined for table {}",
+                table.join(".")
+            ),
+            Self::NotNullViolation { table, column, .. } => write!(
+                f,
+                "column {column} of table {} is not nullable",
+                table.join(".")
+            ),
+            Self::CheckFailed {
+                table, operation, ..
+            } => write!(
+                f,
+                "a row of table {} failed the check of operation {operation}",
+                table.join(".")
+            ),
+            Self::UndeclaredOperator {
+                operator,
+                scalar_type,
+            } => write!(
+                f,
+                "update operator {operator} is not declared for scalar type {scalar_type}"
+            ),
+            Self::InvalidOperand { operator, column } => {
+                write!(
+                    f,
+                    "update operator {operator} cannot be applied to column {column}"
+                )
+            }
+            Self::Unsupported(feature) => {
+                write!(f, "{feature} is not supported by the in-memory executor")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+impl From<ExecutionError> for ErrorResponse {
+    fn from(error: ExecutionError) -> Self {
+        let message = error.to_string();
+        let r#type = match error {
+            ExecutionError::NotNullViolation {
+                table,
+                column,
+                operation,
+                row,
+            } => {
+                return ErrorResponse::with_details(
+                    message,
+                    &ConstraintViolationDetails {
+                        table,
+                        operation_index: operation,
+                        row_index: Some(row),
+                        constraint: None,
+                        columns: Some(vec![column]),
+                    },
+                )
+            }
+            ExecutionError::CheckFailed {
+                table,
+                operation,
+                row,
+            } => {
+                return ErrorResponse::with_details(
+                    message,
+                    &PermissionCheckFailureDetails {
+                        table,
+                        operation_index: operation,
+                        row_index: Some(row),
+                    },
+                )
+            }
+            ExecutionError::Unsupported(_) => ErrorResponseType::UnsupportedOperation,
+            _ => ErrorResponseType::RequestValidationFailure,
+        };
+        ErrorResponse {
+            details: None,
+            message,
+            r#type: Some(r#type),
+        }
+    }
+}
+
+impl Default for MemoryDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryDatabase {
+    pub fn new() -> Self {
+        let mut database = Self {
+            tables: IndexMap::new(),
+            scalar_types: FieldMap::default(),
+            update_operators: IndexMap::new(),
+            generated_ids: 0,
+        };
+        database.update_operators.extend([
+            ("inc".to_owned(), inc as UpdateOperatorFn),
+            ("dec".to_owned(), dec),
+            ("mul".to_owned(), mul),
+            ("append".to_owned(), append),
+            ("prepend".to_owned(), prepend),
+        ]);
+        database
+    }
+
+    /// An empty table for every table in the schema
+    pub fn from_schema(schema: &SchemaResponse) -> Self {
+        let mut database = Self::new();
+        for table in &schema.tables {
+            database.insert_table(table.clone(), Vec::new());
+        }
+        database
+    }
+
+    /// Uses the scalar types of the capabilities to resolve custom update operators
+    pub fn with_capabilities(mut self, capabilities: &Capabilities) -> Self {
+        self.scalar_types = capabilities.scalar_types.clone().unwrap_or_default();
+        self
+    }
+
+    pub fn with_update_operator(mut self, name: impl Into<String>, f: UpdateOperatorFn) -> Self {
+        self.update_operators.insert(name.into(), f);
+        self
+    }
+
+    /// Adds a table, replacing any existing table with the same name
+    pub fn insert_table(&mut self, info: TableInfo, rows: Vec<MemoryRow>) {
+        self.tables
+            .insert(info.name.clone(), MemoryTable { info, rows });
+    }
+
+    pub fn table(&self, name: &TableName) -> Option<&MemoryTable> {
+        self.tables.get(name)
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = &MemoryTable> {
+        self.tables.values()
+    }
+
+    /// Performs the operations in order. If any operation fails, none of the request's changes are kept
+    pub fn execute_mutation(
+        &mut self,
+        request: &MutationRequest,
+    ) -> Result<MutationResponse, ExecutionError> {
+        let mut scratch = self.clone();
+        let operation_results = request
+            .operations
+            .iter()
+            .enumerate()
+            .map(|(index, operation)| scratch.operation(request, index, operation))
+            .collect::<Result<_, _>>()?;
+        *self = scratch;
+        Ok(MutationResponse { operation_results })
+    }
+
+    fn operation(
+        &mut self,
+        request: &MutationRequest,
+        index: usize,
+        operation: &MutationOperation,
+    ) -> Result<MutationOperationResults, ExecutionError> {
+        let relationships = &request.relationships;
+        let (table, rows, returning_fields) = match operation {
+            MutationOperation::Insert {
+                post_insert_check,
+                returning_fields,
+                rows,
+                table,
+            } => {
+                let mut inserted = Vec::with_capacity(rows.len());
+                for (position, row) in rows.iter().enumerate() {
+                    let row = self.new_row(request, table, row, index, position)?;
+                    if let Some(check) = post_insert_check {
+                        if !self.matches(relationships, table, &row, &row, check)? {
+                            return Err(ExecutionError::CheckFailed {
+                                table: table.clone(),
+                                operation: index,
+                                row: position,
+                            });
+                        }
+                    }
+                    self.table_mut(table)?.rows.push(row.clone());
+                    inserted.push(row);
+                }
+                (table, inserted, returning_fields)
+            }
+            MutationOperation::Update {
+                post_update_check,
+                returning_fields,
+                table,
+                updates,
+                r#where,
+            } => {
+                let mut updated = Vec::new();
+                for position in self.matching_rows(relationships, table, r#where.as_ref())? {
+                    let mut row = self.table(table).expect("table exists").rows[position].clone();
+                    for update in updates {
+                        self.apply_update(table, &mut row, update)?;
+                    }
+                    if let Some(check) = post_update_check {
+                        if !self.matches(relationships, table, &row, &row, check)? {
+                            return Err(ExecutionError::CheckFailed {
+                                table: table.clone(),
+                                operation: index,
+                                row: updated.len(),
+                            });
+                        }
+                    }
+                    self.table_mut(table)?.rows[position] = row.clone();
+                    updated.push(row);
+                }
+                (table, updated, returning_fields)
+            }
+            MutationOperation::Delete {
+                returning_fields,
+                table,
+                r#where,
+            } => {
+                let positions = self.matching_rows(relationships, table, r#where.as_ref())?;
+                let table_rows = &mut self.table_mut(table)?.rows;
+                let mut deleted = Vec::with_capacity(positions.len());
+                for position in positions.into_iter().rev() {
+                    deleted.push(table_rows.remove(position));
+                }
+                deleted.reverse();
+                (table, deleted, returning_fields)
+            }
+            MutationOperation::Execute { .. } => {
+                return Err(ExecutionError::Unsupported(
+                    "the execute operation".to_owned(),
+                ))
+            }
+        };
+        let returning = returning_fields
+            .as_ref()
+            .map(|fields| {
+                rows.iter()
+                    .map(|row| self.project(relationships, table, row, fields))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        Ok(MutationOperationResults {
+            affected_rows: rows.len() as u64,
+            returning,
+            status: None,
+            error: None,
+        })
+    }
+
+    fn table_mut(&mut self, name: &TableName) -> Result<&mut MemoryTable, ExecutionError> {
+        self.tables
+            .get_mut(name)
+            .ok_or_else(|| ExecutionError::UnknownTable(name.clone()))
+    }
+
+    fn table_or_error(&self, name: &TableName) -> Result<&MemoryTable, ExecutionError> {
+        self.table(name)
+            .ok_or_else(|| ExecutionError::UnknownTable(name.clone()))
+    }
+
+    fn column_info(&self, table: &TableName, column: &str) -> Result<&ColumnInfo, ExecutionError> {
+        self.table_or_error(table)?
+            .info
+            .columns
+            .iter()
+            .flatten()
+            .find(|info| info.name == column)
+            .ok_or_else(|| ExecutionError::UnknownColumn {
+                table: table.clone(),
+                column: column.to_owned(),
+            })
+    }
+
+    /// Builds a row to insert from the fields of an insert operation, filling in generated and default values
+    fn new_row(
+        &mut self,
+        request: &MutationRequest,
+        table: &TableName,
+        fields: &FieldMap<String, Value>,
+        operation: usize,
+        position: usize,
+    ) -> Result<MemoryRow, ExecutionError> {
+        let insert_schema = request
+            .insert_schema
+            .iter()
+            .find(|schema| &schema.table == table);
+        let mut row = MemoryRow::new();
+        for (field, value) in fields {
+            let column = match insert_schema.and_then(|schema| schema.fields.get(field)) {
+                Some(InsertFieldSchema::Column { column, .. }) => column,
+                Some(_) => return Err(ExecutionError::Unsupported("nested inserts".to_owned())),
+                None => field,
+            };
+            self.column_info(table, column)?;
+            row.insert(column.clone(), value.clone());
+        }
+        let columns = self.table_or_error(table)?.info.columns.clone();
+        for column in columns.iter().flatten() {
+            if row.contains_key(&column.name) {
+                continue;
+            }
+            let value = self.generated_value(table, column)?;
+            row.insert(column.name.clone(), value);
+        }
+        for column in columns.iter().flatten() {
+            if !column.nullable && row.get(&column.name).is_none_or(Value::is_null) {
+                return Err(ExecutionError::NotNullViolation {
+                    table: table.clone(),
+                    column: column.name.clone(),
+                    operation,
+                    row: position,
+                });
+            }
+        }
+        Ok(row)
+    }
+
+    fn generated_value(
+        &mut self,
+        table: &TableName,
+        column: &ColumnInfo,
+    ) -> Result<Value, ExecutionError> {
+        let default = || match &column.default_value {
+            None => Ok(Value::Null),
+            Some(ColumnDefault::Literal { value }) => Ok(value.clone()),
+            Some(ColumnDefault::Expression { .. } | ColumnDefault::Function { .. }) => Err(
+                ExecutionError::Unsupported("expression and function column defaults".to_owned()),
+            ),
+        };
+        match &column.value_generated {
+            Some(ColumnValueGenerationStrategy::AutoIncrement {}) => {
+                let max = self
+                    .table_or_error(table)?
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(&column.name).and_then(Value::as_i64))
+                    .max()
+                    .unwrap_or(0);
+                Ok(Value::from(max + 1))
+            }
+            Some(ColumnValueGenerationStrategy::UniqueIdentifier {}) => {
+                self.generated_ids += 1;
+                Ok(Value::from(format!(
+                    "00000000-0000-4000-8000-{:012x}",
+                    self.generated_ids
+                )))
+            }
+            Some(ColumnValueGenerationStrategy::DefaultValue {}) | None => default(),
+        }
+    }
+
+    fn apply_update(
+        &self,
+        table: &TableName,
+        row: &mut MemoryRow,
+        update: &RowUpdate,
+    ) -> Result<(), ExecutionError> {
+        match update {
+            RowUpdate::Set { column, value, .. } => {
+                self.column_info(table, column)?;
+                row.insert(column.clone(), value.clone());
+            }
+            RowUpdate::CustomOperator {
+                column,
+                operator_name,
+                value,
+                value_type,
+            } => {
+                let scalar_type = match &self.column_info(table, column)?.r#type {
+                    ColumnType::Scalar(scalar_type) => scalar_type,
+                    ColumnType::ColumnTypeNonScalar(_) => value_type,
+                };
+                let declared = self
+                    .scalar_types
+                    .get(scalar_type)
+                    .and_then(|capabilities| capabilities.update_column_operators.as_ref())
+                    .is_some_and(|operators| operators.contains_key(operator_name));
+                if !declared {
+                    return Err(ExecutionError::UndeclaredOperator {
+                        operator: operator_name.clone(),
+                        scalar_type: scalar_type.clone(),
+                    });
+                }
+                let operator = self.update_operators.get(operator_name).ok_or_else(|| {
+                    ExecutionError::Unsupported(format!("update operator {operator_name}"))
+                })?;
+                let current = row.get(column).unwrap_or(&Value::Null);
+                let updated =
+                    operator(current, value).ok_or_else(|| ExecutionError::InvalidOperand {
+                        operator: operator_name.clone(),
+                        column: column.clone(),
+                    })?;
+                row.insert(column.clone(), updated);
+            }
+        }
+        Ok(())
+    }
+
+    /// The positions of the rows of a table that match an optional filter
+    fn matching_rows(
+        &self,
+        relationships: &[TableRelationships],
+        table: &TableName,
+        filter: Option<&Expression>,
+    ) -> Result<Vec<usize>, ExecutionError> {
+        let mut positions = Vec::new();
+        for (position, row) in self.table_or_error(table)?.rows.iter().enumerate() {
+            let matches = match filter {
+                Some(filter) => self.matches(relationships, table, row, row, filter)?,
+                None => true,
+            };
+            if matches {
+                positions.push(position);
+            }
+        }
+        Ok(positions)
+    }
+
+    fn project(
+        &self,
+        relationships: &[TableRelationships],
+        table: &TableName,
+        row: &MemoryRow,
+        fields: &FieldMap<String, Field>,
+    ) -> Result<FieldMap<String, ResponseFieldValue>, ExecutionError> {
+        let mut projected = FieldMap::with_capacity_and_hasher(fields.len(), Default::default());
+        for (name, field) in fields {
+            let value = match field {
+                Field::Column {
+                    column, field_path, ..
+                } => ResponseFieldValue::Column(
+                    navigate(row.get(column), field_path.iter().flatten()).clone(),
+                ),
+                Field::Relationship {
+                    query,
+                    relationship,
+                } => ResponseFieldValue::Relationship(Box::new(self.related_rows(
+                    relationships,
+                    table,
+                    row,
+                    relationship,
+                    query,
+                )?)),
+                Field::Object { .. } | Field::Array { .. } => {
+                    return Err(ExecutionError::Unsupported(
+                        "object and array fields".to_owned(),
+                    ))
+                }
+            };
+            projected.insert(name.clone(), value);
+        }
+        Ok(projected)
+    }
+
+    fn related_rows(
+        &self,
+        relationships: &[TableRelationships],
+        table: &TableName,
+        row: &MemoryRow,
+        relationship: &str,
+        query: &Query,
+    ) -> Result<ResponseRow, ExecutionError> {
+        if query.order_by.is_some() || query.distinct_on.is_some() {
+            return Err(ExecutionError::Unsupported(
+                "ordering relationship fields".to_owned(),
+            ));
+        }
+        let (target, related) = self.related(relationships, table, row, relationship)?;
+        let mut matched = Vec::new();
+        for candidate in related {
+            let matches = match &query.r#where {
+                Some(filter) => {
+                    self.matches(relationships, target, candidate, candidate, filter)?
+                }
+                None => true,
+            };
+            if matches {
+                matched.push(candidate);
+            }
+        }
+        let total_matched = matched.len() as u64;
+        let aggregates = query
+            .aggregates
+            .as_ref()
+            .map(|aggregates| {
+                let limit = query
+                    .aggregates_limit
+                    .map_or(usize::MAX, |limit| limit as usize);
+                let considered: Vec<_> = matched.iter().take(limit).copied().collect();
+                aggregates
+                    .iter()
+                    .map(|(name, aggregate)| {
+                        Ok((name.clone(), aggregate_value(aggregate, &considered)?))
+                    })
+                    .collect::<Result<FieldMap<_, _>, ExecutionError>>()
+            })
+            .transpose()?;
+        let offset = query.offset.unwrap_or(0) as usize;
+        let limit = query.limit.map_or(usize::MAX, |limit| limit as usize);
+        let rows = query
+            .fields
+            .as_ref()
+            .map(|fields| {
+                matched
+                    .iter()
+                    .skip(offset)
+                    .take(limit)
+                    .map(|candidate| self.project(relationships, target, candidate, fields))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        Ok(ResponseRow {
+            aggregates,
+            rows,
+            total_matched: query
+                .include_total_matched
+                .unwrap_or(false)
+                .then_some(total_matched),
+        })
+    }
+
+    /// The rows related to a row through a relationship of its table, and the table they belong to
+    fn related<'a>(
+        &'a self,
+        relationships: &'a [TableRelationships],
+        table: &TableName,
+        row: &MemoryRow,
+        name: &str,
+    ) -> Result<(&'a TableName, Vec<&'a MemoryRow>), ExecutionError> {
+        let relationship = relationships
+            .iter()
+            .filter(|relationships| &relationships.source_table == table)
+            .find_map(|relationships| relationships.relationships.get(name))
+            .ok_or_else(|| ExecutionError::UnknownRelationship {
+                table: table.clone(),
+                relationship: name.to_owned(),
+            })?;
+        let Target::Table { name: target } = &relationship.target else {
+            return Err(ExecutionError::Unsupported(
+                "relationships to functions and interpolated queries".to_owned(),
+            ));
+        };
+        let related = self
+            .table_or_error(target)?
+            .rows
+            .iter()
+            .filter(|candidate| {
+                relationship
+                    .column_mapping
+                    .iter()
+                    .all(|(source, destination)| {
+                        match (row.get(source), candidate.get(destination)) {
+                            (Some(source), Some(destination)) => {
+                                !source.is_null() && values_equal(source, destination)
+                            }
+                            _ => false,
+                        }
+                    })
+            })
+            .collect();
+        Ok((target, related))
+    }
+
+    fn matches(
+        &self,
+        relationships: &[TableRelationships],
+        table: &TableName,
+        row: &MemoryRow,
+        root: &MemoryRow,
+        expression: &Expression,
+    ) -> Result<bool, ExecutionError> {
+        let value = |column: &ComparisonColumn| column_value(row, root, column);
+        let comparison_value = |value: &ComparisonValue| match value {
+            ComparisonValue::Column { column } => column_value(row, root, column),
+            ComparisonValue::Scalar { value, .. } => Ok(value.clone()),
+            ComparisonValue::Geometry { .. } => {
+                Err(ExecutionError::Unsupported("geometry values".to_owned()))
+            }
+        };
+        Ok(match expression {
+            Expression::And { expressions } => {
+                for expression in expressions {
+                    if !self.matches(relationships, table, row, root, expression)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Expression::Or { expressions } => {
+                for expression in expressions {
+                    if self.matches(relationships, table, row, root, expression)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            Expression::Not { expression } => {
+                !self.matches(relationships, table, row, root, expression)?
+            }
+            Expression::ApplyUnaryComparison { column, operator } => match operator {
+                UnaryComparisonOperator::IsNull => value(column)?.is_null(),
+                UnaryComparisonOperator::Other(name) => {
+                    return Err(ExecutionError::Unsupported(format!("operator {name}")))
+                }
+            },
+            Expression::ApplyBinaryComparison {
+                column,
+                operator,
+                value: right,
+                ..
+            } => binary_comparison(operator, &value(column)?, &comparison_value(right)?)?,
+            Expression::ApplyBinaryArrayComparison {
+                column,
+                operator,
+                values,
+                ..
+            } => array_comparison(operator, &value(column)?, values)?,
+            Expression::ApplyBetween {
+                column,
+                low,
+                high,
+                low_inclusive,
+                high_inclusive,
+            } => {
+                let value = value(column)?;
+                let above = compare(&value, &comparison_value(low)?).is_some_and(|ordering| {
+                    ordering == Ordering::Greater || (*low_inclusive && ordering == Ordering::Equal)
+                });
+                let below = compare(&value, &comparison_value(high)?).is_some_and(|ordering| {
+                    ordering == Ordering::Less || (*high_inclusive && ordering == Ordering::Equal)
+                });
+                above && below
+            }
+            Expression::Exists { in_table, r#where } => {
+                let (target, candidates) = match in_table {
+                    ExistsInTable::Related { relationship } => {
+                        self.related(relationships, table, row, relationship)?
+                    }
+                    ExistsInTable::Unrelated {
+                        target: Target::Table { name },
+                    } => (name, self.table_or_error(name)?.rows.iter().collect()),
+                    ExistsInTable::Unrelated { .. } => {
+                        return Err(ExecutionError::Unsupported(
+                            "exists over functions and interpolated queries".to_owned(),
+                        ))
+                    }
+                };
+                for candidate in candidates {
+                    if self.matches(relationships, target, candidate, root, r#where)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+        })
+    }
+}
+
+fn column_value(
+    row: &MemoryRow,
+    root: &MemoryRow,
+    column: &ComparisonColumn,
+) -> Result<Value, ExecutionError> {
+    let row = match column.path.as_deref() {
+        None | Some([]) => row,
+        Some([root_table]) if root_table == "$" => root,
+        Some(_) => {
+            return Err(ExecutionError::Unsupported(
+                "comparison column paths other than [\"$\"]".to_owned(),
+            ))
+        }
+    };
+    let value = match &column.name {
+        ColumnSelector::Name(name) => row.get(name),
+        ColumnSelector::Compound(names) => names
+            .split_first()
+            .map(|(first, rest)| navigate(row.get(first), rest)),
+    };
+    Ok(navigate(value, column.field_path.iter().flatten()).clone())
+}
+
+/// Follows a path of object fields from a value. Missing fields are null
+fn navigate<'a>(value: Option<&'a Value>, path: impl IntoIterator<Item = &'a String>) -> &'a Value {
+    path.into_iter()
+        .fold(value, |value, field| {
+            value.and_then(|value| value.get(field))
+        })
+        .unwrap_or(&Value::Null)
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+    compare(left, right) == Some(Ordering::Equal) || (left == right && !left.is_null())
+}
+
+/// Orders values of the same scalar kind. Nulls and values of different kinds are not comparable
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64()?.partial_cmp(&right.as_f64()?),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        (Value::Bool(left), Value::Bool(right)) => Some(left.cmp(right)),
+        _ => None,
+    }
+}
+
+fn binary_comparison(
+    operator: &BinaryComparisonOperator,
+    left: &Value,
+    right: &Value,
+) -> Result<bool, ExecutionError> {
+    let ordering = compare(left, right);
+    let like = |case_insensitive: bool| match (left, right) {
+        (Value::String(value), Value::String(pattern)) => {
+            like_matches(value, pattern, case_insensitive)
+        }
+        _ => false,
+    };
+    Ok(match operator {
+        BinaryComparisonOperator::Equal => values_equal(left, right),
+        BinaryComparisonOperator::LessThan => ordering == Some(Ordering::Less),
+        BinaryComparisonOperator::LessThanOrEqual => {
+            matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+        }
+        BinaryComparisonOperator::GreaterThan => ordering == Some(Ordering::Greater),
+        BinaryComparisonOperator::GreaterThanOrEqual => {
+            matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+        }
+        BinaryComparisonOperator::Like => like(false),
+        BinaryComparisonOperator::ILike => like(true),
+        BinaryComparisonOperator::NotLike => left.is_string() && !like(false),
+        BinaryComparisonOperator::NotILike => left.is_string() && !like(true),
+        other => {
+            return Err(ExecutionError::Unsupported(format!(
+                "operator {}",
+                other.declared_name().unwrap_or_default()
+            )))
+        }
+    })
+}
+
+fn array_comparison(
+    operator: &BinaryArrayComparisonOperator,
+    value: &Value,
+    values: &[Value],
+) -> Result<bool, ExecutionError> {
+    let contains = |values: &[Value], value: &Value| values.iter().any(|v| values_equal(v, value));
+    let elements = value.as_array().map(Vec::as_slice);
+    Ok(match operator {
+        BinaryArrayComparisonOperator::In => contains(values, value),
+        BinaryArrayComparisonOperator::NotIn => !value.is_null() && !contains(values, value),
+        BinaryArrayComparisonOperator::Contains => {
+            elements.is_some_and(|elements| values.iter().all(|v| contains(elements, v)))
+        }
+        BinaryArrayComparisonOperator::ContainedIn => {
+            elements.is_some_and(|elements| elements.iter().all(|v| contains(values, v)))
+        }
+        BinaryArrayComparisonOperator::Overlaps => {
+            elements.is_some_and(|elements| elements.iter().any(|v| contains(values, v)))
+        }
+        BinaryArrayComparisonOperator::Other(name) => {
+            return Err(ExecutionError::Unsupported(format!("operator {name}")))
+        }
+    })
+}
+
+/// SQL `LIKE` matching, where `%` matches any sequence of characters and `_` any single character
+fn like_matches(value: &str, pattern: &str, case_insensitive: bool) -> bool {
+    let fold = |s: &str| -> Vec<char> {
+        if case_insensitive {
+            s.to_lowercase().chars().collect()
+        } else {
+            s.chars().collect()
+        }
+    };
+    let (value, pattern) = (fold(value), fold(pattern));
+    // matched[j] is whether the pattern so far matches the first j characters of the value
+    let mut matched = vec![false; value.len() + 1];
+    matched[0] = true;
+    for token in pattern {
+        let previous = std::mem::replace(&mut matched, vec![false; value.len() + 1]);
+        for j in 0..=value.len() {
+            matched[j] = match token {
+                '%' => previous[j] || (j > 0 && matched[j - 1]),
+                '_' => j > 0 && previous[j - 1],
+                c => j > 0 && previous[j - 1] && value[j - 1] == c,
+            };
+        }
+    }
+    matched[value.len()]
+}
+
+fn aggregate_value(aggregate: &Aggregate, rows: &[&MemoryRow]) -> Result<Value, ExecutionError> {
+    Ok(match aggregate {
+        Aggregate::StarCount {} => Value::from(rows.len()),
+        Aggregate::ColumnCount { column, distinct } => {
+            let mut values: Vec<&Value> = rows
+                .iter()
+                .filter_map(|row| row.get(column))
+                .filter(|value| !value.is_null())
+                .collect();
+            if *distinct {
+                let mut unique: Vec<&Value> = Vec::new();
+                for value in values {
+                    if !unique.iter().any(|seen| values_equal(seen, value)) {
+                        unique.push(value);
+                    }
+                }
+                values = unique;
+            }
+            Value::from(values.len())
+        }
+        Aggregate::SingleColumn {
+            column, function, ..
+        } => {
+            let values = rows
+                .iter()
+                .filter_map(|row| row.get(column))
+                .filter(|value| !value.is_null());
+            match function.as_str() {
+                "max" => values
+                    .max_by(|a, b| compare(a, b).unwrap_or(Ordering::Equal))
+                    .cloned()
+                    .unwrap_or(Value::Null),
+                "min" => values
+                    .min_by(|a, b| compare(a, b).unwrap_or(Ordering::Equal))
+                    .cloned()
+                    .unwrap_or(Value::Null),
+                "sum" => Value::from(values.filter_map(Value::as_f64).sum::<f64>()),
+                other => {
+                    return Err(ExecutionError::Unsupported(format!(
+                        "aggregate function {other}"
+                    )))
+                }
+            }
+        }
+    })
+}
+
+fn number_operator(current: &Value, argument: &Value, f: fn(f64, f64) -> f64) -> Option<Value> {
+    let current_int = current.as_i64();
+    let argument_int = argument.as_i64();
+    let result = f(current.as_f64()?, argument.as_f64()?);
+    match (current_int, argument_int) {
+        // Keep integer columns integral when both operands are integers
+        (Some(_), Some(_)) if result.fract() == 0.0 && result.abs() < i64::MAX as f64 => {
+            Some(Value::from(result as i64))
+        }
+        _ => serde_json::Number::from_f64(result).map(Value::Number),
+    }
+}
+
+fn inc(current: &Value, argument: &Value) -> Option<Value> {
+    number_operator(current, argument, |a, b| a + b)
+}
+
+fn dec(current: &Value, argument: &Value) -> Option<Value> {
+    number_operator(current, argument, |a, b| a - b)
+}
+
+fn mul(current: &Value, argument: &Value) -> Option<Value> {
+    number_operator(current, argument, |a, b| a * b)
+}
+
+fn append(current: &Value, argument: &Value) -> Option<Value> {
+    Some(Value::from(format!(
+        "{}{}",
+        current.as_str()?,
+        argument.as_str()?
+    )))
+}
+
+fn prepend(current: &Value, argument: &Value) -> Option<Value> {
+    Some(Value::from(format!(
+        "{}{}",
+        argument.as_str()?,
+        current.as_str()?
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn is_named(name: &str) -> Value {
+        json!({
+            "type": "binary_op",
+            "operator": "equal",
+            "column": { "path": [], "name": "Name", "column_type": "string" },
+            "value": { "type": "scalar", "value": name, "value_type": "string" },
+        })
+    }
+
+    fn returning() -> Value {
+        json!({
+            "ArtistId": { "type": "column", "column": "ArtistId", "column_type": "number" },
+            "Name": { "type": "column", "column": "Name", "column_type": "string" },
+            "Country": { "type": "column", "column": "Country", "column_type": "string" },
+            "Code": { "type": "column", "column": "Code", "column_type": "string" },
+            "Plays": { "type": "column", "column": "Plays", "column_type": "number" },
+        })
+    }
+
+    fn double(current: &Value, _: &Value) -> Option<Value> {
+        Some(Value::from(current.as_f64()? * 2.0))
+    }
+
+    fn database() -> MemoryDatabase {
+        let info = serde_json::from_value(json!({
+            "name": ["Artist"],
+            "columns": [
+                { "name": "ArtistId", "type": "number", "nullable": false, "value_generated": { "type": "auto_increment" } },
+                { "name": "Name", "type": "string", "nullable": false },
+                { "name": "Country", "type": "string", "nullable": true, "default_value": { "type": "literal", "value": "unknown" } },
+                { "name": "Code", "type": "string", "nullable": false, "value_generated": { "type": "unique_identifier" } },
+                { "name": "Plays", "type": "number", "nullable": false, "default_value": { "type": "literal", "value": 0 } },
+            ],
+        }))
+        .unwrap();
+        let row = serde_json::from_value(json!({
+            "ArtistId": 1,
+            "Name": "AC/DC",
+            "Country": "Australia",
+            "Code": "acdc",
+            "Plays": 10,
+        }))
+        .unwrap();
+        let capabilities = serde_json::from_value(json!({
+            "scalar_types": {
+                "number": {
+                    "update_column_operators": {
+                        "inc": { "argument_type": "number" },
+                        "double": { "argument_type": "number" },
+                    },
+                },
+            },
+        }))
+        .unwrap();
+        let mut database = MemoryDatabase::new()
+            .with_capabilities(&capabilities)
+            .with_update_operator("double", double);
+        database.insert_table(info, vec![row]);
+        database
+    }
+
+    fn request(operations: Value) -> MutationRequest {
+        serde_json::from_value(json!({
+            "insert_schema": [],
+            "operations": operations,
+            "relationships": [],
+        }))
+        .unwrap()
+    }
+
+    fn rows(database: &MemoryDatabase) -> Value {
+        let table = database.table(&vec!["Artist".to_owned()]).unwrap();
+        serde_json::to_value(&table.rows).unwrap()
+    }
+
+    #[test]
+    fn insert_generates_and_defaults_omitted_columns() {
+        let mut database = database();
+        let response = database
+            .execute_mutation(&request(json!([{
+                "type": "insert",
+                "table": ["Artist"],
+                "rows": [{ "Name": "Accept" }, { "Name": "Aerosmith", "Country": null }],
+                "returning_fields": returning(),
+            }])))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            json!({
+                "operation_results": [{
+                    "affected_rows": 2,
+                    "returning": [
+                        { "ArtistId": 2, "Name": "Accept", "Country": "unknown", "Code": "00000000-0000-4000-8000-000000000001", "Plays": 0 },
+                        { "ArtistId": 3, "Name": "Aerosmith", "Country": null, "Code": "00000000-0000-4000-8000-000000000002", "Plays": 0 },
+                    ],
+                }],
+            })
+        );
+        assert_eq!(rows(&database).as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn insert_schema_maps_fields_to_columns() {
+        let mut database = database();
+        let mut request = request(json!([{
+            "type": "insert",
+            "table": ["Artist"],
+            "rows": [{ "artist_name": "Accept" }],
+        }]));
+        request.insert_schema = serde_json::from_value(json!([{
+            "table": ["Artist"],
+            "fields": {
+                "artist_name": { "type": "column", "column": "Name", "column_type": "string", "nullable": false },
+            },
+        }]))
+        .unwrap();
+        database.execute_mutation(&request).unwrap();
+        assert_eq!(rows(&database)[1]["Name"], json!("Accept"));
+    }
+
+    #[test]
+    fn not_null_violations_are_constraint_violations() {
+        let error = database()
+            .execute_mutation(&request(json!([{
+                "type": "insert",
+                "table": ["Artist"],
+                "rows": [{ "Name": "Accept" }, { "Country": "Germany" }],
+            }])))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ExecutionError::NotNullViolation {
+                table: vec!["Artist".to_owned()],
+                column: "Name".to_owned(),
+                operation: 0,
+                row: 1,
+            }
+        );
+        let response = ErrorResponse::from(error);
+        assert_eq!(
+            response.r#type,
+            Some(ErrorResponseType::MutationConstraintViolation)
+        );
+    }
+
+    #[test]
+    fn updates_apply_set_and_custom_operators() {
+        let mut database = database();
+        let response = database
+            .execute_mutation(&request(json!([{
+                "type": "update",
+                "table": ["Artist"],
+                "updates": [
+                    { "type": "set", "column": "Country", "value": "AU", "value_type": "string" },
+                    { "type": "custom_operator", "operator_name": "inc", "column": "Plays", "value": 5, "value_type": "number" },
+                    { "type": "custom_operator", "operator_name": "double", "column": "Plays", "value": null, "value_type": "number" },
+                ],
+                "where": is_named("AC/DC"),
+            }])))
+            .unwrap();
+        assert_eq!(response.operation_results[0].affected_rows, 1);
+        assert_eq!(rows(&database)[0]["Country"], json!("AU"));
+        assert_eq!(rows(&database)[0]["Plays"], json!(30.0));
+    }
+
+    #[test]
+    fn undeclared_operators_are_rejected() {
+        let error = database()
+            .execute_mutation(&request(json!([{
+                "type": "update",
+                "table": ["Artist"],
+                "updates": [
+                    { "type": "custom_operator", "operator_name": "mul", "column": "Plays", "value": 2, "value_type": "number" },
+                ],
+            }])))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ExecutionError::UndeclaredOperator {
+                operator: "mul".to_owned(),
+                scalar_type: ScalarType::Number,
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_operands_are_rejected() {
+        let error = database()
+            .execute_mutation(&request(json!([{
+                "type": "update",
+                "table": ["Artist"],
+                "updates": [
+                    { "type": "custom_operator", "operator_name": "inc", "column": "Plays", "value": "one", "value_type": "number" },
+                ],
+            }])))
+            .unwrap_err();
+        assert!(matches!(error, ExecutionError::InvalidOperand { .. }));
+    }
+
+    #[test]
+    fn deletes_return_the_deleted_rows() {
+        let mut database = database();
+        let response = database
+            .execute_mutation(&request(json!([{
+                "type": "delete",
+                "table": ["Artist"],
+                "where": is_named("AC/DC"),
+                "returning_fields": {
+                    "Name": { "type": "column", "column": "Name", "column_type": "string" },
+                },
+            }])))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&response.operation_results[0].returning).unwrap(),
+            json!([{ "Name": "AC/DC" }])
+        );
+        assert_eq!(rows(&database), json!([]));
+    }
+
+    #[test]
+    fn failed_checks_roll_back_the_whole_request() {
+        let mut database = database();
+        let before = rows(&database);
+        let error = database
+            .execute_mutation(&request(json!([
+                { "type": "delete", "table": ["Artist"] },
+                {
+                    "type": "insert",
+                    "table": ["Artist"],
+                    "rows": [{ "Name": "Accept" }],
+                    "post_insert_check": is_named("AC/DC"),
+                },
+            ])))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ExecutionError::CheckFailed {
+                table: vec!["Artist".to_owned()],
+                operation: 1,
+                row: 0,
+            }
+        );
+        assert_eq!(
+            ErrorResponse::from(error).r#type,
+            Some(ErrorResponseType::MutationPermissionCheckFailure)
+        );
+        assert_eq!(rows(&database), before);
+    }
+
+    #[test]
+    fn unknown_tables_and_columns_are_rejected() {
+        let error = database()
+            .execute_mutation(&request(json!([
+                { "type": "delete", "table": ["Album"] },
+            ])))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            ExecutionError::UnknownTable(vec!["Album".to_owned()])
+        );
+
+        let error = database()
+            .execute_mutation(&request(json!([{
+                "type": "insert",
+                "table": ["Artist"],
+                "rows": [{ "Name": "Accept", "Genre": "Metal" }],
+            }])))
+            .unwrap_err();
+        assert!(matches!(error, ExecutionError::UnknownColumn { column, .. } if column == "Genre"));
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt::Write;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::map::FieldMap;
+
+/// The content type of a `GET /metrics` response in the Prometheus text exposition format
+pub const PROMETHEUS_TEXT_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// A structured metrics document, as returned by `GET /metrics` when the JSON format is requested
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct MetricsResponse {
+    /// The metric families exposed by the agent
+    pub families: Vec<MetricFamily>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetricFamily {
+    /// The metric name. Must be a valid Prometheus metric name
+    pub name: String,
+    /// Description of the metric
+    pub help: Option<String>,
+    #[serde(rename = "type")]
+    pub r#type: MetricType,
+    /// The samples of this metric, one per distinct label set
+    pub metrics: Vec<Metric>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricType {
+    Counter,
+    Gauge,
+    Histogram,
+    Summary,
+    Untyped,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Metric {
+    /// The labels identifying this sample within its family
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub labels: FieldMap<String, String>,
+    pub value: MetricValue,
+    /// Milliseconds since the unix epoch at which the sample was taken
+    pub timestamp_ms: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MetricValue {
+    /// The value of a counter, gauge or untyped metric
+    Single { value: f64 },
+    Histogram {
+        /// Cumulative bucket counts, ordered by upper bound. The `+Inf` bucket is implied by `count`
+        buckets: Vec<HistogramBucket>,
+        sum: f64,
+        count: u64,
+    },
+    Summary {
+        quantiles: Vec<SummaryQuantile>,
+        sum: f64,
+        count: u64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    /// The inclusive upper bound of the bucket
+    pub upper_bound: f64,
+    /// The number of observations less than or equal to the upper bound
+    pub cumulative_count: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SummaryQuantile {
+    /// The quantile, between 0 and 1
+    pub quantile: f64,
+    pub value: f64,
+}
+
+impl MetricsResponse {
+    /// Renders the document in the Prometheus text exposition format
+    pub fn to_prometheus_text(&self) -> String {
+        let mut out = String::new();
+        for family in &self.families {
+            if let Some(help) = &family.help {
+                let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+                let _ = writeln!(out, "# HELP {} {help}", family.name);
+            }
+            let r#type = match family.r#type {
+                MetricType::Counter => "counter",
+                MetricType::Gauge => "gauge",
+                MetricType::Histogram => "histogram",
+                MetricType::Summary => "summary",
+                MetricType::Untyped => "untyped",
+            };
+            let _ = writeln!(out, "# TYPE {} {type}", family.name);
+            for metric in &family.metrics {
+                write_metric(&mut out, &family.name, metric);
+            }
+        }
+        out
+    }
+}
+
+fn write_metric(out: &mut String, name: &str, metric: &Metric) {
+    let mut sample = |suffix: &str, extra_label: Option<(&str, String)>, value: String| {
+        let mut labels: Vec<String> = metric
+            .labels
+            .iter()
+            .map(|(key, value)| format!("{key}=\"{}\"", escape_label_value(value)))
+            .collect();
+        if let Some((key, value)) = extra_label {
+            labels.push(format!("{key}=\"{value}\""));
+        }
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels.join(","))
+        };
+        let timestamp = metric
+            .timestamp_ms
+            .map(|timestamp| format!(" {timestamp}"))
+            .unwrap_or_default();
+        let _ = writeln!(out, "{name}{suffix}{labels} {value}{timestamp}");
+    };
+    match &metric.value {
+        MetricValue::Single { value } => sample("", None, format_float(*value)),
+        MetricValue::Histogram {
+            buckets,
+            sum,
+            count,
+        } => {
+            for bucket in buckets {
+                sample(
+                    "_bucket",
+                    Some(("le", format_float(bucket.upper_bound))),
+                    bucket.cumulative_count.to_string(),
+                );
+            }
+            sample("_bucket", Some(("le", "+Inf".into())), count.to_string());
+            sample("_sum", None, format_float(*sum));
+            sample("_count", None, count.to_string());
+        }
+        MetricValue::Summary {
+            quantiles,
+            sum,
+            count,
+        } => {
+            for quantile in quantiles {
+                sample(
+                    "",
+                    Some(("quantile", format_float(quantile.quantile))),
+                    format_float(quantile.value),
+                );
+            }
+            sample("_sum", None, format_float(*sum));
+            sample("_count", None, count.to_string());
+        }
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_float(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".into()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.into()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn renders_prometheus_text() {
+        let response: MetricsResponse = serde_json::from_value(json!({
+            "families": [
+                {
+                    "name": "gdc_requests_total",
+                    "help": "Requests served\nby endpoint",
+                    "type": "counter",
+                    "metrics": [
+                        {
+                            "labels": {"endpoint": "query", "path": "a\"b"},
+                            "value": {"type": "single", "value": 3.0},
+                            "timestamp_ms": 1000,
+                        },
+                    ],
+                },
+                {
+                    "name": "gdc_query_seconds",
+                    "type": "histogram",
+                    "metrics": [
+                        {
+                            "value": {
+                                "type": "histogram",
+                                "buckets": [{"upper_bound": 0.5, "cumulative_count": 2}],
+                                "sum": 1.25,
+                                "count": 4,
+                            },
+                        },
+                    ],
+                },
+                {
+                    "name": "gdc_latency",
+                    "type": "summary",
+                    "metrics": [
+                        {
+                            "value": {
+                                "type": "summary",
+                                "quantiles": [{"quantile": 0.99, "value": 2.5}],
+                                "sum": 10.0,
+                                "count": 5,
+                            },
+                        },
+                    ],
+                },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(
+            response.to_prometheus_text(),
+            r#"# HELP gdc_requests_total Requests served\nby endpoint
+# TYPE gdc_requests_total counter
+gdc_requests_total{endpoint="query",path="a\"b"} 3 1000
+# TYPE gdc_query_seconds histogram
+gdc_query_seconds_bucket{le="0.5"} 2
+gdc_query_seconds_bucket{le="+Inf"} 4
+gdc_query_seconds_sum 1.25
+gdc_query_seconds_count 4
+# TYPE gdc_latency summary
+gdc_latency{quantile="0.99"} 2.5
+gdc_latency_sum 10
+gdc_latency_count 5
+"#
+        );
+    }
+
+    #[test]
+    fn formats_special_floats() {
+        assert_eq!(format_float(f64::NAN), "NaN");
+        assert_eq!(format_float(f64::INFINITY), "+Inf");
+        assert_eq!(format_float(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(format_float(0.25), "0.25");
+    }
+}
@@ -0,0 +1,321 @@
+use crate::query::{Expression, Field, OrderBy, Query, QueryRequest};
+
+impl QueryRequest {
+    /// Requests that are one step smaller than this one, largest reductions first. Each removes a field, aggregate, ordering, limit or foreach row, or replaces a filter with a smaller one.
+    ///
+    /// Candidates only remove references, so if this request is valid against a schema, so is every candidate
+    pub fn shrink_candidates(&self) -> Vec<QueryRequest> {
+        let mut candidates: Vec<_> = shrink_query(&self.query)
+            .into_iter()
+            .map(|query| QueryRequest {
+                query,
+                ..self.clone()
+            })
+            .collect();
+        if let Some(foreach) = self.foreach.as_ref().filter(|rows| rows.len() > 1) {
+            candidates.extend(without_each(foreach).map(|foreach| QueryRequest {
+                foreach: Some(foreach),
+                ..self.clone()
+            }));
+        }
+        if self.session.is_some() {
+            candidates.push(QueryRequest {
+                session: None,
+                ..self.clone()
+            });
+        }
+        if self.request_options.is_some() {
+            candidates.push(QueryRequest {
+                request_options: None,
+                ..self.clone()
+            });
+        }
+        candidates
+    }
+}
+
+/// Repeatedly replaces the request with the first of its `shrink_candidates` for which `fails` still returns true, until no candidate does. Returns the smallest failing request found, which is the original request if none of its candidates fail
+pub fn minimize(
+    request: &QueryRequest,
+    mut fails: impl FnMut(&QueryRequest) -> bool,
+) -> QueryRequest {
+    let mut smallest = request.clone();
+    while let Some(candidate) = smallest
+        .shrink_candidates()
+        .into_iter()
+        .find(|candidate| fails(candidate))
+    {
+        smallest = candidate;
+    }
+    smallest
+}
+
+fn shrink_query(query: &Query) -> Vec<Query> {
+    let mut candidates = Vec::new();
+    if let Some(r#where) = &query.r#where {
+        candidates.push(Query {
+            r#where: None,
+            ..query.clone()
+        });
+        candidates.extend(shrink_expression(r#where).into_iter().map(|r#where| Query {
+            r#where: Some(r#where),
+            ..query.clone()
+        }));
+    }
+    if let Some(fields) = &query.fields {
+        for name in fields.keys() {
+            let mut fields = fields.clone();
+            fields.shift_remove(name);
+            candidates.push(Query {
+                fields: Some(fields),
+                ..query.clone()
+            });
+        }
+    }
+    if let Some(aggregates) = &query.aggregates {
+        candidates.push(Query {
+            aggregates: None,
+            aggregates_limit: None,
+            ..query.clone()
+        });
+        if aggregates.len() > 1 {
+            for name in aggregates.keys() {
+                let mut aggregates = aggregates.clone();
+                aggregates.shift_remove(name);
+                candidates.push(Query {
+                    aggregates: Some(aggregates),
+                    ..query.clone()
+                });
+            }
+        }
+    }
+    if let Some(order_by) = &query.order_by {
+        candidates.push(Query {
+            order_by: None,
+            ..query.clone()
+        });
+        if order_by.elements.len() > 1 {
+            candidates.extend(without_each(&order_by.elements).map(|elements| Query {
+                order_by: Some(OrderBy {
+                    elements,
+                    relations: order_by.relations.clone(),
+                }),
+                ..query.clone()
+            }));
+        }
+    }
+    if query.distinct_on.is_some() {
+        candidates.push(Query {
+            distinct_on: None,
+            ..query.clone()
+        });
+    }
+    if query.limit.is_some() || query.offset.is_some() || query.aggregates_limit.is_some() {
+        candidates.push(Query {
+            limit: None,
+            offset: None,
+            aggregates_limit: None,
+            ..query.clone()
+        });
+    }
+    if query.include_total_matched.is_some() {
+        candidates.push(Query {
+            include_total_matched: None,
+            ..query.clone()
+        });
+    }
+    if let Some(fields) = &query.fields {
+        for (name, field) in fields {
+            for field in shrink_field(field) {
+                let mut fields = fields.clone();
+                fields.insert(name.clone(), field);
+                candidates.push(Query {
+                    fields: Some(fields),
+                    ..query.clone()
+                });
+            }
+        }
+    }
+    candidates
+}
+
+fn shrink_field(field: &Field) -> Vec<Field> {
+    match field {
+        Field::Column { .. } => Vec::new(),
+        Field::Object { column, query } => shrink_query(query)
+            .into_iter()
+            .map(|query| Field::Object {
+                column: column.clone(),
+                query,
+            })
+            .collect(),
+        Field::Relationship {
+            query,
+            relationship,
+        } => shrink_query(query)
+            .into_iter()
+            .map(|query| Field::Relationship {
+                query,
+                relationship: relationship.clone(),
+            })
+            .collect(),
+        Field::Array {
+            field,
+            limit,
+            offset,
+            r#where,
+            order_by,
+        } => {
+            let mut candidates = Vec::new();
+            if r#where.is_some() || order_by.is_some() || limit.is_some() || offset.is_some() {
+                candidates.push(Field::Array {
+                    field: field.clone(),
+                    limit: None,
+                    offset: None,
+                    r#where: None,
+                    order_by: None,
+                });
+            }
+            candidates.extend(shrink_field(field).into_iter().map(|field| Field::Array {
+                field: Box::new(field),
+                limit: *limit,
+                offset: *offset,
+                r#where: r#where.clone(),
+                order_by: order_by.clone(),
+            }));
+            candidates
+        }
+    }
+}
+
+fn shrink_expression(expression: &Expression) -> Vec<Expression> {
+    match expression {
+        Expression::And { expressions } | Expression::Or { expressions } => {
+            let rebuild = |expressions| match expression {
+                Expression::And { .. } => Expression::And { expressions },
+                _ => Expression::Or { expressions },
+            };
+            let mut candidates: Vec<_> = expressions.to_vec();
+            if expressions.len() > 1 {
+                candidates.extend(without_each(expressions).map(rebuild));
+            }
+            for (index, child) in expressions.iter().enumerate() {
+                for child in shrink_expression(child) {
+                    let mut expressions = expressions.clone();
+                    expressions[index] = child;
+                    candidates.push(rebuild(expressions));
+                }
+            }
+            candidates
+        }
+        Expression::Not { expression } => {
+            let mut candidates = vec![(**expression).clone()];
+            candidates.extend(shrink_expression(expression).into_iter().map(|expression| {
+                Expression::Not {
+                    expression: Box::new(expression),
+                }
+            }));
+            candidates
+        }
+        Expression::Exists { in_table, r#where } => shrink_expression(r#where)
+            .into_iter()
+            .map(|r#where| Expression::Exists {
+                in_table: in_table.clone(),
+                r#where: Box::new(r#where),
+            })
+            .collect(),
+        Expression::ApplyUnaryComparison { .. }
+        | Expression::ApplyBinaryComparison { .. }
+        | Expression::ApplyBinaryArrayComparison { .. }
+        | Expression::ApplyBetween { .. } => Vec::new(),
+    }
+}
+
+/// Copies of the items with one item removed, for each item
+fn without_each<T: Clone>(items: &[T]) -> impl Iterator<Item = Vec<T>> + '_ {
+    (0..items.len()).map(|index| {
+        let mut items = items.to_vec();
+        items.remove(index);
+        items
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::schema::SchemaResponse;
+
+    fn request() -> QueryRequest {
+        serde_json::from_str(include_str!(
+            "../fixtures/query_requests/array_relationship.json"
+        ))
+        .unwrap()
+    }
+
+    fn has_negation(expression: &Expression) -> bool {
+        match expression {
+            Expression::Not { .. } => true,
+            Expression::And { expressions } | Expression::Or { expressions } => {
+                expressions.iter().any(has_negation)
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn minimize_keeps_only_what_the_failure_needs() {
+        let minimal = minimize(&request(), |request| {
+            request.query.r#where.as_ref().is_some_and(has_negation)
+        });
+        assert_eq!(
+            serde_json::to_value(&minimal.query).unwrap(),
+            json!({
+                "fields": {},
+                "where": {
+                    "type": "not",
+                    "expression": {
+                        "type": "unary_op",
+                        "operator": "is_null",
+                        "column": { "path": [], "name": "Name", "column_type": "string" },
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn minimize_returns_the_request_when_no_candidate_fails() {
+        assert_eq!(minimize(&request(), |_| false), request());
+    }
+
+    #[test]
+    fn candidates_are_smaller_and_stay_valid() {
+        let schema: SchemaResponse = serde_json::from_str(include_str!(
+            "../fixtures/schema_responses/chinook_subset.json"
+        ))
+        .unwrap();
+        let request = request();
+        let size = |request: &QueryRequest| serde_json::to_string(request).unwrap().len();
+        let candidates = request.shrink_candidates();
+        assert!(!candidates.is_empty());
+        for candidate in candidates {
+            assert!(size(&candidate) < size(&request), "{candidate:?}");
+            assert_eq!(candidate.validate(&schema), Ok(()));
+        }
+    }
+
+    #[test]
+    fn foreach_rows_are_removed_one_at_a_time() {
+        let request: QueryRequest =
+            serde_json::from_str(include_str!("../fixtures/query_requests/foreach.json")).unwrap();
+        let rows: Vec<_> = request
+            .shrink_candidates()
+            .into_iter()
+            .filter_map(|candidate| candidate.foreach)
+            .filter(|foreach| foreach.len() == 1)
+            .collect();
+        assert_eq!(rows.len(), 2);
+    }
+}
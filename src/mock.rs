@@ -0,0 +1,381 @@
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{net::TcpListener, sync::oneshot};
+
+use crate::{
+    agent::DataConnectorAgent,
+    axum_router::router,
+    capabilities::CapabilitiesResponse,
+    error::{ErrorResponse, ErrorResponseType},
+    explain::ExplainResponse,
+    memory::MemoryDatabase,
+    mutation::{MutationRequest, MutationResponse},
+    query::{QueryRequest, QueryResponse},
+    raw::{RawRequest, RawResponse},
+    schema::{SchemaRequest, SchemaResponse},
+};
+
+/// Produces the response to a query, or `None` to leave the query to later rules
+pub type QueryRule =
+    Arc<dyn Fn(&QueryRequest) -> Option<Result<QueryResponse, ErrorResponse>> + Send + Sync>;
+/// Produces the response to a mutation, or `None` to leave the mutation to later rules
+pub type MutationRule =
+    Arc<dyn Fn(&MutationRequest) -> Option<Result<MutationResponse, ErrorResponse>> + Send + Sync>;
+
+/// An agent serving canned or rule-based responses, for testing clients of an agent without a database.
+///
+/// Queries are answered by the first rule that matches, then by the canned query response. Mutations are answered the same way, then by executing them against the `MemoryDatabase`, if one was given. Requests without a response fail with an `uncaught-error`. Every query and mutation received is recorded, and clones share the recording and the database
+#[derive(Clone)]
+pub struct MockAgent {
+    capabilities: CapabilitiesResponse,
+    schema: SchemaResponse,
+    query_rules: Vec<QueryRule>,
+    query_response: Option<QueryResponse>,
+    mutation_rules: Vec<MutationRule>,
+    mutation_response: Option<MutationResponse>,
+    database: Option<Arc<Mutex<MemoryDatabase>>>,
+    explain_response: Option<ExplainResponse>,
+    raw_response: Option<RawResponse>,
+    received_queries: Arc<Mutex<Vec<QueryRequest>>>,
+    received_mutations: Arc<Mutex<Vec<MutationRequest>>>,
+}
+
+impl MockAgent {
+    /// An agent that reports these capabilities and schema, and has no responses yet
+    pub fn new(capabilities: CapabilitiesResponse, schema: SchemaResponse) -> Self {
+        Self {
+            capabilities,
+            schema,
+            query_rules: Vec::new(),
+            query_response: None,
+            mutation_rules: Vec::new(),
+            mutation_response: None,
+            database: None,
+            explain_response: None,
+            raw_response: None,
+            received_queries: Arc::default(),
+            received_mutations: Arc::default(),
+        }
+    }
+
+    /// Answers queries the rules do not match with this response
+    pub fn with_query_response(mut self, response: QueryResponse) -> Self {
+        self.query_response = Some(response);
+        self
+    }
+
+    /// Consults the rule for queries, after any rules added before it
+    pub fn with_query_rule(
+        mut self,
+        rule: impl Fn(&QueryRequest) -> Option<Result<QueryResponse, ErrorResponse>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.query_rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Answers mutations the rules do not match with this response
+    pub fn with_mutation_response(mut self, response: MutationResponse) -> Self {
+        self.mutation_response = Some(response);
+        self
+    }
+
+    /// Consults the rule for mutations, after any rules added before it
+    pub fn with_mutation_rule(
+        mut self,
+        rule: impl Fn(&MutationRequest) -> Option<Result<MutationResponse, ErrorResponse>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.mutation_rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Executes mutations without a rule or canned response against the database
+    pub fn with_database(mut self, database: MemoryDatabase) -> Self {
+        self.database = Some(Arc::new(Mutex::new(database)));
+        self
+    }
+
+    pub fn with_explain_response(mut self, response: ExplainResponse) -> Self {
+        self.explain_response = Some(response);
+        self
+    }
+
+    pub fn with_raw_response(mut self, response: RawResponse) -> Self {
+        self.raw_response = Some(response);
+        self
+    }
+
+    /// The queries received so far, in order
+    pub fn received_queries(&self) -> Vec<QueryRequest> {
+        self.received_queries.lock().expect("not poisoned").clone()
+    }
+
+    /// The mutations received so far, in order
+    pub fn received_mutations(&self) -> Vec<MutationRequest> {
+        self.received_mutations
+            .lock()
+            .expect("not poisoned")
+            .clone()
+    }
+
+    /// The database mutations are executed against, as it is after the mutations received so far
+    pub fn database(&self) -> Option<MemoryDatabase> {
+        let database = self.database.as_ref()?;
+        Some(database.lock().expect("not poisoned").clone())
+    }
+}
+
+impl DataConnectorAgent for MockAgent {
+    type Config = serde_json::Value;
+
+    async fn capabilities(&self) -> Result<CapabilitiesResponse, ErrorResponse> {
+        Ok(self.capabilities.clone())
+    }
+
+    async fn schema(
+        &self,
+        _config: &Self::Config,
+        request: SchemaRequest,
+    ) -> Result<SchemaResponse, ErrorResponse> {
+        Ok(self.schema.clone().for_request(&request))
+    }
+
+    async fn query(
+        &self,
+        _config: &Self::Config,
+        request: QueryRequest,
+    ) -> Result<QueryResponse, ErrorResponse> {
+        let response = self.query_rules.iter().find_map(|rule| rule(&request));
+        self.received_queries
+            .lock()
+            .expect("not poisoned")
+            .push(request);
+        response
+            .or_else(|| self.query_response.clone().map(Ok))
+            .unwrap_or_else(|| Err(no_response("query")))
+    }
+
+    async fn query_explain(
+        &self,
+        _config: &Self::Config,
+        _request: QueryRequest,
+    ) -> Result<ExplainResponse, ErrorResponse> {
+        self.explain_response
+            .clone()
+            .ok_or_else(|| no_response("explain"))
+    }
+
+    async fn mutation(
+        &self,
+        _config: &Self::Config,
+        request: MutationRequest,
+    ) -> Result<MutationResponse, ErrorResponse> {
+        let response = self
+            .mutation_rules
+            .iter()
+            .find_map(|rule| rule(&request))
+            .or_else(|| self.mutation_response.clone().map(Ok))
+            .or_else(|| {
+                let database = self.database.as_ref()?;
+                let mut database = database.lock().expect("not poisoned");
+                Some(database.execute_mutation(&request).map_err(Into::into))
+            });
+        self.received_mutations
+            .lock()
+            .expect("not poisoned")
+            .push(request);
+        response.unwrap_or_else(|| Err(no_response("mutation")))
+    }
+
+    async fn raw(
+        &self,
+        _config: &Self::Config,
+        _request: RawRequest,
+    ) -> Result<RawResponse, ErrorResponse> {
+        self.raw_response.clone().ok_or_else(|| no_response("raw"))
+    }
+}
+
+/// A `MockAgent` served over HTTP on a local port, until the server is dropped
+pub struct MockServer {
+    address: SocketAddr,
+    agent: MockAgent,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    /// Serves the agent on a free port of 127.0.0.1. Must be called from within a tokio runtime
+    pub async fn start(agent: MockAgent) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let address = listener.local_addr()?;
+        let (shutdown, stopped) = oneshot::channel::<()>();
+        let app = router(agent.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = stopped.await;
+                })
+                .await
+        });
+        Ok(Self {
+            address,
+            agent,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The base url of the server, e.g. `http://127.0.0.1:41234`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    /// The agent being served, to inspect the requests it received
+    pub fn agent(&self) -> &MockAgent {
+        &self.agent
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+fn no_response(endpoint: &str) -> ErrorResponse {
+    ErrorResponse {
+        details: None,
+        message: format!("the mock agent has no response for this {endpoint} request"),
+        r#type: Some(ErrorResponseType::UncaughtError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+    use crate::agent::tests::now;
+
+    fn agent() -> MockAgent {
+        MockAgent::new(
+            serde_json::from_str(include_str!(
+                "../fixtures/capabilities_responses/sqlite.json"
+            ))
+            .unwrap(),
+            serde_json::from_str(include_str!(
+                "../fixtures/schema_responses/chinook_subset.json"
+            ))
+            .unwrap(),
+        )
+    }
+
+    fn query_request() -> QueryRequest {
+        serde_json::from_str(include_str!(
+            "../fixtures/query_requests/simple_fields.json"
+        ))
+        .unwrap()
+    }
+
+    fn query_response() -> QueryResponse {
+        serde_json::from_str(include_str!("../fixtures/query_responses/aggregates.json")).unwrap()
+    }
+
+    fn mutation_request() -> MutationRequest {
+        serde_json::from_str(include_str!(
+            "../fixtures/mutation_requests/insert_with_returning.json"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn answers_queries_by_rule_then_canned_response() {
+        let config = serde_json::Value::Null;
+        let agent = agent()
+            .with_query_rule(|request| {
+                request
+                    .foreach
+                    .is_some()
+                    .then(|| Err(no_response("foreach")))
+            })
+            .with_query_response(query_response());
+        assert_eq!(
+            now(agent.query(&config, query_request())),
+            Ok(query_response())
+        );
+
+        let foreach: QueryRequest =
+            serde_json::from_str(include_str!("../fixtures/query_requests/foreach.json")).unwrap();
+        assert!(now(agent.query(&config, foreach.clone())).is_err());
+        assert_eq!(
+            agent.clone().received_queries(),
+            vec![query_request(), foreach]
+        );
+    }
+
+    #[test]
+    fn fails_requests_without_a_response() {
+        let config = serde_json::Value::Null;
+        let error = now(agent().query(&config, query_request())).unwrap_err();
+        assert_eq!(error.r#type, Some(ErrorResponseType::UncaughtError));
+        assert!(now(agent().mutation(&config, mutation_request())).is_err());
+        let raw = RawRequest {
+            query: "select 1".to_owned(),
+            session: None,
+        };
+        assert!(now(agent().raw(&config, raw)).is_err());
+    }
+
+    #[test]
+    fn executes_mutations_against_the_database() {
+        let config = serde_json::Value::Null;
+        let agent = agent();
+        let database = MemoryDatabase::from_schema(&agent.schema);
+        let agent = agent.with_database(database);
+        let response = now(agent.mutation(&config, mutation_request())).unwrap();
+        assert_eq!(response.operation_results[0].affected_rows, 2);
+        let database = agent.database().unwrap();
+        let artists = database.table(&vec!["Artist".to_owned()]).unwrap();
+        assert_eq!(artists.rows.len(), 2);
+        assert_eq!(agent.received_mutations(), vec![mutation_request()]);
+    }
+
+    #[test]
+    fn serves_over_http() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        let response = runtime.block_on(async {
+            let server = MockServer::start(agent()).await.unwrap();
+            let address = server.address();
+            tokio::task::spawn_blocking(move || {
+                let mut stream = std::net::TcpStream::connect(address).unwrap();
+                stream
+                    .write_all(b"GET /capabilities HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response
+            })
+            .await
+            .unwrap()
+        });
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.contains("\"display_name\":\"Hasura SQLite\""));
+    }
+}
@@ -1,29 +1,40 @@
-use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
 use crate::{
-    capabilities::{ColumnName, ScalarType, TableName, UpdateOperator},
-    query::{Expression, Field, TableRelationships},
+    capabilities::{AtomicitySupportLevel, ColumnName, ScalarType, TableName, UpdateOperator},
+    error::ErrorResponse,
+    map::FieldMap,
+    query::{Expression, Field, InterpolatedQuery, RequestOptions, TableRelationships, Target},
     schema::ColumnType,
     ColumnValueGenerationStrategy, ResponseFieldValue,
 };
 
+/// A mutation request, generic over the type of insert row values. These are `serde_json::Value` by default. With the `raw_value` feature, use `MutationRequest<RawJson>` to keep them as JSON text instead of building value trees
+#[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct MutationRequest {
+pub struct MutationRequest<V = serde_json::Value> {
     /// The schema by which to interpret row data specified in any insert operations in this request
     pub insert_schema: Vec<TableInsertSchema>,
     /// The mutation operations to perform
-    pub operations: Vec<MutationOperation>,
+    pub operations: Vec<MutationOperation<V>>,
     /// The relationships between tables involved in the entire mutation request
     pub relationships: Vec<TableRelationships>,
+    /// The atomicity the operations must be performed with. Must not exceed the agent's declared `atomicity_support_level`. If absent, the agent applies its declared level
+    pub requested_atomicity: Option<AtomicitySupportLevel>,
+    /// Interpolated queries referenced by `execute` operations with an interpolated target
+    pub interpolated_queries: Option<FieldMap<String, InterpolatedQuery>>,
+    /// The session variables of the user making the request, keyed by their lower-cased `x-hasura-*` name
+    pub session: Option<FieldMap<String, serde_json::Value>>,
+    /// Execution limits and hints for the agent. Requires `request_options` capabilities
+    pub request_options: Option<RequestOptions>,
 }
 
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TableInsertSchema {
     /// The fields that will be found in the insert row data for the table and the schema for each field
-    pub fields: IndexMap<String, InsertFieldSchema>,
+    pub fields: FieldMap<String, InsertFieldSchema>,
     /// The names of the columns that make up the table's primary key
     pub primary_key: Option<Vec<String>>,
     /// The fully qualified name of a table, where the last item in the array is the table name and any earlier items represent the namespacing of the table name
@@ -63,10 +74,11 @@ pub enum ObjectRelationInsertionOrder {
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
-pub enum MutationOperation {
+#[allow(clippy::large_enum_variant)]
+pub enum MutationOperation<V = serde_json::Value> {
     Delete {
         /// The fields to return for the rows affected by this delete operation
-        returning_fields: Option<IndexMap<String, Field>>,
+        returning_fields: Option<FieldMap<String, Field>>,
         /// The fully qualified name of a table, where the last item in the array is the table name and any earlier items represent the namespacing of the table name
         table: TableName,
         #[serde(rename = "where")]
@@ -75,16 +87,16 @@ pub enum MutationOperation {
     Insert {
         post_insert_check: Option<Expression>,
         /// The fields to return for the rows affected by this insert operation
-        returning_fields: Option<IndexMap<String, Field>>,
+        returning_fields: Option<FieldMap<String, Field>>,
         /// The rows to insert into the table
-        rows: Vec<IndexMap<String, serde_json::Value>>,
+        rows: Vec<FieldMap<String, V>>,
         /// The fully qualified name of a table, where the last item in the array is the table name and any earlier items represent the namespacing of the table name
         table: TableName,
     },
     Update {
         post_update_check: Option<Expression>,
         /// The fields to return for the rows affected by this update operation
-        returning_fields: Option<IndexMap<String, Field>>,
+        returning_fields: Option<FieldMap<String, Field>>,
         /// The fully qualified name of a table, where the last item in the array is the table name and any earlier items represent the namespacing of the table name
         table: TableName,
         /// The updates to make to the matched rows in the table
@@ -92,6 +104,13 @@ pub enum MutationOperation {
         #[serde(rename = "where")]
         r#where: Option<Expression>,
     },
+    /// Runs a write function or an interpolated statement
+    Execute {
+        /// The function or interpolated query to run. Table targets must use the insert, update or delete operations instead
+        target: Target,
+        /// The fields to return for the rows returned by the function or statement
+        returning_fields: Option<FieldMap<String, Field>>,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -107,10 +126,10 @@ pub enum RowUpdate {
     },
     Set {
         /// The name of the column in the row
-        column: String,
-        /// The value to use with the column operator
-        value: IndexMap<String, serde_json::Value>,
-        value_type: String,
+        column: ColumnName,
+        /// The value to set the column to. Payloads written against the earlier object typing of this field still deserialize, as an object value
+        value: serde_json::Value,
+        value_type: ScalarType,
     },
 }
 
@@ -126,5 +145,20 @@ pub struct MutationOperationResults {
     /// The number of rows affected by the mutation operation
     pub affected_rows: u64,
     /// The rows affected by the mutation operation
-    pub returning: Option<Vec<IndexMap<String, ResponseFieldValue>>>,
+    pub returning: Option<Vec<FieldMap<String, ResponseFieldValue>>>,
+    /// The outcome of the operation. If absent, the operation succeeded
+    pub status: Option<MutationOperationStatus>,
+    /// Why the operation failed, when `status` is `failed`
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MutationOperationStatus {
+    Succeeded,
+    Failed,
+    /// The operation succeeded but was rolled back because another operation in the same atomic unit failed
+    RolledBack,
+    /// The operation was not attempted because an earlier operation failed
+    Skipped,
 }
@@ -0,0 +1,203 @@
+use std::{fmt, ops::Deref};
+
+use serde::{
+    de::{DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::value::RawValue;
+
+/// The JSON text of a value, kept as received.
+///
+/// Unlike `Box<RawValue>`, this can be deserialized within internally tagged enums and flattened maps. In those positions the value is re-encoded from the buffered input, which still avoids building a `serde_json::Value`
+#[derive(Clone, Debug)]
+pub struct RawJson(Box<RawValue>);
+
+impl RawJson {
+    pub fn from_value(value: &serde_json::Value) -> serde_json::Result<Self> {
+        serde_json::value::to_raw_value(value).map(Self)
+    }
+
+    pub fn to_value(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::from_str(self.0.get())
+    }
+
+    pub fn into_inner(self) -> Box<RawValue> {
+        self.0
+    }
+}
+
+impl Deref for RawJson {
+    type Target = RawValue;
+
+    fn deref(&self) -> &RawValue {
+        &self.0
+    }
+}
+
+impl From<Box<RawValue>> for RawJson {
+    fn from(value: Box<RawValue>) -> Self {
+        Self(value)
+    }
+}
+
+/// Compares the JSON text, so values that differ only in whitespace or key order are not equal
+impl PartialEq for RawJson {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.get() == other.0.get()
+    }
+}
+
+impl fmt::Display for RawJson {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.get())
+    }
+}
+
+/// Written verbatim by `serde_json`. Binary formats, which cannot embed JSON text, get the parsed value
+impl Serialize for RawJson {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            self.0.serialize(serializer)
+        } else {
+            self.to_value()
+                .map_err(serde::ser::Error::custom)?
+                .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RawJson {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut json = vec![];
+        Transcode(&mut json).deserialize(deserializer)?;
+        let json = String::from_utf8(json).map_err(serde::de::Error::custom)?;
+        RawValue::from_string(json)
+            .map(Self)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Writes the JSON text of whatever is deserialized into the buffer
+struct Transcode<'a>(&'a mut Vec<u8>);
+
+impl Transcode<'_> {
+    fn write<T: Serialize + ?Sized, E: serde::de::Error>(self, value: &T) -> Result<(), E> {
+        serde_json::to_writer(self.0, value).map_err(E::custom)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Transcode<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Transcode<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "any JSON value")
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, value: bool) -> Result<(), E> {
+        self.write(&value)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<(), E> {
+        self.write(&value)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<(), E> {
+        self.write(&value)
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<(), E> {
+        self.write(&value)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<(), E> {
+        self.write(value)
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<(), E> {
+        self.write(&())
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<(), E> {
+        self.write(&())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        self.0.push(b'[');
+        let mut first = true;
+        loop {
+            let start = self.0.len();
+            if !first {
+                self.0.push(b',');
+            }
+            if seq.next_element_seed(Transcode(self.0))?.is_none() {
+                self.0.truncate(start);
+                break;
+            }
+            first = false;
+        }
+        self.0.push(b']');
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        self.0.push(b'{');
+        let mut first = true;
+        loop {
+            let start = self.0.len();
+            if !first {
+                self.0.push(b',');
+            }
+            if map.next_key_seed(Transcode(self.0))?.is_none() {
+                self.0.truncate(start);
+                break;
+            }
+            self.0.push(b':');
+            map.next_value_seed(Transcode(self.0))?;
+            first = false;
+        }
+        self.0.push(b'}');
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{capabilities::CapabilitiesResponse, mutation::MutationRequest};
+
+    #[test]
+    fn capabilities_pass_opaque_values_through() {
+        let mut json: serde_json::Value = serde_json::from_str(include_str!(
+            "../fixtures/capabilities_responses/sqlite.json"
+        ))
+        .unwrap();
+        json["capabilities"]["relationships"]["future_join_kind"] = json!({"lateral": true});
+        let response: CapabilitiesResponse<RawJson, RawJson> =
+            serde_json::from_value(json.clone()).unwrap();
+        let extra = &response.capabilities.relationships.as_ref().unwrap().extra;
+        assert_eq!(extra["future_join_kind"].get(), r#"{"lateral":true}"#);
+        assert_eq!(serde_json::to_value(&response).unwrap(), json);
+    }
+
+    #[test]
+    fn insert_rows_are_kept_as_text() {
+        let json = include_str!("../fixtures/mutation_requests/insert_with_returning.json");
+        let request: MutationRequest<RawJson> = serde_json::from_str(json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_value(&request).unwrap(), value);
+    }
+}
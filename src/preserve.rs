@@ -0,0 +1,287 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// A protocol value along with any fields of its JSON representation that this crate does not model, so that they survive a round trip through an agent built against an older version of the crate.
+///
+/// Unknown fields are found at any depth, including within internally tagged and untagged enums such as `Field` and `Expression`, by serializing the parsed value again. An object key that does not come back is unknown if the deserializer skipped it, which is checked by parsing again with its value replaced, so fields left out when serializing, such as `null`s, or read in a legacy shape, such as negative limits, are not mistaken for unknown ones. Unknown fields nested within a value read in a legacy shape are not found
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preserved<T> {
+    pub value: T,
+    /// Fields that were present in the received JSON but are not modelled by `T`
+    pub unknown_fields: Vec<UnknownField>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownField {
+    /// The location of the object containing the field, from the root of the document
+    pub path: Vec<PathSegment>,
+    /// The name of the field
+    pub name: String,
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl<T> Preserved<T> {
+    /// Wraps a value that has no unknown fields
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            unknown_fields: vec![],
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: DeserializeOwned + Serialize + PartialEq> Preserved<T> {
+    pub fn from_value(json: Value) -> serde_json::Result<Self> {
+        let value = T::deserialize(&json)?;
+        let mut missing = vec![];
+        missing_keys(
+            &json,
+            &serde_json::to_value(&value)?,
+            &mut vec![],
+            &mut missing,
+        );
+        let unknown_fields = missing
+            .into_iter()
+            .filter(|(path, name)| skipped(&json, path, name, &value))
+            .filter_map(|(path, name)| {
+                let value = lookup(&json, &path)?.get(&name)?.clone();
+                Some(UnknownField { path, name, value })
+            })
+            .collect();
+        Ok(Self {
+            value,
+            unknown_fields,
+        })
+    }
+}
+
+impl<T: Serialize> Preserved<T> {
+    /// The JSON representation of the value, with the unknown fields merged back in
+    pub fn to_value(&self) -> serde_json::Result<Value> {
+        let mut json = serde_json::to_value(&self.value)?;
+        for field in &self.unknown_fields {
+            let target = field
+                .path
+                .iter()
+                .try_fold(&mut json, |json, segment| match segment {
+                    PathSegment::Key(key) => json.get_mut(key),
+                    PathSegment::Index(index) => json.get_mut(index),
+                });
+            if let Some(Value::Object(object)) = target {
+                object
+                    .entry(field.name.clone())
+                    .or_insert_with(|| field.value.clone());
+            }
+        }
+        Ok(json)
+    }
+}
+
+impl<T> Deref for Preserved<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Preserved<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Serialize> Serialize for Preserved<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_value()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de, T: DeserializeOwned + Serialize + PartialEq> Deserialize<'de> for Preserved<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::from_value(Value::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.path {
+            match segment {
+                PathSegment::Key(key) => write!(f, ".{key}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        write!(f, ".{}", self.name)
+    }
+}
+
+/// Collects the keys of objects in `received` that are absent from the same object in `serialized`, without descending into them
+fn missing_keys(
+    received: &Value,
+    serialized: &Value,
+    path: &mut Vec<PathSegment>,
+    missing: &mut Vec<(Vec<PathSegment>, String)>,
+) {
+    match (received, serialized) {
+        (Value::Object(received), Value::Object(serialized)) => {
+            for (key, received) in received {
+                match serialized.get(key) {
+                    Some(serialized) => {
+                        path.push(PathSegment::Key(key.clone()));
+                        missing_keys(received, serialized, path, missing);
+                        path.pop();
+                    }
+                    None => missing.push((path.clone(), key.clone())),
+                }
+            }
+        }
+        (Value::Array(received), Value::Array(serialized)) => {
+            for (index, (received, serialized)) in received.iter().zip(serialized).enumerate() {
+                path.push(PathSegment::Index(index));
+                missing_keys(received, serialized, path, missing);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether the deserializer skipped the field: replacing its value with one no protocol type reads the same way as any other parses to the same value
+fn skipped<T: DeserializeOwned + PartialEq>(
+    json: &Value,
+    path: &[PathSegment],
+    name: &str,
+    value: &T,
+) -> bool {
+    let mut probe = json.clone();
+    let target = path
+        .iter()
+        .try_fold(&mut probe, |json, segment| match segment {
+            PathSegment::Key(key) => json.get_mut(key),
+            PathSegment::Index(index) => json.get_mut(index),
+        });
+    let Some(Value::Object(object)) = target else {
+        return false;
+    };
+    object.insert(
+        name.to_owned(),
+        serde_json::json!({ "\u{0}unknown field probe": [] }),
+    );
+    T::deserialize(&probe).is_ok_and(|probed| probed == *value)
+}
+
+fn lookup<'a>(json: &'a Value, path: &[PathSegment]) -> Option<&'a Value> {
+    path.iter().try_fold(json, |json, segment| match segment {
+        PathSegment::Key(key) => json.get(key),
+        PathSegment::Index(index) => json.get(index),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::query::QueryRequest;
+
+    fn request(fields: Value, r#where: Value) -> Value {
+        json!({
+            "target": {"type": "table", "name": ["Artist"]},
+            "relationships": [],
+            "query": {"fields": fields, "where": r#where},
+        })
+    }
+
+    fn name_column() -> Value {
+        json!({"type": "column", "column": "Name", "column_type": "string"})
+    }
+
+    fn name_is_null() -> Value {
+        json!({
+            "type": "unary_op",
+            "operator": "is_null",
+            "column": {"name": "Name", "column_type": "string"},
+        })
+    }
+
+    #[test]
+    fn finds_and_restores_unknown_fields_within_tagged_enums() {
+        let mut column = name_column();
+        column["future"] = json!({"level": 1});
+        let mut r#where = name_is_null();
+        r#where["column"]["future"] = json!(true);
+        let json = request(json!({"Name": column}), r#where);
+
+        let preserved = Preserved::<QueryRequest>::from_value(json.clone()).unwrap();
+        let found: Vec<String> = preserved
+            .unknown_fields
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            ["$.query.fields.Name.future", "$.query.where.column.future"]
+        );
+        assert_eq!(preserved.to_value().unwrap(), json);
+    }
+
+    #[test]
+    fn finds_unknown_fields_at_the_root() {
+        let mut json = request(json!({"Name": name_column()}), name_is_null());
+        json["future"] = json!([1, 2]);
+        let preserved = Preserved::<QueryRequest>::from_value(json.clone()).unwrap();
+        assert_eq!(
+            preserved.unknown_fields,
+            [UnknownField {
+                path: vec![],
+                name: "future".to_owned(),
+                value: json!([1, 2]),
+            }]
+        );
+        assert_eq!(preserved.to_value().unwrap(), json);
+    }
+
+    #[test]
+    fn unknown_null_fields_are_found() {
+        let mut column = name_column();
+        column["future"] = Value::Null;
+        let json = request(json!({"Name": column}), name_is_null());
+        let preserved = Preserved::<QueryRequest>::from_value(json.clone()).unwrap();
+        assert_eq!(preserved.unknown_fields.len(), 1);
+        assert_eq!(preserved.to_value().unwrap(), json);
+    }
+
+    #[test]
+    fn known_fields_left_out_when_serializing_are_not_unknown() {
+        let mut column = name_column();
+        column["field_path"] = Value::Null;
+        let array = json!({
+            "type": "array",
+            "field": name_column(),
+            "limit": -1,
+            "where": {"elements": [], "relations": {}},
+        });
+        let mut json = request(json!({"Name": column, "Names": array}), Value::Null);
+        json["session"] = Value::Null;
+        let preserved = Preserved::<QueryRequest>::from_value(json).unwrap();
+        assert_eq!(preserved.unknown_fields, []);
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Header carrying the agent configuration for the source the request was made against, as JSON
+pub const CONFIG_HEADER: &str = "X-Hasura-DataConnector-Config";
+/// Header carrying the name of the HGE source the request was made against
+pub const SOURCE_NAME_HEADER: &str = "X-Hasura-DataConnector-SourceName";
+/// Header carrying the version of the data connector API that HGE speaks
+pub const API_VERSION_HEADER: &str = "X-Hasura-DataConnector-API-Version";
+
+/// The name of the HGE source a request was made against
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SourceName(pub String);
+
+/// The data connector API version HGE sent with a request
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ApiVersion(pub String);
+
+/// The agent configuration for a request, decoded into the agent's own config type
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigHeader<C>(pub C);
+
+impl<C: DeserializeOwned> ConfigHeader<C> {
+    /// Parses a config header value. The value is expected to be JSON, but base64 encoded JSON is also accepted
+    pub fn parse(header_value: &str) -> Result<Self, ConfigHeaderError> {
+        let header_value = header_value.trim();
+        match serde_json::from_str(header_value) {
+            Ok(config) => Ok(Self(config)),
+            Err(json_error) if header_value.starts_with(['{', '[']) => {
+                Err(ConfigHeaderError::Json(json_error))
+            }
+            Err(json) => {
+                let decoded = BASE64
+                    .decode(header_value)
+                    .map_err(|base64| ConfigHeaderError::NotJsonOrBase64 { json, base64 })?;
+                serde_json::from_slice(&decoded)
+                    .map(Self)
+                    .map_err(ConfigHeaderError::Json)
+            }
+        }
+    }
+}
+
+impl<C: Serialize> ConfigHeader<C> {
+    /// Encodes the config as a JSON header value
+    pub fn to_header_value(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&self.0)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigHeaderError {
+    /// The header was not valid JSON, or did not match the config type
+    Json(serde_json::Error),
+    /// The header was neither JSON nor valid base64. Both errors are kept, as either may explain a malformed value
+    NotJsonOrBase64 {
+        json: serde_json::Error,
+        base64: base64::DecodeError,
+    },
+}
+
+impl fmt::Display for ConfigHeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(error) => write!(f, "invalid {CONFIG_HEADER} header: {error}"),
+            Self::NotJsonOrBase64 { json, base64 } => write!(
+                f,
+                "invalid {CONFIG_HEADER} header: not JSON ({json}) or base64 ({base64})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigHeaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(error) => Some(error),
+            Self::NotJsonOrBase64 { json, .. } => Some(json),
+        }
+    }
+}
+
+/// An endpoint of the agent API
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Endpoint {
+    Capabilities,
+    Schema,
+    Query,
+    Explain,
+    Mutation,
+    Raw,
+    Health,
+    /// The dataset template and clone endpoints
+    Datasets,
+}
+
+impl Endpoint {
+    pub const ALL: [Endpoint; 8] = [
+        Self::Capabilities,
+        Self::Schema,
+        Self::Query,
+        Self::Explain,
+        Self::Mutation,
+        Self::Raw,
+        Self::Health,
+        Self::Datasets,
+    ];
+
+    /// The endpoint a request path is for. Paths are matched by suffix, so agents served under a prefix are also recognised
+    pub fn of_path(path: &str) -> Option<Self> {
+        let path = path.trim_end_matches('/');
+        if path.contains("/datasets/templates/") || path.contains("/datasets/clones/") {
+            return Some(Self::Datasets);
+        }
+        Self::ALL
+            .into_iter()
+            .filter(|endpoint| *endpoint != Self::Datasets)
+            .find(|endpoint| {
+                path.strip_suffix(endpoint.as_str())
+                    .is_some_and(|prefix| prefix.ends_with('/'))
+            })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Capabilities => "capabilities",
+            Self::Schema => "schema",
+            Self::Query => "query",
+            Self::Explain => "explain",
+            Self::Mutation => "mutation",
+            Self::Raw => "raw",
+            Self::Health => "health",
+            Self::Datasets => "datasets",
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        db: String,
+    }
+
+    #[test]
+    fn parses_json_and_base64_config_headers() {
+        let expected = ConfigHeader(Config {
+            db: "chinook.db".to_owned(),
+        });
+        assert_eq!(
+            ConfigHeader::parse(r#" {"db": "chinook.db"} "#).unwrap(),
+            expected
+        );
+        let encoded = BASE64.encode(expected.to_header_value().unwrap());
+        assert_eq!(ConfigHeader::parse(&encoded).unwrap(), expected);
+    }
+
+    #[test]
+    fn reports_config_header_errors() {
+        assert!(matches!(
+            ConfigHeader::<Config>::parse(r#"{"db": 1}"#),
+            Err(ConfigHeaderError::Json(_))
+        ));
+        assert!(matches!(
+            ConfigHeader::<Config>::parse(&BASE64.encode(json!({}).to_string())),
+            Err(ConfigHeaderError::Json(_))
+        ));
+        let error = ConfigHeader::<Config>::parse("not json!").unwrap_err();
+        assert!(matches!(error, ConfigHeaderError::NotJsonOrBase64 { .. }));
+        assert!(error.to_string().contains("not JSON"));
+    }
+
+    #[test]
+    fn matches_endpoint_paths_by_suffix() {
+        assert_eq!(Endpoint::of_path("/query"), Some(Endpoint::Query));
+        assert_eq!(
+            Endpoint::of_path("/v1/agent/schema/"),
+            Some(Endpoint::Schema)
+        );
+        assert_eq!(
+            Endpoint::of_path("/datasets/templates/chinook"),
+            Some(Endpoint::Datasets)
+        );
+        assert_eq!(Endpoint::of_path("/subquery"), None);
+        assert_eq!(Endpoint::of_path("/unknown"), None);
+        for endpoint in Endpoint::ALL {
+            assert_eq!(endpoint.to_string(), endpoint.as_str());
+        }
+    }
+}
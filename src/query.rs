@@ -1,23 +1,40 @@
-use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use serde_enum_str::{
     Deserialize_enum_str as DeserializeEnumStr, Serialize_enum_str as SerializeEnumStr,
 };
 use serde_with::skip_serializing_none;
 
-use crate::capabilities::{AggregateFunction, ColumnName, FunctionName, ScalarType, TableName};
+use crate::{
+    capabilities::{AggregateFunction, ColumnName, FunctionName, ScalarType, TableName},
+    map::FieldMap,
+};
 
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct QueryRequest {
     /// If present, a list of columns and values for the columns that the query must be repeated for, applying the column values as a filter for each query.
-    pub foreach: Option<Vec<IndexMap<ColumnName, ScalarValue>>>,
-    pub interpolated_queries: Option<IndexMap<String, InterpolatedQuery>>,
+    pub foreach: Option<Vec<FieldMap<ColumnName, ScalarValue>>>,
+    pub interpolated_queries: Option<FieldMap<String, InterpolatedQuery>>,
     pub query: Query,
     /// The target of the query.
     pub target: Target,
     /// The relationships between tables involved in the entire query request
     pub relationships: Vec<TableRelationships>,
+    /// The session variables of the user making the request, keyed by their lower-cased `x-hasura-*` name
+    pub session: Option<FieldMap<String, serde_json::Value>>,
+    /// Execution limits and hints for the agent. Requires `request_options` capabilities
+    pub request_options: Option<RequestOptions>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct RequestOptions {
+    /// The agent should abandon the request and return an error once it has run for this many milliseconds
+    pub timeout_ms: Option<u64>,
+    /// The agent should return an error rather than produce more than this many rows in any single result set
+    pub max_rows: Option<u64>,
+    /// Backend-specific execution hints. Agents should ignore hints they do not recognise
+    pub hints: Option<FieldMap<String, serde_json::Value>>,
 }
 
 #[skip_serializing_none]
@@ -81,7 +98,7 @@ pub struct ScalarValue {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TableRelationships {
     /// A map of relationships from the source table to target tables. The key of the map is the relationship name
-    pub relationships: IndexMap<String, Relationship>,
+    pub relationships: FieldMap<String, Relationship>,
     /// The fully qualified name of a table, where the last item in the array is the table name and any earlier items represent the namespacing of the table name
     pub source_table: Vec<String>,
 }
@@ -89,7 +106,7 @@ pub struct TableRelationships {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Relationship {
     /// A mapping between columns on the source table to columns on the target table
-    pub column_mapping: IndexMap<ColumnName, ColumnName>,
+    pub column_mapping: FieldMap<ColumnName, ColumnName>,
     pub relationship_type: RelationshipType,
     /// The target of the relationship.
     pub target: Target,
@@ -106,11 +123,11 @@ pub enum RelationshipType {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Query {
     /// Aggregate fields of the query
-    pub aggregates: Option<IndexMap<String, Aggregate>>,
+    pub aggregates: Option<FieldMap<String, Aggregate>>,
     /// Optionally limit the maximum number of rows considered while applying aggregations. This limit does not apply to returned rows.
     pub aggregates_limit: Option<u64>,
     /// Fields of the query
-    pub fields: Option<IndexMap<String, Field>>,
+    pub fields: Option<FieldMap<String, Field>>,
     /// Optionally limit the maximum number of returned rows. This limit does not apply to records considered while apply aggregations.
     pub limit: Option<u64>,
     /// Optionally offset from the Nth result. This applies to both row and aggregation results.
@@ -118,6 +135,10 @@ pub struct Query {
     pub order_by: Option<OrderBy>,
     #[serde(rename = "where")]
     pub r#where: Option<Expression>,
+    /// Only return the first row of each set of rows with equal values in these columns, as determined by `order_by`. Requires `supports_distinct_on`
+    pub distinct_on: Option<Vec<ColumnSelector>>,
+    /// Whether to return the number of rows matched before `limit` and `offset` were applied, in `ResponseRow.total_matched`
+    pub include_total_matched: Option<bool>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -146,17 +167,24 @@ pub enum Field {
     Column {
         column: ColumnName,
         column_type: ScalarType,
+        /// The path to a nested field within an object typed column. Requires `supports_nested_field_paths`
+        field_path: Option<Vec<String>>,
     },
     Object {
         column: ColumnName,
         query: Query,
     },
+    #[serde(deserialize_with = "deserialize_array_field")]
     Array {
         field: Box<Field>,
-        limit: Option<i64>,
-        offset: Option<i64>,
+        /// Optionally limit the maximum number of returned elements. Negative values sent by older clients are read as no limit
+        limit: Option<u64>,
+        /// Optionally offset from the Nth element. Negative values sent by older clients are read as no offset
+        offset: Option<u64>,
+        /// Only include array elements matching this expression. Earlier versions of this crate typed this as an `OrderBy`; payloads in that shape are still accepted and are read as `order_by`
         #[serde(rename = "where")]
-        r#where: Option<OrderBy>,
+        r#where: Option<Expression>,
+        order_by: Option<OrderBy>,
     },
     Relationship {
         query: Query,
@@ -165,27 +193,92 @@ pub enum Field {
     },
 }
 
+type ArrayFieldParts = (
+    Box<Field>,
+    Option<u64>,
+    Option<u64>,
+    Option<Expression>,
+    Option<OrderBy>,
+);
+
+fn deserialize_array_field<'de, D>(deserializer: D) -> Result<ArrayFieldParts, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct ArrayField {
+        field: Box<Field>,
+        #[serde(default, deserialize_with = "deserialize_legacy_signed")]
+        limit: Option<u64>,
+        #[serde(default, deserialize_with = "deserialize_legacy_signed")]
+        offset: Option<u64>,
+        #[serde(rename = "where")]
+        r#where: Option<ArrayFieldWhere>,
+        order_by: Option<OrderBy>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    #[allow(clippy::large_enum_variant)]
+    enum ArrayFieldWhere {
+        Expression(Expression),
+        LegacyOrderBy(OrderBy),
+    }
+
+    let array = ArrayField::deserialize(deserializer)?;
+    let (r#where, order_by) = match array.r#where {
+        Some(ArrayFieldWhere::Expression(expression)) => (Some(expression), array.order_by),
+        Some(ArrayFieldWhere::LegacyOrderBy(order_by)) => (None, array.order_by.or(Some(order_by))),
+        None => (None, array.order_by),
+    };
+    Ok((array.field, array.limit, array.offset, r#where, order_by))
+}
+
+/// Reads a limit or offset that was previously typed as signed, treating negative values as absent
+pub(crate) fn deserialize_legacy_signed<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Integer {
+        Unsigned(u64),
+        Signed(i64),
+    }
+
+    Ok(match Option::<Integer>::deserialize(deserializer)? {
+        Some(Integer::Unsigned(value)) => Some(value),
+        Some(Integer::Signed(value)) => u64::try_from(value).ok(),
+        None => None,
+    })
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct OrderBy {
     /// The elements to order by, in priority order
     pub elements: Vec<OrderByElement>,
     /// A map of relationships from the current query table to target tables. The key of the map is the relationship name. The relationships are used within the order by elements.
-    pub relations: IndexMap<String, OrderByRelation>,
+    pub relations: FieldMap<String, OrderByRelation>,
 }
 
+#[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct OrderByElement {
     pub order_direction: OrderDirection,
     pub target: OrderByTarget,
     /// The relationship path from the current query table to the table that contains the target to order by. This is always non-empty for aggregate order by targets
     pub target_path: Vec<String>,
+    /// Where nulls are placed. If absent, the backend's default placement applies. Requires `supports_nulls_ordering`
+    pub nulls: Option<NullsOrder>,
+    /// The collation to order string values by. Must be one of the `collations` declared for the target's scalar type
+    pub collation: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct OrderByRelation {
     /// Further relationships to follow from the relationship's target table. The key of the map is the relationship name.
     #[serde(rename = "subrelations")]
-    pub subrelations: IndexMap<String, OrderByRelation>,
+    pub subrelations: FieldMap<String, OrderByRelation>,
     #[serde(rename = "where", skip_serializing_if = "Option::is_none")]
     pub r#where: Option<Expression>,
 }
@@ -197,11 +290,21 @@ pub enum OrderDirection {
     Desc,
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+#[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum OrderByTarget {
     Column {
         column: ColumnSelector,
+        /// The path to a nested field within an object typed column. Requires `supports_nested_field_paths`
+        field_path: Option<Vec<String>>,
     },
     SingleColumnAggregate {
         /// The column to apply the aggregation function to
@@ -211,6 +314,12 @@ pub enum OrderByTarget {
         result_type: ScalarType,
     },
     StarCountAggregate {},
+    ColumnCountAggregate {
+        /// The column to apply the count aggregate function to
+        column: ColumnName,
+        /// Whether or not only distinct items should be counted
+        distinct: bool,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -220,6 +329,7 @@ pub enum ColumnSelector {
     Name(String),
 }
 
+#[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Expression {
@@ -242,12 +352,14 @@ pub enum Expression {
         column: ComparisonColumn,
         operator: BinaryComparisonOperator,
         value: ComparisonValue,
+        /// The collation to compare string values with. Must be one of the `collations` declared for the column's scalar type
+        collation: Option<String>,
     },
     #[serde(rename = "binary_arr_op")]
     ApplyBinaryArrayComparison {
         column: ComparisonColumn,
         operator: BinaryArrayComparisonOperator,
-        value_type: String,
+        value_type: ScalarType,
         values: Vec<serde_json::Value>,
     },
     Exists {
@@ -255,6 +367,23 @@ pub enum Expression {
         #[serde(rename = "where")]
         r#where: Box<Expression>,
     },
+    /// Requires `supports_between`
+    #[serde(rename = "between")]
+    ApplyBetween {
+        column: ComparisonColumn,
+        low: ComparisonValue,
+        high: ComparisonValue,
+        /// Whether values equal to `low` match
+        #[serde(default = "default_true")]
+        low_inclusive: bool,
+        /// Whether values equal to `high` match
+        #[serde(default = "default_true")]
+        high_inclusive: bool,
+    },
+}
+
+pub(crate) fn default_true() -> bool {
+    true
 }
 
 #[skip_serializing_none]
@@ -265,6 +394,8 @@ pub struct ComparisonColumn {
     pub name: ColumnSelector,
     /// The path to the table that contains the specified column. Missing or empty array means the current table. [\"$\"] means the query table. No other values are supported at this time.
     pub path: Option<Vec<String>>,
+    /// The path to a nested field within an object typed column. Requires `supports_nested_field_paths`
+    pub field_path: Option<Vec<String>>,
 }
 
 #[derive(Clone, Debug, PartialEq, SerializeEnumStr, DeserializeEnumStr)]
@@ -283,18 +414,84 @@ pub enum BinaryComparisonOperator {
     Equal,
     GreaterThan,
     GreaterThanOrEqual,
+    #[serde(rename = "_like")]
+    Like,
+    #[serde(rename = "_ilike")]
+    ILike,
+    #[serde(rename = "_nlike")]
+    NotLike,
+    #[serde(rename = "_nilike")]
+    NotILike,
+    #[serde(rename = "_regex")]
+    Regex,
+    #[serde(rename = "_iregex")]
+    IRegex,
+    #[serde(rename = "_st_within")]
+    StWithin,
+    #[serde(rename = "_st_intersects")]
+    StIntersects,
+    /// Requires a `geometry` comparison value with a `distance`
+    #[serde(rename = "_st_d_within")]
+    StDWithin,
     #[serde(other)]
     Other(String),
 }
 
+impl BinaryComparisonOperator {
+    /// The name of this operator as declared in `ScalarTypeCapabilities.comparison_operators`. Built-in operators are never declared there and return `None`
+    pub fn declared_name(&self) -> Option<&str> {
+        match self {
+            Self::LessThan
+            | Self::LessThanOrEqual
+            | Self::Equal
+            | Self::GreaterThan
+            | Self::GreaterThanOrEqual => None,
+            Self::Like => Some("_like"),
+            Self::ILike => Some("_ilike"),
+            Self::NotLike => Some("_nlike"),
+            Self::NotILike => Some("_nilike"),
+            Self::Regex => Some("_regex"),
+            Self::IRegex => Some("_iregex"),
+            Self::StWithin => Some("_st_within"),
+            Self::StIntersects => Some("_st_intersects"),
+            Self::StDWithin => Some("_st_d_within"),
+            Self::Other(name) => Some(name),
+        }
+    }
+
+    /// The operator for a name declared in `ScalarTypeCapabilities.comparison_operators`
+    pub fn from_declared_name(name: &str) -> Self {
+        match name {
+            "_like" => Self::Like,
+            "_ilike" => Self::ILike,
+            "_nlike" => Self::NotLike,
+            "_nilike" => Self::NotILike,
+            "_regex" => Self::Regex,
+            "_iregex" => Self::IRegex,
+            "_st_within" => Self::StWithin,
+            "_st_intersects" => Self::StIntersects,
+            "_st_d_within" => Self::StDWithin,
+            other => Self::Other(other.to_owned()),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, SerializeEnumStr, DeserializeEnumStr)]
 #[serde(rename_all = "snake_case")]
 pub enum BinaryArrayComparisonOperator {
     In,
+    NotIn,
+    /// The array typed column contains all of the values
+    Contains,
+    /// All elements of the array typed column are among the values
+    ContainedIn,
+    /// The array typed column and the values have at least one element in common
+    Overlaps,
     #[serde(other)]
     Other(String),
 }
 
+#[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ComparisonValue {
@@ -305,6 +502,15 @@ pub enum ComparisonValue {
         value: serde_json::Value,
         value_type: ScalarType,
     },
+    /// Requires `supports_geometry_values`
+    Geometry {
+        /// A GeoJSON geometry object
+        geometry: serde_json::Value,
+        /// The spatial reference system identifier of the geometry's coordinates. If absent, 4326 (WGS 84) is assumed
+        srid: Option<u32>,
+        /// The distance argument of `_st_d_within`, in the units of the spatial reference system
+        distance: Option<f64>,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -313,13 +519,35 @@ pub enum ExistsInTable {
     Related {
         relationship: String,
     },
+    #[serde(deserialize_with = "deserialize_unrelated")]
     Unrelated {
-        /// The fully qualified name of a table, where the last item in the array is the table name and any earlier items represent the namespacing of the table name
-        // TODO: this probably needs to be a target, eventually?
-        table: Vec<String>,
+        /// The table, function or interpolated query to look for rows in. Payloads with a `table` name array instead of a target are still accepted
+        target: Target,
     },
 }
 
+fn deserialize_unrelated<'de, D>(deserializer: D) -> Result<Target, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Unrelated {
+        target: Option<Target>,
+        table: Option<TableName>,
+    }
+
+    match Unrelated::deserialize(deserializer)? {
+        Unrelated {
+            target: Some(target),
+            ..
+        } => Ok(target),
+        Unrelated {
+            table: Some(name), ..
+        } => Ok(Target::Table { name }),
+        _ => Err(serde::de::Error::missing_field("target")),
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum QueryResponse {
@@ -338,14 +566,65 @@ pub struct ForEachRow {
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ResponseRow {
     /// The results of the aggregates returned by the query
-    pub aggregates: Option<IndexMap<String, serde_json::Value>>,
+    pub aggregates: Option<FieldMap<String, serde_json::Value>>,
     /// The rows returned by the query, corresponding to the query's fields
-    pub rows: Option<Vec<IndexMap<String, ResponseFieldValue>>>,
+    pub rows: Option<Vec<FieldMap<String, ResponseFieldValue>>>,
+    /// The number of rows matched before `limit` and `offset` were applied, if requested via `include_total_matched`
+    pub total_matched: Option<u64>,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 #[serde(untagged)]
 pub enum ResponseFieldValue {
     Relationship(Box<ResponseRow>),
     Column(serde_json::Value),
 }
+
+/// Objects with no fields other than `rows`, `aggregates` and `total_matched` are read as relationship results, so that an empty result round-trips. Anything else, including such an object that is not a valid result, is read as a column value.
+/// This means column values such as `{}` and `{"rows": []}` are read as relationship results. Use `ResponseFieldValue::is_ambiguous` to detect such values, and `from_value_with_hint` or `with_hint` with the query field's `response_kind` to read them correctly
+impl<'de> Deserialize<'de> for ResponseFieldValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let is_relationship = match &value {
+            serde_json::Value::Object(object) => object
+                .keys()
+                .all(|key| matches!(key.as_str(), "rows" | "aggregates" | "total_matched")),
+            _ => false,
+        };
+        if is_relationship {
+            if let Ok(row) = ResponseRow::deserialize(&value) {
+                return Ok(Self::Relationship(Box::new(row)));
+            }
+        }
+        Ok(Self::Column(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn round_trip(value: &ResponseFieldValue) -> ResponseFieldValue {
+        serde_json::from_value(serde_json::to_value(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn object_column_values_round_trip() {
+        for value in [json!({"rows": 5}), json!({"aggregates": "x"})] {
+            let column = ResponseFieldValue::Column(value);
+            assert_eq!(round_trip(&column), column);
+        }
+    }
+
+    #[test]
+    fn empty_relationship_result_round_trips() {
+        let relationship = ResponseFieldValue::Relationship(Box::new(ResponseRow {
+            aggregates: None,
+            rows: None,
+            total_matched: None,
+        }));
+        assert_eq!(round_trip(&relationship), relationship);
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt::Debug;
+
+use proptest::{
+    strategy::{BoxedStrategy, Strategy, ValueTree},
+    test_runner::{Config, RngAlgorithm, TestRng, TestRunner},
+};
+use quickcheck::{Arbitrary, Gen};
+
+use crate::{
+    arbitrary::{
+        arb_capabilities_response, arb_dataset, arb_expression, arb_mutation_request,
+        arb_query_request, arb_schema_response, GeneratedDataset, StrategyConfig,
+    },
+    capabilities::CapabilitiesResponse,
+    mutation::MutationRequest,
+    query::{Expression, QueryRequest},
+    schema::SchemaResponse,
+};
+
+// Values are generated by the proptest strategies, seeded from the quickcheck generator, so both produce the same kinds of values
+
+/// The strategy config for a quickcheck generator. Quickcheck's default size of 100 corresponds to `StrategyConfig::default()`
+fn strategy_config(g: &Gen) -> StrategyConfig {
+    let default = StrategyConfig::default();
+    StrategyConfig {
+        size: (g.size() * default.size / 100).clamp(1, 4 * default.size),
+        ..default
+    }
+}
+
+fn generate<T: Debug>(g: &mut Gen, strategy: fn(StrategyConfig) -> BoxedStrategy<T>) -> T {
+    let seed: Vec<u8> = (0..4)
+        .flat_map(|_| u64::arbitrary(g).to_le_bytes())
+        .collect();
+    let mut runner = TestRunner::new_with_rng(
+        Config::default(),
+        TestRng::from_seed(RngAlgorithm::ChaCha, &seed),
+    );
+    strategy(strategy_config(g))
+        .new_tree(&mut runner)
+        .expect("the protocol strategies do not reject values")
+        .current()
+}
+
+macro_rules! impl_quickcheck_arbitrary {
+    ($($t:ty => $strategy:path),* $(,)?) => {
+        $(impl Arbitrary for $t {
+            fn arbitrary(g: &mut Gen) -> Self {
+                generate(g, $strategy)
+            }
+        })*
+    };
+}
+
+impl_quickcheck_arbitrary!(
+    CapabilitiesResponse => arb_capabilities_response,
+    Expression => arb_expression,
+    GeneratedDataset => arb_dataset,
+    MutationRequest => arb_mutation_request,
+    SchemaResponse => arb_schema_response,
+);
+
+/// Shrinks by `QueryRequest::shrink_candidates`
+impl Arbitrary for QueryRequest {
+    fn arbitrary(g: &mut Gen) -> Self {
+        generate(g, arb_query_request)
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        Box::new(self.shrink_candidates().into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::QuickCheck;
+
+    use super::*;
+
+    fn round_trips<T>(value: T) -> bool
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + PartialEq,
+    {
+        serde_json::from_str::<T>(&serde_json::to_string(&value).unwrap()).unwrap() == value
+    }
+
+    #[test]
+    fn generated_values_round_trip() {
+        let mut quickcheck = QuickCheck::new().tests(32);
+        quickcheck.quickcheck(round_trips::<QueryRequest> as fn(QueryRequest) -> bool);
+        quickcheck.quickcheck(round_trips::<MutationRequest> as fn(MutationRequest) -> bool);
+        quickcheck.quickcheck(round_trips::<SchemaResponse> as fn(SchemaResponse) -> bool);
+        quickcheck.quickcheck(round_trips::<Expression> as fn(Expression) -> bool);
+    }
+
+    #[test]
+    fn the_generator_size_scales_the_strategy_size() {
+        assert_eq!(strategy_config(&Gen::new(100)), StrategyConfig::default());
+        assert_eq!(strategy_config(&Gen::new(0)).size, 1);
+        assert_eq!(strategy_config(&Gen::new(10_000)).size, 16);
+    }
+
+    #[test]
+    fn query_requests_shrink_by_their_candidates() {
+        let request = QueryRequest::arbitrary(&mut Gen::new(100));
+        assert_eq!(
+            request.shrink().collect::<Vec<_>>(),
+            request.shrink_candidates()
+        );
+    }
+}
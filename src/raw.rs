@@ -1,14 +1,19 @@
-use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
 
+use crate::map::FieldMap;
+
+#[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct RawRequest {
     /// A string representing a raw query
     pub query: String,
+    /// The session variables of the user making the request, keyed by their lower-cased `x-hasura-*` name
+    pub session: Option<FieldMap<String, serde_json::Value>>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct RawResponse {
     /// The rows returned by the raw query.
-    pub rows: Vec<IndexMap<String, serde_json::Value>>,
+    pub rows: Vec<FieldMap<String, serde_json::Value>>,
 }
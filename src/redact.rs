@@ -0,0 +1,167 @@
+use serde_json::Value;
+
+use crate::{mutation::MutationRequest, query::QueryRequest, raw::RawRequest};
+
+impl QueryRequest {
+    /// The request as JSON with every literal (comparison values, arguments, foreach values, interpolated scalars) and session variable value replaced with `null`, so it can be logged without leaking user data
+    pub fn redacted(&self) -> Value {
+        let mut value = serde_json::to_value(self).expect("requests always serialize");
+        strip_literals(&mut value);
+        strip_session(&mut value);
+        value
+    }
+}
+
+impl MutationRequest {
+    /// The request as JSON with every literal, session variable value and inserted row value replaced with `null`. The column names of inserted rows are kept
+    pub fn redacted(&self) -> Value {
+        let mut value = serde_json::to_value(self).expect("requests always serialize");
+        strip_literals(&mut value);
+        strip_session(&mut value);
+        if let Some(Value::Array(operations)) = value.get_mut("operations") {
+            for operation in operations {
+                if let Some(Value::Array(rows)) = operation.get_mut("rows") {
+                    rows.iter_mut().for_each(strip_row);
+                }
+            }
+        }
+        value
+    }
+}
+
+impl RawRequest {
+    /// The request as JSON with the query text and session variable values replaced with `null`, as raw queries may embed literals anywhere
+    pub fn redacted(&self) -> Value {
+        let mut value = serde_json::to_value(self).expect("requests always serialize");
+        if let Some(query) = value.get_mut("query") {
+            *query = Value::Null;
+        }
+        strip_session(&mut value);
+        value
+    }
+}
+
+/// Every literal in the protocol is serialized next to its `value_type`, as either `value` or `values`, except geometry comparison values
+pub(crate) fn strip_literals(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            if map.get("type").and_then(Value::as_str) == Some("geometry") {
+                for key in ["geometry", "distance"] {
+                    if let Some(literal) = map.get_mut(key) {
+                        *literal = Value::Null;
+                    }
+                }
+            }
+            if map.contains_key("value_type") {
+                if let Some(literal) = map.get_mut("value") {
+                    *literal = Value::Null;
+                }
+                if let Some(Value::Array(literals)) = map.get_mut("values") {
+                    literals
+                        .iter_mut()
+                        .for_each(|literal| *literal = Value::Null);
+                }
+            }
+            map.values_mut().for_each(strip_literals);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip_literals),
+        _ => {}
+    }
+}
+
+fn strip_session(value: &mut Value) {
+    if let Some(Value::Object(session)) = value.get_mut("session") {
+        session.values_mut().for_each(|value| *value = Value::Null);
+    }
+}
+
+fn strip_row(row: &mut Value) {
+    if let Value::Object(row) = row {
+        row.values_mut().for_each(|value| *value = Value::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Every string in the JSON, to check that none of the redacted literals remain
+    fn strings(value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::String(string) => found.push(string.clone()),
+            Value::Array(values) => values.iter().for_each(|value| strings(value, found)),
+            Value::Object(map) => map.values().for_each(|value| strings(value, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn query_literals_and_session_values_are_removed() {
+        let mut request: QueryRequest = serde_json::from_str(include_str!(
+            "../fixtures/query_requests/array_relationship.json"
+        ))
+        .unwrap();
+        request.session = Some(
+            [("x-hasura-user-id".to_owned(), json!("secret-user"))]
+                .into_iter()
+                .collect(),
+        );
+        let redacted = request.redacted();
+        let mut found = Vec::new();
+        strings(&redacted, &mut found);
+        assert!(!found
+            .iter()
+            .any(|string| string == "%Rock%" || string == "secret-user"));
+        assert_eq!(redacted["session"], json!({ "x-hasura-user-id": null }));
+        assert_eq!(
+            redacted["query"]["where"]["expressions"][0]["value"],
+            json!({ "type": "scalar", "value": null, "value_type": "number" })
+        );
+        assert_eq!(redacted["query"]["fields"]["Name"]["column"], json!("Name"));
+    }
+
+    #[test]
+    fn foreach_and_array_literals_are_removed() {
+        let request: QueryRequest =
+            serde_json::from_str(include_str!("../fixtures/query_requests/foreach.json")).unwrap();
+        assert_eq!(
+            request.redacted()["foreach"],
+            json!([
+                { "ArtistId": { "value": null, "value_type": "number" } },
+                { "ArtistId": { "value": null, "value_type": "number" } },
+            ])
+        );
+
+        let mut value = json!({
+            "type": "binary_arr_op",
+            "operator": "in",
+            "values": [1, 2],
+            "value_type": "number",
+        });
+        strip_literals(&mut value);
+        assert_eq!(value["values"], json!([null, null]));
+    }
+
+    #[test]
+    fn inserted_row_values_are_removed() {
+        let request: MutationRequest = serde_json::from_str(include_str!(
+            "../fixtures/mutation_requests/insert_with_returning.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            request.redacted()["operations"][0]["rows"],
+            json!([{ "Name": null }, { "Name": null }])
+        );
+    }
+
+    #[test]
+    fn raw_queries_are_removed() {
+        let request = RawRequest {
+            query: "select * from users where name = 'alice'".to_owned(),
+            session: None,
+        };
+        assert_eq!(request.redacted(), json!({ "query": null }));
+    }
+}
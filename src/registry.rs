@@ -0,0 +1,185 @@
+use crate::{
+    capabilities::{
+        Capabilities, ScalarType, ScalarTypeCapabilities, UpdateColumnOperatorDefinition,
+    },
+    map::FieldMap,
+    query::{BinaryArrayComparisonOperator, BinaryComparisonOperator},
+};
+
+/// Lookups over the scalar types declared in `Capabilities.scalar_types`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ScalarTypeRegistry<'a> {
+    scalar_types: Option<&'a FieldMap<ScalarType, ScalarTypeCapabilities>>,
+}
+
+impl<'a> ScalarTypeRegistry<'a> {
+    pub fn new(capabilities: &'a Capabilities) -> Self {
+        Self {
+            scalar_types: capabilities.scalar_types.as_ref(),
+        }
+    }
+
+    pub fn from_scalar_types(
+        scalar_types: &'a FieldMap<ScalarType, ScalarTypeCapabilities>,
+    ) -> Self {
+        Self {
+            scalar_types: Some(scalar_types),
+        }
+    }
+
+    pub fn get(&self, scalar_type: &ScalarType) -> Option<&'a ScalarTypeCapabilities> {
+        self.scalar_types?.get(scalar_type)
+    }
+
+    pub fn contains(&self, scalar_type: &ScalarType) -> bool {
+        self.get(scalar_type).is_some()
+    }
+
+    pub fn scalar_types(
+        &self,
+    ) -> impl Iterator<Item = (&'a ScalarType, &'a ScalarTypeCapabilities)> {
+        self.scalar_types.into_iter().flatten()
+    }
+
+    /// The type of the value a column of the given scalar type may be compared to with the operator. Built-in operators compare against the column's own type. Returns `None` if the scalar type is not declared or does not support the operator
+    pub fn operator_argument_type(
+        &self,
+        scalar_type: &ScalarType,
+        operator: &BinaryComparisonOperator,
+    ) -> Option<&'a ScalarType> {
+        let (declared_type, capabilities) = self.scalar_types?.get_key_value(scalar_type)?;
+        match operator.declared_name() {
+            None => Some(declared_type),
+            Some(name) => capabilities.comparison_operators.as_ref()?.get(name),
+        }
+    }
+
+    /// Whether the scalar type supports the array comparison operator. `in` is supported by every declared scalar type
+    pub fn supports_array_operator(
+        &self,
+        scalar_type: &ScalarType,
+        operator: &BinaryArrayComparisonOperator,
+    ) -> bool {
+        match self.get(scalar_type) {
+            None => false,
+            Some(_) if *operator == BinaryArrayComparisonOperator::In => true,
+            Some(capabilities) => capabilities
+                .array_comparison_operators
+                .as_ref()
+                .is_some_and(|operators| operators.contains(operator)),
+        }
+    }
+
+    /// The result type of applying the aggregate function to a column of the given scalar type
+    pub fn aggregate_result_type(
+        &self,
+        scalar_type: &ScalarType,
+        function: &str,
+    ) -> Option<&'a ScalarType> {
+        self.get(scalar_type)?
+            .aggregate_functions
+            .as_ref()?
+            .get(function)
+    }
+
+    pub fn update_operator(
+        &self,
+        scalar_type: &ScalarType,
+        operator_name: &str,
+    ) -> Option<&'a UpdateColumnOperatorDefinition> {
+        self.get(scalar_type)?
+            .update_column_operators
+            .as_ref()?
+            .get(operator_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn capabilities() -> Capabilities {
+        serde_json::from_value(json!({
+            "scalar_types": {
+                "string": {
+                    "comparison_operators": {"_like": "string"},
+                    "aggregate_functions": {"max": "string"},
+                    "array_comparison_operators": ["contains"],
+                    "update_column_operators": {"append": {"argument_type": "string"}},
+                },
+                "number": {},
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn looks_up_declared_scalar_types() {
+        let capabilities = capabilities();
+        let registry = ScalarTypeRegistry::new(&capabilities);
+        assert!(registry.contains(&ScalarType::String));
+        assert!(!registry.contains(&ScalarType::Bool));
+        assert_eq!(registry.scalar_types().count(), 2);
+
+        let empty: Capabilities = serde_json::from_value(json!({})).unwrap();
+        let registry = ScalarTypeRegistry::new(&empty);
+        assert!(!registry.contains(&ScalarType::String));
+        assert_eq!(registry.scalar_types().count(), 0);
+    }
+
+    #[test]
+    fn resolves_operator_argument_types() {
+        let capabilities = capabilities();
+        let registry = ScalarTypeRegistry::new(&capabilities);
+        assert_eq!(
+            registry.operator_argument_type(&ScalarType::Number, &BinaryComparisonOperator::Equal),
+            Some(&ScalarType::Number)
+        );
+        assert_eq!(
+            registry.operator_argument_type(&ScalarType::String, &BinaryComparisonOperator::Like),
+            Some(&ScalarType::String)
+        );
+        assert_eq!(
+            registry.operator_argument_type(&ScalarType::Number, &BinaryComparisonOperator::Like),
+            None
+        );
+        assert_eq!(
+            registry.operator_argument_type(&ScalarType::Bool, &BinaryComparisonOperator::Equal),
+            None
+        );
+    }
+
+    #[test]
+    fn resolves_array_operators_aggregates_and_update_operators() {
+        let capabilities = capabilities();
+        let registry = ScalarTypeRegistry::new(&capabilities);
+        assert!(registry
+            .supports_array_operator(&ScalarType::Number, &BinaryArrayComparisonOperator::In));
+        assert!(registry.supports_array_operator(
+            &ScalarType::String,
+            &BinaryArrayComparisonOperator::Contains
+        ));
+        assert!(!registry.supports_array_operator(
+            &ScalarType::Number,
+            &BinaryArrayComparisonOperator::Contains
+        ));
+        assert!(!registry
+            .supports_array_operator(&ScalarType::Bool, &BinaryArrayComparisonOperator::In));
+        assert_eq!(
+            registry.aggregate_result_type(&ScalarType::String, "max"),
+            Some(&ScalarType::String)
+        );
+        assert_eq!(
+            registry.aggregate_result_type(&ScalarType::Number, "max"),
+            None
+        );
+        assert_eq!(
+            registry
+                .update_operator(&ScalarType::String, "append")
+                .map(|operator| &operator.argument_type),
+            Some(&ScalarType::String)
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+
+use crate::{
+    map::FieldMap,
+    query::{Field, QueryResponse, ResponseFieldValue, ResponseRow},
+};
+
+/// Whether a response field holds a column value or a relationship result, as known from the query field it answers
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResponseFieldKind {
+    Column,
+    Relationship,
+}
+
+impl Field {
+    /// The kind of value this field is answered with in a response row
+    pub fn response_kind(&self) -> ResponseFieldKind {
+        match self {
+            Self::Relationship { .. } => ResponseFieldKind::Relationship,
+            Self::Column { .. } | Self::Object { .. } | Self::Array { .. } => {
+                ResponseFieldKind::Column
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum QueryResponseError {
+    /// A single result set was expected, but the response contains a result set per foreach entry
+    ExpectedSingle,
+    /// A result set per foreach entry was expected, but the response contains a single result set
+    ExpectedForEach,
+    /// The result set does not contain rows, i.e. because the query had no fields
+    MissingRows,
+    /// The result set does not contain aggregates, i.e. because the query had no aggregates
+    MissingAggregates,
+}
+
+impl fmt::Display for QueryResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ExpectedSingle => {
+                "expected a single result set, but the response is a foreach response"
+            }
+            Self::ExpectedForEach => {
+                "expected a foreach response, but the response is a single result set"
+            }
+            Self::MissingRows => "the result set does not contain rows",
+            Self::MissingAggregates => "the result set does not contain aggregates",
+        })
+    }
+}
+
+impl std::error::Error for QueryResponseError {}
+
+impl QueryResponse {
+    pub fn into_single(self) -> Result<ResponseRow, QueryResponseError> {
+        match self {
+            Self::Single(row) => Ok(row),
+            Self::ForEach { .. } => Err(QueryResponseError::ExpectedSingle),
+        }
+    }
+
+    /// The result sets of a foreach response, in foreach order
+    pub fn into_foreach(self) -> Result<Vec<ResponseRow>, QueryResponseError> {
+        match self {
+            Self::ForEach { rows } => Ok(rows.into_iter().map(|row| row.query).collect()),
+            Self::Single(_) => Err(QueryResponseError::ExpectedForEach),
+        }
+    }
+
+    /// The rows of a single result set response
+    pub fn rows(&self) -> Result<&Vec<FieldMap<String, ResponseFieldValue>>, QueryResponseError> {
+        match self {
+            Self::Single(row) => row.rows.as_ref().ok_or(QueryResponseError::MissingRows),
+            Self::ForEach { .. } => Err(QueryResponseError::ExpectedSingle),
+        }
+    }
+
+    /// The aggregates of a single result set response
+    pub fn aggregates(&self) -> Result<&FieldMap<String, serde_json::Value>, QueryResponseError> {
+        match self {
+            Self::Single(row) => row
+                .aggregates
+                .as_ref()
+                .ok_or(QueryResponseError::MissingAggregates),
+            Self::ForEach { .. } => Err(QueryResponseError::ExpectedSingle),
+        }
+    }
+}
+
+impl ResponseFieldValue {
+    /// The value of a relationship field
+    pub fn relationship(row: ResponseRow) -> Self {
+        Self::Relationship(Box::new(row))
+    }
+
+    pub fn column(value: serde_json::Value) -> Self {
+        Self::Column(value)
+    }
+
+    pub fn as_relationship(&self) -> Option<&ResponseRow> {
+        match self {
+            Self::Relationship(row) => Some(row),
+            Self::Column(_) => None,
+        }
+    }
+
+    pub fn as_column(&self) -> Option<&serde_json::Value> {
+        match self {
+            Self::Column(value) => Some(value),
+            Self::Relationship(_) => None,
+        }
+    }
+
+    /// The JSON representation of the field value
+    pub fn into_value(self) -> serde_json::Value {
+        match self {
+            Self::Column(value) => value,
+            Self::Relationship(row) => {
+                serde_json::to_value(row).expect("response rows always serialize to JSON")
+            }
+        }
+    }
+
+    /// Whether the JSON reads as a relationship result without a hint, although it may be a column value, e.g. `{}` or `{"rows": []}`
+    pub fn is_ambiguous(value: &serde_json::Value) -> bool {
+        matches!(
+            serde_json::from_value(value.clone()),
+            Ok(Self::Relationship(_))
+        )
+    }
+
+    /// Reads the JSON as the kind of value the query field is answered with, so that ambiguous shapes are read correctly. Fails if a relationship result is expected and the JSON is not one
+    pub fn from_value_with_hint(
+        value: serde_json::Value,
+        kind: ResponseFieldKind,
+    ) -> Result<Self, serde_json::Error> {
+        match kind {
+            ResponseFieldKind::Column => Ok(Self::Column(value)),
+            ResponseFieldKind::Relationship => {
+                Ok(Self::Relationship(Box::new(serde_json::from_value(value)?)))
+            }
+        }
+    }
+
+    /// Re-reads an already deserialized value as the kind of value the query field is answered with
+    pub fn with_hint(self, kind: ResponseFieldKind) -> Result<Self, serde_json::Error> {
+        match (self, kind) {
+            (value @ Self::Column(_), ResponseFieldKind::Column)
+            | (value @ Self::Relationship(_), ResponseFieldKind::Relationship) => Ok(value),
+            (value, kind) => Self::from_value_with_hint(value.into_value(), kind),
+        }
+    }
+}
+
+impl ResponseRow {
+    /// The rows of the result set. Yields nothing if the query had no fields
+    pub fn iter_rows(&self) -> std::slice::Iter<'_, FieldMap<String, ResponseFieldValue>> {
+        self.rows.as_deref().unwrap_or_default().iter()
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.as_ref().map_or(0, Vec::len)
+    }
+}
+
+impl IntoIterator for ResponseRow {
+    type Item = FieldMap<String, ResponseFieldValue>;
+    type IntoIter = std::vec::IntoIter<FieldMap<String, ResponseFieldValue>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.unwrap_or_default().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ResponseRow {
+    type Item = &'a FieldMap<String, ResponseFieldValue>;
+    type IntoIter = std::slice::Iter<'a, FieldMap<String, ResponseFieldValue>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_rows()
+    }
+}
+
+/// The value of a column field in a response row. Returns `None` if the field is missing or is a relationship field
+pub fn get_column<'a>(
+    row: &'a FieldMap<String, ResponseFieldValue>,
+    field_name: &str,
+) -> Option<&'a serde_json::Value> {
+    row.get(field_name)?.as_column()
+}
+
+/// The value of a relationship field in a response row. Returns `None` if the field is missing or is a column field
+pub fn get_relationship<'a>(
+    row: &'a FieldMap<String, ResponseFieldValue>,
+    field_name: &str,
+) -> Option<&'a ResponseRow> {
+    row.get(field_name)?.as_relationship()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn empty_result() -> ResponseRow {
+        ResponseRow {
+            aggregates: None,
+            rows: Some(vec![]),
+            total_matched: None,
+        }
+    }
+
+    #[test]
+    fn accessors() {
+        let column = ResponseFieldValue::column(json!(1));
+        assert_eq!(column.as_column(), Some(&json!(1)));
+        assert_eq!(column.as_relationship(), None);
+        assert_eq!(column.into_value(), json!(1));
+
+        let relationship = ResponseFieldValue::relationship(empty_result());
+        assert_eq!(relationship.as_relationship(), Some(&empty_result()));
+        assert_eq!(relationship.as_column(), None);
+        assert_eq!(relationship.into_value(), json!({"rows": []}));
+    }
+
+    #[test]
+    fn ambiguous_shapes() {
+        for value in [json!({}), json!({"rows": []}), json!({"total_matched": 3})] {
+            assert!(ResponseFieldValue::is_ambiguous(&value), "{value}");
+        }
+        for value in [
+            json!(null),
+            json!({"rows": 5}),
+            json!({"rows": [], "name": "x"}),
+        ] {
+            assert!(!ResponseFieldValue::is_ambiguous(&value), "{value}");
+        }
+    }
+
+    #[test]
+    fn hint_reads_ambiguous_shapes_as_columns() {
+        let value = json!({"rows": []});
+        let column =
+            ResponseFieldValue::from_value_with_hint(value.clone(), ResponseFieldKind::Column)
+                .unwrap();
+        assert_eq!(column, ResponseFieldValue::Column(value.clone()));
+
+        let read: ResponseFieldValue = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(
+            read.with_hint(ResponseFieldKind::Column).unwrap(),
+            ResponseFieldValue::Column(value)
+        );
+        assert_eq!(
+            ResponseFieldValue::Column(json!({}))
+                .with_hint(ResponseFieldKind::Relationship)
+                .unwrap(),
+            ResponseFieldValue::relationship(ResponseRow {
+                aggregates: None,
+                rows: None,
+                total_matched: None,
+            })
+        );
+    }
+
+    #[test]
+    fn hint_rejects_invalid_relationship_results() {
+        let error = ResponseFieldValue::from_value_with_hint(
+            json!({"rows": 5}),
+            ResponseFieldKind::Relationship,
+        );
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn field_response_kind() {
+        let field: Field = serde_json::from_value(json!({
+            "type": "relationship",
+            "relationship": "Albums",
+            "query": {},
+        }))
+        .unwrap();
+        assert_eq!(field.response_kind(), ResponseFieldKind::Relationship);
+    }
+}
@@ -0,0 +1,490 @@
+use std::{
+    borrow::Cow,
+    hash::{BuildHasher, Hash},
+    sync::Arc,
+};
+
+use indexmap::IndexMap;
+
+use crate::{
+    capabilities::TableName,
+    query::{
+        ExistsInTable, Expression, Field, OrderBy, OrderByRelation, Query, QueryRequest,
+        Relationship, TableRelationships, Target,
+    },
+};
+
+/// A transformation of query requests, such as renaming tables or injecting permission filters.
+///
+/// Each method returns `Cow::Borrowed` for a subtree it leaves unchanged, so a rewrite that changes nothing returns the request itself without cloning. Once anything changes the result is a full copy, with unchanged subtrees cloned rather than shared. The defaults rewrite nothing themselves and recurse into children using the `walk_*` functions, which overriding methods can also call
+pub trait QueryRewriter {
+    /// Called for query and exists targets, and for relationship source and target tables
+    fn rewrite_table_name<'a>(&mut self, name: &'a TableName) -> Cow<'a, TableName> {
+        Cow::Borrowed(name)
+    }
+
+    fn rewrite_target<'a>(&mut self, target: &'a Target) -> Cow<'a, Target> {
+        walk_target(self, target)
+    }
+
+    /// Called for the top level query and for every nested object, array and relationship query
+    fn rewrite_query<'a>(&mut self, query: &'a Query) -> Cow<'a, Query> {
+        walk_query(self, query)
+    }
+
+    /// Called for every expression, after its subexpressions have been rewritten if the default is not overridden
+    fn rewrite_expression<'a>(&mut self, expression: &'a Expression) -> Cow<'a, Expression> {
+        walk_expression(self, expression)
+    }
+}
+
+impl QueryRequest {
+    /// Applies the rewriter, returning the request itself if nothing changed
+    pub fn rewrite<R: QueryRewriter + ?Sized>(&self, rewriter: &mut R) -> Cow<'_, QueryRequest> {
+        let target = rewriter.rewrite_target(&self.target);
+        let relationships = rewrite_each(&self.relationships, |relationships| {
+            walk_table_relationships(rewriter, relationships)
+        });
+        let query = rewriter.rewrite_query(&self.query);
+        if is_unchanged(&target) && is_unchanged(&relationships) && is_unchanged(&query) {
+            return Cow::Borrowed(self);
+        }
+        Cow::Owned(QueryRequest {
+            foreach: self.foreach.clone(),
+            interpolated_queries: self.interpolated_queries.clone(),
+            query: query.into_owned(),
+            target: target.into_owned(),
+            relationships: relationships.into_owned(),
+            session: self.session.clone(),
+            request_options: self.request_options.clone(),
+        })
+    }
+}
+
+/// Applies the rewriter to a shared request, returning the same allocation if nothing changed
+pub fn rewrite_shared<R: QueryRewriter + ?Sized>(
+    request: &Arc<QueryRequest>,
+    rewriter: &mut R,
+) -> Arc<QueryRequest> {
+    match request.rewrite(rewriter) {
+        Cow::Borrowed(_) => Arc::clone(request),
+        Cow::Owned(rewritten) => Arc::new(rewritten),
+    }
+}
+
+pub fn walk_target<'a, R: QueryRewriter + ?Sized>(
+    rewriter: &mut R,
+    target: &'a Target,
+) -> Cow<'a, Target> {
+    match target {
+        Target::Table { name } => match rewriter.rewrite_table_name(name) {
+            Cow::Borrowed(_) => Cow::Borrowed(target),
+            Cow::Owned(name) => Cow::Owned(Target::Table { name }),
+        },
+        Target::Interpolated { .. } | Target::Function { .. } => Cow::Borrowed(target),
+    }
+}
+
+pub fn walk_query<'a, R: QueryRewriter + ?Sized>(
+    rewriter: &mut R,
+    query: &'a Query,
+) -> Cow<'a, Query> {
+    let fields = rewrite_option(&query.fields, |fields| {
+        rewrite_values(fields, |field| walk_field(rewriter, field))
+    });
+    let r#where = rewrite_option(&query.r#where, |expression| {
+        rewriter.rewrite_expression(expression)
+    });
+    let order_by = rewrite_option(&query.order_by, |order_by| {
+        walk_order_by(rewriter, order_by)
+    });
+    if is_unchanged(&fields) && is_unchanged(&r#where) && is_unchanged(&order_by) {
+        return Cow::Borrowed(query);
+    }
+    Cow::Owned(Query {
+        aggregates: query.aggregates.clone(),
+        aggregates_limit: query.aggregates_limit,
+        fields: fields.into_owned(),
+        limit: query.limit,
+        offset: query.offset,
+        order_by: order_by.into_owned(),
+        r#where: r#where.into_owned(),
+        distinct_on: query.distinct_on.clone(),
+        include_total_matched: query.include_total_matched,
+    })
+}
+
+pub fn walk_field<'a, R: QueryRewriter + ?Sized>(
+    rewriter: &mut R,
+    field: &'a Field,
+) -> Cow<'a, Field> {
+    match field {
+        Field::Column { .. } => Cow::Borrowed(field),
+        Field::Object { column, query } => match rewriter.rewrite_query(query) {
+            Cow::Borrowed(_) => Cow::Borrowed(field),
+            Cow::Owned(query) => Cow::Owned(Field::Object {
+                column: column.clone(),
+                query,
+            }),
+        },
+        Field::Array {
+            field: element,
+            limit,
+            offset,
+            r#where,
+            order_by,
+        } => {
+            let element = walk_field(rewriter, element);
+            let rewritten_where = rewrite_option(r#where, |expression| {
+                rewriter.rewrite_expression(expression)
+            });
+            let rewritten_order_by =
+                rewrite_option(order_by, |order_by| walk_order_by(rewriter, order_by));
+            if is_unchanged(&element)
+                && is_unchanged(&rewritten_where)
+                && is_unchanged(&rewritten_order_by)
+            {
+                return Cow::Borrowed(field);
+            }
+            Cow::Owned(Field::Array {
+                field: Box::new(element.into_owned()),
+                limit: *limit,
+                offset: *offset,
+                r#where: rewritten_where.into_owned(),
+                order_by: rewritten_order_by.into_owned(),
+            })
+        }
+        Field::Relationship {
+            query,
+            relationship,
+        } => match rewriter.rewrite_query(query) {
+            Cow::Borrowed(_) => Cow::Borrowed(field),
+            Cow::Owned(query) => Cow::Owned(Field::Relationship {
+                query,
+                relationship: relationship.clone(),
+            }),
+        },
+    }
+}
+
+pub fn walk_expression<'a, R: QueryRewriter + ?Sized>(
+    rewriter: &mut R,
+    expression: &'a Expression,
+) -> Cow<'a, Expression> {
+    match expression {
+        Expression::And { expressions } => {
+            match rewrite_each(expressions, |expression| {
+                rewriter.rewrite_expression(expression)
+            }) {
+                Cow::Borrowed(_) => Cow::Borrowed(expression),
+                Cow::Owned(expressions) => Cow::Owned(Expression::And { expressions }),
+            }
+        }
+        Expression::Or { expressions } => {
+            match rewrite_each(expressions, |expression| {
+                rewriter.rewrite_expression(expression)
+            }) {
+                Cow::Borrowed(_) => Cow::Borrowed(expression),
+                Cow::Owned(expressions) => Cow::Owned(Expression::Or { expressions }),
+            }
+        }
+        Expression::Not {
+            expression: negated,
+        } => match rewriter.rewrite_expression(negated) {
+            Cow::Borrowed(_) => Cow::Borrowed(expression),
+            Cow::Owned(negated) => Cow::Owned(Expression::Not {
+                expression: Box::new(negated),
+            }),
+        },
+        Expression::Exists { in_table, r#where } => {
+            let rewritten_in_table = match in_table {
+                ExistsInTable::Related { .. } => Cow::Borrowed(in_table),
+                ExistsInTable::Unrelated { target } => match rewriter.rewrite_target(target) {
+                    Cow::Borrowed(_) => Cow::Borrowed(in_table),
+                    Cow::Owned(target) => Cow::Owned(ExistsInTable::Unrelated { target }),
+                },
+            };
+            let rewritten_where = rewriter.rewrite_expression(r#where);
+            if is_unchanged(&rewritten_in_table) && is_unchanged(&rewritten_where) {
+                return Cow::Borrowed(expression);
+            }
+            Cow::Owned(Expression::Exists {
+                in_table: rewritten_in_table.into_owned(),
+                r#where: Box::new(rewritten_where.into_owned()),
+            })
+        }
+        Expression::ApplyUnaryComparison { .. }
+        | Expression::ApplyBinaryComparison { .. }
+        | Expression::ApplyBinaryArrayComparison { .. }
+        | Expression::ApplyBetween { .. } => Cow::Borrowed(expression),
+    }
+}
+
+fn walk_order_by<'a, R: QueryRewriter + ?Sized>(
+    rewriter: &mut R,
+    order_by: &'a OrderBy,
+) -> Cow<'a, OrderBy> {
+    match rewrite_values(&order_by.relations, |relation| {
+        walk_order_by_relation(rewriter, relation)
+    }) {
+        Cow::Borrowed(_) => Cow::Borrowed(order_by),
+        Cow::Owned(relations) => Cow::Owned(OrderBy {
+            elements: order_by.elements.clone(),
+            relations,
+        }),
+    }
+}
+
+fn walk_order_by_relation<'a, R: QueryRewriter + ?Sized>(
+    rewriter: &mut R,
+    relation: &'a OrderByRelation,
+) -> Cow<'a, OrderByRelation> {
+    let subrelations = rewrite_values(&relation.subrelations, |relation| {
+        walk_order_by_relation(rewriter, relation)
+    });
+    let r#where = rewrite_option(&relation.r#where, |expression| {
+        rewriter.rewrite_expression(expression)
+    });
+    if is_unchanged(&subrelations) && is_unchanged(&r#where) {
+        return Cow::Borrowed(relation);
+    }
+    Cow::Owned(OrderByRelation {
+        subrelations: subrelations.into_owned(),
+        r#where: r#where.into_owned(),
+    })
+}
+
+fn walk_table_relationships<'a, R: QueryRewriter + ?Sized>(
+    rewriter: &mut R,
+    table_relationships: &'a TableRelationships,
+) -> Cow<'a, TableRelationships> {
+    let source_table = rewriter.rewrite_table_name(&table_relationships.source_table);
+    let relationships =
+        rewrite_values(
+            &table_relationships.relationships,
+            |relationship| match rewriter.rewrite_target(&relationship.target) {
+                Cow::Borrowed(_) => Cow::Borrowed(relationship),
+                Cow::Owned(target) => Cow::Owned(Relationship {
+                    column_mapping: relationship.column_mapping.clone(),
+                    relationship_type: relationship.relationship_type.clone(),
+                    target,
+                }),
+            },
+        );
+    if is_unchanged(&source_table) && is_unchanged(&relationships) {
+        return Cow::Borrowed(table_relationships);
+    }
+    Cow::Owned(TableRelationships {
+        relationships: relationships.into_owned(),
+        source_table: source_table.into_owned(),
+    })
+}
+
+#[allow(clippy::ptr_arg)]
+fn is_unchanged<T: ToOwned + ?Sized>(value: &Cow<'_, T>) -> bool {
+    matches!(value, Cow::Borrowed(_))
+}
+
+fn rewrite_option<'a, T: Clone>(
+    value: &'a Option<T>,
+    rewrite: impl FnOnce(&'a T) -> Cow<'a, T>,
+) -> Cow<'a, Option<T>> {
+    match value.as_ref().map(rewrite) {
+        Some(Cow::Owned(rewritten)) => Cow::Owned(Some(rewritten)),
+        _ => Cow::Borrowed(value),
+    }
+}
+
+/// Clones the items only if at least one of them changed
+fn rewrite_each<'a, T: Clone>(
+    items: &'a [T],
+    mut rewrite: impl FnMut(&'a T) -> Cow<'a, T>,
+) -> Cow<'a, [T]> {
+    let mut rewritten: Option<Vec<T>> = None;
+    for (index, item) in items.iter().enumerate() {
+        match (rewrite(item), rewritten.as_mut()) {
+            (Cow::Borrowed(_), None) => {}
+            (Cow::Borrowed(item), Some(rewritten)) => rewritten.push(item.clone()),
+            (Cow::Owned(item), Some(rewritten)) => rewritten.push(item),
+            (Cow::Owned(item), None) => {
+                let mut items = items[..index].to_vec();
+                items.push(item);
+                rewritten = Some(items);
+            }
+        }
+    }
+    rewritten.map_or(Cow::Borrowed(items), Cow::Owned)
+}
+
+/// Clones the map only if at least one of its values changed
+fn rewrite_values<'a, K, V, S>(
+    map: &'a IndexMap<K, V, S>,
+    mut rewrite: impl FnMut(&'a V) -> Cow<'a, V>,
+) -> Cow<'a, IndexMap<K, V, S>>
+where
+    K: Clone + Hash + Eq,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    let mut rewritten: Option<IndexMap<K, V, S>> = None;
+    for (index, (key, value)) in map.iter().enumerate() {
+        match (rewrite(value), rewritten.as_mut()) {
+            (Cow::Borrowed(_), None) => {}
+            (Cow::Borrowed(value), Some(rewritten)) => {
+                rewritten.insert(key.clone(), value.clone());
+            }
+            (Cow::Owned(value), Some(rewritten)) => {
+                rewritten.insert(key.clone(), value);
+            }
+            (Cow::Owned(value), None) => {
+                let mut entries =
+                    IndexMap::with_capacity_and_hasher(map.len(), map.hasher().clone());
+                for (key, value) in map.iter().take(index) {
+                    entries.insert(key.clone(), value.clone());
+                }
+                entries.insert(key.clone(), value);
+                rewritten = Some(entries);
+            }
+        }
+    }
+    rewritten.map_or(Cow::Borrowed(map), Cow::Owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn request() -> QueryRequest {
+        serde_json::from_str(include_str!(
+            "../fixtures/query_requests/array_relationship.json"
+        ))
+        .unwrap()
+    }
+
+    struct Noop;
+
+    impl QueryRewriter for Noop {}
+
+    struct Rename(&'static str, &'static str);
+
+    impl QueryRewriter for Rename {
+        fn rewrite_table_name<'a>(&mut self, name: &'a TableName) -> Cow<'a, TableName> {
+            if name == &[self.0] {
+                Cow::Owned(vec![self.1.to_owned()])
+            } else {
+                Cow::Borrowed(name)
+            }
+        }
+    }
+
+    /// Restricts every query, at any level, to rows with a non-null `Name`
+    struct Restrict;
+
+    impl QueryRewriter for Restrict {
+        fn rewrite_query<'a>(&mut self, query: &'a Query) -> Cow<'a, Query> {
+            let mut query = walk_query(self, query).into_owned();
+            let filter: Expression = serde_json::from_value(json!({
+                "type": "not",
+                "expression": {
+                    "type": "unary_op",
+                    "operator": "is_null",
+                    "column": { "path": [], "name": "Name", "column_type": "string" },
+                },
+            }))
+            .unwrap();
+            query.r#where = Some(match query.r#where.take() {
+                Some(existing) => Expression::And {
+                    expressions: vec![existing, filter],
+                },
+                None => filter,
+            });
+            Cow::Owned(query)
+        }
+    }
+
+    #[test]
+    fn unchanged_requests_are_borrowed() {
+        let request = request();
+        assert!(
+            matches!(request.rewrite(&mut Noop), Cow::Borrowed(rewritten) if std::ptr::eq(rewritten, &request))
+        );
+        assert!(matches!(
+            request.rewrite(&mut Rename("Track", "Song")),
+            Cow::Borrowed(_)
+        ));
+
+        let shared = Arc::new(request);
+        assert!(Arc::ptr_eq(&rewrite_shared(&shared, &mut Noop), &shared));
+        assert!(!Arc::ptr_eq(
+            &rewrite_shared(&shared, &mut Rename("Artist", "Performer")),
+            &shared
+        ));
+    }
+
+    #[test]
+    fn unchanged_subtrees_are_borrowed() {
+        let request = request();
+        let query = walk_query(&mut Rename("Album", "Record"), &request.query);
+        assert!(matches!(query, Cow::Borrowed(query) if std::ptr::eq(query, &request.query)));
+    }
+
+    #[test]
+    fn renames_every_table_reference() {
+        let request = request();
+        let rewritten = request.rewrite(&mut Rename("Album", "Record")).into_owned();
+        let relationship = &rewritten.relationships[0].relationships["Albums"];
+        assert_eq!(
+            relationship.target,
+            Target::Table {
+                name: vec!["Record".to_owned()]
+            }
+        );
+        assert_eq!(rewritten.query, request.query);
+
+        let rewritten = request
+            .rewrite(&mut Rename("Artist", "Performer"))
+            .into_owned();
+        assert_eq!(
+            rewritten.target,
+            Target::Table {
+                name: vec!["Performer".to_owned()]
+            }
+        );
+        assert_eq!(
+            rewritten.relationships[0].source_table,
+            vec!["Performer".to_owned()]
+        );
+    }
+
+    #[test]
+    fn renames_unrelated_exists_targets() {
+        let expression: Expression = serde_json::from_value(json!({
+            "type": "exists",
+            "in_table": { "type": "unrelated", "target": { "type": "table", "name": ["Album"] } },
+            "where": { "type": "and", "expressions": [] },
+        }))
+        .unwrap();
+        let rewritten = Rename("Album", "Record").rewrite_expression(&expression);
+        assert_eq!(
+            serde_json::to_value(rewritten.as_ref()).unwrap()["in_table"]["target"]["name"],
+            json!(["Record"])
+        );
+    }
+
+    #[test]
+    fn injects_filters_into_nested_queries() {
+        let rewritten = request().rewrite(&mut Restrict).into_owned();
+        let albums = match &rewritten.query.fields.as_ref().unwrap()["Albums"] {
+            Field::Relationship { query, .. } => query,
+            field => panic!("unexpected field {field:?}"),
+        };
+        for query in [&rewritten.query, albums] {
+            assert!(matches!(
+                &query.r#where,
+                Some(Expression::And { expressions }) if expressions.len() == 2
+            ));
+        }
+    }
+}
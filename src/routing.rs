@@ -0,0 +1,287 @@
+use std::fmt;
+
+use indexmap::IndexSet;
+
+use crate::{
+    capabilities::TableName,
+    mutation::{MutationOperation, MutationRequest},
+    protocol::SourceName,
+    query::{QueryRequest, TableRelationships, Target},
+};
+
+/// The parts of an agent's config that decide which database a request is routed to. Agents serving several databases implement it for their config type
+pub trait RoutingConfig {
+    /// The tenant every request made with this config belongs to. If `None`, the source name is used
+    fn tenant(&self) -> Option<&str> {
+        None
+    }
+
+    /// The catalog of tables whose names do not include one
+    fn default_catalog(&self) -> Option<&str> {
+        None
+    }
+
+    /// The schema of tables whose names do not include one
+    fn default_schema(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Where a request should be executed: the tenant it belongs to and the catalog and schema of the tables it refers to. Parts that could not be determined are `None`
+#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct RoutingKey {
+    pub tenant: Option<String>,
+    pub catalog: Option<String>,
+    pub schema: Option<String>,
+}
+
+impl RoutingKey {
+    /// Routes a request referring to the tables. A table name's namespace is read from the right: `[schema, table]` or `[catalog, schema, table]`. Fails if the tables are in different catalogs or schemas, as one connection cannot serve the request
+    pub fn for_tables<'a, C: RoutingConfig + ?Sized>(
+        config: &C,
+        source_name: Option<&SourceName>,
+        tables: impl IntoIterator<Item = &'a TableName>,
+    ) -> Result<Self, RoutingError> {
+        let mut catalog = None;
+        let mut schema = None;
+        for table in tables {
+            let mut namespace = table.iter().rev().skip(1);
+            let table_schema = namespace.next().map(String::as_str);
+            let table_catalog = namespace.next().map(String::as_str);
+            agree(
+                RoutingPart::Schema,
+                &mut schema,
+                table_schema.or(config.default_schema()),
+            )?;
+            agree(
+                RoutingPart::Catalog,
+                &mut catalog,
+                table_catalog.or(config.default_catalog()),
+            )?;
+        }
+        let tenant = config
+            .tenant()
+            .map(str::to_owned)
+            .or_else(|| source_name.map(|SourceName(name)| name.clone()));
+        Ok(Self {
+            tenant,
+            catalog: catalog.or_else(|| config.default_catalog().map(str::to_owned)),
+            schema: schema.or_else(|| config.default_schema().map(str::to_owned)),
+        })
+    }
+}
+
+fn agree(
+    part: RoutingPart,
+    current: &mut Option<String>,
+    next: Option<&str>,
+) -> Result<(), RoutingError> {
+    match (current.as_deref(), next) {
+        (_, None) => Ok(()),
+        (None, Some(next)) => {
+            *current = Some(next.to_owned());
+            Ok(())
+        }
+        (Some(current), Some(next)) if current == next => Ok(()),
+        (Some(current), Some(next)) => Err(RoutingError {
+            part,
+            first: current.to_owned(),
+            second: next.to_owned(),
+        }),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RoutingPart {
+    Catalog,
+    Schema,
+}
+
+/// A request referring to tables in more than one catalog or schema
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RoutingError {
+    pub part: RoutingPart,
+    pub first: String,
+    pub second: String,
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let part = match self.part {
+            RoutingPart::Catalog => "catalog",
+            RoutingPart::Schema => "schema",
+        };
+        write!(
+            f,
+            "the request refers to tables in more than one {part}: {} and {}",
+            self.first, self.second
+        )
+    }
+}
+
+impl std::error::Error for RoutingError {}
+
+impl QueryRequest {
+    /// The tables the request refers to: its target, the tables columns are read from and the source and target tables of its relationships
+    pub fn referenced_tables(&self) -> IndexSet<TableName> {
+        let mut tables = IndexSet::new();
+        if let Target::Table { name } = &self.target {
+            tables.insert(name.clone());
+        }
+        tables.extend(self.referenced_columns().into_keys());
+        tables.extend(relationship_tables(&self.relationships));
+        tables
+    }
+
+    /// Routes the request by its `referenced_tables`. Function and interpolated query targets, including those of relationships, name no table and are not considered, so a request targeting only a function is routed by the config's defaults and source name alone
+    pub fn routing_key<C: RoutingConfig + ?Sized>(
+        &self,
+        config: &C,
+        source_name: Option<&SourceName>,
+    ) -> Result<RoutingKey, RoutingError> {
+        RoutingKey::for_tables(config, source_name, &self.referenced_tables())
+    }
+}
+
+impl MutationRequest {
+    /// The tables the request refers to: the tables its operations write, the tables of its insert schema and the source and target tables of its relationships
+    pub fn referenced_tables(&self) -> IndexSet<TableName> {
+        let mut tables = IndexSet::new();
+        for operation in &self.operations {
+            match operation {
+                MutationOperation::Insert { table, .. }
+                | MutationOperation::Update { table, .. }
+                | MutationOperation::Delete { table, .. } => {
+                    tables.insert(table.clone());
+                }
+                MutationOperation::Execute { .. } => {}
+            }
+        }
+        tables.extend(self.insert_schema.iter().map(|schema| schema.table.clone()));
+        tables.extend(relationship_tables(&self.relationships));
+        tables
+    }
+
+    /// Routes the request by its `referenced_tables`. `Execute` operations call procedures, which are not considered, so a request made only of them is routed by the config's defaults and source name alone
+    pub fn routing_key<C: RoutingConfig + ?Sized>(
+        &self,
+        config: &C,
+        source_name: Option<&SourceName>,
+    ) -> Result<RoutingKey, RoutingError> {
+        RoutingKey::for_tables(config, source_name, &self.referenced_tables())
+    }
+}
+
+fn relationship_tables(
+    relationships: &[TableRelationships],
+) -> impl Iterator<Item = TableName> + '_ {
+    relationships.iter().flat_map(|relationships| {
+        let targets = relationships
+            .relationships
+            .values()
+            .filter_map(|relationship| match &relationship.target {
+                Target::Table { name } => Some(name.clone()),
+                _ => None,
+            });
+        std::iter::once(relationships.source_table.clone()).chain(targets)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Defaults;
+
+    impl RoutingConfig for Defaults {
+        fn default_schema(&self) -> Option<&str> {
+            Some("public")
+        }
+    }
+
+    struct Tenant;
+
+    impl RoutingConfig for Tenant {
+        fn tenant(&self) -> Option<&str> {
+            Some("acme")
+        }
+    }
+
+    fn table(name: &[&str]) -> TableName {
+        name.iter().map(|part| part.to_string()).collect()
+    }
+
+    #[test]
+    fn reads_namespaces_from_the_right() {
+        let tables = [
+            table(&["db", "sales", "Invoice"]),
+            table(&["sales", "Customer"]),
+        ];
+        let key = RoutingKey::for_tables(&Tenant, None, &tables).unwrap();
+        assert_eq!(
+            key,
+            RoutingKey {
+                tenant: Some("acme".to_owned()),
+                catalog: Some("db".to_owned()),
+                schema: Some("sales".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_config_defaults_and_source_name() {
+        let source = SourceName("chinook".to_owned());
+        let key = RoutingKey::for_tables(&Defaults, Some(&source), &[table(&["Artist"])]).unwrap();
+        assert_eq!(key.tenant.as_deref(), Some("chinook"));
+        assert_eq!(key.catalog, None);
+        assert_eq!(key.schema.as_deref(), Some("public"));
+    }
+
+    #[test]
+    fn rejects_tables_in_different_schemas() {
+        let error = RoutingKey::for_tables(
+            &Defaults,
+            None,
+            &[table(&["Artist"]), table(&["archive", "Album"])],
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            RoutingError {
+                part: RoutingPart::Schema,
+                first: "public".to_owned(),
+                second: "archive".to_owned(),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "the request refers to tables in more than one schema: public and archive"
+        );
+    }
+
+    #[test]
+    fn collects_request_tables() {
+        let request: QueryRequest = serde_json::from_str(include_str!(
+            "../fixtures/query_requests/exists_filter.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            request.referenced_tables().into_iter().collect::<Vec<_>>(),
+            vec![table(&["Customer"]), table(&["Employee"])]
+        );
+
+        let request: MutationRequest = serde_json::from_str(include_str!(
+            "../fixtures/mutation_requests/update_and_delete.json"
+        ))
+        .unwrap();
+        assert!(!request.referenced_tables().is_empty());
+        assert_eq!(
+            request
+                .routing_key(&Defaults, None)
+                .unwrap()
+                .schema
+                .as_deref(),
+            Some("public")
+        );
+    }
+}
@@ -2,7 +2,10 @@ use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
-use crate::capabilities::{ColumnName, FunctionName, ScalarType, TableName};
+use crate::{
+    capabilities::{ColumnName, FunctionName, ScalarType, TableName},
+    map::FieldMap,
+};
 
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -37,6 +40,8 @@ pub struct SchemaResponse {
     pub tables: Vec<TableInfo>,
     /// Available functions
     pub functions: Option<Vec<FunctionInfo>>,
+    /// Available stored procedures
+    pub procedures: Option<Vec<ProcedureInfo>>,
 }
 
 #[skip_serializing_none]
@@ -51,6 +56,8 @@ pub struct FunctionInfo {
     pub returns: Option<FunctionReturnType>,
     #[serde(rename = "type")]
     pub r#type: FunctionType,
+    /// Vendor metadata about the function, e.g. `postgres.volatility`, passed through untouched by HGE
+    pub extensions: Option<FieldMap<String, serde_json::Value>>,
 }
 
 #[skip_serializing_none]
@@ -85,6 +92,60 @@ pub enum FunctionReturnType {
     Unknown {},
 }
 
+/// A stored procedure. Unlike a function, a procedure may return several result sets and pass values back through output parameters
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcedureInfo {
+    pub name: FunctionName,
+    /// Description of the procedure
+    pub description: Option<String>,
+    /// The parameters of the procedure, in declaration order
+    pub parameters: Option<Vec<ProcedureParameter>>,
+    /// The result sets the procedure returns, in order
+    pub result_sets: Option<Vec<ProcedureResultSet>>,
+    /// The type of the procedure's return value, for backends where procedures return a status, e.g. SQL Server
+    pub return_type: Option<ScalarType>,
+    /// Vendor metadata about the procedure, e.g. `mssql.execute_as`
+    pub extensions: Option<FieldMap<String, serde_json::Value>>,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcedureParameter {
+    /// The name of the parameter
+    pub name: String,
+    #[serde(rename = "type")]
+    pub r#type: ScalarType,
+    /// How values are passed through the parameter. Defaults to `in`
+    pub mode: Option<ParameterMode>,
+    /// If the parameter can be omitted
+    pub optional: Option<bool>,
+    /// Description of the parameter
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterMode {
+    /// A value passed to the procedure
+    In,
+    /// A value the procedure passes back
+    Out,
+    /// A value passed to the procedure, which the procedure may change and pass back
+    InOut,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcedureResultSet {
+    /// The name of the result set, if the backend gives one
+    pub name: Option<String>,
+    /// The columns of the result set's rows, in order
+    pub columns: Vec<ColumnInfo>,
+    /// Description of the result set
+    pub description: Option<String>,
+}
+
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ObjectTypeDefinition {
@@ -94,6 +155,39 @@ pub struct ObjectTypeDefinition {
     pub description: Option<String>,
     /// The name of the type
     pub name: String,
+    /// Present if values of this type take one of several shapes, distinguished by a discriminator column
+    pub polymorphism: Option<PolymorphicObjectType>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PolymorphicObjectType {
+    pub kind: PolymorphicTypeKind,
+    /// The column whose value determines the variant of each value. Must be one of the type's `columns`
+    pub discriminator_column: ColumnName,
+    /// The possible variants
+    pub variants: Vec<ObjectTypeVariant>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolymorphicTypeKind {
+    /// Values only have the columns of their variant, and the discriminator column
+    Union,
+    /// Values have the type's `columns`, plus the columns of their variant
+    Interface,
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjectTypeVariant {
+    /// The name of the variant. Must be unique among the variants of the type
+    pub name: String,
+    /// The value of the discriminator column identifying this variant
+    pub discriminator_value: serde_json::Value,
+    /// The columns specific to this variant
+    pub columns: Vec<ColumnInfo>,
+    /// The description of the variant
+    pub description: Option<String>,
 }
 
 #[skip_serializing_none]
@@ -112,6 +206,43 @@ pub struct ColumnInfo {
     /// Whether or not the column can be updated
     pub updatable: Option<bool>,
     pub value_generated: Option<ColumnValueGenerationStrategy>,
+    /// Present if the column is computed by the backend rather than stored
+    pub computed: Option<ComputedColumnInfo>,
+    /// The value the column takes when it is omitted from an insert
+    pub default_value: Option<ColumnDefault>,
+    /// Vendor metadata about the column, e.g. `mysql.charset`
+    pub extensions: Option<FieldMap<String, serde_json::Value>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ColumnDefault {
+    /// A constant value
+    Literal { value: serde_json::Value },
+    /// A backend expression evaluated at insert time, e.g. `now()`
+    Expression { expression: String },
+    /// A function called at insert time
+    Function { function: FunctionName },
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ComputedColumnInfo {
+    /// How the column's value is computed
+    pub source: ComputedColumnSource,
+    /// Whether the column may be used in filter expressions
+    pub filterable: Option<bool>,
+    /// Whether the column may be used in order by elements
+    pub sortable: Option<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ComputedColumnSource {
+    /// A backend expression over the table's other columns, e.g. a SQL generation expression
+    Expression { expression: String },
+    /// A function taking the table's row as its argument
+    Function { function: FunctionName },
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -133,6 +264,311 @@ pub enum ColumnTypeNonScalar {
     },
 }
 
+impl ColumnType {
+    pub fn is_scalar(&self) -> bool {
+        matches!(self, Self::Scalar(_))
+    }
+
+    /// The scalar type at the bottom of any nested arrays, or `None` if this is or contains an object type
+    pub fn leaf_scalar_type(&self) -> Option<&ScalarType> {
+        match self {
+            Self::Scalar(scalar_type) => Some(scalar_type),
+            Self::ColumnTypeNonScalar(ColumnTypeNonScalar::Array { element_type, .. }) => {
+                element_type.leaf_scalar_type()
+            }
+            Self::ColumnTypeNonScalar(ColumnTypeNonScalar::Object { .. }) => None,
+        }
+    }
+
+    /// The number of array and object types wrapped around the innermost type. Scalars have a depth of 0, object types a depth of 1
+    pub fn nesting_depth(&self) -> usize {
+        match self {
+            Self::Scalar(_) => 0,
+            Self::ColumnTypeNonScalar(ColumnTypeNonScalar::Object { .. }) => 1,
+            Self::ColumnTypeNonScalar(ColumnTypeNonScalar::Array { element_type, .. }) => {
+                1 + element_type.nesting_depth()
+            }
+        }
+    }
+
+    /// Pushes the name of the object type this type refers to, if any, looking through nested arrays
+    pub(crate) fn object_type_names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Self::Scalar(_) => {}
+            Self::ColumnTypeNonScalar(ColumnTypeNonScalar::Object { name }) => names.push(name),
+            Self::ColumnTypeNonScalar(ColumnTypeNonScalar::Array { element_type, .. }) => {
+                element_type.object_type_names(names)
+            }
+        }
+    }
+
+    /// Rewrites every scalar type within this type, including the element types of nested arrays. Object types are referenced by name, so their columns must be rewritten via `ObjectTypeDefinition::map_scalars`
+    pub fn map_scalars(self, mut f: impl FnMut(ScalarType) -> ScalarType) -> Self {
+        self.map_scalars_dyn(&mut f)
+    }
+
+    fn map_scalars_dyn(self, f: &mut dyn FnMut(ScalarType) -> ScalarType) -> Self {
+        match self {
+            Self::Scalar(scalar_type) => Self::Scalar(f(scalar_type)),
+            Self::ColumnTypeNonScalar(ColumnTypeNonScalar::Array {
+                element_type,
+                nullable,
+            }) => Self::ColumnTypeNonScalar(ColumnTypeNonScalar::Array {
+                element_type: Box::new(element_type.map_scalars_dyn(f)),
+                nullable,
+            }),
+            object @ Self::ColumnTypeNonScalar(ColumnTypeNonScalar::Object { .. }) => object,
+        }
+    }
+}
+
+impl ObjectTypeDefinition {
+    /// Rewrites every scalar type used by the columns of this type, including those of its polymorphic variants
+    pub fn map_scalars(mut self, mut f: impl FnMut(ScalarType) -> ScalarType) -> Self {
+        let mut map_columns = |columns: Vec<ColumnInfo>| -> Vec<ColumnInfo> {
+            columns
+                .into_iter()
+                .map(|column| ColumnInfo {
+                    r#type: column.r#type.map_scalars_dyn(&mut f),
+                    ..column
+                })
+                .collect()
+        };
+        self.columns = map_columns(self.columns);
+        if let Some(polymorphism) = &mut self.polymorphism {
+            for variant in &mut polymorphism.variants {
+                variant.columns = map_columns(std::mem::take(&mut variant.columns));
+            }
+        }
+        self
+    }
+}
+
+impl SchemaResponse {
+    /// Answers a schema request from the full schema, applying its filters and then its detail level
+    pub fn for_request(self, request: &SchemaRequest) -> Self {
+        let schema = match &request.filters {
+            Some(filters) => self.apply_filters(filters),
+            None => self,
+        };
+        match &request.detail_level {
+            Some(detail_level) => schema.to_detail_level(detail_level.clone()),
+            None => schema,
+        }
+    }
+
+    /// Keeps only the tables and functions the filters list. A filter that is not set keeps every table or function. `only_functions` applies to functions only, and procedures, which the filters cannot name, are always kept. Object types are kept only if a remaining table, procedure result set or object type uses them
+    pub fn apply_filters(mut self, filters: &SchemaFilters) -> Self {
+        if let Some(only_tables) = &filters.only_tables {
+            self.tables
+                .retain(|table| only_tables.contains(&table.name));
+        }
+        if let Some(only_functions) = &filters.only_functions {
+            if let Some(functions) = &mut self.functions {
+                functions.retain(|function| only_functions.contains(&function.name));
+            }
+        }
+        if let Some(object_types) = self.object_types.take() {
+            let mut used = std::collections::HashSet::new();
+            let mut pending: Vec<&str> = Vec::new();
+            let result_sets = self
+                .procedures
+                .iter()
+                .flatten()
+                .flat_map(|procedure| procedure.result_sets.iter().flatten());
+            let columns = self
+                .tables
+                .iter()
+                .flat_map(|table| table.columns.iter().flatten())
+                .chain(result_sets.flat_map(|result_set| &result_set.columns));
+            for column in columns {
+                column.r#type.object_type_names(&mut pending);
+            }
+            while let Some(name) = pending.pop() {
+                if !used.insert(name.to_owned()) {
+                    continue;
+                }
+                let Some(object_type) = object_types
+                    .iter()
+                    .find(|object_type| object_type.name == name)
+                else {
+                    continue;
+                };
+                let variants = object_type
+                    .polymorphism
+                    .iter()
+                    .flat_map(|polymorphism| &polymorphism.variants);
+                let columns = object_type
+                    .columns
+                    .iter()
+                    .chain(variants.flat_map(|variant| &variant.columns));
+                for column in columns {
+                    column.r#type.object_type_names(&mut pending);
+                }
+            }
+            self.object_types = Some(
+                object_types
+                    .into_iter()
+                    .filter(|object_type| used.contains(&object_type.name))
+                    .collect(),
+            );
+        }
+        self
+    }
+
+    /// Drops the information a detail level leaves out. `basic_info` keeps only the name and type of tables and functions, the name of procedures, and no object types
+    pub fn to_detail_level(self, detail_level: DetailLevel) -> Self {
+        match detail_level {
+            DetailLevel::Everything => self,
+            DetailLevel::BasicInfo => Self {
+                object_types: None,
+                tables: self
+                    .tables
+                    .into_iter()
+                    .map(|table| TableInfo {
+                        columns: None,
+                        deletable: None,
+                        description: None,
+                        foreign_keys: None,
+                        insertable: None,
+                        name: table.name,
+                        primary_key: None,
+                        r#type: table.r#type,
+                        updatable: None,
+                        extensions: None,
+                    })
+                    .collect(),
+                functions: self.functions.map(|functions| {
+                    functions
+                        .into_iter()
+                        .map(|function| FunctionInfo {
+                            args: None,
+                            description: None,
+                            name: function.name,
+                            response_cardinality: None,
+                            returns: None,
+                            r#type: function.r#type,
+                            extensions: None,
+                        })
+                        .collect()
+                }),
+                procedures: self.procedures.map(|procedures| {
+                    procedures
+                        .into_iter()
+                        .map(|procedure| ProcedureInfo {
+                            name: procedure.name,
+                            description: None,
+                            parameters: None,
+                            result_sets: None,
+                            return_type: None,
+                            extensions: None,
+                        })
+                        .collect()
+                }),
+            },
+        }
+    }
+
+    /// Sorts tables, functions, procedures, object types, columns, foreign keys and extensions by name, and drops optional values that only restate the default: `false` flags, empty lists and maps, empty descriptions, the `table` table type and the `in` parameter mode. Primary key columns, function arguments, procedure parameters and result set columns and polymorphic variants keep their order, as it is meaningful
+    pub fn normalized(mut self) -> Self {
+        for table in &mut self.tables {
+            normalize_columns(table.columns.as_deref_mut().unwrap_or_default());
+            if let Some(foreign_keys) = &mut table.foreign_keys {
+                for constraint in foreign_keys.values_mut() {
+                    constraint.column_mapping.sort_keys();
+                }
+                foreign_keys.sort_keys();
+            }
+            trim(&mut table.foreign_keys, IndexMap::is_empty);
+            trim(&mut table.primary_key, Vec::is_empty);
+            trim(&mut table.description, String::is_empty);
+            trim(&mut table.r#type, |r#type| *r#type == TableType::Table);
+            trim_false(&mut table.insertable);
+            trim_false(&mut table.updatable);
+            trim_false(&mut table.deletable);
+            normalize_extensions(&mut table.extensions);
+        }
+        self.tables.sort_by(|a, b| a.name.cmp(&b.name));
+        if let Some(functions) = &mut self.functions {
+            for function in functions.iter_mut() {
+                for arg in function.args.iter_mut().flatten() {
+                    trim_false(&mut arg.optional);
+                }
+                trim(&mut function.args, Vec::is_empty);
+                trim(&mut function.description, String::is_empty);
+                normalize_extensions(&mut function.extensions);
+            }
+            functions.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        trim(&mut self.functions, Vec::is_empty);
+        if let Some(procedures) = &mut self.procedures {
+            for procedure in procedures.iter_mut() {
+                for parameter in procedure.parameters.iter_mut().flatten() {
+                    trim(&mut parameter.mode, |mode| *mode == ParameterMode::In);
+                    trim_false(&mut parameter.optional);
+                    trim(&mut parameter.description, String::is_empty);
+                }
+                for result_set in procedure.result_sets.iter_mut().flatten() {
+                    result_set.columns.iter_mut().for_each(normalize_column);
+                    trim(&mut result_set.description, String::is_empty);
+                }
+                trim(&mut procedure.parameters, Vec::is_empty);
+                trim(&mut procedure.result_sets, Vec::is_empty);
+                trim(&mut procedure.description, String::is_empty);
+                normalize_extensions(&mut procedure.extensions);
+            }
+            procedures.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        trim(&mut self.procedures, Vec::is_empty);
+        if let Some(object_types) = &mut self.object_types {
+            for object_type in object_types.iter_mut() {
+                normalize_columns(&mut object_type.columns);
+                for variant in object_type
+                    .polymorphism
+                    .iter_mut()
+                    .flat_map(|polymorphism| &mut polymorphism.variants)
+                {
+                    normalize_columns(&mut variant.columns);
+                    trim(&mut variant.description, String::is_empty);
+                }
+                trim(&mut object_type.description, String::is_empty);
+            }
+            object_types.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        trim(&mut self.object_types, Vec::is_empty);
+        self
+    }
+}
+
+fn normalize_columns(columns: &mut [ColumnInfo]) {
+    columns.sort_by(|a, b| a.name.cmp(&b.name));
+    columns.iter_mut().for_each(normalize_column);
+}
+
+fn normalize_column(column: &mut ColumnInfo) {
+    trim(&mut column.description, String::is_empty);
+    trim_false(&mut column.insertable);
+    trim_false(&mut column.updatable);
+    normalize_extensions(&mut column.extensions);
+}
+
+fn normalize_extensions(extensions: &mut Option<FieldMap<String, serde_json::Value>>) {
+    if let Some(extensions) = extensions {
+        extensions.sort_keys();
+    }
+    trim(extensions, IndexMap::is_empty);
+}
+
+fn trim<T>(value: &mut Option<T>, is_default: impl FnOnce(&T) -> bool) {
+    if value.as_ref().is_some_and(is_default) {
+        *value = None;
+    }
+}
+
+fn trim_false(value: &mut Option<bool>) {
+    trim(value, |value| !value);
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum ColumnValueGenerationStrategy {
@@ -151,7 +587,7 @@ pub struct TableInfo {
     /// Description of the table
     pub description: Option<String>,
     /// Foreign key constraints
-    pub foreign_keys: Option<IndexMap<String, Constraint>>,
+    pub foreign_keys: Option<FieldMap<String, Constraint>>,
     /// Whether or not new rows can be inserted into the table
     pub insertable: Option<bool>,
     /// The fully qualified name of a table, where the last item in the array is the table name and any earlier items represent the namespacing of the table name
@@ -162,12 +598,14 @@ pub struct TableInfo {
     pub r#type: Option<TableType>,
     /// Whether or not existing rows can be updated in the table
     pub updatable: Option<bool>,
+    /// Vendor metadata about the table, keyed by a vendor namespace, e.g. `mysql.engine`. HGE ignores it
+    pub extensions: Option<FieldMap<String, serde_json::Value>>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Constraint {
     /// The columns on which you want want to define the foreign key.
-    pub column_mapping: IndexMap<ColumnName, ColumnName>,
+    pub column_mapping: FieldMap<ColumnName, ColumnName>,
     /// The fully qualified name of a table, where the last item in the array is the table name and any earlier items represent the namespacing of the table name
     pub foreign_table: TableName,
 }
@@ -0,0 +1,156 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::{header, Method, Request, Response, StatusCode, Uri};
+use http_body_util::{BodyExt, Full};
+
+use crate::{
+    agent::{validation_failure, DataConnectorAgent},
+    dispatch::{
+        dispatch, error_response, json_response, AgentRequest, AgentResponse, Route,
+        SummaryRequested,
+    },
+    error::{ErrorResponse, ErrorResponseType},
+    protocol::{Endpoint, CONFIG_HEADER},
+};
+
+/// A tower service serving every data connector endpoint from the agent, for mounting an agent in hyper, warp or any other server built on `http` types.
+///
+/// Requests are decoded and answered by the same code as the axum and actix adapters, with the same method for each endpoint. Unlike those adapters, which match paths exactly, this service matches paths by suffix, so it can be mounted under a prefix, and a request to `/v1/query` is served as `/query`. Unmatched paths get a `404` and unserved methods a `405`, both as `request-validation-failure` `ErrorResponse`s. Responses, including errors, are encoded as JSON, with errors sent with the status given by `ErrorResponse::status_code`
+pub struct AgentService<A> {
+    agent: Arc<A>,
+}
+
+impl<A> AgentService<A> {
+    pub fn new(agent: A) -> Self {
+        Self {
+            agent: Arc::new(agent),
+        }
+    }
+}
+
+impl<A> Clone for AgentService<A> {
+    fn clone(&self) -> Self {
+        Self {
+            agent: self.agent.clone(),
+        }
+    }
+}
+
+impl<A, B> tower::Service<Request<B>> for AgentService<A>
+where
+    A: DataConnectorAgent + 'static,
+    B: http_body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: std::fmt::Display,
+{
+    type Response = Response<Full<Bytes>>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let agent = self.agent.clone();
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = match body.collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(error) => {
+                    let error = validation_failure(format!("failed to read request body: {error}"));
+                    return Ok(into_response(error_response(&error)));
+                }
+            };
+            let Some(route) = route(&parts.method, &parts.uri) else {
+                return Ok(into_response(unrouted(&parts.method, parts.uri.path())));
+            };
+            let headers = &parts.headers;
+            let request = AgentRequest {
+                config_header: headers.get(CONFIG_HEADER).map(|value| value.as_bytes()),
+                content_type: headers
+                    .get(header::CONTENT_TYPE)
+                    .map(|value| value.as_bytes()),
+                body: &body,
+                summary: parts.extensions.get::<SummaryRequested>().copied(),
+            };
+            Ok(into_response(dispatch(&*agent, route, request).await))
+        })
+    }
+}
+
+/// The endpoint for the method and path, matching paths by suffix
+fn route(method: &Method, uri: &Uri) -> Option<Route> {
+    let route = match (Endpoint::of_path(uri.path())?, method) {
+        (Endpoint::Capabilities, &Method::GET) => Route::Capabilities,
+        (Endpoint::Schema, &Method::GET | &Method::POST) => Route::Schema,
+        (Endpoint::Query, &Method::POST) => Route::Query,
+        (Endpoint::Explain, &Method::POST) => Route::Explain,
+        (Endpoint::Mutation, &Method::POST) => Route::Mutation,
+        (Endpoint::Raw, &Method::POST) => Route::Raw,
+        (Endpoint::Health, &Method::GET) => {
+            let request = serde_urlencoded::from_str(uri.query().unwrap_or_default())
+                .map_err(|error| validation_failure(error.to_string()));
+            Route::Health(request)
+        }
+        (Endpoint::Datasets, _) => match (dataset_path(uri.path()), method) {
+            (("templates", name), &Method::GET) => Route::DatasetGetTemplate(name),
+            (("clones", name), &Method::POST) => Route::DatasetCreateClone(name),
+            (("clones", name), &Method::DELETE) => Route::DatasetDeleteClone(name),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(route)
+}
+
+/// Splits a dataset path into `templates` or `clones` and the percent-decoded name that follows
+fn dataset_path(path: &str) -> (&str, String) {
+    let mut segments = path.trim_end_matches('/').rsplit('/');
+    let name = segments.next().unwrap_or_default();
+    let collection = segments.next().unwrap_or_default();
+    let name = percent_encoding::percent_decode_str(name)
+        .decode_utf8_lossy()
+        .into_owned();
+    (collection, name)
+}
+
+/// A `404` for paths that are not agent endpoints, or a `405` for methods an endpoint does not serve
+fn unrouted(method: &Method, path: &str) -> AgentResponse {
+    let (status, message) = match Endpoint::of_path(path) {
+        Some(_) => (
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{method} is not allowed for {path}"),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("no data connector endpoint at {path}"),
+        ),
+    };
+    let error = ErrorResponse {
+        details: None,
+        message,
+        r#type: Some(ErrorResponseType::RequestValidationFailure),
+    };
+    json_response(status.as_u16(), &error)
+}
+
+fn into_response(response: AgentResponse) -> Response<Full<Bytes>> {
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut builder = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, response.content_type);
+    if let Some(summary) = response.summary {
+        builder = builder.extension(summary);
+    }
+    builder
+        .body(Full::new(Bytes::from(response.body)))
+        .expect("status and header are valid")
+}
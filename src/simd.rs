@@ -0,0 +1,69 @@
+use serde::de::DeserializeOwned;
+
+use crate::{
+    dataset::DatasetCreateCloneRequest,
+    health::HealthCheckRequest,
+    licensing::{LicenseActivationRequest, LicenseHeartbeatRequest, LicenseKeyRequest},
+    mutation::MutationRequest,
+    query::QueryRequest,
+    raw::RawRequest,
+    schema::SchemaRequest,
+    subscription::SubscriptionClientMessage,
+};
+
+/// Parses a protocol value with `simd-json`. The input is used as scratch space while parsing and is left in an unspecified state
+pub fn from_simd_slice<T: DeserializeOwned>(json: &mut [u8]) -> simd_json::Result<T> {
+    simd_json::serde::from_slice(json)
+}
+
+macro_rules! impl_from_simd_slice {
+    ($($t:ty),* $(,)?) => {
+        $(impl $t {
+            /// Parses the request with `simd-json`. The input is used as scratch space while parsing and is left in an unspecified state
+            pub fn from_simd_slice(json: &mut [u8]) -> simd_json::Result<Self> {
+                from_simd_slice(json)
+            }
+        })*
+    };
+}
+
+impl_from_simd_slice!(
+    DatasetCreateCloneRequest,
+    HealthCheckRequest,
+    LicenseActivationRequest,
+    LicenseHeartbeatRequest,
+    LicenseKeyRequest,
+    MutationRequest,
+    QueryRequest,
+    RawRequest,
+    SchemaRequest,
+    SubscriptionClientMessage,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_like_serde_json() {
+        let json = include_str!("../fixtures/query_requests/exists_filter.json");
+        let mut bytes = json.as_bytes().to_vec();
+        assert_eq!(
+            QueryRequest::from_simd_slice(&mut bytes).unwrap(),
+            serde_json::from_str::<QueryRequest>(json).unwrap()
+        );
+
+        let json = include_str!("../fixtures/mutation_requests/insert_with_returning.json");
+        let mut bytes = json.as_bytes().to_vec();
+        assert_eq!(
+            MutationRequest::from_simd_slice(&mut bytes).unwrap(),
+            serde_json::from_str::<MutationRequest>(json).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        let mut bytes = b"{\"target\": ".to_vec();
+        assert!(QueryRequest::from_simd_slice(&mut bytes).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    capabilities::ScalarType,
+    metrics::{Metric, MetricFamily, MetricType, MetricValue},
+    schema::{ColumnInfo, ColumnType, ColumnTypeNonScalar, SchemaResponse, TableType},
+};
+
+/// Counts describing the size and shape of a schema. Column counts are of table columns, not the columns of object types
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SchemaStats {
+    /// Tables whose type is `table` or not given
+    pub tables: usize,
+    pub views: usize,
+    pub functions: usize,
+    pub procedures: usize,
+    pub object_types: usize,
+    pub columns: usize,
+    pub nullable_columns: usize,
+    /// Columns by the scalar type at the bottom of any nested arrays. Columns of object types are not included
+    pub columns_by_scalar_type: IndexMap<ScalarType, usize>,
+    /// Columns whose type is or contains an object type
+    pub object_columns: usize,
+    /// Tables with a primary key
+    pub primary_keys: usize,
+    pub foreign_keys: usize,
+    /// The deepest nesting of object types within a table column, 0 if no column uses an object type. A recursive object type counts once
+    pub max_object_type_depth: usize,
+}
+
+impl SchemaStats {
+    /// The fraction of columns that are nullable, 0 if there are no columns
+    pub fn nullable_ratio(&self) -> f64 {
+        if self.columns == 0 {
+            0.0
+        } else {
+            self.nullable_columns as f64 / self.columns as f64
+        }
+    }
+
+    /// The counts as gauges, for agents to include in their `GET /metrics` response. Names start with the prefix, e.g. `my_agent_schema_tables`
+    pub fn metric_families(&self, prefix: &str) -> Vec<MetricFamily> {
+        let gauge = |name: &str, help: &str, metrics: Vec<Metric>| MetricFamily {
+            name: format!("{prefix}{name}"),
+            help: Some(help.to_owned()),
+            r#type: MetricType::Gauge,
+            metrics,
+        };
+        let sample = |labels: &[(&str, String)], value: usize| Metric {
+            labels: labels
+                .iter()
+                .map(|(key, value)| ((*key).to_owned(), value.clone()))
+                .collect(),
+            value: MetricValue::Single {
+                value: value as f64,
+            },
+            timestamp_ms: None,
+        };
+        vec![
+            gauge(
+                "schema_tables",
+                "Tables and views in the schema",
+                vec![
+                    sample(&[("type", "table".to_owned())], self.tables),
+                    sample(&[("type", "view".to_owned())], self.views),
+                ],
+            ),
+            gauge(
+                "schema_functions",
+                "Functions in the schema",
+                vec![sample(&[], self.functions)],
+            ),
+            gauge(
+                "schema_procedures",
+                "Stored procedures in the schema",
+                vec![sample(&[], self.procedures)],
+            ),
+            gauge(
+                "schema_object_types",
+                "Object types in the schema",
+                vec![sample(&[], self.object_types)],
+            ),
+            gauge(
+                "schema_columns",
+                "Table columns by scalar type",
+                self.columns_by_scalar_type
+                    .iter()
+                    .map(|(scalar_type, count)| {
+                        sample(&[("scalar_type", scalar_type.to_string())], *count)
+                    })
+                    .collect(),
+            ),
+            gauge(
+                "schema_nullable_columns",
+                "Nullable table columns",
+                vec![sample(&[], self.nullable_columns)],
+            ),
+            gauge(
+                "schema_foreign_keys",
+                "Foreign keys in the schema",
+                vec![sample(&[], self.foreign_keys)],
+            ),
+        ]
+    }
+}
+
+impl SchemaResponse {
+    pub fn stats(&self) -> SchemaStats {
+        let mut stats = SchemaStats {
+            functions: self.functions.as_ref().map_or(0, Vec::len),
+            procedures: self.procedures.as_ref().map_or(0, Vec::len),
+            object_types: self.object_types.as_ref().map_or(0, Vec::len),
+            ..SchemaStats::default()
+        };
+        let mut depths = HashMap::new();
+        for table in &self.tables {
+            match table.r#type {
+                Some(TableType::View) => stats.views += 1,
+                Some(TableType::Table) | None => stats.tables += 1,
+            }
+            if table.primary_key.is_some() {
+                stats.primary_keys += 1;
+            }
+            stats.foreign_keys += table.foreign_keys.as_ref().map_or(0, |keys| keys.len());
+            for column in table.columns.iter().flatten() {
+                stats.columns += 1;
+                if column.nullable {
+                    stats.nullable_columns += 1;
+                }
+                match column.r#type.leaf_scalar_type() {
+                    Some(scalar_type) => {
+                        *stats
+                            .columns_by_scalar_type
+                            .entry(scalar_type.clone())
+                            .or_default() += 1
+                    }
+                    None => stats.object_columns += 1,
+                }
+                let depth =
+                    self.object_type_depth(&column.r#type, &mut HashSet::new(), &mut depths);
+                stats.max_object_type_depth = stats.max_object_type_depth.max(depth);
+            }
+        }
+        stats
+    }
+
+    /// The number of object types nested within the type, skipping types already being visited. The depth of each object type is computed once and kept in `depths`, so shared object types do not make this exponential
+    fn object_type_depth<'a>(
+        &'a self,
+        r#type: &'a ColumnType,
+        visiting: &mut HashSet<&'a str>,
+        depths: &mut HashMap<&'a str, usize>,
+    ) -> usize {
+        match r#type {
+            ColumnType::Scalar(_) => 0,
+            ColumnType::ColumnTypeNonScalar(ColumnTypeNonScalar::Array {
+                element_type, ..
+            }) => self.object_type_depth(element_type, visiting, depths),
+            ColumnType::ColumnTypeNonScalar(ColumnTypeNonScalar::Object { name }) => {
+                if let Some(depth) = depths.get(name.as_str()) {
+                    return *depth;
+                }
+                if !visiting.insert(name) {
+                    return 0;
+                }
+                let object_type = self
+                    .object_types
+                    .iter()
+                    .flatten()
+                    .find(|object_type| object_type.name == *name);
+                let columns: Vec<&ColumnInfo> = object_type
+                    .into_iter()
+                    .flat_map(|object_type| {
+                        let variants = object_type
+                            .polymorphism
+                            .iter()
+                            .flat_map(|polymorphism| &polymorphism.variants);
+                        object_type
+                            .columns
+                            .iter()
+                            .chain(variants.flat_map(|variant| &variant.columns))
+                    })
+                    .collect();
+                let depth = columns
+                    .into_iter()
+                    .map(|column| self.object_type_depth(&column.r#type, visiting, depths))
+                    .max()
+                    .unwrap_or(0);
+                visiting.remove(name.as_str());
+                depths.insert(name, 1 + depth);
+                1 + depth
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema(object_types: serde_json::Value) -> SchemaResponse {
+        serde_json::from_value(json!({
+            "tables": [{
+                "name": ["T"],
+                "columns": [{ "name": "c", "type": { "type": "object", "name": "T0" }, "nullable": false }],
+            }],
+            "object_types": object_types,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn shared_object_types_are_measured_once() {
+        let count = 64;
+        let object_types: Vec<_> = (0..count)
+            .map(|index| {
+                let columns = if index + 1 == count {
+                    json!([{ "name": "leaf", "type": "string", "nullable": false }])
+                } else {
+                    let next = json!({ "type": "object", "name": format!("T{}", index + 1) });
+                    json!([
+                        { "name": "left", "type": next, "nullable": false },
+                        { "name": "right", "type": { "type": "array", "element_type": next, "nullable": false }, "nullable": false },
+                    ])
+                };
+                json!({ "name": format!("T{index}"), "columns": columns })
+            })
+            .collect();
+        let stats = schema(json!(object_types)).stats();
+        assert_eq!(stats.max_object_type_depth, count);
+    }
+
+    #[test]
+    fn recursive_object_types_terminate() {
+        let stats = schema(json!([{
+            "name": "T0",
+            "columns": [{ "name": "parent", "type": { "type": "object", "name": "T0" }, "nullable": true }],
+        }]))
+        .stats();
+        assert_eq!(stats.max_object_type_depth, 1);
+    }
+}
@@ -0,0 +1,251 @@
+use std::{cell::RefCell, io};
+
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
+
+use crate::{
+    error::ErrorResponse,
+    map::FieldMap,
+    query::{ResponseFieldValue, ResponseRow},
+};
+
+/// The content type of a streamed query response, where each line is a JSON `QueryResponseChunk`
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// One line of a streamed query response. A stream consists of exactly one header, any number of row batches, and exactly one trailer
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QueryResponseChunk {
+    Header {
+        /// The results of the aggregates returned by the query
+        #[serde(skip_serializing_if = "Option::is_none")]
+        aggregates: Option<FieldMap<String, serde_json::Value>>,
+    },
+    Rows {
+        /// The next batch of rows returned by the query, corresponding to the query's fields
+        rows: Vec<FieldMap<String, ResponseFieldValue>>,
+    },
+    Trailer {
+        /// The total number of rows sent in row batches
+        row_count: u64,
+        /// The number of rows matched before `limit` and `offset` were applied, if requested via `include_total_matched`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        total_matched: Option<u64>,
+        /// Present if the agent failed part way through the stream. Rows already sent should be discarded
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<ErrorResponse>,
+    },
+}
+
+impl ResponseRow {
+    /// Reassembles a complete response from the chunks of a stream, checking they were sent in order and that the row count matches
+    pub fn from_chunks(
+        chunks: impl IntoIterator<Item = QueryResponseChunk>,
+    ) -> Result<ResponseRow, ErrorResponse> {
+        let protocol_error = |message: &str| ErrorResponse {
+            details: None,
+            message: format!("invalid query response stream: {message}"),
+            r#type: None,
+        };
+        let mut chunks = chunks.into_iter();
+        let aggregates = match chunks.next() {
+            Some(QueryResponseChunk::Header { aggregates }) => aggregates,
+            _ => return Err(protocol_error("expected a header chunk")),
+        };
+        let mut rows = None;
+        for chunk in chunks {
+            match chunk {
+                QueryResponseChunk::Rows { rows: batch } => {
+                    rows.get_or_insert_with(Vec::new).extend(batch)
+                }
+                QueryResponseChunk::Trailer {
+                    error: Some(error), ..
+                } => return Err(error),
+                QueryResponseChunk::Trailer {
+                    row_count,
+                    total_matched,
+                    ..
+                } => {
+                    let received = rows.as_ref().map_or(0, Vec::len) as u64;
+                    if received != row_count {
+                        return Err(protocol_error(&format!(
+                            "trailer declared {row_count} rows but {received} were received"
+                        )));
+                    }
+                    return Ok(ResponseRow {
+                        aggregates,
+                        rows,
+                        total_matched,
+                    });
+                }
+                QueryResponseChunk::Header { .. } => {
+                    return Err(protocol_error("unexpected second header chunk"))
+                }
+            }
+        }
+        Err(protocol_error("stream ended without a trailer chunk"))
+    }
+}
+
+/// A single result set whose rows are serialized from an iterator as they are produced, so that they never need to be held in memory at once.
+///
+/// Serializes exactly like a `QueryResponse` with the same rows. The iterator is consumed by the first serialization, and serializing again is an error
+pub struct StreamingResponseRow<I> {
+    /// The results of the aggregates returned by the query
+    pub aggregates: Option<FieldMap<String, serde_json::Value>>,
+    /// The number of rows matched before `limit` and `offset` were applied, if requested via `include_total_matched`.
+    pub total_matched: Option<u64>,
+    rows: RefCell<Option<I>>,
+}
+
+impl<I> StreamingResponseRow<I> {
+    pub fn new(rows: I) -> Self {
+        Self {
+            aggregates: None,
+            total_matched: None,
+            rows: RefCell::new(Some(rows)),
+        }
+    }
+
+    pub fn with_aggregates(mut self, aggregates: FieldMap<String, serde_json::Value>) -> Self {
+        self.aggregates = Some(aggregates);
+        self
+    }
+
+    pub fn with_total_matched(mut self, total_matched: u64) -> Self {
+        self.total_matched = Some(total_matched);
+        self
+    }
+}
+
+impl<I> Serialize for StreamingResponseRow<I>
+where
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let rows = self.rows.borrow_mut().take().ok_or_else(|| {
+            serde::ser::Error::custom(
+                "the rows of a streaming response can only be serialized once",
+            )
+        })?;
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(aggregates) = &self.aggregates {
+            map.serialize_entry("aggregates", aggregates)?;
+        }
+        map.serialize_entry("rows", &RowIter(RefCell::new(Some(rows))))?;
+        if let Some(total_matched) = self.total_matched {
+            map.serialize_entry("total_matched", &total_matched)?;
+        }
+        map.end()
+    }
+}
+
+struct RowIter<I>(RefCell<Option<I>>);
+
+impl<I> Serialize for RowIter<I>
+where
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.borrow_mut().take().into_iter().flatten())
+    }
+}
+
+/// Serializes a single result set `QueryResponse` containing the given rows, consuming them one at a time
+pub fn serialize_rows<S, I>(serializer: S, rows: I) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    StreamingResponseRow::new(rows).serialize(serializer)
+}
+
+/// Writes a single result set `QueryResponse` containing the given rows as JSON, consuming them one at a time
+pub fn write_rows<W, I>(writer: W, rows: I) -> serde_json::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    serialize_rows(&mut serde_json::Serializer::new(writer), rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn chunk(value: serde_json::Value) -> QueryResponseChunk {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn reassembles_chunks() {
+        let row = ResponseRow::from_chunks([
+            chunk(json!({"type": "header", "aggregates": {"count": 3}})),
+            chunk(json!({"type": "rows", "rows": [{"id": 1}, {"id": 2}]})),
+            chunk(json!({"type": "rows", "rows": [{"id": 3}]})),
+            chunk(json!({"type": "trailer", "row_count": 3, "total_matched": 10})),
+        ])
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(row).unwrap(),
+            json!({
+                "aggregates": {"count": 3},
+                "rows": [{"id": 1}, {"id": 2}, {"id": 3}],
+                "total_matched": 10,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_order_chunks() {
+        let error = |chunks: Vec<serde_json::Value>| {
+            ResponseRow::from_chunks(chunks.into_iter().map(chunk))
+                .unwrap_err()
+                .message
+        };
+        let header = json!({"type": "header"});
+        let trailer = json!({"type": "trailer", "row_count": 0});
+        assert!(error(vec![trailer.clone()]).contains("expected a header chunk"));
+        assert!(error(vec![header.clone(), header.clone()]).contains("second header"));
+        assert!(error(vec![header.clone()]).contains("without a trailer"));
+        assert!(error(vec![
+            header,
+            json!({"type": "rows", "rows": [{"id": 1}]}),
+            trailer,
+        ])
+        .contains("declared 0 rows but 1 were received"));
+    }
+
+    #[test]
+    fn returns_trailer_errors() {
+        let error = ResponseRow::from_chunks([
+            chunk(json!({"type": "header"})),
+            chunk(json!({"type": "trailer", "row_count": 0, "error": {"message": "boom"}})),
+        ])
+        .unwrap_err();
+        assert_eq!(error.message, "boom");
+    }
+
+    #[test]
+    fn streams_rows_like_a_response_row() {
+        let rows = (1..=3).map(|id| json!({"id": id}));
+        let response = StreamingResponseRow::new(rows).with_total_matched(3);
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({"rows": [{"id": 1}, {"id": 2}, {"id": 3}], "total_matched": 3})
+        );
+        assert!(serde_json::to_value(&response).is_err());
+
+        let mut written = Vec::new();
+        write_rows(&mut written, vec![json!({"id": 1})]).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&written).unwrap(),
+            json!({"rows": [{"id": 1}]})
+        );
+    }
+}
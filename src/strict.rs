@@ -0,0 +1,109 @@
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::preserve::{Preserved, UnknownField};
+
+/// Parses a protocol value, failing if the JSON contains fields this crate does not model at any depth, including within `Field`, `Expression` and other enums, as found by `Preserved`. Intended for protocol test suites, to detect when HGE starts sending fields that are not yet supported
+pub fn from_str_strict<T: DeserializeOwned + Serialize + PartialEq>(
+    json: &str,
+) -> Result<T, StrictError> {
+    from_value_strict(serde_json::from_str(json).map_err(StrictError::Json)?)
+}
+
+pub fn from_slice_strict<T: DeserializeOwned + Serialize + PartialEq>(
+    json: &[u8],
+) -> Result<T, StrictError> {
+    from_value_strict(serde_json::from_slice(json).map_err(StrictError::Json)?)
+}
+
+pub fn from_value_strict<T: DeserializeOwned + Serialize + PartialEq>(
+    json: Value,
+) -> Result<T, StrictError> {
+    let preserved = Preserved::<T>::from_value(json).map_err(StrictError::Json)?;
+    if preserved.unknown_fields.is_empty() {
+        Ok(preserved.value)
+    } else {
+        Err(StrictError::UnknownFields(preserved.unknown_fields))
+    }
+}
+
+#[derive(Debug)]
+pub enum StrictError {
+    /// The JSON was malformed or did not match the type
+    Json(serde_json::Error),
+    /// The JSON matched the type, but contained fields the type does not model
+    UnknownFields(Vec<UnknownField>),
+}
+
+impl fmt::Display for StrictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(error) => write!(f, "{error}"),
+            Self::UnknownFields(fields) => {
+                write!(f, "unknown fields: ")?;
+                for (index, field) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{field}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for StrictError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(error) => Some(error),
+            Self::UnknownFields(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::query::QueryRequest;
+
+    fn request(column: Value) -> Value {
+        json!({
+            "target": {"type": "table", "name": ["Artist"]},
+            "relationships": [],
+            "query": {
+                "fields": {
+                    "Name": {"type": "column", "column": "Name", "column_type": "string"},
+                },
+                "where": {"type": "unary_op", "operator": "is_null", "column": column},
+            },
+        })
+    }
+
+    #[test]
+    fn accepts_modelled_fields() {
+        let json = request(json!({"name": "Name", "column_type": "string", "path": null}));
+        assert!(from_value_strict::<QueryRequest>(json).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_fields_within_enums() {
+        let mut json = request(json!({"name": "Name", "column_type": "string", "future": 1}));
+        json["query"]["fields"]["Name"]["future"] = json!("x");
+        let error = from_value_strict::<QueryRequest>(json).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown fields: $.query.fields.Name.future, $.query.where.column.future"
+        );
+    }
+
+    #[test]
+    fn reports_malformed_json() {
+        let error = from_str_strict::<QueryRequest>("{").unwrap_err();
+        assert!(matches!(error, StrictError::Json(_)));
+    }
+}
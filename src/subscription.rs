@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ErrorResponse,
+    query::{QueryRequest, QueryResponse},
+};
+
+/// A message sent from HGE to the agent over a subscription stream
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubscriptionClientMessage {
+    /// Start a live query. The agent should respond with a data frame whenever the result changes
+    Subscribe {
+        /// An id chosen by the client, unique among the subscriptions active on the stream
+        id: String,
+        query: Box<QueryRequest>,
+    },
+    /// Stop the subscription with the given id
+    Complete { id: String },
+}
+
+/// A message sent from the agent to HGE over a subscription stream
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubscriptionServerMessage {
+    /// The current result of the subscription with the given id
+    Data { id: String, data: QueryResponse },
+    /// Sent periodically while no other messages are sent, so that idle streams are not closed
+    KeepAlive {},
+    /// The subscription with the given id has ended and no further data frames will be sent for it
+    Complete { id: String },
+    /// The subscription with the given id failed, or the stream itself failed if no id is present
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        error: ErrorResponse,
+    },
+}
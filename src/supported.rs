@@ -0,0 +1,748 @@
+use crate::{
+    capabilities::{Capabilities, QueryCapabilities, ScalarType, ScalarTypeCapabilities},
+    map::FieldMap,
+    mutation::{InsertFieldSchema, MutationOperation, MutationRequest, RowUpdate},
+    query::{
+        BinaryArrayComparisonOperator, ComparisonColumn, ComparisonValue, ExistsInTable,
+        Expression, Field, InterpolatedItem, InterpolatedQuery, OrderBy, OrderByRelation,
+        OrderByTarget, Query, QueryRequest, RequestOptions, Target,
+    },
+    validate::{ErrorCode, ValidationError},
+};
+
+impl QueryRequest {
+    /// Checks that the request only uses features the agent's capabilities declare, such as relationships, foreach, nested field paths, `between` and non built-in comparison operators. Returns every problem found.
+    ///
+    /// Unlike `validate`, this needs no schema, so agents can reject unsupported requests before doing any other work
+    pub fn check_capabilities(
+        &self,
+        capabilities: &Capabilities,
+    ) -> Result<(), Vec<ValidationError>> {
+        let mut support = Support {
+            capabilities,
+            errors: Vec::new(),
+        };
+        support.target(&self.target, "target");
+        support.interpolated_queries(self.interpolated_queries.as_ref());
+        if let Some(foreach) = &self.foreach {
+            support.foreach(foreach.len());
+        }
+        support.query(&self.query, "query");
+        support.request_options(self.request_options.as_ref());
+        support.finish()
+    }
+}
+
+impl MutationRequest {
+    /// Checks that the request only uses the mutation operations and features the agent's capabilities declare. Returns every problem found
+    pub fn check_capabilities(
+        &self,
+        capabilities: &Capabilities,
+    ) -> Result<(), Vec<ValidationError>> {
+        let mut support = Support {
+            capabilities,
+            errors: Vec::new(),
+        };
+        let mutations = capabilities.mutations.as_ref();
+        if let Some(requested) = &self.requested_atomicity {
+            let declared = mutations.and_then(|m| m.atomicity_support_level.as_ref());
+            if declared.is_none_or(|declared| requested > declared) {
+                support.error(
+                    "requested_atomicity",
+                    ErrorCode::UnsupportedFeature,
+                    "the requested atomicity exceeds the declared atomicity support level"
+                        .to_owned(),
+                );
+            }
+        }
+        let nested_inserts = mutations
+            .and_then(|m| m.insert.as_ref())
+            .is_some_and(|insert| insert.supports_nested_inserts == Some(true));
+        for (index, schema) in self.insert_schema.iter().enumerate() {
+            for (name, field) in &schema.fields {
+                let nested = matches!(
+                    field,
+                    InsertFieldSchema::ArrayRelation { .. }
+                        | InsertFieldSchema::ObjectRelation { .. }
+                );
+                if nested && !nested_inserts {
+                    support.error(
+                        &format!("insert_schema[{index}].fields.{name}"),
+                        ErrorCode::UnsupportedMutation,
+                        "nested inserts are not supported".to_owned(),
+                    );
+                }
+            }
+        }
+        for (index, operation) in self.operations.iter().enumerate() {
+            support.operation(operation, &format!("operations[{index}]"));
+        }
+        support.interpolated_queries(self.interpolated_queries.as_ref());
+        support.request_options(self.request_options.as_ref());
+        support.finish()
+    }
+}
+
+struct Support<'a> {
+    capabilities: &'a Capabilities,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Support<'a> {
+    fn finish(self) -> Result<(), Vec<ValidationError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn operation(&mut self, operation: &MutationOperation, path: &str) {
+        let mutations = self.capabilities.mutations.as_ref();
+        match operation {
+            MutationOperation::Insert {
+                post_insert_check,
+                returning_fields,
+                ..
+            } => {
+                if mutations.is_none_or(|m| m.insert.is_none()) {
+                    self.error(
+                        path,
+                        ErrorCode::UnsupportedMutation,
+                        "inserts are not supported".to_owned(),
+                    );
+                }
+                if let Some(check) = post_insert_check {
+                    self.expression(check, &format!("{path}.post_insert_check"));
+                }
+                self.returning_fields(returning_fields.as_ref(), path);
+            }
+            MutationOperation::Update {
+                post_update_check,
+                returning_fields,
+                updates,
+                r#where,
+                ..
+            } => {
+                let update = mutations.and_then(|m| m.update.as_ref());
+                if update.is_none() {
+                    self.error(
+                        path,
+                        ErrorCode::UnsupportedMutation,
+                        "updates are not supported".to_owned(),
+                    );
+                }
+                if let Some(check) = post_update_check {
+                    let path = format!("{path}.post_update_check");
+                    if update.is_some_and(|update| update.supports_post_update_check != Some(true))
+                    {
+                        self.error(
+                            &path,
+                            ErrorCode::UnsupportedMutation,
+                            "post update checks are not supported".to_owned(),
+                        );
+                    }
+                    self.expression(check, &path);
+                }
+                let custom_operators =
+                    update.is_some_and(|update| update.supports_custom_operators == Some(true));
+                for (index, row_update) in updates.iter().enumerate() {
+                    if matches!(row_update, RowUpdate::CustomOperator { .. }) && !custom_operators {
+                        self.error(
+                            &format!("{path}.updates[{index}]"),
+                            ErrorCode::UnsupportedMutation,
+                            "custom update operators are not supported".to_owned(),
+                        );
+                    }
+                }
+                if let Some(expression) = r#where {
+                    self.expression(expression, &format!("{path}.where"));
+                }
+                self.returning_fields(returning_fields.as_ref(), path);
+            }
+            MutationOperation::Delete {
+                returning_fields,
+                r#where,
+                ..
+            } => {
+                let delete = mutations.and_then(|m| m.delete.as_ref());
+                if delete.is_none() {
+                    self.error(
+                        path,
+                        ErrorCode::UnsupportedMutation,
+                        "deletes are not supported".to_owned(),
+                    );
+                }
+                if returning_fields.is_some()
+                    && delete.is_some_and(|delete| delete.supports_returning != Some(true))
+                {
+                    self.error(
+                        &format!("{path}.returning_fields"),
+                        ErrorCode::UnsupportedMutation,
+                        "returning deleted rows is not supported".to_owned(),
+                    );
+                }
+                if let Some(expression) = r#where {
+                    self.expression(expression, &format!("{path}.where"));
+                }
+                self.returning_fields(returning_fields.as_ref(), path);
+            }
+            MutationOperation::Execute {
+                target,
+                returning_fields,
+            } => {
+                let path = format!("{path}.target");
+                let write_functions = self
+                    .capabilities
+                    .user_defined_functions
+                    .as_ref()
+                    .is_some_and(|functions| functions.supports_write_functions == Some(true));
+                if matches!(target, Target::Function { .. }) && !write_functions {
+                    self.error(
+                        &path,
+                        ErrorCode::UnsupportedFunction,
+                        "write functions are not supported".to_owned(),
+                    );
+                } else {
+                    self.target(target, &path);
+                }
+                self.returning_fields(returning_fields.as_ref(), &path);
+            }
+        }
+    }
+
+    fn returning_fields(&mut self, fields: Option<&FieldMap<String, Field>>, path: &str) {
+        let relationships = self
+            .capabilities
+            .mutations
+            .as_ref()
+            .and_then(|m| m.returning.as_ref())
+            .is_some_and(|returning| returning.supports_relationships == Some(true));
+        for (name, field) in fields.into_iter().flatten() {
+            let path = format!("{path}.returning_fields.{name}");
+            if matches!(field, Field::Relationship { .. }) && !relationships {
+                self.error(
+                    &path,
+                    ErrorCode::UnsupportedMutation,
+                    "returning relationship fields is not supported".to_owned(),
+                );
+            }
+            self.field(field, &path);
+        }
+    }
+
+    fn target(&mut self, target: &Target, path: &str) {
+        match target {
+            Target::Table { .. } => {}
+            Target::Function { .. } => {
+                if self.capabilities.user_defined_functions.is_none() {
+                    self.error(
+                        path,
+                        ErrorCode::UnsupportedFunction,
+                        "user defined functions are not supported".to_owned(),
+                    );
+                }
+            }
+            Target::Interpolated { .. } => {
+                if self.capabilities.interpolated_queries.is_none() {
+                    self.error(
+                        path,
+                        ErrorCode::UnsupportedFeature,
+                        "interpolated queries are not supported".to_owned(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn interpolated_queries(&mut self, queries: Option<&FieldMap<String, InterpolatedQuery>>) {
+        let Some(queries) = queries.filter(|queries| !queries.is_empty()) else {
+            return;
+        };
+        let Some(interpolated) = &self.capabilities.interpolated_queries else {
+            self.error(
+                "interpolated_queries",
+                ErrorCode::UnsupportedFeature,
+                "interpolated queries are not supported".to_owned(),
+            );
+            return;
+        };
+        let Some(supported) = &interpolated.supported_scalar_types else {
+            return;
+        };
+        for (id, query) in queries {
+            for (index, item) in query.items.iter().enumerate() {
+                if let InterpolatedItem::Scalar { value_type, .. } = item {
+                    if !supported.contains(value_type) {
+                        self.error(
+                            &format!("interpolated_queries.{id}.items[{index}]"),
+                            ErrorCode::UnsupportedFeature,
+                            format!(
+                                "scalar type {value_type} is not supported in interpolated queries"
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn foreach(&mut self, rows: usize) {
+        let foreach = self
+            .capabilities
+            .queries
+            .as_ref()
+            .and_then(|queries| queries.foreach.as_ref());
+        match foreach {
+            None => self.error(
+                "foreach",
+                ErrorCode::UnsupportedFeature,
+                "foreach queries are not supported".to_owned(),
+            ),
+            Some(foreach) => {
+                let max = foreach.max_batch_size;
+                if let Some(max) = max.filter(|&max| rows as u64 > max) {
+                    self.error(
+                        "foreach",
+                        ErrorCode::UnsupportedFeature,
+                        format!("{rows} foreach rows exceed the maximum batch size of {max}"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn request_options(&mut self, options: Option<&RequestOptions>) {
+        let Some(options) = options else {
+            return;
+        };
+        let supported = self.capabilities.request_options.as_ref();
+        if options.timeout_ms.is_some()
+            && supported.is_none_or(|s| s.supports_timeout != Some(true))
+        {
+            self.error(
+                "request_options.timeout_ms",
+                ErrorCode::UnsupportedFeature,
+                "request timeouts are not supported".to_owned(),
+            );
+        }
+        if options.max_rows.is_some() && supported.is_none_or(|s| s.supports_max_rows != Some(true))
+        {
+            self.error(
+                "request_options.max_rows",
+                ErrorCode::UnsupportedFeature,
+                "row limits are not supported".to_owned(),
+            );
+        }
+    }
+
+    fn query(&mut self, query: &Query, path: &str) {
+        for (name, field) in query.fields.iter().flatten() {
+            self.field(field, &format!("{path}.fields.{name}"));
+        }
+        if let Some(expression) = &query.r#where {
+            self.expression(expression, &format!("{path}.where"));
+        }
+        if let Some(order_by) = &query.order_by {
+            self.order_by(order_by, &format!("{path}.order_by"));
+        }
+        if query.distinct_on.is_some() && !self.query_flag(|queries| queries.supports_distinct_on) {
+            self.error(
+                &format!("{path}.distinct_on"),
+                ErrorCode::UnsupportedFeature,
+                "distinct on is not supported".to_owned(),
+            );
+        }
+    }
+
+    fn field(&mut self, field: &Field, path: &str) {
+        match field {
+            Field::Column { field_path, .. } => self.field_path(field_path.as_deref(), path),
+            Field::Object { query, .. } => self.query(query, &format!("{path}.query")),
+            Field::Array {
+                field,
+                r#where,
+                order_by,
+                ..
+            } => {
+                self.field(field, path);
+                if let Some(expression) = r#where {
+                    self.expression(expression, &format!("{path}.where"));
+                }
+                if let Some(order_by) = order_by {
+                    self.order_by(order_by, &format!("{path}.order_by"));
+                }
+            }
+            Field::Relationship { query, .. } => {
+                self.relationships(path);
+                self.query(query, &format!("{path}.query"));
+            }
+        }
+    }
+
+    fn order_by(&mut self, order_by: &OrderBy, path: &str) {
+        for (index, element) in order_by.elements.iter().enumerate() {
+            let path = format!("{path}.elements[{index}]");
+            if !element.target_path.is_empty() {
+                self.relationships(&path);
+            }
+            if element.nulls.is_some()
+                && !self.query_flag(|queries| queries.supports_nulls_ordering)
+            {
+                self.error(
+                    &path,
+                    ErrorCode::UnsupportedFeature,
+                    "nulls ordering is not supported".to_owned(),
+                );
+            }
+            if let OrderByTarget::Column { field_path, .. } = &element.target {
+                self.field_path(field_path.as_deref(), &path);
+            }
+        }
+        self.order_by_relations(&order_by.relations, &format!("{path}.relations"));
+    }
+
+    fn order_by_relations(&mut self, relations: &FieldMap<String, OrderByRelation>, path: &str) {
+        for (name, relation) in relations {
+            let path = format!("{path}.{name}");
+            if let Some(expression) = &relation.r#where {
+                self.expression(expression, &format!("{path}.where"));
+            }
+            self.order_by_relations(&relation.subrelations, &format!("{path}.subrelations"));
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression, path: &str) {
+        match expression {
+            Expression::And { expressions } | Expression::Or { expressions } => {
+                for (index, expression) in expressions.iter().enumerate() {
+                    self.expression(expression, &format!("{path}.expressions[{index}]"));
+                }
+            }
+            Expression::Not { expression } => {
+                self.expression(expression, &format!("{path}.expression"))
+            }
+            Expression::ApplyUnaryComparison { column, .. } => self.comparison_column(column, path),
+            Expression::ApplyBinaryComparison {
+                column,
+                operator,
+                value,
+                collation,
+            } => {
+                self.comparison_column(column, path);
+                self.comparison_value(value, path);
+                let scalar_type = self.scalar_type(&column.column_type);
+                if let Some(name) = operator.declared_name() {
+                    let declared = scalar_type
+                        .and_then(|scalar_type| scalar_type.comparison_operators.as_ref())
+                        .is_some_and(|operators| operators.contains_key(name));
+                    if !declared {
+                        self.error(
+                            path,
+                            ErrorCode::UnsupportedFeature,
+                            format!(
+                                "operator {name} is not declared for scalar type {}",
+                                column.column_type
+                            ),
+                        );
+                    }
+                }
+                if let Some(collation) = collation {
+                    let declared = scalar_type
+                        .and_then(|scalar_type| scalar_type.collations.as_ref())
+                        .is_some_and(|collations| collations.contains(collation));
+                    if !declared {
+                        self.error(
+                            path,
+                            ErrorCode::UnsupportedFeature,
+                            format!(
+                                "collation {collation} is not declared for scalar type {}",
+                                column.column_type
+                            ),
+                        );
+                    }
+                }
+            }
+            Expression::ApplyBinaryArrayComparison {
+                column, operator, ..
+            } => {
+                self.comparison_column(column, path);
+                if *operator != BinaryArrayComparisonOperator::In {
+                    let declared = self
+                        .scalar_type(&column.column_type)
+                        .and_then(|scalar_type| scalar_type.array_comparison_operators.as_ref())
+                        .is_some_and(|operators| operators.contains(operator));
+                    if !declared {
+                        self.error(
+                            path,
+                            ErrorCode::UnsupportedFeature,
+                            format!(
+                                "array operator {operator} is not declared for scalar type {}",
+                                column.column_type
+                            ),
+                        );
+                    }
+                }
+            }
+            Expression::ApplyBetween {
+                column, low, high, ..
+            } => {
+                let supported = self
+                    .capabilities
+                    .comparisons
+                    .as_ref()
+                    .is_some_and(|comparisons| comparisons.supports_between == Some(true));
+                if !supported {
+                    self.error(
+                        path,
+                        ErrorCode::UnsupportedFeature,
+                        "between expressions are not supported".to_owned(),
+                    );
+                }
+                self.comparison_column(column, path);
+                self.comparison_value(low, path);
+                self.comparison_value(high, path);
+            }
+            Expression::Exists { in_table, r#where } => {
+                let in_table_path = format!("{path}.in_table");
+                let subquery = self
+                    .capabilities
+                    .comparisons
+                    .as_ref()
+                    .and_then(|comparisons| comparisons.subquery.as_ref());
+                match in_table {
+                    ExistsInTable::Related { .. } => {
+                        if subquery.is_none_or(|subquery| subquery.supports_relations != Some(true))
+                        {
+                            self.error(
+                                &in_table_path,
+                                ErrorCode::UnsupportedFeature,
+                                "exists comparisons over relationships are not supported"
+                                    .to_owned(),
+                            );
+                        }
+                        self.relationships(&in_table_path);
+                    }
+                    ExistsInTable::Unrelated { target } => {
+                        if subquery.is_none() {
+                            self.error(
+                                &in_table_path,
+                                ErrorCode::UnsupportedFeature,
+                                "exists comparisons are not supported".to_owned(),
+                            );
+                        }
+                        self.target(target, &in_table_path);
+                    }
+                }
+                self.expression(r#where, &format!("{path}.where"));
+            }
+        }
+    }
+
+    fn comparison_column(&mut self, column: &ComparisonColumn, path: &str) {
+        self.field_path(column.field_path.as_deref(), path);
+    }
+
+    fn comparison_value(&mut self, value: &ComparisonValue, path: &str) {
+        match value {
+            ComparisonValue::Column { column } => self.comparison_column(column, path),
+            ComparisonValue::Scalar { .. } => {}
+            ComparisonValue::Geometry { .. } => {
+                let supported = self
+                    .capabilities
+                    .comparisons
+                    .as_ref()
+                    .is_some_and(|comparisons| comparisons.supports_geometry_values == Some(true));
+                if !supported {
+                    self.error(
+                        path,
+                        ErrorCode::UnsupportedFeature,
+                        "geometry values are not supported".to_owned(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn field_path(&mut self, field_path: Option<&[String]>, path: &str) {
+        let nested = field_path.is_some_and(|field_path| !field_path.is_empty());
+        if nested && !self.query_flag(|queries| queries.supports_nested_field_paths) {
+            self.error(
+                path,
+                ErrorCode::UnsupportedFeature,
+                "nested field paths are not supported".to_owned(),
+            );
+        }
+    }
+
+    fn relationships(&mut self, path: &str) {
+        if self.capabilities.relationships.is_none() {
+            self.error(
+                path,
+                ErrorCode::UnsupportedFeature,
+                "relationships are not supported".to_owned(),
+            );
+        }
+    }
+
+    fn query_flag(&self, flag: impl Fn(&QueryCapabilities) -> Option<bool>) -> bool {
+        self.capabilities
+            .queries
+            .as_ref()
+            .is_some_and(|queries| flag(queries) == Some(true))
+    }
+
+    fn scalar_type(&self, scalar_type: &ScalarType) -> Option<&'a ScalarTypeCapabilities> {
+        self.capabilities.scalar_types.as_ref()?.get(scalar_type)
+    }
+
+    fn error(&mut self, path: &str, code: ErrorCode, message: String) {
+        self.errors.push(ValidationError {
+            path: path.to_owned(),
+            code,
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::capabilities::CapabilitiesResponse;
+
+    fn sqlite() -> Capabilities {
+        let response: CapabilitiesResponse = serde_json::from_str(include_str!(
+            "../fixtures/capabilities_responses/sqlite.json"
+        ))
+        .unwrap();
+        response.capabilities
+    }
+
+    fn none() -> Capabilities {
+        serde_json::from_value(json!({})).unwrap()
+    }
+
+    fn query_request(json: &str) -> QueryRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn mutation_request(json: &str) -> MutationRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn paths(errors: Vec<ValidationError>) -> Vec<(String, ErrorCode)> {
+        errors
+            .into_iter()
+            .map(|error| (error.path, error.code))
+            .collect()
+    }
+
+    #[test]
+    fn declared_features_are_accepted() {
+        for json in [
+            include_str!("../fixtures/query_requests/array_relationship.json"),
+            include_str!("../fixtures/query_requests/exists_filter.json"),
+            include_str!("../fixtures/query_requests/foreach.json"),
+        ] {
+            assert_eq!(query_request(json).check_capabilities(&sqlite()), Ok(()));
+        }
+        let insert = include_str!("../fixtures/mutation_requests/insert_with_returning.json");
+        assert_eq!(
+            mutation_request(insert).check_capabilities(&sqlite()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn relationships_and_operators_must_be_declared() {
+        let request = query_request(include_str!(
+            "../fixtures/query_requests/array_relationship.json"
+        ));
+        assert_eq!(
+            paths(request.check_capabilities(&none()).unwrap_err()),
+            [
+                (
+                    "query.fields.Albums".to_owned(),
+                    ErrorCode::UnsupportedFeature
+                ),
+                (
+                    "query.fields.Albums.query.where".to_owned(),
+                    ErrorCode::UnsupportedFeature
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn foreach_must_be_declared_and_fit_the_batch_size() {
+        let request = query_request(include_str!("../fixtures/query_requests/foreach.json"));
+        assert_eq!(
+            paths(request.check_capabilities(&none()).unwrap_err()),
+            [("foreach".to_owned(), ErrorCode::UnsupportedFeature)]
+        );
+        let mut capabilities = sqlite();
+        capabilities.queries =
+            serde_json::from_value(json!({ "foreach": { "max_batch_size": 1 } })).unwrap();
+        let errors = request.check_capabilities(&capabilities).unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "2 foreach rows exceed the maximum batch size of 1"
+        );
+    }
+
+    #[test]
+    fn between_and_nested_field_paths_must_be_declared() {
+        let mut request = query_request(include_str!(
+            "../fixtures/query_requests/simple_fields.json"
+        ));
+        request.query.r#where = Some(
+            serde_json::from_value(json!({
+                "type": "between",
+                "column": { "path": [], "name": "address", "field_path": ["city"], "column_type": "string" },
+                "low": { "type": "scalar", "value": "A", "value_type": "string" },
+                "high": { "type": "scalar", "value": "B", "value_type": "string" },
+            }))
+            .unwrap(),
+        );
+        assert_eq!(
+            paths(request.check_capabilities(&none()).unwrap_err()),
+            [
+                ("query.where".to_owned(), ErrorCode::UnsupportedFeature),
+                ("query.where".to_owned(), ErrorCode::UnsupportedFeature),
+            ]
+        );
+    }
+
+    #[test]
+    fn mutation_operations_must_be_declared() {
+        let request = mutation_request(include_str!(
+            "../fixtures/mutation_requests/update_and_delete.json"
+        ));
+        assert_eq!(
+            paths(request.check_capabilities(&none()).unwrap_err()),
+            [
+                ("operations[0]".to_owned(), ErrorCode::UnsupportedMutation),
+                (
+                    "operations[0].updates[1]".to_owned(),
+                    ErrorCode::UnsupportedMutation
+                ),
+                ("operations[1]".to_owned(), ErrorCode::UnsupportedMutation),
+            ]
+        );
+        assert_eq!(
+            paths(request.check_capabilities(&sqlite()).unwrap_err()),
+            [
+                (
+                    "operations[0].post_update_check".to_owned(),
+                    ErrorCode::UnsupportedMutation
+                ),
+                (
+                    "operations[0].updates[1]".to_owned(),
+                    ErrorCode::UnsupportedMutation
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use indexmap::IndexSet;
+
+use crate::{
+    mutation::{MutationOperation, MutationOperationStatus, MutationRequest, MutationResponse},
+    query::{QueryRequest, QueryResponse, ResponseRow, Target},
+};
+
+/// Whether the request targets a `table`, `function` or `interpolated` query
+pub const TARGET_TYPE_ATTRIBUTE: &str = "gdc.target.type";
+/// The fully qualified name of the target table, joined with `.`
+pub const TARGET_TABLE_ATTRIBUTE: &str = "gdc.target.table";
+/// The fully qualified name of the target function, joined with `.`
+pub const TARGET_FUNCTION_ATTRIBUTE: &str = "gdc.target.function";
+/// The id of the target interpolated query
+pub const TARGET_INTERPOLATED_QUERY_ATTRIBUTE: &str = "gdc.target.interpolated_query";
+/// The number of top-level fields requested
+pub const QUERY_FIELD_COUNT_ATTRIBUTE: &str = "gdc.query.field_count";
+/// The number of top-level aggregates requested
+pub const QUERY_AGGREGATE_COUNT_ATTRIBUTE: &str = "gdc.query.aggregate_count";
+/// The number of relationships defined by the request
+pub const RELATIONSHIP_COUNT_ATTRIBUTE: &str = "gdc.relationship_count";
+/// The number of foreach rows in a query request, or result sets in a foreach response
+pub const FOREACH_LEN_ATTRIBUTE: &str = "gdc.foreach.len";
+/// The number of rows returned, summed over foreach result sets
+pub const RESPONSE_ROW_COUNT_ATTRIBUTE: &str = "gdc.response.row_count";
+/// The number of mutation operations
+pub const MUTATION_OPERATION_COUNT_ATTRIBUTE: &str = "gdc.mutation.operation_count";
+/// The type of each mutation operation, in order, e.g. `["insert", "update"]`
+pub const MUTATION_OPERATION_TYPES_ATTRIBUTE: &str = "gdc.mutation.operation_types";
+/// The distinct tables written by the mutation operations
+pub const MUTATION_TABLES_ATTRIBUTE: &str = "gdc.mutation.tables";
+/// The number of rows affected, summed over all operations
+pub const MUTATION_AFFECTED_ROWS_ATTRIBUTE: &str = "gdc.mutation.affected_rows";
+/// The number of operations whose status is `failed`
+pub const MUTATION_FAILED_OPERATION_COUNT_ATTRIBUTE: &str = "gdc.mutation.failed_operation_count";
+
+/// The value of a span attribute
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    I64(i64),
+    String(String),
+    StringArray(Vec<String>),
+}
+
+/// Span attributes as key value pairs, with keys from the `*_ATTRIBUTE` constants
+pub type SpanAttributes = Vec<(&'static str, AttributeValue)>;
+
+impl QueryRequest {
+    /// Standard span attributes describing the request: its target, top-level field and aggregate counts, relationship count and foreach length
+    pub fn span_attributes(&self) -> SpanAttributes {
+        let mut attributes = target_attributes(&self.target);
+        let query = &self.query;
+        attributes.push((
+            QUERY_FIELD_COUNT_ATTRIBUTE,
+            count(query.fields.as_ref().map_or(0, |fields| fields.len())),
+        ));
+        attributes.push((
+            QUERY_AGGREGATE_COUNT_ATTRIBUTE,
+            count(
+                query
+                    .aggregates
+                    .as_ref()
+                    .map_or(0, |aggregates| aggregates.len()),
+            ),
+        ));
+        attributes.push((
+            RELATIONSHIP_COUNT_ATTRIBUTE,
+            count(
+                self.relationships
+                    .iter()
+                    .map(|r| r.relationships.len())
+                    .sum(),
+            ),
+        ));
+        if let Some(foreach) = &self.foreach {
+            attributes.push((FOREACH_LEN_ATTRIBUTE, count(foreach.len())));
+        }
+        attributes
+    }
+}
+
+impl QueryResponse {
+    /// Standard span attributes describing the response: the number of rows returned, and the number of result sets for foreach responses
+    pub fn span_attributes(&self) -> SpanAttributes {
+        let row_count = |row: &ResponseRow| row.rows.as_ref().map_or(0, |rows| rows.len());
+        match self {
+            QueryResponse::Single(row) => {
+                vec![(RESPONSE_ROW_COUNT_ATTRIBUTE, count(row_count(row)))]
+            }
+            QueryResponse::ForEach { rows } => vec![
+                (FOREACH_LEN_ATTRIBUTE, count(rows.len())),
+                (
+                    RESPONSE_ROW_COUNT_ATTRIBUTE,
+                    count(rows.iter().map(|row| row_count(&row.query)).sum()),
+                ),
+            ],
+        }
+    }
+}
+
+impl MutationRequest {
+    /// Standard span attributes describing the request: the number and types of its operations, the tables they write and its relationship count
+    pub fn span_attributes(&self) -> SpanAttributes {
+        let operation_types = self
+            .operations
+            .iter()
+            .map(|operation| {
+                match operation {
+                    MutationOperation::Insert { .. } => "insert",
+                    MutationOperation::Update { .. } => "update",
+                    MutationOperation::Delete { .. } => "delete",
+                    MutationOperation::Execute { .. } => "execute",
+                }
+                .to_owned()
+            })
+            .collect();
+        let tables: IndexSet<String> = self
+            .operations
+            .iter()
+            .filter_map(|operation| match operation {
+                MutationOperation::Insert { table, .. }
+                | MutationOperation::Update { table, .. }
+                | MutationOperation::Delete { table, .. } => Some(table.join(".")),
+                MutationOperation::Execute { .. } => None,
+            })
+            .collect();
+        vec![
+            (
+                MUTATION_OPERATION_COUNT_ATTRIBUTE,
+                count(self.operations.len()),
+            ),
+            (
+                MUTATION_OPERATION_TYPES_ATTRIBUTE,
+                AttributeValue::StringArray(operation_types),
+            ),
+            (
+                MUTATION_TABLES_ATTRIBUTE,
+                AttributeValue::StringArray(tables.into_iter().collect()),
+            ),
+            (
+                RELATIONSHIP_COUNT_ATTRIBUTE,
+                count(
+                    self.relationships
+                        .iter()
+                        .map(|r| r.relationships.len())
+                        .sum(),
+                ),
+            ),
+        ]
+    }
+}
+
+impl MutationResponse {
+    /// Standard span attributes describing the response: the number of operations, affected rows and failed operations
+    pub fn span_attributes(&self) -> SpanAttributes {
+        let results = &self.operation_results;
+        let affected_rows: u64 = results.iter().map(|result| result.affected_rows).sum();
+        let failed = results
+            .iter()
+            .filter(|result| result.status == Some(MutationOperationStatus::Failed))
+            .count();
+        vec![
+            (MUTATION_OPERATION_COUNT_ATTRIBUTE, count(results.len())),
+            (
+                MUTATION_AFFECTED_ROWS_ATTRIBUTE,
+                AttributeValue::I64(i64::try_from(affected_rows).unwrap_or(i64::MAX)),
+            ),
+            (MUTATION_FAILED_OPERATION_COUNT_ATTRIBUTE, count(failed)),
+        ]
+    }
+}
+
+#[cfg(feature = "opentelemetry")]
+impl From<AttributeValue> for opentelemetry::Value {
+    fn from(value: AttributeValue) -> Self {
+        match value {
+            AttributeValue::I64(value) => value.into(),
+            AttributeValue::String(value) => value.into(),
+            AttributeValue::StringArray(values) => opentelemetry::Value::Array(
+                values
+                    .into_iter()
+                    .map(opentelemetry::StringValue::from)
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+        }
+    }
+}
+
+/// Converts span attributes to OpenTelemetry key values, i.e. for `Span::set_attributes`
+#[cfg(feature = "opentelemetry")]
+pub fn to_key_values(attributes: SpanAttributes) -> Vec<opentelemetry::KeyValue> {
+    attributes
+        .into_iter()
+        .map(|(key, value)| opentelemetry::KeyValue::new(key, value))
+        .collect()
+}
+
+fn target_attributes(target: &Target) -> SpanAttributes {
+    let (target_type, key, name) = match target {
+        Target::Table { name } => ("table", TARGET_TABLE_ATTRIBUTE, name.join(".")),
+        Target::Function { name, .. } => ("function", TARGET_FUNCTION_ATTRIBUTE, name.join(".")),
+        Target::Interpolated { id } => (
+            "interpolated",
+            TARGET_INTERPOLATED_QUERY_ATTRIBUTE,
+            id.clone(),
+        ),
+    };
+    vec![
+        (
+            TARGET_TYPE_ATTRIBUTE,
+            AttributeValue::String(target_type.to_owned()),
+        ),
+        (key, AttributeValue::String(name)),
+    ]
+}
+
+fn count(count: usize) -> AttributeValue {
+    AttributeValue::I64(i64::try_from(count).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn string(value: &str) -> AttributeValue {
+        AttributeValue::String(value.to_owned())
+    }
+
+    fn strings(values: &[&str]) -> AttributeValue {
+        AttributeValue::StringArray(values.iter().map(|value| value.to_string()).collect())
+    }
+
+    #[test]
+    fn describes_query_requests() {
+        let request: QueryRequest =
+            serde_json::from_str(include_str!("../fixtures/query_requests/foreach.json")).unwrap();
+        assert_eq!(
+            request.span_attributes(),
+            vec![
+                (TARGET_TYPE_ATTRIBUTE, string("table")),
+                (TARGET_TABLE_ATTRIBUTE, string("Album")),
+                (QUERY_FIELD_COUNT_ATTRIBUTE, AttributeValue::I64(2)),
+                (QUERY_AGGREGATE_COUNT_ATTRIBUTE, AttributeValue::I64(0)),
+                (RELATIONSHIP_COUNT_ATTRIBUTE, AttributeValue::I64(0)),
+                (FOREACH_LEN_ATTRIBUTE, AttributeValue::I64(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn describes_query_responses() {
+        let single: QueryResponse =
+            serde_json::from_value(json!({"rows": [{"id": 1}, {"id": 2}]})).unwrap();
+        assert_eq!(
+            single.span_attributes(),
+            vec![(RESPONSE_ROW_COUNT_ATTRIBUTE, AttributeValue::I64(2))]
+        );
+        let foreach: QueryResponse = serde_json::from_value(json!({
+            "rows": [
+                {"query": {"rows": [{"id": 1}]}},
+                {"query": {"rows": [{"id": 2}, {"id": 3}]}},
+            ],
+        }))
+        .unwrap();
+        assert_eq!(
+            foreach.span_attributes(),
+            vec![
+                (FOREACH_LEN_ATTRIBUTE, AttributeValue::I64(2)),
+                (RESPONSE_ROW_COUNT_ATTRIBUTE, AttributeValue::I64(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn describes_mutations() {
+        let request: MutationRequest = serde_json::from_str(include_str!(
+            "../fixtures/mutation_requests/update_and_delete.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            request.span_attributes(),
+            vec![
+                (MUTATION_OPERATION_COUNT_ATTRIBUTE, AttributeValue::I64(2)),
+                (
+                    MUTATION_OPERATION_TYPES_ATTRIBUTE,
+                    strings(&["update", "delete"])
+                ),
+                (
+                    MUTATION_TABLES_ATTRIBUTE,
+                    strings(&["Track", "InvoiceLine"])
+                ),
+                (RELATIONSHIP_COUNT_ATTRIBUTE, AttributeValue::I64(0)),
+            ]
+        );
+
+        let response: MutationResponse = serde_json::from_str(include_str!(
+            "../fixtures/mutation_responses/update_and_delete.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            response.span_attributes(),
+            vec![
+                (MUTATION_OPERATION_COUNT_ATTRIBUTE, AttributeValue::I64(2)),
+                (MUTATION_AFFECTED_ROWS_ATTRIBUTE, AttributeValue::I64(8)),
+                (
+                    MUTATION_FAILED_OPERATION_COUNT_ATTRIBUTE,
+                    AttributeValue::I64(0)
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use std::{any::type_name, fmt, fmt::Debug, fs, io, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// Asserts that a value serializes to JSON and deserializes back to an equal value, and that the value read back serializes to the same JSON text.
+///
+/// Panics with the differences between the two JSON documents if not
+pub fn assert_roundtrip<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let json = to_json(value);
+    let text = json.to_string();
+    let parsed: T = serde_json::from_str(&text).unwrap_or_else(|error| {
+        panic!(
+            "{} does not deserialize from its own serialization: {error}\njson: {text}",
+            type_name::<T>()
+        )
+    });
+    let reserialized = to_json(&parsed);
+    if &parsed != value {
+        panic!(
+            "round trip of {} changed the value\n{}\nbefore: {value:?}\nafter: {parsed:?}",
+            type_name::<T>(),
+            describe_differences(&json, &reserialized),
+        );
+    }
+    let reserialized_text = reserialized.to_string();
+    if reserialized_text != text {
+        panic!(
+            "round trip of {} changed the serialized JSON\n{}",
+            type_name::<T>(),
+            describe_differences(&json, &reserialized),
+        );
+    }
+}
+
+/// Asserts that a JSON document deserializes to the expected value.
+///
+/// Panics with the deserialization error, or with the differences between the expected and parsed values serialized as JSON
+pub fn assert_parses_to<T>(json: &str, expected: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let parsed: T = serde_json::from_str(json).unwrap_or_else(|error| {
+        panic!(
+            "json does not deserialize as {}: {error}\njson: {json}",
+            type_name::<T>()
+        )
+    });
+    if &parsed != expected {
+        panic!(
+            "json did not deserialize to the expected {}\n{}\nexpected: {expected:?}\nparsed: {parsed:?}",
+            type_name::<T>(),
+            describe_differences(&to_json(expected), &to_json(&parsed)),
+        );
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value)
+        .unwrap_or_else(|error| panic!("{} does not serialize: {error}", type_name::<T>()))
+}
+
+/// How `check_wire_compat` compares a document with its re-serialization
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WireCompatOptions {
+    /// Treat object fields set to `null` as absent. The TypeScript types send `null` for many optional fields this crate omits
+    pub null_is_absent: bool,
+    /// Compare numbers by value, so that `1` and `1.0` are equal
+    pub numbers_by_value: bool,
+    /// Report objects whose keys are the same but in a different order
+    pub report_key_order: bool,
+}
+
+impl Default for WireCompatOptions {
+    fn default() -> Self {
+        Self {
+            null_is_absent: true,
+            numbers_by_value: true,
+            report_key_order: false,
+        }
+    }
+}
+
+const STRICT: WireCompatOptions = WireCompatOptions {
+    null_is_absent: false,
+    numbers_by_value: false,
+    report_key_order: true,
+};
+
+/// A difference between two JSON documents, at a JSONPath-like location such as `$.query.fields.name`
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonDifference {
+    pub path: String,
+    pub kind: JsonDifferenceKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonDifferenceKind {
+    /// An object field present in the expected document is absent
+    Missing {
+        expected: Value,
+    },
+    /// An object field not present in the expected document was added
+    Unexpected {
+        actual: Value,
+    },
+    Changed {
+        expected: Value,
+        actual: Value,
+    },
+    /// The arrays have different lengths. Elements present in both are compared individually
+    Length {
+        expected: usize,
+        actual: usize,
+    },
+    /// The object has the same keys in a different order
+    KeyOrder {
+        expected: Vec<String>,
+        actual: Vec<String>,
+    },
+}
+
+impl fmt::Display for JsonDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = &self.path;
+        match &self.kind {
+            JsonDifferenceKind::Missing { expected } => {
+                write!(f, "{path}: missing, expected {expected}")
+            }
+            JsonDifferenceKind::Unexpected { actual } => write!(f, "{path}: unexpected {actual}"),
+            JsonDifferenceKind::Changed { expected, actual } => {
+                write!(f, "{path}: expected {expected}, got {actual}")
+            }
+            JsonDifferenceKind::Length { expected, actual } => {
+                write!(f, "{path}: expected {expected} elements, got {actual}")
+            }
+            JsonDifferenceKind::KeyOrder { expected, actual } => write!(
+                f,
+                "{path}: same keys in a different order, expected {expected:?}, got {actual:?}. Maps compare equal regardless of order"
+            ),
+        }
+    }
+}
+
+/// The result of reading a document produced by another implementation of the protocol, such as the TypeScript `dc-api-types`, and writing it back out
+#[derive(Clone, Debug, PartialEq)]
+pub struct WireCompatReport {
+    /// Identifies the document, i.e. its file name
+    pub name: String,
+    /// The type the document was read as
+    pub type_name: &'static str,
+    /// Why the document could not be read, if it could not
+    pub error: Option<String>,
+    /// How the re-serialized document differs from the original
+    pub differences: Vec<JsonDifference>,
+}
+
+impl WireCompatReport {
+    /// Whether the document was read and written back without differences
+    pub fn is_compatible(&self) -> bool {
+        self.error.is_none() && self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for WireCompatReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            Some(error) => write!(
+                f,
+                "{}: does not deserialize as {}: {error}",
+                self.name, self.type_name
+            ),
+            None if self.differences.is_empty() => {
+                write!(f, "{}: compatible with {}", self.name, self.type_name)
+            }
+            None => {
+                write!(
+                    f,
+                    "{}: changed by a round trip through {}",
+                    self.name, self.type_name
+                )?;
+                for difference in &self.differences {
+                    write!(f, "\n  {difference}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Deserializes a document emitted by another implementation as `T`, serializes it again and compares the result with the original
+pub fn check_wire_compat<T>(
+    name: impl Into<String>,
+    json: &str,
+    options: WireCompatOptions,
+) -> WireCompatReport
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut report = WireCompatReport {
+        name: name.into(),
+        type_name: type_name::<T>(),
+        error: None,
+        differences: Vec::new(),
+    };
+    let original: Value = match serde_json::from_str(json) {
+        Ok(original) => original,
+        Err(error) => {
+            report.error = Some(format!("not valid JSON: {error}"));
+            return report;
+        }
+    };
+    match serde_json::from_str::<T>(json).and_then(|value| serde_json::to_value(&value)) {
+        Ok(reserialized) => diff(
+            &original,
+            &reserialized,
+            "$",
+            options,
+            &mut report.differences,
+        ),
+        Err(error) => report.error = Some(error.to_string()),
+    }
+    report
+}
+
+/// Checks every `.json` file in a directory as a `T`, in file name order. Reports are named by file name
+pub fn check_wire_compat_dir<T>(
+    dir: impl AsRef<Path>,
+    options: WireCompatOptions,
+) -> io::Result<Vec<WireCompatReport>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let json = fs::read_to_string(&path)?;
+            let name = path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            Ok(check_wire_compat::<T>(name, &json, options))
+        })
+        .collect()
+}
+
+/// Asserts that a document emitted by another implementation survives a round trip through `T`, using the default `WireCompatOptions`.
+///
+/// Panics with the report if not
+pub fn assert_wire_compat<T>(json: &str)
+where
+    T: Serialize + DeserializeOwned,
+{
+    let report = check_wire_compat::<T>("document", json, WireCompatOptions::default());
+    if !report.is_compatible() {
+        panic!("{report}");
+    }
+}
+
+fn describe_differences(expected: &Value, actual: &Value) -> String {
+    let mut differences = Vec::new();
+    diff(expected, actual, "$", STRICT, &mut differences);
+    if differences.is_empty() {
+        // Values that serialize identically can still compare unequal, i.e. a float that is NaN
+        return "  the serialized JSON is identical".to_owned();
+    }
+    differences
+        .iter()
+        .map(|difference| format!("  {difference}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn diff(
+    expected: &Value,
+    actual: &Value,
+    path: &str,
+    options: WireCompatOptions,
+    differences: &mut Vec<JsonDifference>,
+) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            let present = |value: &Value| !(options.null_is_absent && value.is_null());
+            for (key, value) in expected.iter().filter(|(_, value)| present(value)) {
+                let path = format!("{path}.{key}");
+                match actual.get(key).filter(|value| present(value)) {
+                    Some(actual) => diff(value, actual, &path, options, differences),
+                    None => differences.push(JsonDifference {
+                        path,
+                        kind: JsonDifferenceKind::Missing {
+                            expected: value.clone(),
+                        },
+                    }),
+                }
+            }
+            for (key, value) in actual.iter().filter(|(_, value)| present(value)) {
+                if !expected.get(key).is_some_and(present) {
+                    differences.push(JsonDifference {
+                        path: format!("{path}.{key}"),
+                        kind: JsonDifferenceKind::Unexpected {
+                            actual: value.clone(),
+                        },
+                    });
+                }
+            }
+            let expected_keys: Vec<_> = expected.keys().cloned().collect();
+            let actual_keys: Vec<_> = actual.keys().cloned().collect();
+            if options.report_key_order
+                && expected.len() == actual.len()
+                && expected_keys != actual_keys
+                && expected.keys().all(|key| actual.contains_key(key))
+            {
+                // `IndexMap` equality ignores order, so this alone does not make values unequal, but it changes the JSON an agent sends
+                differences.push(JsonDifference {
+                    path: path.to_owned(),
+                    kind: JsonDifferenceKind::KeyOrder {
+                        expected: expected_keys,
+                        actual: actual_keys,
+                    },
+                });
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            if expected.len() != actual.len() {
+                differences.push(JsonDifference {
+                    path: path.to_owned(),
+                    kind: JsonDifferenceKind::Length {
+                        expected: expected.len(),
+                        actual: actual.len(),
+                    },
+                });
+            }
+            for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                diff(
+                    expected,
+                    actual,
+                    &format!("{path}[{index}]"),
+                    options,
+                    differences,
+                );
+            }
+        }
+        (Value::Number(expected), Value::Number(actual))
+            if options.numbers_by_value && expected.as_f64() == actual.as_f64() => {}
+        (expected, actual) if expected != actual => differences.push(JsonDifference {
+            path: path.to_owned(),
+            kind: JsonDifferenceKind::Changed {
+                expected: expected.clone(),
+                actual: actual.clone(),
+            },
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::query::{QueryRequest, ScalarValue};
+
+    #[test]
+    fn round_trips_and_parses() {
+        let request: QueryRequest =
+            serde_json::from_str(include_str!("../fixtures/query_requests/aggregates.json"))
+                .unwrap();
+        assert_roundtrip(&request);
+        assert_parses_to(
+            r#"{"value": 1, "value_type": "number"}"#,
+            &ScalarValue::number(1),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "$.value: expected 2, got 1")]
+    fn reports_parse_differences() {
+        assert_parses_to(
+            r#"{"value": 1, "value_type": "number"}"#,
+            &ScalarValue::number(2),
+        );
+    }
+
+    #[derive(Serialize, serde::Deserialize)]
+    struct Measurement {
+        value: f64,
+        unit: Option<String>,
+    }
+
+    #[test]
+    fn tolerates_nulls_and_number_formatting_by_default() {
+        let json = r#"{"value": 1, "unit": null}"#;
+        let report =
+            check_wire_compat::<Measurement>("measurement", json, WireCompatOptions::default());
+        assert!(report.is_compatible(), "{report}");
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "measurement: compatible with {}",
+                type_name::<Measurement>()
+            )
+        );
+
+        let report = check_wire_compat::<Measurement>("measurement", json, STRICT);
+        assert_eq!(
+            report.differences,
+            vec![JsonDifference {
+                path: "$.value".to_owned(),
+                kind: JsonDifferenceKind::Changed {
+                    expected: json!(1),
+                    actual: json!(1.0),
+                },
+            },]
+        );
+    }
+
+    #[test]
+    fn reports_dropped_fields_and_errors() {
+        let report = check_wire_compat::<ScalarValue>(
+            "scalar",
+            r#"{"value": 1, "value_type": "number", "extra": true}"#,
+            WireCompatOptions::default(),
+        );
+        assert_eq!(
+            report.differences,
+            vec![JsonDifference {
+                path: "$.extra".to_owned(),
+                kind: JsonDifferenceKind::Missing {
+                    expected: json!(true),
+                },
+            }]
+        );
+        assert!(report
+            .to_string()
+            .contains("\n  $.extra: missing, expected true"));
+
+        let report = check_wire_compat::<ScalarValue>("scalar", "{", WireCompatOptions::default());
+        assert!(report.error.unwrap().starts_with("not valid JSON"));
+        let report = check_wire_compat::<ScalarValue>("scalar", "{}", WireCompatOptions::default());
+        assert!(!report.is_compatible());
+    }
+
+    #[test]
+    fn checks_a_directory_in_file_name_order() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/query_requests");
+        let reports =
+            check_wire_compat_dir::<QueryRequest>(dir, WireCompatOptions::default()).unwrap();
+        let names: Vec<_> = reports.iter().map(|report| report.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "aggregates.json",
+                "array_relationship.json",
+                "exists_filter.json",
+                "foreach.json",
+                "simple_fields.json",
+            ]
+        );
+        for report in reports {
+            assert!(report.is_compatible(), "{report}");
+        }
+    }
+}
@@ -0,0 +1,1018 @@
+use std::{collections::HashMap, fmt};
+
+use indexmap::{IndexMap, IndexSet};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    capabilities::{ColumnName, FunctionName, TableName},
+    map::FieldMap,
+    query::{
+        Aggregate, ColumnSelector, ComparisonColumn, ComparisonValue, ExistsInTable, Expression,
+        Field, OrderBy, OrderByTarget, Query, QueryRequest, Relationship, ScalarValue, Target,
+    },
+    schema::{
+        ColumnInfo, ColumnType, ColumnTypeNonScalar, FunctionInfo, FunctionReturnType,
+        ObjectTypeDefinition, SchemaResponse, TableInfo,
+    },
+};
+
+/// A reference in a query request that does not match the agent's schema, or a part of a schema or request its capabilities do not allow
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    /// Where in the request the problem was found, e.g. `query.fields.albums.query.where`
+    pub path: String,
+    /// What kind of problem this is. Unlike the message, codes do not change between releases
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+/// The kind of problem a `ValidationError` reports. New codes may be added in minor releases
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// A table, or the table returned by a function, is not in the schema
+    UnknownTable,
+    /// A function target is not in the schema
+    UnknownFunction,
+    /// A table or object type has no column with the name used
+    UnknownColumn,
+    /// A relationship is not defined for the table it is used from
+    UnknownRelationship,
+    /// An interpolated query target does not match any of the request's interpolated queries
+    UnknownInterpolatedQuery,
+    /// A foreach value does not have the type of its column
+    TypeMismatch,
+    /// A schema uses a scalar type that the capabilities do not declare
+    UndeclaredScalarType,
+    /// A schema marks a table or column as mutable without the matching mutation capability
+    UnsupportedMutation,
+    /// A schema has primary or foreign keys without the matching data schema capability
+    UnsupportedConstraint,
+    /// A schema has functions the user defined function capabilities do not allow
+    UnsupportedFunction,
+    /// A request uses a feature the capabilities do not declare
+    UnsupportedFeature,
+}
+
+impl ErrorCode {
+    /// The stable name of the code, as it is serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnknownTable => "unknown-table",
+            ErrorCode::UnknownFunction => "unknown-function",
+            ErrorCode::UnknownColumn => "unknown-column",
+            ErrorCode::UnknownRelationship => "unknown-relationship",
+            ErrorCode::UnknownInterpolatedQuery => "unknown-interpolated-query",
+            ErrorCode::TypeMismatch => "type-mismatch",
+            ErrorCode::UndeclaredScalarType => "undeclared-scalar-type",
+            ErrorCode::UnsupportedMutation => "unsupported-mutation",
+            ErrorCode::UnsupportedConstraint => "unsupported-constraint",
+            ErrorCode::UnsupportedFunction => "unsupported-function",
+            ErrorCode::UnsupportedFeature => "unsupported-feature",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// The columns a request reads from each table, keyed by fully qualified table name
+pub type ReferencedColumns = IndexMap<TableName, IndexSet<ColumnName>>;
+
+impl QueryRequest {
+    /// Checks that the tables, functions, relationships and columns referred to by the request exist in the schema, and that foreach values have the types of their columns. Returns every problem found.
+    ///
+    /// Columns are only checked for tables and object types whose columns the schema lists
+    pub fn validate(&self, schema: &SchemaResponse) -> Result<(), Vec<ValidationError>> {
+        Validator::new(self, schema).run(Mode::Sequential)
+    }
+
+    /// The columns read from each table, including those used in filters, ordering, aggregates, foreach rows and relationship column mappings. Only tables targeted by name are included
+    pub fn referenced_columns(&self) -> ReferencedColumns {
+        Collector { request: self }.run(Mode::Sequential)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl QueryRequest {
+    /// Like `validate`, but checks top-level fields and foreach rows in parallel. Errors are returned in the same order
+    pub fn par_validate(&self, schema: &SchemaResponse) -> Result<(), Vec<ValidationError>> {
+        Validator::new(self, schema).run(Mode::Parallel)
+    }
+
+    /// Like `referenced_columns`, but analyzes top-level fields and foreach rows in parallel. Columns are returned in the same order
+    pub fn par_referenced_columns(&self) -> ReferencedColumns {
+        Collector { request: self }.run(Mode::Parallel)
+    }
+}
+
+type ForeachRow = FieldMap<ColumnName, ScalarValue>;
+
+/// Whether top-level fields and foreach rows are processed one at a time or spread across the rayon thread pool
+#[derive(Clone, Copy)]
+enum Mode {
+    Sequential,
+    #[cfg(feature = "rayon")]
+    Parallel,
+}
+
+impl Mode {
+    fn map_fields<'a, T, F>(self, fields: &'a FieldMap<String, Field>, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(&'a String, &'a Field) -> T + Sync + Send,
+    {
+        match self {
+            Self::Sequential => fields.iter().map(|(name, field)| f(name, field)).collect(),
+            #[cfg(feature = "rayon")]
+            Self::Parallel => fields
+                .par_iter()
+                .map(|(name, field)| f(name, field))
+                .collect(),
+        }
+    }
+
+    fn map_rows<'a, T, F>(self, rows: &'a [ForeachRow], f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(usize, &'a ForeachRow) -> T + Sync + Send,
+    {
+        match self {
+            Self::Sequential => rows
+                .iter()
+                .enumerate()
+                .map(|(index, row)| f(index, row))
+                .collect(),
+            #[cfg(feature = "rayon")]
+            Self::Parallel => rows
+                .par_iter()
+                .enumerate()
+                .map(|(index, row)| f(index, row))
+                .collect(),
+        }
+    }
+}
+
+/// The table or object type whose columns a part of the request refers to
+#[derive(Clone, Copy, Default)]
+struct Scope<'a> {
+    /// The table relationships are followed from
+    table: Option<&'a TableName>,
+    object_type: Option<&'a str>,
+    /// `None` if the columns are not known, in which case they are not checked
+    columns: Option<&'a [ColumnInfo]>,
+}
+
+impl Scope<'_> {
+    fn describe(&self) -> String {
+        match (self.object_type, self.table) {
+            (Some(object_type), _) => format!("object type {object_type}"),
+            (None, Some(table)) => format!("table {}", table.join(".")),
+            (None, None) => "the target".to_owned(),
+        }
+    }
+}
+
+struct Validator<'a> {
+    request: &'a QueryRequest,
+    tables: HashMap<&'a TableName, &'a TableInfo>,
+    functions: HashMap<&'a FunctionName, &'a FunctionInfo>,
+    object_types: HashMap<&'a str, &'a ObjectTypeDefinition>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(request: &'a QueryRequest, schema: &'a SchemaResponse) -> Self {
+        Self {
+            request,
+            tables: schema
+                .tables
+                .iter()
+                .map(|table| (&table.name, table))
+                .collect(),
+            functions: schema
+                .functions
+                .iter()
+                .flatten()
+                .map(|function| (&function.name, function))
+                .collect(),
+            object_types: schema
+                .object_types
+                .iter()
+                .flatten()
+                .map(|object_type| (object_type.name.as_str(), object_type))
+                .collect(),
+        }
+    }
+
+    fn run(&self, mode: Mode) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        let root = self.target_scope(&self.request.target, "target", &mut errors);
+        let query = &self.request.query;
+        if let Some(fields) = &query.fields {
+            let field_errors = mode.map_fields(fields, |name, field| {
+                let mut errors = Vec::new();
+                self.field(
+                    root,
+                    root,
+                    field,
+                    &format!("query.fields.{name}"),
+                    &mut errors,
+                );
+                errors
+            });
+            errors.extend(field_errors.into_iter().flatten());
+        }
+        self.query_without_fields(root, root, query, "query", &mut errors);
+        if let Some(foreach) = &self.request.foreach {
+            let row_errors = mode.map_rows(foreach, |index, row| {
+                let mut errors = Vec::new();
+                self.foreach_row(root, row, &format!("foreach[{index}]"), &mut errors);
+                errors
+            });
+            errors.extend(row_errors.into_iter().flatten());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn target_scope(
+        &self,
+        target: &'a Target,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) -> Scope<'a> {
+        match target {
+            Target::Table { name } => self.table_scope(name, path, errors),
+            Target::Function { name, .. } => match self.functions.get(name) {
+                Some(FunctionInfo {
+                    returns: Some(FunctionReturnType::Table { table }),
+                    ..
+                }) => self.table_scope(table, path, errors),
+                Some(_) => Scope::default(),
+                None => {
+                    error(
+                        errors,
+                        path,
+                        ErrorCode::UnknownFunction,
+                        format!("{target} does not exist"),
+                    );
+                    Scope::default()
+                }
+            },
+            Target::Interpolated { id } => {
+                let defined = self
+                    .request
+                    .interpolated_queries
+                    .as_ref()
+                    .is_some_and(|queries| queries.contains_key(id));
+                if !defined {
+                    error(
+                        errors,
+                        path,
+                        ErrorCode::UnknownInterpolatedQuery,
+                        format!("interpolated query {id} is not defined"),
+                    );
+                }
+                Scope::default()
+            }
+        }
+    }
+
+    fn table_scope(
+        &self,
+        name: &'a TableName,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) -> Scope<'a> {
+        let table = self.tables.get(name);
+        if table.is_none() {
+            error(
+                errors,
+                path,
+                ErrorCode::UnknownTable,
+                format!("table {} does not exist", name.join(".")),
+            );
+        }
+        Scope {
+            table: Some(name),
+            object_type: None,
+            columns: table.and_then(|table| table.columns.as_deref()),
+        }
+    }
+
+    fn column(
+        &self,
+        scope: Scope<'a>,
+        name: &str,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) -> Option<&'a ColumnInfo> {
+        let columns = scope.columns?;
+        let column = columns.iter().find(|column| column.name == name);
+        if column.is_none() {
+            error(
+                errors,
+                path,
+                ErrorCode::UnknownColumn,
+                format!("{} has no column {name}", scope.describe()),
+            );
+        }
+        column
+    }
+
+    fn relationship(
+        &self,
+        scope: Scope<'a>,
+        name: &str,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) -> Option<&'a Relationship> {
+        let table = scope.table?;
+        let relationship = self
+            .request
+            .relationships
+            .iter()
+            .filter(|relationships| &relationships.source_table == table)
+            .find_map(|relationships| relationships.relationships.get(name));
+        if relationship.is_none() {
+            error(
+                errors,
+                path,
+                ErrorCode::UnknownRelationship,
+                format!(
+                    "relationship {name} is not defined for table {}",
+                    table.join(".")
+                ),
+            );
+        }
+        relationship
+    }
+
+    fn query(
+        &self,
+        root: Scope<'a>,
+        scope: Scope<'a>,
+        query: &'a Query,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        for (name, field) in query.fields.iter().flatten() {
+            self.field(root, scope, field, &format!("{path}.fields.{name}"), errors);
+        }
+        self.query_without_fields(root, scope, query, path, errors);
+    }
+
+    fn query_without_fields(
+        &self,
+        root: Scope<'a>,
+        scope: Scope<'a>,
+        query: &'a Query,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        for (name, aggregate) in query.aggregates.iter().flatten() {
+            if let Aggregate::ColumnCount { column, .. } | Aggregate::SingleColumn { column, .. } =
+                aggregate
+            {
+                self.column(scope, column, &format!("{path}.aggregates.{name}"), errors);
+            }
+        }
+        if let Some(expression) = &query.r#where {
+            self.expression(root, scope, expression, &format!("{path}.where"), errors);
+        }
+        if let Some(order_by) = &query.order_by {
+            self.order_by(scope, order_by, &format!("{path}.order_by"), errors);
+        }
+        for selector in query.distinct_on.iter().flatten() {
+            self.column(
+                scope,
+                selector_column(selector),
+                &format!("{path}.distinct_on"),
+                errors,
+            );
+        }
+    }
+
+    fn field(
+        &self,
+        root: Scope<'a>,
+        scope: Scope<'a>,
+        field: &'a Field,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        match field {
+            Field::Column { column, .. } => {
+                self.column(scope, column, path, errors);
+            }
+            Field::Object { column, query } => {
+                let object_type = match self.column(scope, column, path, errors) {
+                    Some(ColumnInfo {
+                        r#type:
+                            ColumnType::ColumnTypeNonScalar(ColumnTypeNonScalar::Object { name }),
+                        ..
+                    }) => self.object_types.get(name.as_str()),
+                    _ => None,
+                };
+                let nested = Scope {
+                    table: None,
+                    object_type: object_type.map(|object_type| object_type.name.as_str()),
+                    columns: object_type.map(|object_type| object_type.columns.as_slice()),
+                };
+                self.query(root, nested, query, &format!("{path}.query"), errors);
+            }
+            Field::Array { field, .. } => self.field(root, scope, field, path, errors),
+            Field::Relationship {
+                query,
+                relationship,
+            } => {
+                let Some(relationship) = self.relationship(scope, relationship, path, errors)
+                else {
+                    return;
+                };
+                let target = self.target_scope(&relationship.target, path, errors);
+                self.query(root, target, query, &format!("{path}.query"), errors);
+            }
+        }
+    }
+
+    fn expression(
+        &self,
+        root: Scope<'a>,
+        scope: Scope<'a>,
+        expression: &'a Expression,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        match expression {
+            Expression::And { expressions } | Expression::Or { expressions } => {
+                for (index, expression) in expressions.iter().enumerate() {
+                    let path = format!("{path}.expressions[{index}]");
+                    self.expression(root, scope, expression, &path, errors);
+                }
+            }
+            Expression::Not { expression } => self.expression(
+                root,
+                scope,
+                expression,
+                &format!("{path}.expression"),
+                errors,
+            ),
+            Expression::ApplyUnaryComparison { column, .. }
+            | Expression::ApplyBinaryArrayComparison { column, .. } => {
+                self.comparison_column(root, scope, column, path, errors)
+            }
+            Expression::ApplyBinaryComparison { column, value, .. } => {
+                self.comparison_column(root, scope, column, path, errors);
+                self.comparison_value(root, scope, value, path, errors);
+            }
+            Expression::ApplyBetween {
+                column, low, high, ..
+            } => {
+                self.comparison_column(root, scope, column, path, errors);
+                self.comparison_value(root, scope, low, path, errors);
+                self.comparison_value(root, scope, high, path, errors);
+            }
+            Expression::Exists { in_table, r#where } => {
+                let path = format!("{path}.in_table");
+                let target = match in_table {
+                    ExistsInTable::Related { relationship } => {
+                        match self.relationship(scope, relationship, &path, errors) {
+                            Some(relationship) => {
+                                self.target_scope(&relationship.target, &path, errors)
+                            }
+                            None => return,
+                        }
+                    }
+                    ExistsInTable::Unrelated { target } => self.target_scope(target, &path, errors),
+                };
+                self.expression(root, target, r#where, &format!("{path}.where"), errors);
+            }
+        }
+    }
+
+    fn comparison_column(
+        &self,
+        root: Scope<'a>,
+        scope: Scope<'a>,
+        column: &ComparisonColumn,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let scope = match column.path.as_deref() {
+            None | Some([]) => scope,
+            Some([root_table]) if root_table == "$" => root,
+            Some(_) => return,
+        };
+        self.column(scope, selector_column(&column.name), path, errors);
+    }
+
+    fn comparison_value(
+        &self,
+        root: Scope<'a>,
+        scope: Scope<'a>,
+        value: &ComparisonValue,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        if let ComparisonValue::Column { column } = value {
+            self.comparison_column(root, scope, column, path, errors);
+        }
+    }
+
+    fn order_by(
+        &self,
+        scope: Scope<'a>,
+        order_by: &OrderBy,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        for (index, element) in order_by.elements.iter().enumerate() {
+            let path = format!("{path}.elements[{index}]");
+            let mut target = scope;
+            for relationship in &element.target_path {
+                let Some(relationship) = self.relationship(target, relationship, &path, errors)
+                else {
+                    break;
+                };
+                target = self.target_scope(&relationship.target, &path, errors);
+            }
+            let column = match &element.target {
+                OrderByTarget::Column { column, .. } => selector_column(column),
+                OrderByTarget::SingleColumnAggregate { column, .. }
+                | OrderByTarget::ColumnCountAggregate { column, .. } => column,
+                OrderByTarget::StarCountAggregate {} => continue,
+            };
+            self.column(target, column, &path, errors);
+        }
+    }
+
+    fn foreach_row(
+        &self,
+        root: Scope<'a>,
+        row: &ForeachRow,
+        path: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        for (name, value) in row {
+            let path = format!("{path}.{name}");
+            let Some(column) = self.column(root, name, &path, errors) else {
+                continue;
+            };
+            if let ColumnType::Scalar(scalar_type) = &column.r#type {
+                if scalar_type != &value.value_type {
+                    error(
+                        errors,
+                        &path,
+                        ErrorCode::TypeMismatch,
+                        format!(
+                            "value has type {}, but column {name} has type {scalar_type}",
+                            value.value_type
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn error(errors: &mut Vec<ValidationError>, path: &str, code: ErrorCode, message: String) {
+    errors.push(ValidationError {
+        path: path.to_owned(),
+        code,
+        message,
+    });
+}
+
+/// The column a selector starts from. Compound selectors name a path into nested object columns
+fn selector_column(selector: &ColumnSelector) -> &str {
+    match selector {
+        ColumnSelector::Name(name) => name,
+        ColumnSelector::Compound(names) => names.first().map_or("", String::as_str),
+    }
+}
+
+struct Collector<'a> {
+    request: &'a QueryRequest,
+}
+
+impl<'a> Collector<'a> {
+    fn run(&self, mode: Mode) -> ReferencedColumns {
+        let table = match &self.request.target {
+            Target::Table { name } => Some(name),
+            _ => None,
+        };
+        let query = &self.request.query;
+        let mut columns = ReferencedColumns::new();
+        if let Some(fields) = &query.fields {
+            let field_columns = mode.map_fields(fields, |_, field| {
+                let mut columns = ReferencedColumns::new();
+                self.field(table, table, field, &mut columns);
+                columns
+            });
+            field_columns
+                .into_iter()
+                .for_each(|partial| merge(&mut columns, partial));
+        }
+        self.query_without_fields(table, table, query, &mut columns);
+        if let (Some(table), Some(foreach)) = (table, &self.request.foreach) {
+            let row_columns = mode.map_rows(foreach, |_, row| row.keys().collect::<Vec<_>>());
+            let entry = columns.entry(table.clone()).or_default();
+            for name in row_columns.into_iter().flatten() {
+                if !entry.contains(name) {
+                    entry.insert(name.clone());
+                }
+            }
+        }
+        columns
+    }
+
+    fn query(
+        &self,
+        root: Option<&'a TableName>,
+        table: Option<&'a TableName>,
+        query: &'a Query,
+        columns: &mut ReferencedColumns,
+    ) {
+        for field in query.fields.iter().flat_map(|fields| fields.values()) {
+            self.field(root, table, field, columns);
+        }
+        self.query_without_fields(root, table, query, columns);
+    }
+
+    fn query_without_fields(
+        &self,
+        root: Option<&'a TableName>,
+        table: Option<&'a TableName>,
+        query: &'a Query,
+        columns: &mut ReferencedColumns,
+    ) {
+        for aggregate in query
+            .aggregates
+            .iter()
+            .flat_map(|aggregates| aggregates.values())
+        {
+            if let Aggregate::ColumnCount { column, .. } | Aggregate::SingleColumn { column, .. } =
+                aggregate
+            {
+                add(columns, table, column);
+            }
+        }
+        if let Some(expression) = &query.r#where {
+            self.expression(root, table, expression, columns);
+        }
+        if let Some(order_by) = &query.order_by {
+            for element in &order_by.elements {
+                let target = element
+                    .target_path
+                    .iter()
+                    .try_fold(table, |table, name| self.follow(table, name, columns));
+                let column = match &element.target {
+                    OrderByTarget::Column { column, .. } => selector_column(column),
+                    OrderByTarget::SingleColumnAggregate { column, .. }
+                    | OrderByTarget::ColumnCountAggregate { column, .. } => column,
+                    OrderByTarget::StarCountAggregate {} => continue,
+                };
+                add(columns, target.flatten(), column);
+            }
+        }
+        for selector in query.distinct_on.iter().flatten() {
+            add(columns, table, selector_column(selector));
+        }
+    }
+
+    fn field(
+        &self,
+        root: Option<&'a TableName>,
+        table: Option<&'a TableName>,
+        field: &'a Field,
+        columns: &mut ReferencedColumns,
+    ) {
+        match field {
+            Field::Column { column, .. } => add(columns, table, column),
+            // The columns of the nested query belong to an object type rather than a table
+            Field::Object { column, .. } => add(columns, table, column),
+            Field::Array { field, .. } => self.field(root, table, field, columns),
+            Field::Relationship {
+                query,
+                relationship,
+            } => {
+                if let Some(target) = self.follow(table, relationship, columns) {
+                    self.query(root, target, query, columns);
+                }
+            }
+        }
+    }
+
+    fn expression(
+        &self,
+        root: Option<&'a TableName>,
+        table: Option<&'a TableName>,
+        expression: &'a Expression,
+        columns: &mut ReferencedColumns,
+    ) {
+        match expression {
+            Expression::And { expressions } | Expression::Or { expressions } => {
+                for expression in expressions {
+                    self.expression(root, table, expression, columns);
+                }
+            }
+            Expression::Not { expression } => self.expression(root, table, expression, columns),
+            Expression::ApplyUnaryComparison { column, .. }
+            | Expression::ApplyBinaryArrayComparison { column, .. } => {
+                comparison_column(root, table, column, columns)
+            }
+            Expression::ApplyBinaryComparison { column, value, .. } => {
+                comparison_column(root, table, column, columns);
+                comparison_value(root, table, value, columns);
+            }
+            Expression::ApplyBetween {
+                column, low, high, ..
+            } => {
+                comparison_column(root, table, column, columns);
+                comparison_value(root, table, low, columns);
+                comparison_value(root, table, high, columns);
+            }
+            Expression::Exists { in_table, r#where } => {
+                let target = match in_table {
+                    ExistsInTable::Related { relationship } => {
+                        self.follow(table, relationship, columns).flatten()
+                    }
+                    ExistsInTable::Unrelated {
+                        target: Target::Table { name },
+                    } => Some(name),
+                    ExistsInTable::Unrelated { .. } => None,
+                };
+                self.expression(root, target, r#where, columns);
+            }
+        }
+    }
+
+    /// Records the columns a relationship joins on, returning its target table. `None` if the relationship is not defined
+    fn follow(
+        &self,
+        table: Option<&'a TableName>,
+        name: &str,
+        columns: &mut ReferencedColumns,
+    ) -> Option<Option<&'a TableName>> {
+        let table = table?;
+        let relationship = self
+            .request
+            .relationships
+            .iter()
+            .filter(|relationships| &relationships.source_table == table)
+            .find_map(|relationships| relationships.relationships.get(name))?;
+        let target = match &relationship.target {
+            Target::Table { name } => Some(name),
+            _ => None,
+        };
+        for (source, destination) in &relationship.column_mapping {
+            add(columns, Some(table), source);
+            add(columns, target, destination);
+        }
+        Some(target)
+    }
+}
+
+fn comparison_column(
+    root: Option<&TableName>,
+    table: Option<&TableName>,
+    column: &ComparisonColumn,
+    columns: &mut ReferencedColumns,
+) {
+    let table = match column.path.as_deref() {
+        None | Some([]) => table,
+        Some([root_table]) if root_table == "$" => root,
+        Some(_) => None,
+    };
+    add(columns, table, selector_column(&column.name));
+}
+
+fn comparison_value(
+    root: Option<&TableName>,
+    table: Option<&TableName>,
+    value: &ComparisonValue,
+    columns: &mut ReferencedColumns,
+) {
+    if let ComparisonValue::Column { column } = value {
+        comparison_column(root, table, column, columns);
+    }
+}
+
+fn add(columns: &mut ReferencedColumns, table: Option<&TableName>, column: &str) {
+    let Some(table) = table else {
+        return;
+    };
+    let table_columns = match columns.get_mut(table) {
+        Some(table_columns) => table_columns,
+        None => columns.entry(table.clone()).or_default(),
+    };
+    if !table_columns.contains(column) {
+        table_columns.insert(column.to_owned());
+    }
+}
+
+fn merge(columns: &mut ReferencedColumns, partial: ReferencedColumns) {
+    for (table, partial_columns) in partial {
+        let table_columns = columns.entry(table).or_default();
+        table_columns.extend(partial_columns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> SchemaResponse {
+        serde_json::from_str(include_str!(
+            "../fixtures/schema_responses/chinook_subset.json"
+        ))
+        .unwrap()
+    }
+
+    fn request(json: &str) -> QueryRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn codes(request: &QueryRequest) -> Vec<(String, ErrorCode)> {
+        request
+            .validate(&schema())
+            .unwrap_err()
+            .into_iter()
+            .map(|error| (error.path, error.code))
+            .collect()
+    }
+
+    fn with(request: &QueryRequest, change: serde_json::Value) -> QueryRequest {
+        let mut json = serde_json::to_value(request).unwrap();
+        json.as_object_mut()
+            .unwrap()
+            .extend(change.as_object().unwrap().clone());
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn array_relationship() -> QueryRequest {
+        request(include_str!(
+            "../fixtures/query_requests/array_relationship.json"
+        ))
+    }
+
+    #[test]
+    fn matching_requests_are_valid() {
+        for json in [
+            include_str!("../fixtures/query_requests/array_relationship.json"),
+            include_str!("../fixtures/query_requests/foreach.json"),
+        ] {
+            assert_eq!(request(json).validate(&schema()), Ok(()));
+        }
+    }
+
+    #[test]
+    fn unknown_tables_columns_and_relationships() {
+        let request = with(
+            &array_relationship(),
+            json!({
+                "query": {
+                    "fields": {
+                        "Nickname": { "type": "column", "column": "Nickname", "column_type": "string" },
+                        "Songs": { "type": "relationship", "relationship": "Songs", "query": {} },
+                        "Albums": {
+                            "type": "relationship",
+                            "relationship": "Albums",
+                            "query": {
+                                "where": {
+                                    "type": "unary_op",
+                                    "operator": "is_null",
+                                    "column": { "path": ["$"], "name": "Year", "column_type": "number" },
+                                },
+                            },
+                        },
+                    },
+                },
+            }),
+        );
+        assert_eq!(
+            codes(&request),
+            [
+                ("query.fields.Nickname".to_owned(), ErrorCode::UnknownColumn),
+                (
+                    "query.fields.Songs".to_owned(),
+                    ErrorCode::UnknownRelationship
+                ),
+                (
+                    "query.fields.Albums.query.where".to_owned(),
+                    ErrorCode::UnknownColumn
+                ),
+            ]
+        );
+
+        let request = with(
+            &array_relationship(),
+            json!({ "target": { "type": "table", "name": ["Band"] }, "query": {} }),
+        );
+        assert_eq!(
+            codes(&request),
+            [("target".to_owned(), ErrorCode::UnknownTable)]
+        );
+    }
+
+    #[test]
+    fn unknown_functions_and_interpolated_queries() {
+        let request = with(
+            &array_relationship(),
+            json!({ "target": { "type": "function", "name": ["search"], "arguments": [] }, "query": {} }),
+        );
+        assert_eq!(
+            codes(&request),
+            [("target".to_owned(), ErrorCode::UnknownFunction)]
+        );
+
+        let request = with(
+            &array_relationship(),
+            json!({ "target": { "type": "interpolated", "id": "q1" }, "query": {} }),
+        );
+        assert_eq!(
+            codes(&request),
+            [("target".to_owned(), ErrorCode::UnknownInterpolatedQuery)]
+        );
+    }
+
+    #[test]
+    fn foreach_values_must_match_column_types() {
+        let request = with(
+            &request(include_str!("../fixtures/query_requests/foreach.json")),
+            json!({ "foreach": [{ "ArtistId": { "value": "1", "value_type": "string" } }] }),
+        );
+        assert_eq!(
+            codes(&request),
+            [("foreach[0].ArtistId".to_owned(), ErrorCode::TypeMismatch)]
+        );
+    }
+
+    #[test]
+    fn codes_serialize_as_their_stable_names() {
+        for code in [
+            ErrorCode::UnknownTable,
+            ErrorCode::UnknownInterpolatedQuery,
+            ErrorCode::UndeclaredScalarType,
+            ErrorCode::UnsupportedFeature,
+        ] {
+            assert_eq!(serde_json::to_value(code).unwrap(), json!(code.as_str()));
+            assert_eq!(code.to_string(), code.as_str());
+        }
+    }
+
+    #[test]
+    fn referenced_columns() {
+        let columns = array_relationship().referenced_columns();
+        let columns: Vec<(String, Vec<&str>)> = columns
+            .iter()
+            .map(|(table, columns)| {
+                (
+                    table.join("."),
+                    columns.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("Artist".to_owned(), vec!["Name", "ArtistId"]),
+                ("Album".to_owned(), vec!["ArtistId", "Title"]),
+            ]
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_results_match_sequential_results() {
+        let request = with(
+            &array_relationship(),
+            json!({ "target": { "type": "table", "name": ["Band"] } }),
+        );
+        assert_eq!(request.par_validate(&schema()), request.validate(&schema()));
+        assert_eq!(
+            request.par_referenced_columns(),
+            request.referenced_columns()
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+
+use serde_json::{Number, Value};
+
+use crate::{
+    capabilities::ScalarType,
+    query::{ArgumentValue, ComparisonValue, ScalarValue},
+};
+
+impl ScalarValue {
+    pub fn new(value: Value, value_type: ScalarType) -> Self {
+        Self { value, value_type }
+    }
+
+    pub fn string(value: impl Into<String>) -> Self {
+        Self::new(Value::String(value.into()), ScalarType::String)
+    }
+
+    pub fn number(value: impl Into<Number>) -> Self {
+        Self::new(Value::Number(value.into()), ScalarType::Number)
+    }
+
+    pub fn bool(value: bool) -> Self {
+        Self::new(Value::Bool(value), ScalarType::Bool)
+    }
+
+    pub fn null(value_type: ScalarType) -> Self {
+        Self::new(Value::Null, value_type)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NonFiniteNumberError(pub f64);
+
+impl fmt::Display for NonFiniteNumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} cannot be represented as a JSON number", self.0)
+    }
+}
+
+impl std::error::Error for NonFiniteNumberError {}
+
+macro_rules! impl_from_integer {
+    ($($t:ty),*) => {
+        $(impl From<$t> for ScalarValue {
+            fn from(value: $t) -> Self {
+                Self::number(value)
+            }
+        })*
+    };
+}
+
+impl_from_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl TryFrom<f64> for ScalarValue {
+    type Error = NonFiniteNumberError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        Number::from_f64(value)
+            .map(Self::number)
+            .ok_or(NonFiniteNumberError(value))
+    }
+}
+
+impl TryFrom<f32> for ScalarValue {
+    type Error = NonFiniteNumberError;
+
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        Self::try_from(f64::from(value))
+    }
+}
+
+impl From<bool> for ScalarValue {
+    fn from(value: bool) -> Self {
+        Self::bool(value)
+    }
+}
+
+impl From<String> for ScalarValue {
+    fn from(value: String) -> Self {
+        Self::string(value)
+    }
+}
+
+impl From<&str> for ScalarValue {
+    fn from(value: &str) -> Self {
+        Self::string(value)
+    }
+}
+
+macro_rules! impl_scalar_constructors {
+    ($($t:ident),*) => {
+        $(
+            impl From<ScalarValue> for $t {
+                fn from(ScalarValue { value, value_type }: ScalarValue) -> Self {
+                    Self::Scalar { value, value_type }
+                }
+            }
+
+            impl $t {
+                pub fn string(value: impl Into<String>) -> Self {
+                    ScalarValue::string(value).into()
+                }
+
+                pub fn number(value: impl Into<Number>) -> Self {
+                    ScalarValue::number(value).into()
+                }
+
+                pub fn bool(value: bool) -> Self {
+                    ScalarValue::bool(value).into()
+                }
+
+                pub fn null(value_type: ScalarType) -> Self {
+                    ScalarValue::null(value_type).into()
+                }
+
+                /// Converts any value with a `ScalarValue` conversion, i.e. Rust integers, floats, booleans and strings
+                pub fn try_scalar<T: TryInto<ScalarValue>>(value: T) -> Result<Self, T::Error> {
+                    value.try_into().map(Self::from)
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_constructors!(ComparisonValue, ArgumentValue);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn converts_rust_values() {
+        assert_eq!(ScalarValue::from(42u8), ScalarValue::number(42));
+        assert_eq!(ScalarValue::from("a"), ScalarValue::string("a"));
+        assert_eq!(ScalarValue::from(true).value_type, ScalarType::Bool);
+        assert_eq!(ScalarValue::try_from(1.5f32).unwrap().value, json!(1.5));
+        assert_eq!(
+            ScalarValue::null(ScalarType::String),
+            ScalarValue::new(Value::Null, ScalarType::String)
+        );
+    }
+
+    #[test]
+    fn rejects_non_finite_floats() {
+        assert_eq!(
+            ScalarValue::try_from(f64::NAN).unwrap_err().to_string(),
+            "NaN cannot be represented as a JSON number"
+        );
+        assert!(ComparisonValue::try_scalar(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn builds_comparison_and_argument_values() {
+        assert_eq!(
+            serde_json::to_value(ComparisonValue::number(5)).unwrap(),
+            json!({"type": "scalar", "value": 5, "value_type": "number"})
+        );
+        assert_eq!(
+            ArgumentValue::try_scalar("x").unwrap(),
+            ArgumentValue::string("x")
+        );
+        assert_eq!(
+            serde_json::to_value(ArgumentValue::null(ScalarType::Bool)).unwrap(),
+            json!({"type": "scalar", "value": null, "value_type": "bool"})
+        );
+    }
+}
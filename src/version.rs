@@ -0,0 +1,252 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    error::{ErrorDetails, ErrorResponse, ErrorResponseType},
+    protocol::{ApiVersion, API_VERSION_HEADER},
+};
+
+/// A semantic version of the data connector API, e.g. `1.2.0`. Serialized as a string
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ProtocolVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl ProtocolVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Whether the versions are compatible under semantic versioning: they share a major version, or for `0.x` versions, a major and minor version
+    pub fn is_compatible_with(&self, other: &ProtocolVersion) -> bool {
+        self.major == other.major && (self.major != 0 || self.minor == other.minor)
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = ParseProtocolVersionError;
+
+    /// Parses `major.minor.patch`, with an optional `v` prefix. Missing minor and patch numbers are taken as 0, and pre-release and build suffixes are ignored
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseProtocolVersionError(s.to_owned());
+        let trimmed = s.trim();
+        let trimmed = trimmed.strip_prefix('v').unwrap_or(trimmed);
+        let core = trimmed.split(['-', '+']).next().unwrap_or_default();
+        let mut numbers = core.split('.').map(|number| {
+            if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(error());
+            }
+            number.parse::<u64>().map_err(|_| error())
+        });
+        let major = numbers.next().ok_or_else(error)??;
+        let minor = numbers.next().transpose()?.unwrap_or(0);
+        let patch = numbers.next().transpose()?.unwrap_or(0);
+        if numbers.next().is_some() {
+            return Err(error());
+        }
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+impl Serialize for ProtocolVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ProtocolVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        version.parse().map_err(de::Error::custom)
+    }
+}
+
+/// A version string that is not a semantic version
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseProtocolVersionError(pub String);
+
+impl fmt::Display for ParseProtocolVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid data connector API version {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseProtocolVersionError {}
+
+impl ApiVersion {
+    pub fn parse(&self) -> Result<ProtocolVersion, ParseProtocolVersionError> {
+        self.0.parse()
+    }
+}
+
+/// The range of data connector API versions an agent can serve, both inclusive
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct SupportedVersions {
+    pub min: ProtocolVersion,
+    /// The latest version served. If `None`, every later version compatible with `min` is served
+    pub max: Option<ProtocolVersion>,
+}
+
+impl SupportedVersions {
+    pub fn new(min: ProtocolVersion, max: Option<ProtocolVersion>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn contains(&self, version: &ProtocolVersion) -> bool {
+        let within_max = match &self.max {
+            Some(max) => version <= max,
+            None => self.min.is_compatible_with(version),
+        };
+        self.min <= *version && within_max
+    }
+
+    /// Checks the value of the `X-Hasura-DataConnector-API-Version` header, returning the version requested. Requests without the header are accepted, as older HGE versions do not send it
+    pub fn negotiate(
+        &self,
+        header: Option<&str>,
+    ) -> Result<Option<ProtocolVersion>, ErrorResponse> {
+        let Some(header) = header else {
+            return Ok(None);
+        };
+        match header.parse::<ProtocolVersion>() {
+            Ok(version) if self.contains(&version) => Ok(Some(version)),
+            Ok(_) => Err(self.unsupported(header)),
+            Err(error) => Err(ErrorResponse {
+                details: None,
+                message: format!("invalid {API_VERSION_HEADER} header: {error}"),
+                r#type: Some(ErrorResponseType::RequestValidationFailure),
+            }),
+        }
+    }
+
+    /// The error an agent responds with to requests for a version outside this range, carrying `UnsupportedVersionDetails`
+    pub fn unsupported(&self, requested: &str) -> ErrorResponse {
+        let range = match &self.max {
+            Some(max) => format!("{} to {max}", self.min),
+            None => format!("{} and later compatible versions", self.min),
+        };
+        ErrorResponse::with_details(
+            format!(
+                "data connector API version {requested} is not supported by this agent, which supports versions {range}"
+            ),
+            &UnsupportedVersionDetails {
+                requested: requested.to_owned(),
+                min_supported: self.min,
+                max_supported: self.max,
+            },
+        )
+    }
+}
+
+/// The details of a `request-validation-failure` error for a data connector API version the agent does not support
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnsupportedVersionDetails {
+    /// The version sent in the `X-Hasura-DataConnector-API-Version` header
+    pub requested: String,
+    pub min_supported: ProtocolVersion,
+    pub max_supported: Option<ProtocolVersion>,
+}
+
+impl ErrorDetails for UnsupportedVersionDetails {
+    fn error_type() -> ErrorResponseType {
+        ErrorResponseType::RequestValidationFailure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse(version: &str) -> Result<ProtocolVersion, ParseProtocolVersionError> {
+        version.parse()
+    }
+
+    #[test]
+    fn parses_semantic_versions() {
+        for (input, expected) in [
+            ("1.2.3", ProtocolVersion::new(1, 2, 3)),
+            ("v1.2.3", ProtocolVersion::new(1, 2, 3)),
+            (" 1.2 ", ProtocolVersion::new(1, 2, 0)),
+            ("2", ProtocolVersion::new(2, 0, 0)),
+            ("1.2.3-beta.1+build.5", ProtocolVersion::new(1, 2, 3)),
+        ] {
+            assert_eq!(parse(input), Ok(expected), "{input}");
+        }
+        for input in ["", "v", "1.", "1..2", "1.2.3.4", "1.x", "-1.0.0", "1.+2"] {
+            assert!(parse(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn orders_and_serializes_as_strings() {
+        assert!(parse("1.10.0").unwrap() > parse("1.9.9").unwrap());
+        let version = ProtocolVersion::new(1, 2, 3);
+        assert_eq!(serde_json::to_value(version).unwrap(), json!("1.2.3"));
+        assert_eq!(
+            serde_json::from_value::<ProtocolVersion>(json!("v1.2.3")).unwrap(),
+            version
+        );
+        assert!(serde_json::from_value::<ProtocolVersion>(json!("one")).is_err());
+    }
+
+    #[test]
+    fn compatibility() {
+        let v = ProtocolVersion::new;
+        assert!(v(1, 0, 0).is_compatible_with(&v(1, 5, 2)));
+        assert!(!v(1, 0, 0).is_compatible_with(&v(2, 0, 0)));
+        assert!(v(0, 3, 0).is_compatible_with(&v(0, 3, 9)));
+        assert!(!v(0, 3, 0).is_compatible_with(&v(0, 4, 0)));
+    }
+
+    #[test]
+    fn negotiation() {
+        let v = ProtocolVersion::new;
+        let open = SupportedVersions::new(v(1, 2, 0), None);
+        assert_eq!(open.negotiate(None), Ok(None));
+        assert_eq!(open.negotiate(Some("1.9.0")), Ok(Some(v(1, 9, 0))));
+        assert!(open.negotiate(Some("2.0.0")).is_err());
+        assert!(open.negotiate(Some("1.1.0")).is_err());
+
+        let bounded = SupportedVersions::new(v(1, 2, 0), Some(v(1, 4, 0)));
+        assert!(bounded.contains(&v(1, 4, 0)));
+        assert!(!bounded.contains(&v(1, 4, 1)));
+
+        let error = bounded.negotiate(Some("1.5.0")).unwrap_err();
+        assert_eq!(error.status_code(), 400);
+        assert_eq!(
+            error
+                .details_as::<UnsupportedVersionDetails>()
+                .unwrap()
+                .unwrap(),
+            UnsupportedVersionDetails {
+                requested: "1.5.0".to_owned(),
+                min_supported: v(1, 2, 0),
+                max_supported: Some(v(1, 4, 0)),
+            }
+        );
+
+        let error = bounded.negotiate(Some("latest")).unwrap_err();
+        assert_eq!(
+            error.r#type,
+            Some(ErrorResponseType::RequestValidationFailure)
+        );
+        assert!(error.details.is_none());
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    canonical::to_canonical_value,
+    capabilities::CapabilitiesResponse,
+    contract::check_contract,
+    mutation::{MutationRequest, MutationResponse},
+    query::{QueryRequest, QueryResponse},
+    schema::SchemaResponse,
+    validate::ValidationError,
+};
+
+// Values cross the wasm boundary as JSON text, so JavaScript callers work with the same wire format HGE sends
+
+fn parse<T: DeserializeOwned>(json: &str) -> Result<T, JsError> {
+    serde_json::from_str(json).map_err(|error| JsError::new(&error.to_string()))
+}
+
+/// Parses the JSON and serializes it back in canonical form, failing if it is not a valid `T`
+fn normalize<T: DeserializeOwned + Serialize>(json: &str) -> Result<String, JsError> {
+    let value =
+        to_canonical_value(&parse::<T>(json)?).map_err(|error| JsError::new(&error.to_string()))?;
+    Ok(value.to_string())
+}
+
+/// The errors as a JSON array of `{path, code, message}` objects, empty when there are none
+fn errors_json(result: Result<(), Vec<ValidationError>>) -> String {
+    let errors: Vec<Value> = result
+        .err()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|error| {
+            json!({
+                "path": error.path,
+                "code": error.code,
+                "message": error.message,
+            })
+        })
+        .collect();
+    Value::Array(errors).to_string()
+}
+
+#[wasm_bindgen(js_name = normalizeQueryRequest)]
+pub fn normalize_query_request(json: &str) -> Result<String, JsError> {
+    normalize::<QueryRequest>(json)
+}
+
+#[wasm_bindgen(js_name = normalizeQueryResponse)]
+pub fn normalize_query_response(json: &str) -> Result<String, JsError> {
+    normalize::<QueryResponse>(json)
+}
+
+#[wasm_bindgen(js_name = normalizeMutationRequest)]
+pub fn normalize_mutation_request(json: &str) -> Result<String, JsError> {
+    normalize::<MutationRequest>(json)
+}
+
+#[wasm_bindgen(js_name = normalizeMutationResponse)]
+pub fn normalize_mutation_response(json: &str) -> Result<String, JsError> {
+    normalize::<MutationResponse>(json)
+}
+
+#[wasm_bindgen(js_name = normalizeSchemaResponse)]
+pub fn normalize_schema_response(json: &str) -> Result<String, JsError> {
+    normalize::<SchemaResponse>(json)
+}
+
+#[wasm_bindgen(js_name = normalizeCapabilitiesResponse)]
+pub fn normalize_capabilities_response(json: &str) -> Result<String, JsError> {
+    normalize::<CapabilitiesResponse>(json)
+}
+
+/// Checks a query request against a schema, returning the problems found as a JSON array
+#[wasm_bindgen(js_name = validateQueryRequest)]
+pub fn validate_query_request(request: &str, schema: &str) -> Result<String, JsError> {
+    let request: QueryRequest = parse(request)?;
+    let schema: SchemaResponse = parse(schema)?;
+    Ok(errors_json(request.validate(&schema)))
+}
+
+/// Checks that a query request only uses features a capabilities response declares, returning the problems found as a JSON array
+#[wasm_bindgen(js_name = checkQueryCapabilities)]
+pub fn check_query_capabilities(request: &str, capabilities: &str) -> Result<String, JsError> {
+    let request: QueryRequest = parse(request)?;
+    let capabilities: CapabilitiesResponse = parse(capabilities)?;
+    Ok(errors_json(
+        request.check_capabilities(&capabilities.capabilities),
+    ))
+}
+
+/// Checks that a mutation request only uses features a capabilities response declares, returning the problems found as a JSON array
+#[wasm_bindgen(js_name = checkMutationCapabilities)]
+pub fn check_mutation_capabilities(request: &str, capabilities: &str) -> Result<String, JsError> {
+    let request: MutationRequest = parse(request)?;
+    let capabilities: CapabilitiesResponse = parse(capabilities)?;
+    Ok(errors_json(
+        request.check_capabilities(&capabilities.capabilities),
+    ))
+}
+
+/// Checks that a schema only uses what a capabilities response declares, returning the problems found as a JSON array
+#[wasm_bindgen(js_name = checkContract)]
+pub fn check_schema_contract(capabilities: &str, schema: &str) -> Result<String, JsError> {
+    let capabilities: CapabilitiesResponse = parse(capabilities)?;
+    let schema: SchemaResponse = parse(schema)?;
+    Ok(errors_json(check_contract(&capabilities, &schema)))
+}